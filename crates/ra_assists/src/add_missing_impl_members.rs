@@ -42,11 +42,10 @@ pub(crate) fn add_missing_impl_members(mut ctx: AssistCtx<impl HirDatabase>) ->
         return None;
     }
 
+    let indent_unit = ctx.indent_unit();
     ctx.add_action(AssistId("add_impl_missing_members"), "add missing impl members", |edit| {
         let (parent_indent, indent) = {
-            // FIXME: Find a way to get the indent already used in the file.
-            // Now, we copy the indent of first item or indent with 4 spaces relative to impl block
-            const DEFAULT_INDENT: &str = "    ";
+            // We copy the indent of the first item, or indent one level relative to the impl block.
             let first_item = impl_item_list.impl_items().next();
             let first_item_indent =
                 first_item.and_then(|i| leading_indent(i.syntax())).map(ToOwned::to_owned);
@@ -54,7 +53,7 @@ pub(crate) fn add_missing_impl_members(mut ctx: AssistCtx<impl HirDatabase>) ->
 
             (
                 impl_block_indent.to_owned(),
-                first_item_indent.unwrap_or_else(|| impl_block_indent.to_owned() + DEFAULT_INDENT),
+                first_item_indent.unwrap_or_else(|| impl_block_indent.to_owned() + &indent_unit),
             )
         };
 
@@ -114,6 +114,11 @@ impl<'a, DB: HirDatabase> AssistCtx<'a, DB> {
     pub(crate) fn covering_node(&self) -> &'a SyntaxNode {
         find_covering_node(self.source_file.syntax(), self.frange.range)
     }
+
+    /// A single level of indentation, as used elsewhere in the file.
+    pub(crate) fn indent_unit(&self) -> String {
+        self.db.indent_style(self.frange.file_id).unit()
+    }
 }
 
 #[derive(Default)]
@@ -363,11 +363,8 @@ fn best_action_for_target<'b, 'a: 'b>(
             // We have no action and no UseItem was found in container so we find
             // another item and we use it as anchor.
             // If there are no items, we choose the target path itself as anchor.
-            let anchor = container
-                .children()
-                .find_map(ast::ModuleItem::cast)
-                .map(AstNode::syntax)
-                .or(Some(path.syntax()));
+            let anchor =
+                container.children().find(|it| it.kind().is_item()).or(Some(path.syntax()));
 
             return ImportAction::add_new_use(anchor, false);
         }
@@ -2,7 +2,7 @@ use hir::db::HirDatabase;
 use ra_syntax::{
     AstNode, SyntaxNode, TextUnit,
     ast::{self, VisibilityOwner, NameOwner},
-    SyntaxKind::{VISIBILITY, FN_KW, MOD_KW, STRUCT_KW, ENUM_KW, TRAIT_KW, FN_DEF, MODULE, STRUCT_DEF, ENUM_DEF, TRAIT_DEF, IDENT, WHITESPACE, COMMENT, ATTR},
+    SyntaxKind::{VISIBILITY, FN_KW, MOD_KW, STRUCT_KW, ENUM_KW, TRAIT_KW, FN_DEF, MODULE, STRUCT_DEF, ENUM_DEF, TRAIT_DEF, IDENT, ATTR},
 };
 
 use crate::{AssistCtx, Assist, AssistId};
@@ -53,8 +53,8 @@ fn add_vis(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
 fn vis_offset(node: &SyntaxNode) -> TextUnit {
     node.children()
         .skip_while(|it| match it.kind() {
-            WHITESPACE | COMMENT | ATTR => true,
-            _ => false,
+            ATTR => true,
+            kind => kind.is_trivia(),
         })
         .next()
         .map(|it| it.range().start())
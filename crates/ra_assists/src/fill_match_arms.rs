@@ -32,6 +32,7 @@ pub(crate) fn fill_match_arms(mut ctx: AssistCtx<impl HirDatabase>) -> Option<As
     })?;
     let enum_name = enum_def.name(ctx.db)?;
     let db = ctx.db;
+    let indent = ctx.indent_unit();
 
     ctx.add_action(AssistId("fill_match_arms"), "fill match arms", |edit| {
         let mut buf = format!("match {} {{\n", expr.syntax().text().to_string());
@@ -41,7 +42,7 @@ pub(crate) fn fill_match_arms(mut ctx: AssistCtx<impl HirDatabase>) -> Option<As
                 Some(it) => it,
                 None => continue,
             };
-            write!(&mut buf, "    {}::{}", enum_name, name.to_string()).unwrap();
+            write!(&mut buf, "{}{}::{}", indent, enum_name, name.to_string()).unwrap();
 
             let pat = variant
                 .fields(db)
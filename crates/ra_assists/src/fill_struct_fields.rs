@@ -84,9 +84,10 @@ where
     }
 
     fn struct_fields_string(&mut self) -> Option<String> {
+        let indent = self.ctx.indent_unit();
         let mut buf = String::from("{\n");
         for (name, expr) in &self.struct_fields {
-            write!(&mut buf, "    {}: {},\n", name, expr).unwrap();
+            write!(&mut buf, "{}{}: {},\n", indent, name, expr).unwrap();
         }
         buf.push_str("}");
         Some(buf)
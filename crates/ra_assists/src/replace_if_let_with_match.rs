@@ -15,8 +15,9 @@ pub(crate) fn replace_if_let_with_match(mut ctx: AssistCtx<impl HirDatabase>) ->
         ast::ElseBranchFlavor::IfExpr(_) => return None,
     };
 
+    let indent = ctx.indent_unit();
     ctx.add_action(AssistId("replace_if_let_with_match"), "replace with match", |edit| {
-        let match_expr = build_match_expr(expr, pat, then_block, else_block);
+        let match_expr = build_match_expr(expr, pat, then_block, else_block, &indent);
         edit.target(if_expr.syntax().range());
         edit.replace_node_and_indent(if_expr.syntax(), match_expr);
         edit.set_cursor(if_expr.syntax().range().start())
@@ -30,11 +31,12 @@ fn build_match_expr(
     pat1: &ast::Pat,
     arm1: &ast::Block,
     arm2: &ast::Block,
+    indent: &str,
 ) -> String {
     let mut buf = String::new();
     buf.push_str(&format!("match {} {{\n", expr.syntax().text()));
-    buf.push_str(&format!("    {} => {}\n", pat1.syntax().text(), format_arm(arm1)));
-    buf.push_str(&format!("    _ => {}\n", format_arm(arm2)));
+    buf.push_str(&format!("{}{} => {}\n", indent, pat1.syntax().text(), format_arm(arm1)));
+    buf.push_str(&format!("{}_ => {}\n", indent, format_arm(arm2)));
     buf.push_str("}");
     buf
 }
@@ -0,0 +1,176 @@
+//! Dumps the results of a single-file analysis, so that editor behavior can
+//! be reproduced from the command line, and files can be analyzed by scripts.
+
+use std::{fs, path::Path};
+
+use ra_ide_api::{Analysis, FeatureSet, FileAnalysis, Severity};
+use ra_syntax::TextRange;
+use serde_json::{json, Value};
+
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpKind {
+    Highlight,
+    Structure,
+    SyntaxTree,
+    Diagnostics,
+}
+
+impl DumpKind {
+    pub const NAMES: &'static [&'static str] =
+        &["highlight", "structure", "syntax-tree", "diagnostics"];
+
+    pub fn from_name(name: &str) -> Option<DumpKind> {
+        let res = match name {
+            "highlight" => DumpKind::Highlight,
+            "structure" => DumpKind::Structure,
+            "syntax-tree" => DumpKind::SyntaxTree,
+            "diagnostics" => DumpKind::Diagnostics,
+            _ => return None,
+        };
+        Some(res)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    Text,
+    Json,
+}
+
+/// Analyzes the file at `path` as a standalone crate and renders the `kind`
+/// of results.
+pub fn dump(path: &Path, kind: DumpKind, format: DumpFormat) -> Result<String> {
+    let text = fs::read_to_string(path)?;
+    Ok(dump_text(text, kind, format))
+}
+
+fn dump_text(text: String, kind: DumpKind, format: DumpFormat) -> String {
+    let (analysis, file_id) = Analysis::from_single_file(text);
+    if kind == DumpKind::SyntaxTree {
+        let tree = analysis.syntax_tree(file_id, None);
+        return match format {
+            DumpFormat::Text => tree,
+            DumpFormat::Json => json!({ "syntax_tree": tree }).to_string(),
+        };
+    }
+
+    let wanted = FeatureSet {
+        highlight: kind == DumpKind::Highlight,
+        structure: kind == DumpKind::Structure,
+        diagnostics: kind == DumpKind::Diagnostics,
+        ..FeatureSet::default()
+    };
+    let res = analysis.analyze_file(file_id, wanted).unwrap();
+    match format {
+        DumpFormat::Text => to_text(&res, kind),
+        DumpFormat::Json => to_json(&res, kind).to_string(),
+    }
+}
+
+fn to_text(res: &FileAnalysis, kind: DumpKind) -> String {
+    let lines: Vec<String> = match kind {
+        DumpKind::Highlight => {
+            res.highlight.iter().map(|hl| format!("{:?} {}", hl.range, hl.tag)).collect()
+        }
+        DumpKind::Structure => res
+            .structure
+            .iter()
+            .map(|node| {
+                let indent = "  ".repeat(depth(res, node.parent));
+                format!("{}{:?} {} {:?}", indent, node.kind, node.label, node.node_range)
+            })
+            .collect(),
+        DumpKind::Diagnostics => res
+            .diagnostics
+            .iter()
+            .map(|d| format!("{:?} {}: {}", d.range, severity(d.severity), d.message))
+            .collect(),
+        DumpKind::SyntaxTree => unreachable!(),
+    };
+    lines.iter().map(|it| format!("{}\n", it)).collect()
+}
+
+fn to_json(res: &FileAnalysis, kind: DumpKind) -> Value {
+    match kind {
+        DumpKind::Highlight => {
+            let highlight = res
+                .highlight
+                .iter()
+                .map(|hl| json!({ "range": range(hl.range), "tag": hl.tag }))
+                .collect::<Vec<_>>();
+            json!({ "highlight": highlight })
+        }
+        DumpKind::Structure => {
+            let structure = res
+                .structure
+                .iter()
+                .map(|node| {
+                    json!({
+                        "parent": node.parent,
+                        "label": node.label,
+                        "kind": format!("{:?}", node.kind),
+                        "detail": node.detail,
+                        "node_range": range(node.node_range),
+                        "navigation_range": range(node.navigation_range),
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({ "structure": structure })
+        }
+        DumpKind::Diagnostics => {
+            let diagnostics = res
+                .diagnostics
+                .iter()
+                .map(|d| {
+                    json!({
+                        "range": range(d.range),
+                        "severity": severity(d.severity),
+                        "message": d.message,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({ "diagnostics": diagnostics })
+        }
+        DumpKind::SyntaxTree => unreachable!(),
+    }
+}
+
+fn depth(res: &FileAnalysis, mut parent: Option<usize>) -> usize {
+    let mut depth = 0;
+    while let Some(idx) = parent {
+        depth += 1;
+        parent = res.structure[idx].parent;
+    }
+    depth
+}
+
+fn range(range: TextRange) -> Value {
+    json!([u32::from(range.start()), u32::from(range.end())])
+}
+
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::WeakWarning => "warning",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_structure_as_text_and_json() {
+        let text = "mod m {\n    fn f() {}\n}\n".to_string();
+        assert_eq!(
+            dump_text(text.clone(), DumpKind::Structure, DumpFormat::Text),
+            "Module m [0; 23)\n  Function f [12; 21)\n"
+        );
+        let json: Value =
+            serde_json::from_str(&dump_text(text, DumpKind::Structure, DumpFormat::Json)).unwrap();
+        assert_eq!(json["structure"][1]["label"], "f");
+        assert_eq!(json["structure"][1]["parent"], 0);
+    }
+}
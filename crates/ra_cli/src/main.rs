@@ -1,4 +1,5 @@
 mod analysis_stats;
+mod dump;
 
 use std::{fs, io::Read, path::Path, time::Instant};
 
@@ -10,6 +11,8 @@ use ra_syntax::{SourceFile, TextRange, TreeArc, AstNode};
 use tools::collect_tests;
 use flexi_logger::Logger;
 
+use crate::dump::{DumpFormat, DumpKind};
+
 type Result<T> = ::std::result::Result<T, failure::Error>;
 
 fn main() -> Result<()> {
@@ -31,6 +34,18 @@ fn main() -> Result<()> {
         .subcommand(
             SubCommand::with_name("analysis-stats").arg(Arg::with_name("verbose").short("v")),
         )
+        .subcommand(
+            SubCommand::with_name("dump")
+                .arg(Arg::with_name("path").required(true))
+                .arg(
+                    Arg::with_name("kind")
+                        .long("--kind")
+                        .takes_value(true)
+                        .possible_values(DumpKind::NAMES)
+                        .default_value("structure"),
+                )
+                .arg(Arg::with_name("json").long("--json")),
+        )
         .get_matches();
     match matches.subcommand() {
         ("parse", Some(matches)) => {
@@ -68,6 +83,13 @@ fn main() -> Result<()> {
             let verbose = matches.is_present("verbose");
             analysis_stats::run(verbose)?;
         }
+        ("dump", Some(matches)) => {
+            let path = Path::new(matches.value_of("path").unwrap());
+            let kind = DumpKind::from_name(matches.value_of("kind").unwrap()).unwrap();
+            let format =
+                if matches.is_present("json") { DumpFormat::Json } else { DumpFormat::Text };
+            print!("{}", dump::dump(path, kind, format)?);
+        }
         _ => unreachable!(),
     }
     Ok(())
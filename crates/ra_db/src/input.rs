@@ -5,6 +5,8 @@
 /// Note that neither this module, nor any other part of the analyzer's core do
 /// actual IO. See `vfs` and `project_model` in the `ra_lsp_server` crate for how
 /// actual IO is done and lowered to input.
+use std::fmt;
+
 use relative_path::RelativePathBuf;
 use rustc_hash::FxHashMap;
 
@@ -50,8 +52,35 @@ pub struct CrateGraph {
     arena: FxHashMap<CrateId, CrateData>,
 }
 
-#[derive(Debug)]
-pub struct CyclicDependencies;
+/// Error returned when adding a dependency would create a cycle in the
+/// `CrateGraph`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CyclicDependencies {
+    path: Vec<CrateId>,
+}
+
+impl CyclicDependencies {
+    /// The crates forming the cycle, starting and ending with the crate from
+    /// which the rejected dependency edge originates.
+    pub fn path(&self) -> &[CrateId] {
+        &self.path
+    }
+}
+
+impl fmt::Display for CyclicDependencies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cyclic crate dependencies: ")?;
+        for (idx, crate_id) in self.path.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{}", crate_id.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CyclicDependencies {}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CrateId(pub u32);
@@ -114,8 +143,9 @@ impl CrateGraph {
         name: SmolStr,
         to: CrateId,
     ) -> Result<(), CyclicDependencies> {
-        if self.dfs_find(from, to, &mut FxHashSet::default()) {
-            return Err(CyclicDependencies);
+        if let Some(path) = self.find_path(to, from, &mut FxHashSet::default()) {
+            let path = std::iter::once(from).chain(path).collect();
+            return Err(CyclicDependencies { path });
         }
         Ok(self.arena.get_mut(&from).unwrap().add_dep(name, to))
     }
@@ -149,6 +179,59 @@ impl CrateGraph {
         self.arena[&crate_id].dependencies.iter()
     }
 
+    /// Groups all crates into "waves": crates of each wave depend only on the
+    /// crates from the previous waves, so crates inside a wave can be
+    /// processed in parallel.
+    pub fn dependency_waves(&self) -> Vec<Vec<CrateId>> {
+        fn depth(
+            graph: &CrateGraph,
+            crate_id: CrateId,
+            depths: &mut FxHashMap<CrateId, usize>,
+        ) -> usize {
+            if let Some(&depth) = depths.get(&crate_id) {
+                return depth;
+            }
+            let depth = graph
+                .dependencies(crate_id)
+                .map(|dep| depth(graph, dep.crate_id(), depths) + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(crate_id, depth);
+            depth
+        }
+
+        let mut depths = FxHashMap::default();
+        let mut waves: Vec<Vec<CrateId>> = Vec::new();
+        for crate_id in self.iter() {
+            let depth = depth(self, crate_id, &mut depths);
+            if waves.len() <= depth {
+                waves.resize(depth + 1, Vec::new());
+            }
+            waves[depth].push(crate_id);
+        }
+        for wave in waves.iter_mut() {
+            wave.sort();
+        }
+        waves
+    }
+
+    /// The crate itself and all crates which depend on it, directly or
+    /// transitively, sorted.
+    pub fn dependents(&self, crate_id: CrateId) -> Vec<CrateId> {
+        let mut res = vec![crate_id];
+        // Waves are topologically sorted, so the dependencies of a crate are
+        // checked before the crate itself.
+        for wave in self.dependency_waves() {
+            for krate in wave {
+                if self.dependencies(krate).any(|dep| res.contains(&dep.crate_id())) {
+                    res.push(krate);
+                }
+            }
+        }
+        res.sort();
+        res
+    }
+
     /// Extends this crate graph by adding a complete disjoint second crate
     /// graph.
     pub fn extend(&mut self, other: CrateGraph) {
@@ -162,22 +245,27 @@ impl CrateGraph {
         }));
     }
 
-    fn dfs_find(&self, target: CrateId, from: CrateId, visited: &mut FxHashSet<CrateId>) -> bool {
+    /// Finds a dependency path from `from` to `target`, both ends included.
+    fn find_path(
+        &self,
+        from: CrateId,
+        target: CrateId,
+        visited: &mut FxHashSet<CrateId>,
+    ) -> Option<Vec<CrateId>> {
+        if from == target {
+            return Some(vec![target]);
+        }
         if !visited.insert(from) {
-            return false;
+            return None;
         }
 
         for dep in self.dependencies(from) {
-            let crate_id = dep.crate_id();
-            if crate_id == target {
-                return true;
-            }
-
-            if self.dfs_find(target, crate_id, visited) {
-                return true;
+            if let Some(mut path) = self.find_path(dep.crate_id(), target, visited) {
+                path.insert(0, from);
+                return Some(path);
             }
         }
-        return false;
+        None
     }
 }
 
@@ -193,7 +281,48 @@ mod tests {
         let crate3 = graph.add_crate_root(FileId(3u32), Edition2018);
         assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
         assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
-        assert!(graph.add_dep(crate3, SmolStr::new("crate1"), crate1).is_err());
+        let err = graph.add_dep(crate3, SmolStr::new("crate1"), crate1).unwrap_err();
+        assert_eq!(err.path(), &[crate3, crate1, crate2, crate3]);
+        assert_eq!(err.to_string(), "cyclic crate dependencies: 2 -> 0 -> 1 -> 2");
+    }
+
+    #[test]
+    fn self_dependency_is_a_cycle() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
+        let err = graph.add_dep(crate1, SmolStr::new("crate1"), crate1).unwrap_err();
+        assert_eq!(err.path(), &[crate1, crate1]);
+        assert_eq!(graph.dependencies(crate1).count(), 0);
+    }
+
+    #[test]
+    fn dependency_waves() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
+        let crate2 = graph.add_crate_root(FileId(2u32), Edition2018);
+        let crate3 = graph.add_crate_root(FileId(3u32), Edition2018);
+        let crate4 = graph.add_crate_root(FileId(4u32), Edition2018);
+        assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
+        assert!(graph.add_dep(crate1, SmolStr::new("crate3"), crate3).is_ok());
+        assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
+        assert_eq!(
+            graph.dependency_waves(),
+            vec![vec![crate3, crate4], vec![crate2], vec![crate1]]
+        );
+    }
+
+    #[test]
+    fn dependents() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
+        let crate2 = graph.add_crate_root(FileId(2u32), Edition2018);
+        let crate3 = graph.add_crate_root(FileId(3u32), Edition2018);
+        let crate4 = graph.add_crate_root(FileId(4u32), Edition2018);
+        assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
+        assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
+        assert!(graph.add_dep(crate4, SmolStr::new("crate2"), crate2).is_ok());
+        assert_eq!(graph.dependents(crate3), vec![crate1, crate2, crate3, crate4]);
+        assert_eq!(graph.dependents(crate1), vec![crate1]);
     }
 
     #[test]
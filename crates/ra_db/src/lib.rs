@@ -2,19 +2,22 @@
 mod cancellation;
 mod input;
 mod loc2id;
+pub mod profile;
 
 use std::{
     panic, sync::Arc,
 };
 
 use ra_syntax::{TextUnit, TextRange, SourceFile, TreeArc};
+use ra_fmt::IndentStyle;
 use relative_path::RelativePathBuf;
 
 pub use ::salsa as salsa;
 pub use crate::{
     cancellation::Canceled,
     input::{
-        FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, Dependency, Edition,
+        FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, CyclicDependencies, Dependency,
+        Edition,
     },
     loc2id::LocationInterner,
 };
@@ -76,6 +79,8 @@ pub trait SourceDatabase: CheckCanceled + std::fmt::Debug {
     fn file_text(&self, file_id: FileId) -> Arc<String>;
     // Parses the file into the syntax tree.
     fn parse(&self, file_id: FileId) -> TreeArc<SourceFile>;
+    /// The indentation used in the file, for the code generated in it.
+    fn indent_style(&self, file_id: FileId) -> IndentStyle;
     /// Path to a file, relative to the root of its source root.
     #[salsa::input]
     fn file_relative_path(&self, file_id: FileId) -> RelativePathBuf;
@@ -103,3 +108,8 @@ fn parse(db: &impl SourceDatabase, file_id: FileId) -> TreeArc<SourceFile> {
     let text = db.file_text(file_id);
     SourceFile::parse(&*text)
 }
+
+fn indent_style(db: &impl SourceDatabase, file_id: FileId) -> IndentStyle {
+    let text = db.file_text(file_id);
+    IndentStyle::detect(&*text)
+}
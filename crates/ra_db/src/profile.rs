@@ -0,0 +1,140 @@
+//! Opt-in profiling of a single request.
+//!
+//! When a request is slow, it is usually hard to say why from the outside:
+//! maybe some heavy query was invalidated, maybe we were blocked by another
+//! thread. `profile` runs a closure and records what salsa did meanwhile, so
+//! that the summary can be attached to a performance issue.
+//!
+//! Salsa executes the queries on the thread which requested them, so the
+//! profile is kept in a thread-local. The time spent in the heavyweight
+//! queries comes from the `ra_prof` spans opened during the request.
+
+use std::{cell::RefCell, fmt, time::Duration};
+
+use rustc_hash::FxHashMap;
+
+thread_local! {
+    static PROFILE: RefCell<Option<QueryProfile>> = RefCell::new(None);
+}
+
+/// A summary of the work done to answer a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryProfile {
+    /// Wall-clock time of the whole request.
+    pub total: Duration,
+    /// Queries which were (re)computed, that is, cache misses.
+    pub executed: Vec<String>,
+    /// Memoized values which were checked and reused because their inputs
+    /// didn't change. Values computed in the current revision are reused
+    /// without any checks and are not counted.
+    pub validated: usize,
+    /// How many times we waited for a query computed by another thread.
+    pub blocked: usize,
+    /// The profiling spans of the instrumented heavyweight queries, like
+    /// `crate_def_map`.
+    pub spans: Vec<ra_prof::Span>,
+}
+
+/// The result of a request, with the profile if profiling was requested.
+#[derive(Debug, Clone)]
+pub struct Profiled<T> {
+    pub value: T,
+    pub profile: Option<QueryProfile>,
+}
+
+/// Runs `f`, recording a `QueryProfile` of the queries it triggers.
+pub fn profile<T>(f: impl FnOnce() -> T) -> (T, QueryProfile) {
+    let outer = PROFILE.with(|it| it.replace(Some(QueryProfile::default())));
+    let (value, span) = ra_prof::capture("profile", f);
+    let mut profile = PROFILE.with(|it| it.replace(outer)).unwrap_or_default();
+    profile.total = span.duration;
+    profile.spans = span.children;
+    (value, profile)
+}
+
+/// Like `profile`, but only records the profile if `enabled` is set.
+pub fn profile_if<T>(enabled: bool, f: impl FnOnce() -> T) -> Profiled<T> {
+    if !enabled {
+        return Profiled { value: f(), profile: None };
+    }
+    let (value, profile) = profile(f);
+    Profiled { value, profile: Some(profile) }
+}
+
+/// Whether a profile is being recorded on the current thread. Use this to
+/// avoid computing expensive data for `record_event`.
+pub fn is_profiling() -> bool {
+    PROFILE.with(|it| it.borrow().is_some())
+}
+
+/// Records a salsa event. This should be called from
+/// `salsa::Database::salsa_event`.
+pub fn record_event<DB: salsa::Database>(event: &salsa::Event<DB>) {
+    with_profile(|profile| match &event.kind {
+        salsa::EventKind::WillExecute { database_key } => {
+            profile.executed.push(format!("{:?}", database_key))
+        }
+        salsa::EventKind::DidValidateMemoizedValue { .. } => profile.validated += 1,
+        salsa::EventKind::WillBlockOn { .. } => profile.blocked += 1,
+    })
+}
+
+fn with_profile(f: impl FnOnce(&mut QueryProfile)) {
+    PROFILE.with(|it| {
+        if let Some(profile) = &mut *it.borrow_mut() {
+            f(profile)
+        }
+    })
+}
+
+impl fmt::Display for QueryProfile {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "total: {:?}", self.total)?;
+        writeln!(
+            fmt,
+            "{} executed, {} validated, {} blocked",
+            self.executed.len(),
+            self.validated,
+            self.blocked
+        )?;
+        for span in self.spans.iter() {
+            write!(fmt, "{}", span)?;
+        }
+        // Query keys are long and numerous, so only the counts per query are
+        // shown.
+        let mut per_query = FxHashMap::<&str, usize>::default();
+        for key in self.executed.iter() {
+            let name = key.split('(').next().unwrap_or(key);
+            *per_query.entry(name).or_default() += 1;
+        }
+        let mut per_query = per_query.into_iter().collect::<Vec<_>>();
+        per_query.sort_by(|(name1, n1), (name2, n2)| n2.cmp(n1).then(name1.cmp(name2)));
+        for (name, n) in per_query {
+            writeln!(fmt, "  {:>5} {}", n, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_only_inside_of_profile() {
+        let (value, profile) = profile(|| {
+            assert!(is_profiling());
+            let _p = ra_prof::profile("inside");
+            92
+        });
+        assert!(!is_profiling());
+        assert_eq!(value, 92);
+        let spans = profile.spans.iter().map(|it| it.label).collect::<Vec<_>>();
+        assert_eq!(spans, ["inside"]);
+
+        let profiled = profile_if(false, || {
+            let _p = ra_prof::profile("disabled");
+        });
+        assert!(profiled.profile.is_none());
+    }
+}
@@ -8,6 +8,102 @@ use ra_syntax::{
     algo::generate,
 };
 
+/// The line ending used in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`
+    Unix,
+    /// `\r\n`
+    Dos,
+}
+
+impl LineEnding {
+    /// Detects the line ending used by the majority of lines in `text`. Text
+    /// without line breaks is assumed to use `\n`.
+    pub fn detect(text: &str) -> LineEnding {
+        let newlines = text.matches('\n').count();
+        let crlfs = text.matches("\r\n").count();
+        if crlfs * 2 > newlines {
+            LineEnding::Dos
+        } else {
+            LineEnding::Unix
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Unix => "\n",
+            LineEnding::Dos => "\r\n",
+        }
+    }
+}
+
+/// How a single level of indentation is written in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentStyle {
+    pub use_tabs: bool,
+    /// The width of an indentation level in spaces, ignored when using tabs.
+    pub width: u32,
+}
+
+impl Default for IndentStyle {
+    fn default() -> IndentStyle {
+        IndentStyle { use_tabs: false, width: 4 }
+    }
+}
+
+impl IndentStyle {
+    /// Detects the indentation used by the majority of lines in `text`. The
+    /// width is the most common step by which the indentation increases
+    /// between consecutive lines, four spaces are assumed if there is none.
+    pub fn detect(text: &str) -> IndentStyle {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        // `steps[n]` is how many times indentation increased by `n` spaces.
+        let mut steps = [0u32; 9];
+        let mut prev_indent = 0;
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let indent = &line[..line.len() - trimmed.len()];
+            if indent.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+            let indent = indent.chars().take_while(|&c| c == ' ').count();
+            if indent > 0 {
+                space_lines += 1;
+            }
+            // A step of a single space is usually an alignment, like in the
+            // body of a block comment.
+            if indent >= prev_indent + 2 && indent - prev_indent < steps.len() {
+                steps[indent - prev_indent] += 1;
+            }
+            prev_indent = indent;
+        }
+        let default = IndentStyle::default();
+        if tab_lines > space_lines {
+            return IndentStyle { use_tabs: true, ..default };
+        }
+        let width = (2..steps.len())
+            .filter(|&step| steps[step] > 0)
+            .max_by_key(|&step| (steps[step], step == default.width as usize))
+            .map_or(default.width, |step| step as u32);
+        IndentStyle { use_tabs: false, width }
+    }
+
+    /// A single level of indentation.
+    pub fn unit(&self) -> String {
+        if self.use_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.width as usize)
+        }
+    }
+}
+
 pub fn reindent(text: &str, indent: &str) -> String {
     let indent = format!("\n{}", indent);
     text.lines().intersperse(&indent).collect()
@@ -126,6 +126,17 @@ pub struct StructFieldData {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct VariantData(VariantDataInner);
 
+/// The shape of a struct or an enum variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VariantKind {
+    /// `struct S { x: i32 }`
+    Named,
+    /// `struct S(i32);`, the fields are named `0`, `1` and so on.
+    Tuple,
+    /// `struct S;`
+    Unit,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum VariantDataInner {
     Struct(Arena<StructFieldId, StructFieldData>),
@@ -140,6 +151,14 @@ impl VariantData {
             _ => None,
         }
     }
+
+    pub(crate) fn kind(&self) -> VariantKind {
+        match &self.0 {
+            VariantDataInner::Struct(_) => VariantKind::Named,
+            VariantDataInner::Tuple(_) => VariantKind::Tuple,
+            VariantDataInner::Unit => VariantKind::Unit,
+        }
+    }
 }
 
 impl VariantData {
@@ -180,6 +199,17 @@ pub enum VariantDef {
 impl_froms!(VariantDef: Struct, EnumVariant);
 
 impl VariantDef {
+    pub fn kind(self, db: &impl DefDatabase) -> VariantKind {
+        self.variant_data(db).kind()
+    }
+
+    pub fn fields(self, db: &impl HirDatabase) -> Vec<StructField> {
+        match self {
+            VariantDef::Struct(it) => it.fields(db),
+            VariantDef::EnumVariant(it) => it.fields(db),
+        }
+    }
+
     pub(crate) fn field(self, db: &impl HirDatabase, name: &Name) -> Option<StructField> {
         match self {
             VariantDef::Struct(it) => it.field(db, name),
@@ -231,3 +261,70 @@ impl StructField {
         (file_id, field)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_db::SourceDatabase;
+    use ra_syntax::ast::{self, AstNode};
+
+    use crate::{
+        Struct, ModuleDef, VariantDef, VariantKind, mock::MockDatabase, source_binder,
+        type_ref::TypeRef,
+    };
+
+    #[test]
+    fn lowers_fields_and_variants() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            struct Named { x: i32, y: Vec<u8> }
+            struct Tuple(i32, u8);
+            struct Unit;
+            enum E { A { a: bool }, B(u8), C }
+            ",
+        );
+        let module = source_binder::module_from_file_id(&db, file_id).unwrap();
+        let source_file = db.parse(file_id);
+        let structs = source_file
+            .syntax()
+            .descendants()
+            .filter_map(ast::StructDef::cast)
+            .map(|it| source_binder::struct_from_module(&db, module, it))
+            .collect::<Vec<Struct>>();
+
+        let describe = |variant: VariantDef| {
+            let fields = variant
+                .fields(&db)
+                .iter()
+                .map(|field| {
+                    let ty = match field.type_ref(&db) {
+                        TypeRef::Path(path) => path.segments.last().unwrap().name.to_string(),
+                        it => format!("{:?}", it),
+                    };
+                    format!("{}: {}", field.name(&db), ty)
+                })
+                .collect::<Vec<_>>();
+            (variant.kind(&db), fields.join(", "))
+        };
+        assert_eq!(describe(structs[0].into()), (VariantKind::Named, "x: i32, y: Vec".to_string()));
+        assert_eq!(describe(structs[1].into()), (VariantKind::Tuple, "0: i32, 1: u8".to_string()));
+        assert_eq!(describe(structs[2].into()), (VariantKind::Unit, String::new()));
+
+        let e = module
+            .declarations(&db)
+            .into_iter()
+            .find_map(|it| match it {
+                ModuleDef::Enum(it) => Some(it),
+                _ => None,
+            })
+            .unwrap();
+        let variants = e
+            .variants(&db)
+            .into_iter()
+            .map(|it| {
+                let (kind, fields) = describe(it.into());
+                format!("{} {:?} {}", it.name(&db).unwrap(), kind, fields)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(variants, ["A Named a: bool", "B Tuple 0: u8", "C Unit "]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Built-in macros, like `line!()` or `concat!()`, are implemented by the
+//! compiler rather than by `macro_rules!`.
+//!
+//! We expand them eagerly, to a single literal token. Values which can't be
+//! known, like the ones of environment variables, are replaced by
+//! placeholders of the right type.
+
+use crate::{Path, PathKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BuiltinMacro {
+    Line,
+    Column,
+    File,
+    Stringify,
+    Concat,
+    Env,
+}
+
+/// The location of a built-in macro call, as seen by `line!()`, `column!()`
+/// and `file!()`.
+#[derive(Debug)]
+pub(crate) struct CallSite {
+    pub(crate) file: String,
+    /// 1-based line of the macro call.
+    pub(crate) line: u32,
+    /// 1-based column of the macro call, in characters.
+    pub(crate) column: u32,
+}
+
+impl CallSite {
+    /// Computes the call site of a macro call starting at `offset` in `text`.
+    pub(crate) fn new(file: String, text: &str, offset: usize) -> CallSite {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |it| it + 1);
+        CallSite {
+            file,
+            line: before.matches('\n').count() as u32 + 1,
+            column: before[line_start..].chars().count() as u32 + 1,
+        }
+    }
+}
+
+impl BuiltinMacro {
+    /// Recognizes `name!`, `std::name!` and `core::name!` calls of built-in
+    /// macros.
+    pub(crate) fn from_path(path: &Path) -> Option<BuiltinMacro> {
+        let name = match path.segments.as_slice() {
+            [it] if path.kind == PathKind::Plain => it.name.to_string(),
+            [krate, it] if path.kind == PathKind::Plain || path.kind == PathKind::Abs => {
+                let krate = krate.name.to_string();
+                if krate != "std" && krate != "core" {
+                    return None;
+                }
+                it.name.to_string()
+            }
+            _ => return None,
+        };
+        BuiltinMacro::from_name(&name)
+    }
+
+    fn from_name(name: &str) -> Option<BuiltinMacro> {
+        let res = match name {
+            "line" => BuiltinMacro::Line,
+            "column" => BuiltinMacro::Column,
+            "file" => BuiltinMacro::File,
+            "stringify" => BuiltinMacro::Stringify,
+            "concat" => BuiltinMacro::Concat,
+            "env" => BuiltinMacro::Env,
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    /// Whether the macro expands to an `u32` literal, rather than to a string
+    /// literal.
+    pub(crate) fn expands_to_integer(self) -> bool {
+        match self {
+            BuiltinMacro::Line | BuiltinMacro::Column => true,
+            _ => false,
+        }
+    }
+
+    /// Expands the macro call with argument `arg`. Returns `None` if the
+    /// argument is malformed.
+    pub(crate) fn expand(self, call_site: &CallSite, arg: &tt::Subtree) -> Option<tt::Subtree> {
+        let text = match self {
+            BuiltinMacro::Line => format!("{}u32", call_site.line),
+            BuiltinMacro::Column => format!("{}u32", call_site.column),
+            BuiltinMacro::File => format!("{:?}", call_site.file),
+            BuiltinMacro::Stringify => {
+                let tokens = tt::Subtree {
+                    delimiter: tt::Delimiter::None,
+                    token_trees: arg.token_trees.clone(),
+                };
+                format!("{:?}", tokens.to_string())
+            }
+            BuiltinMacro::Concat => concat(call_site, arg)?,
+            BuiltinMacro::Env => {
+                // The optional second argument is the error message.
+                let var = match arg.token_trees.first() {
+                    Some(tt::TokenTree::Leaf(tt::Leaf::Literal(it))) => string_contents(it)?,
+                    _ => return None,
+                };
+                // The value of the variable at compile time is unknowable.
+                format!("\"${}\"", var)
+            }
+        };
+        let literal = tt::Leaf::from(tt::Literal { text: text.into() });
+        Some(tt::Subtree { delimiter: tt::Delimiter::None, token_trees: vec![literal.into()] })
+    }
+}
+
+/// Concatenates literals into a string literal. Escape sequences are kept as
+/// is, as they are valid in the resulting literal too.
+fn concat(call_site: &CallSite, arg: &tt::Subtree) -> Option<String> {
+    let mut buf = String::new();
+    let mut tokens = arg.token_trees.iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            tt::TokenTree::Leaf(tt::Leaf::Punct(p)) if p.char == ',' => continue,
+            tt::TokenTree::Leaf(tt::Leaf::Punct(p)) if p.char == '-' => buf.push('-'),
+            tt::TokenTree::Leaf(tt::Leaf::Literal(it)) => buf.push_str(&literal_contents(it)?),
+            tt::TokenTree::Leaf(tt::Leaf::Ident(it)) if it.text == "true" || it.text == "false" => {
+                buf.push_str(&it.text)
+            }
+            // A nested call of a built-in macro, like `env!("OUT_DIR")`.
+            tt::TokenTree::Leaf(tt::Leaf::Ident(it)) => {
+                let mac = BuiltinMacro::from_name(&it.text)?;
+                match tokens.next() {
+                    Some(tt::TokenTree::Leaf(tt::Leaf::Punct(p))) if p.char == '!' => (),
+                    _ => return None,
+                }
+                let nested_arg = match tokens.next() {
+                    Some(tt::TokenTree::Subtree(it)) => it,
+                    _ => return None,
+                };
+                let expansion = mac.expand(call_site, nested_arg)?;
+                match expansion.token_trees.as_slice() {
+                    [tt::TokenTree::Leaf(tt::Leaf::Literal(it))] => {
+                        buf.push_str(&literal_contents(it)?)
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(format!("\"{}\"", buf))
+}
+
+/// The text of a literal, as it should appear inside of a string literal.
+fn literal_contents(literal: &tt::Literal) -> Option<String> {
+    let text = literal.text.as_str();
+    if let Some(contents) = string_contents(literal) {
+        return Some(contents);
+    }
+    if text.starts_with('\'') && text.ends_with('\'') && text.len() >= 3 {
+        let contents = &text[1..text.len() - 1];
+        return Some(if contents == "\"" { "\\\"".to_string() } else { contents.to_string() });
+    }
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        let suffixes = [
+            "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+            "f32", "f64",
+        ];
+        let suffix = suffixes.iter().find(|&&it| text.ends_with(it));
+        return Some(text[..text.len() - suffix.map_or(0, |it| it.len())].to_string());
+    }
+    None
+}
+
+/// The text between the quotes of a (raw) string literal, escaped as in a
+/// non-raw string literal.
+fn string_contents(literal: &tt::Literal) -> Option<String> {
+    let text = literal.text.as_str();
+    if text.starts_with('"') && text.ends_with('"') && text.len() >= 2 {
+        return Some(text[1..text.len() - 1].to_string());
+    }
+    if text.starts_with('r') {
+        let contents = text[1..].trim_matches('#');
+        if contents.starts_with('"') && contents.ends_with('"') && contents.len() >= 2 {
+            let contents = &contents[1..contents.len() - 1];
+            return Some(contents.escape_default().to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::{SourceFile, AstNode, ast};
+
+    use super::*;
+
+    fn expand(text: &str) -> String {
+        let file = SourceFile::parse(text);
+        let macro_call = file.syntax().descendants().find_map(ast::MacroCall::cast).unwrap();
+        let path = macro_call.path().and_then(Path::from_ast).unwrap();
+        let mac = BuiltinMacro::from_path(&path).unwrap();
+        let (arg, _) = mbe::ast_to_token_tree(macro_call.token_tree().unwrap()).unwrap();
+        let call_site = CallSite::new(
+            "foo/bar.rs".to_string(),
+            text,
+            macro_call.syntax().range().start().to_usize(),
+        );
+        mac.expand(&call_site, &arg).map(|it| it.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn expand_builtin_macros() {
+        assert_eq!(expand("fn f() {\n    let x = line!();\n}"), "2u32");
+        assert_eq!(expand("fn f() {\n    let x = std::column!();\n}"), "13u32");
+        assert_eq!(expand("const F: &str = file!();"), r#""foo/bar.rs""#);
+        assert_eq!(
+            expand(r#"const S: &str = stringify!(a + b::c, "d");"#),
+            r#""a + b :: c , \"d\"""#
+        );
+        assert_eq!(
+            expand(r#"const S: &str = concat!("a\n", r"\b", 'c', 1u32, -2.5, true);"#),
+            r#""a\n\\bc1-2.5true""#
+        );
+        assert_eq!(
+            expand(r#"const S: &str = concat!(env!("OUT_DIR"), "/x.rs");"#),
+            r#""$OUT_DIR/x.rs""#
+        );
+        assert_eq!(expand(r#"const S: &str = env!("HOME");"#), r#""$HOME""#);
+        assert_eq!(expand("const S: &str = concat!(x);"), "");
+    }
+}
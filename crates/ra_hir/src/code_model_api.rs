@@ -1,22 +1,29 @@
 use std::sync::Arc;
 
 use relative_path::RelativePathBuf;
-use ra_db::{CrateId, SourceRootId, Edition};
-use ra_syntax::{ast::self, TreeArc, SyntaxNode};
+use ra_db::{CrateId, SourceRootId, Edition, FileRange};
+use ra_syntax::{
+    ast::{self, NameOwner},
+    TreeArc, SyntaxNode, SyntaxNodePtr, AstNode, AstPtr, TextRange,
+};
 
 use crate::{
-    Name, ScopesWithSourceMap, Ty, HirFileId,
+    Name, ScopesWithSourceMap, Ty, HirFileId, MacroCallId,
     HirDatabase, DefDatabase,
     type_ref::TypeRef,
     nameres::{ModuleScope, Namespace, ImportId, CrateModuleId},
-    expr::{Body, BodySourceMap},
+    expr::{Body, BodySourceMap, validation::{ExprValidator, validate_module_paths}},
     ty::InferenceResult,
-    adt::{EnumVariantId, StructFieldId, VariantDef},
+    adt::{EnumVariantId, StructFieldId, VariantDef, VariantKind},
     generics::GenericParams,
-    docs::{Documentation, Docs, docs_from_ast},
-    ids::{FunctionId, StructId, EnumId, AstItemDef, ConstId, StaticId, TraitId, TypeId},
-    impl_block::ImplBlock,
+    docs::{Documentation, Docs, docs_from_ast, inner_docs_from_ast},
+    ids::{
+        FunctionId, StructId, EnumId, AstItemDef, ConstId, StaticId, TraitId, TypeId, SourceItemId,
+    },
+    impl_block::{ImplBlock, ImplItem},
+    traits::{TraitData, TraitItem},
     resolve::Resolver,
+    diagnostics::{DiagnosticSink, MacroError, UnusedImport},
 };
 
 /// hir::Crate describes a single crate. It's the main interface with which
@@ -51,6 +58,16 @@ impl Crate {
         crate_graph.edition(self.crate_id)
     }
 
+    /// All crates of the crate graph, grouped so that crates in each group
+    /// depend only on crates from the previous groups.
+    pub fn dependency_waves(db: &impl DefDatabase) -> Vec<Vec<Crate>> {
+        let waves = db.crate_graph().dependency_waves();
+        waves
+            .into_iter()
+            .map(|wave| wave.into_iter().map(|crate_id| Crate { crate_id }).collect())
+            .collect()
+    }
+
     // FIXME: should this be in source_binder?
     pub fn source_root_crates(db: &impl DefDatabase, source_root: SourceRootId) -> Vec<Crate> {
         let crate_ids = db.source_root_crates(source_root);
@@ -90,14 +107,108 @@ impl_froms!(
     TypeAlias
 );
 
+impl ModuleDef {
+    /// Range of the definition's name in the original source file, suitable
+    /// for placing the cursor on. For definitions produced by macros, this is
+    /// the range of the macro call. `None` for the crate root module.
+    pub fn name_range(self, db: &impl HirDatabase) -> Option<FileRange> {
+        match self {
+            ModuleDef::Module(it) => {
+                let (file_id, node) = it.declaration_source(db)?;
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::Function(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::Struct(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::Enum(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::EnumVariant(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::Const(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::Static(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::Trait(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+            ModuleDef::TypeAlias(it) => {
+                let (file_id, node) = it.source(db);
+                name_range(db, file_id, &*node)
+            }
+        }
+    }
+}
+
+fn name_range(
+    db: &impl DefDatabase,
+    file_id: HirFileId,
+    node: &impl NameOwner,
+) -> Option<FileRange> {
+    let name = node.name()?;
+    Some(file_id.original_range(db, name.syntax().range()))
+}
+
 pub enum ModuleSource {
     SourceFile(TreeArc<ast::SourceFile>),
     Module(TreeArc<ast::Module>),
 }
 
+/// An item declared in a module: either a definition or an impl block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModuleItem {
+    Def(ModuleDef),
+    ImplBlock(ImplBlock),
+}
+impl_froms!(ModuleItem: ImplBlock);
+
+impl From<ModuleDef> for ModuleItem {
+    fn from(it: ModuleDef) -> ModuleItem {
+        ModuleItem::Def(it)
+    }
+}
+
+/// A module item with its location, as returned by
+/// `Module::declarations_in_order`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleItemSource {
+    pub item: ModuleItem,
+    /// The range of the whole item in the original source file. For items
+    /// produced by macros, this is the range of the macro call.
+    pub range: FileRange,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Problem {
-    UnresolvedModule { candidate: RelativePathBuf },
+    UnresolvedModule {
+        candidate: RelativePathBuf,
+    },
+    /// An item with the same name already exists in the same namespace of the
+    /// module. Items brought in by glob imports can be shadowed, so they never
+    /// cause this problem.
+    DuplicateDefinition {
+        name: Name,
+        namespace: Namespace,
+        first_definition: SourceItemId,
+    },
+    /// A `macro_rules` definition couldn't be parsed, or a macro call
+    /// couldn't be expanded.
+    MacroError {
+        message: String,
+    },
 }
 
 impl Module {
@@ -129,6 +240,19 @@ impl Module {
         self.import_source_impl(db, import)
     }
 
+    /// Returns the range of the use-tree leaf (or of the whole `extern crate`
+    /// item) corresponding to this import.
+    pub fn import_range(&self, db: &impl HirDatabase, import: ImportId) -> Option<TextRange> {
+        self.import_range_impl(db, import)
+    }
+
+    /// Returns the private imports of this module which no path in the module
+    /// or in its nested modules resolves through. Glob imports, imports of
+    /// traits and `extern crate`s are never reported.
+    pub fn unused_imports(&self, db: &impl HirDatabase) -> Vec<ImportId> {
+        self.unused_imports_impl(db)
+    }
+
     /// Returns the crate this module is part of.
     pub fn krate(&self, _db: &impl DefDatabase) -> Option<Crate> {
         Some(self.krate)
@@ -192,6 +316,61 @@ impl Module {
             .collect()
     }
 
+    /// Returns the items declared in this module, including impl blocks, in
+    /// the order they appear in the source. Items produced by a macro call
+    /// are ordered as the call, and then by their position in the expansion.
+    pub fn declarations_in_order(self, db: &impl HirDatabase) -> Vec<ModuleItemSource> {
+        self.declarations_in_order_impl(db)
+    }
+
+    /// Returns defs of this module which were produced by macro expansion,
+    /// grouped by the macro call they come from. Defs produced by nested
+    /// macro calls are attributed to the innermost call.
+    pub fn expanded_items(self, db: &impl HirDatabase) -> Vec<(MacroCallId, Vec<ModuleDef>)> {
+        let def_map = db.crate_def_map(self.krate);
+        def_map[self.module_id].expansions.clone()
+    }
+
+    /// Reports macros in this module which fail to expand, unused imports,
+    /// paths to private items, and semantic problems in the bodies of the
+    /// functions defined directly in this module, including methods.
+    pub fn diagnostics(self, db: &impl HirDatabase, sink: &mut DiagnosticSink) {
+        let (file, _) = self.definition_source(db);
+        for (node, problem) in self.problems(db) {
+            if let Problem::MacroError { message } = problem {
+                sink.push(MacroError { file, node: SyntaxNodePtr::new(&node), message });
+            }
+        }
+        for import in self.unused_imports(db) {
+            let range = match self.import_range(db, import) {
+                Some(it) => it,
+                None => continue,
+            };
+            let segment = self.import_source(db, import);
+            let use_tree = segment
+                .syntax()
+                .ancestors()
+                .filter_map(ast::UseTree::cast)
+                .find(|it| it.syntax().range() == range);
+            if let Some(use_tree) = use_tree {
+                sink.push(UnusedImport { file, use_tree: AstPtr::new(use_tree) });
+            }
+        }
+        validate_module_paths(self, db, sink);
+        for decl in self.declarations(db) {
+            if let ModuleDef::Function(f) = decl {
+                f.diagnostics(db, sink);
+            }
+        }
+        for impl_block in self.impl_blocks(db) {
+            for item in impl_block.items(db) {
+                if let ImplItem::Method(f) = item {
+                    f.diagnostics(db, sink);
+                }
+            }
+        }
+    }
+
     pub fn impl_blocks(self, db: &impl HirDatabase) -> Vec<ImplBlock> {
         let module_impl_blocks = db.impls_in_module(self);
         module_impl_blocks
@@ -203,8 +382,20 @@ impl Module {
 }
 
 impl Docs for Module {
+    /// Joins the docs on the `mod` declaration with the inner docs of the
+    /// module itself.
     fn docs(&self, db: &impl HirDatabase) -> Option<Documentation> {
-        self.declaration_source(db).and_then(|it| docs_from_ast(&*it.1))
+        let outer = self.declaration_source(db).and_then(|it| docs_from_ast(&*it.1));
+        let inner = match self.definition_source(db).1 {
+            ModuleSource::SourceFile(file) => inner_docs_from_ast(&*file),
+            ModuleSource::Module(module) => module.item_list().and_then(inner_docs_from_ast),
+        };
+        match (outer, inner) {
+            (Some(outer), Some(inner)) => {
+                Some(Documentation::new(&format!("{}\n{}", outer.contents(), inner.contents())))
+            }
+            (docs, None) | (None, docs) => docs,
+        }
     }
 }
 
@@ -233,6 +424,11 @@ impl StructField {
         db.type_for_field(*self)
     }
 
+    /// The declared type of the field.
+    pub fn type_ref(&self, db: &impl DefDatabase) -> TypeRef {
+        self.parent.variant_data(db).fields().unwrap()[self.id].type_ref.clone()
+    }
+
     pub fn parent_def(&self, _db: &impl HirDatabase) -> VariantDef {
         self.parent
     }
@@ -275,6 +471,10 @@ impl Struct {
             .collect()
     }
 
+    pub fn kind(&self, db: &impl DefDatabase) -> VariantKind {
+        db.struct_data(*self).variant_data.kind()
+    }
+
     pub fn field(&self, db: &impl HirDatabase, name: &Name) -> Option<StructField> {
         db.struct_data(*self)
             .variant_data
@@ -405,6 +605,10 @@ impl EnumVariant {
             .collect()
     }
 
+    pub fn kind(&self, db: &impl DefDatabase) -> VariantKind {
+        self.variant_data(db).kind()
+    }
+
     pub fn field(&self, db: &impl HirDatabase, name: &Name) -> Option<StructField> {
         self.variant_data(db)
             .fields()
@@ -500,6 +704,13 @@ impl Function {
         db.generic_params((*self).into())
     }
 
+    pub fn diagnostics(&self, db: &impl HirDatabase, sink: &mut DiagnosticSink) {
+        let infer = self.infer(db);
+        infer.add_diagnostics(db, *self, sink);
+        let mut validator = ExprValidator::new(*self, infer, sink);
+        validator.validate_body(db);
+    }
+
     /// The containing impl block, if this is a method.
     pub fn impl_block(&self, db: &impl DefDatabase) -> Option<ImplBlock> {
         let module_impls = db.impls_in_module(self.module(db));
@@ -545,6 +756,12 @@ impl Const {
         db.const_signature(*self)
     }
 
+    /// The initializer expression. It is taken from the source, so that the
+    /// signature doesn't change when the const moves in the file.
+    pub fn body(&self, db: &impl DefDatabase) -> Option<TreeArc<ast::Expr>> {
+        self.source(db).1.body().map(ast::Expr::to_owned)
+    }
+
     /// The containing impl block, if this is a method.
     pub fn impl_block(&self, db: &impl DefDatabase) -> Option<ImplBlock> {
         let module_impls = db.impls_in_module(self.module(db));
@@ -569,7 +786,7 @@ impl Docs for Const {
     }
 }
 
-/// The declared signature of a const.
+/// The declared signature of a const or a static.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConstSignature {
     pub(crate) name: Name,
@@ -604,6 +821,11 @@ impl Static {
         db.static_signature(*self)
     }
 
+    /// The initializer expression, see `Const::body`.
+    pub fn body(&self, db: &impl DefDatabase) -> Option<TreeArc<ast::Expr>> {
+        self.source(db).1.body().map(ast::Expr::to_owned)
+    }
+
     /// Builds a resolver for code inside this item.
     pub fn resolver(&self, db: &impl HirDatabase) -> Resolver {
         // take the outer scope...
@@ -634,6 +856,15 @@ impl Trait {
     pub fn generic_params(&self, db: &impl DefDatabase) -> Arc<GenericParams> {
         db.generic_params((*self).into())
     }
+
+    pub fn trait_data(&self, db: &impl DefDatabase) -> Arc<TraitData> {
+        db.trait_data(*self)
+    }
+
+    /// The associated functions, consts and types of the trait.
+    pub fn items(&self, db: &impl DefDatabase) -> Vec<TraitItem> {
+        self.trait_data(db).items().to_vec()
+    }
 }
 
 impl Docs for Trait {
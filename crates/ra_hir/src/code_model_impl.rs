@@ -1,4 +1,5 @@
 mod krate; // `crate` is invalid ident :(
 mod konst; // `const` is invalid ident :(
 mod module;
+mod used_imports;
 pub(crate) mod function;
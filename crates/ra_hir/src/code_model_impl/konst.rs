@@ -32,3 +32,44 @@ impl ConstSignature {
         const_signature_for(&*node)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::AstNode;
+
+    use crate::{ModuleDef, mock::MockDatabase, source_binder, type_ref::TypeRef};
+
+    fn path_name(type_ref: &TypeRef) -> String {
+        match type_ref {
+            TypeRef::Path(path) => path.as_ident().unwrap().to_string(),
+            TypeRef::Reference(inner, _) => format!("&{}", path_name(inner)),
+            it => panic!("unexpected type: {:?}", it),
+        }
+    }
+
+    #[test]
+    fn lowers_const_and_static_signatures() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            const MAX: u32 = 92;
+            static NAME: &str = \"foo\";
+            ",
+        );
+        let module = source_binder::module_from_file_id(&db, file_id).unwrap();
+        let mut sigs = module
+            .declarations(&db)
+            .into_iter()
+            .filter_map(|def| match def {
+                ModuleDef::Const(it) => Some((it.signature(&db), it.body(&db))),
+                ModuleDef::Static(it) => Some((it.signature(&db), it.body(&db))),
+                _ => None,
+            })
+            .map(|(sig, body)| {
+                let body = body.unwrap().syntax().text().to_string();
+                format!("{}: {} = {}", sig.name(), path_name(sig.type_ref()), body)
+            })
+            .collect::<Vec<_>>();
+        sigs.sort();
+        assert_eq!(sigs, ["MAX: u32 = 92", "NAME: &str = \"foo\""]);
+    }
+}
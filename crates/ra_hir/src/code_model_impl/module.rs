@@ -1,8 +1,8 @@
 use ra_db::FileId;
-use ra_syntax::{ast, SyntaxNode, TreeArc, AstNode};
+use ra_syntax::{ast, SyntaxNode, TreeArc, AstNode, TextRange};
 
 use crate::{
-    Module, ModuleSource, Problem, Name,
+    Module, ModuleSource, ModuleDef, ModuleItem, ModuleItemSource, Problem, Name,
     nameres::{CrateModuleId, ImportId},
     HirDatabase, DefDatabase,
     HirFileId, SourceItemId,
@@ -80,6 +80,26 @@ impl Module {
         source_map.get(&source, import)
     }
 
+    pub(crate) fn import_range_impl(
+        &self,
+        db: &impl HirDatabase,
+        import: ImportId,
+    ) -> Option<TextRange> {
+        // The ids of imports from macro expansions index the raw items of the
+        // expansion, which has no source map.
+        let def_map = db.crate_def_map(self.krate);
+        if def_map.is_macro_import(self.module_id, import) {
+            return None;
+        }
+        let (file_id, _) = self.definition_source(db);
+        let original_file = file_id.original_file(db);
+        if file_id != original_file.into() {
+            return None;
+        }
+        let (_, source_map) = db.raw_items_with_source_map(original_file);
+        source_map.range(import)
+    }
+
     pub(crate) fn crate_root_impl(&self, db: &impl DefDatabase) -> Module {
         let def_map = db.crate_def_map(self.krate);
         self.with_module_id(def_map.root())
@@ -123,4 +143,80 @@ impl Module {
             .map(|(source_item_id, problem)| (db.file_item(*source_item_id), problem.clone()))
             .collect()
     }
+
+    pub(crate) fn declarations_in_order_impl(
+        &self,
+        db: &impl HirDatabase,
+    ) -> Vec<ModuleItemSource> {
+        let mut items: Vec<ModuleItem> = Vec::new();
+        for def in self.declarations(db) {
+            // Unit and tuple structs are in both namespaces.
+            if !items.contains(&def.into()) {
+                items.push(def.into());
+            }
+        }
+        items.extend(self.impl_blocks(db).into_iter().map(ModuleItem::from));
+
+        let mut res = items
+            .into_iter()
+            .filter_map(|item| {
+                let (file_id, range) = item_source_range(db, item)?;
+                let original = file_id.original_range(db, range);
+                Some(((original.range.start(), range.start()), item, original))
+            })
+            .collect::<Vec<_>>();
+        res.sort_by_key(|(key, _, _)| *key);
+        res.into_iter().map(|(_, item, range)| ModuleItemSource { item, range }).collect()
+    }
+}
+
+fn item_source_range(db: &impl HirDatabase, item: ModuleItem) -> Option<(HirFileId, TextRange)> {
+    fn range(file_id: HirFileId, node: &impl AstNode) -> Option<(HirFileId, TextRange)> {
+        Some((file_id, node.syntax().range()))
+    }
+    let def = match item {
+        ModuleItem::Def(it) => it,
+        ModuleItem::ImplBlock(it) => {
+            let (file_id, node) = it.source(db);
+            return range(file_id, &*node);
+        }
+    };
+    match def {
+        ModuleDef::Module(it) => {
+            let (file_id, node) = it.declaration_source(db)?;
+            range(file_id, &*node)
+        }
+        ModuleDef::Function(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::Struct(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::Enum(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::EnumVariant(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::Const(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::Static(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::Trait(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+        ModuleDef::TypeAlias(it) => {
+            let (file_id, node) = it.source(db);
+            range(file_id, &*node)
+        }
+    }
 }
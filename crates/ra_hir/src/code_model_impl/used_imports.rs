@@ -0,0 +1,344 @@
+//! Finds the imports of a module which are never used.
+//!
+//! An import is used if a path resolves through it: a path in an item
+//! signature, in a function body, or in another import. Private imports are
+//! visible in the nested modules, so their paths are checked as well. The
+//! walk stops as soon as all the imports are known to be used.
+
+use std::sync::Arc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use ra_syntax::{
+    AstNode, SyntaxNode,
+    SyntaxKind::{IDENT, TOKEN_TREE},
+    ast,
+};
+
+use crate::{
+    Module, ModuleDef, ModuleSource, Function, HirDatabase, Name, KnownName, Path, Resolution,
+    ImplItem, TraitItem,
+    nameres::{CrateDefMap, CrateModuleId, ImportId, Visibility},
+    expr::{Body, Expr, Pat, Statement, resolver_for_expr},
+    adt::VariantDef,
+    path::GenericArg,
+    type_ref::TypeRef,
+};
+
+impl Module {
+    pub(crate) fn unused_imports_impl(&self, db: &impl HirDatabase) -> Vec<ImportId> {
+        let def_map = db.crate_def_map(self.krate);
+        let (file_id, _) = self.definition_source(db);
+        let raw_items = db.raw_items(file_id.original_file(db));
+        let mut candidates = Vec::new();
+        let mut names = FxHashMap::default();
+        for (name, resolution) in def_map[self.module_id].scope.entries_in_source_order() {
+            let import = match resolution.import {
+                Some(it) => it,
+                None => continue,
+            };
+            // Re-exports are used by definition.
+            if resolution.visibility != Visibility::Module(*self) {
+                continue;
+            }
+            // Traits can be used implicitly, by method calls.
+            if let Some(ModuleDef::Trait(_)) = resolution.def.types {
+                continue;
+            }
+            if def_map.is_macro_import(self.module_id, import) {
+                continue;
+            }
+            let data = &raw_items[import];
+            if data.is_glob || data.is_extern_crate {
+                continue;
+            }
+            // A single import may introduce several names, like a unit struct
+            // in both namespaces, and is used if any of them is.
+            names.insert(name.to_string(), import);
+            if !candidates.contains(&import) {
+                candidates.push(import);
+            }
+        }
+        if candidates.is_empty() {
+            return candidates;
+        }
+
+        let mut collector = UsedImports {
+            db,
+            def_map: Arc::clone(&def_map),
+            target: self.module_id,
+            module_id: self.module_id,
+            unused: candidates.iter().cloned().collect(),
+            names,
+            followed_globs: FxHashSet::default(),
+        };
+        collector.collect_module(*self);
+        candidates.retain(|import| collector.unused.contains(import));
+        candidates
+    }
+}
+
+struct UsedImports<'a, DB> {
+    db: &'a DB,
+    def_map: Arc<CrateDefMap>,
+    /// The module whose imports are checked.
+    target: CrateModuleId,
+    /// The module whose paths are being collected.
+    module_id: CrateModuleId,
+    /// The imports of `target` which are not used so far.
+    unused: FxHashSet<ImportId>,
+    /// The names introduced by the imports of `target`.
+    names: FxHashMap<String, ImportId>,
+    /// Glob imports, together with the names they were used for.
+    followed_globs: FxHashSet<(CrateModuleId, ImportId, Name)>,
+}
+
+impl<'a, DB: HirDatabase> UsedImports<'a, DB> {
+    fn collect_module(&mut self, module: Module) {
+        let db = self.db;
+        self.module_id = module.module_id;
+        self.collect_syntax(module);
+        self.collect_imports(module);
+        for decl in module.declarations(db) {
+            if self.unused.is_empty() {
+                return;
+            }
+            match decl {
+                ModuleDef::Function(it) => self.collect_function(it),
+                ModuleDef::Struct(it) => self.collect_variant(it.into()),
+                ModuleDef::Enum(it) => {
+                    for variant in it.variants(db) {
+                        self.collect_variant(variant.into());
+                    }
+                }
+                ModuleDef::Const(it) => self.collect_type_ref(it.signature(db).type_ref()),
+                ModuleDef::Static(it) => self.collect_type_ref(it.signature(db).type_ref()),
+                ModuleDef::TypeAlias(it) => self.collect_type_ref(&it.type_ref(db)),
+                ModuleDef::Trait(it) => {
+                    for item in it.items(db) {
+                        match item {
+                            TraitItem::Function(it) => self.collect_function(it),
+                            TraitItem::Const(it) => {
+                                self.collect_type_ref(it.signature(db).type_ref())
+                            }
+                            TraitItem::TypeAlias(it) => self.collect_type_ref(&it.type_ref(db)),
+                        }
+                    }
+                }
+                ModuleDef::Module(_) | ModuleDef::EnumVariant(_) => (),
+            }
+        }
+        for impl_block in module.impl_blocks(db) {
+            if self.unused.is_empty() {
+                return;
+            }
+            self.collect_type_ref(&impl_block.target_type(db));
+            if let Some(trait_ref) = impl_block.target_trait_ref(db) {
+                self.collect_type_ref(&trait_ref);
+            }
+            for item in impl_block.items(db) {
+                match item {
+                    ImplItem::Method(it) => self.collect_function(it),
+                    ImplItem::Const(it) => self.collect_type_ref(it.signature(db).type_ref()),
+                    ImplItem::TypeAlias(it) => self.collect_type_ref(&it.type_ref(db)),
+                }
+            }
+        }
+        for child in module.children(db) {
+            if self.unused.is_empty() {
+                return;
+            }
+            self.collect_module(child);
+        }
+    }
+
+    /// HIR doesn't keep the bounds of generic parameters, where clauses,
+    /// `impl Trait` and `dyn Trait` types, so the type paths are collected
+    /// from the syntax as well.
+    fn collect_syntax(&mut self, module: Module) {
+        let (_, source) = module.definition_source(self.db);
+        let node = match &source {
+            ModuleSource::SourceFile(it) => it.syntax(),
+            ModuleSource::Module(it) => it.syntax(),
+        };
+        self.collect_syntax_node(node);
+    }
+
+    fn collect_syntax_node(&mut self, node: &SyntaxNode) {
+        for child in node.children() {
+            // Inline modules are collected with their own scopes.
+            if ast::Module::cast(child).and_then(|it| it.item_list()).is_some() {
+                continue;
+            }
+            // Macro definitions are checked where they are expanded.
+            let macro_name = ast::MacroCall::cast(child)
+                .and_then(|it| it.path())
+                .and_then(Path::from_ast)
+                .and_then(|it| it.as_ident().and_then(Name::as_known_name));
+            if macro_name == Some(KnownName::MacroRules) {
+                continue;
+            }
+            let path = ast::PathType::cast(child).and_then(|it| it.path()).and_then(Path::from_ast);
+            if let Some(path) = path {
+                self.collect_path(&path);
+            }
+            // Macro calls and `derive` attributes are not expanded, so the
+            // identifiers in their arguments are assumed to use the imports
+            // of the same names.
+            if child.kind() == IDENT && node.kind() == TOKEN_TREE {
+                if let Some(&import) = child.leaf_text().and_then(|it| self.names.get(it.as_str()))
+                {
+                    self.unused.remove(&import);
+                }
+            }
+            self.collect_syntax_node(child);
+        }
+    }
+
+    /// Imports can be resolved through other imports, as in `use foo::bar;
+    /// use bar::Baz;`.
+    fn collect_imports(&mut self, module: Module) {
+        let db = self.db;
+        let (file_id, _) = module.definition_source(db);
+        let raw_items = db.raw_items(file_id.original_file(db));
+        let imports = self.def_map[module.module_id]
+            .scope
+            .entries()
+            .filter_map(|(_, resolution)| resolution.import)
+            .filter(|&import| !self.def_map.is_macro_import(module.module_id, import))
+            .collect::<FxHashSet<_>>();
+        for import in imports {
+            let data = &raw_items[import];
+            if data.is_extern_crate {
+                continue;
+            }
+            let used = self.def_map.imports_used_by_path(db, module.module_id, &data.path, true);
+            for (module_id, used_import, name) in used {
+                // `use self::foo` would otherwise use itself.
+                if (module_id, used_import) != (module.module_id, import) {
+                    self.mark_used(module_id, used_import, name);
+                }
+            }
+        }
+    }
+
+    fn collect_function(&mut self, func: Function) {
+        let db = self.db;
+        let signature = func.signature(db);
+        for type_ref in signature.params().iter().chain(Some(signature.ret_type())) {
+            self.collect_type_ref(type_ref);
+        }
+        if self.unused.is_empty() {
+            return;
+        }
+        let body = func.body(db);
+        for (id, expr) in body.exprs() {
+            match expr {
+                Expr::Path(path) => {
+                    // Locals shadow the imports.
+                    let resolver = resolver_for_expr(Arc::clone(&body), db, id);
+                    match resolver.resolve_path(db, path).take_values() {
+                        Some(Resolution::LocalBinding(_)) => (),
+                        _ => self.collect_path(path),
+                    }
+                }
+                Expr::StructLit { path: Some(path), .. } => self.collect_path(path),
+                Expr::Cast { type_ref, .. } => self.collect_type_ref(type_ref),
+                Expr::Lambda { arg_types, .. } => {
+                    for type_ref in arg_types.iter().filter_map(Option::as_ref) {
+                        self.collect_type_ref(type_ref);
+                    }
+                }
+                Expr::Block { statements, .. } => {
+                    for statement in statements {
+                        if let Statement::Let { type_ref: Some(type_ref), .. } = statement {
+                            self.collect_type_ref(type_ref);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.collect_pats(&body);
+    }
+
+    fn collect_pats(&mut self, body: &Body) {
+        for (_, pat) in body.pats() {
+            match pat {
+                Pat::Path(path)
+                | Pat::TupleStruct { path: Some(path), .. }
+                | Pat::Struct { path: Some(path), .. } => self.collect_path(path),
+                _ => (),
+            }
+        }
+    }
+
+    fn collect_variant(&mut self, variant: VariantDef) {
+        let variant_data = variant.variant_data(self.db);
+        for (_, field) in variant_data.fields().into_iter().flat_map(|it| it.iter()) {
+            self.collect_type_ref(&field.type_ref);
+        }
+    }
+
+    fn collect_type_ref(&mut self, type_ref: &TypeRef) {
+        match type_ref {
+            TypeRef::Path(path) => self.collect_path(path),
+            TypeRef::Tuple(type_refs) | TypeRef::Fn(type_refs) => {
+                for type_ref in type_refs {
+                    self.collect_type_ref(type_ref);
+                }
+            }
+            TypeRef::RawPtr(type_ref, _)
+            | TypeRef::Reference(type_ref, _)
+            | TypeRef::Array(type_ref)
+            | TypeRef::Slice(type_ref) => self.collect_type_ref(type_ref),
+            TypeRef::Never | TypeRef::Placeholder | TypeRef::Error => (),
+        }
+    }
+
+    fn collect_path(&mut self, path: &Path) {
+        let used = self.def_map.imports_used_by_path(self.db, self.module_id, path, false);
+        for (module_id, import, name) in used {
+            self.mark_used(module_id, import, name);
+        }
+        let args = path.segments.iter().filter_map(|it| it.args_and_bindings.as_ref());
+        for args in args {
+            for GenericArg::Type(type_ref) in args.args.iter() {
+                self.collect_type_ref(type_ref);
+            }
+        }
+    }
+
+    /// Marks the import as used. A name found through a glob import, as in
+    /// `mod tests { use super::*; }`, also uses the import which brought the
+    /// name into the scope of the glob's module.
+    fn mark_used(&mut self, module_id: CrateModuleId, import: ImportId, name: Name) {
+        if module_id == self.target {
+            self.unused.remove(&import);
+        }
+        if self.def_map.is_macro_import(module_id, import) {
+            return;
+        }
+        let module = self.def_map.mk_module(module_id);
+        let (file_id, _) = module.definition_source(self.db);
+        let raw_items = self.db.raw_items(file_id.original_file(self.db));
+        if !raw_items[import].is_glob
+            || !self.followed_globs.insert((module_id, import, name.clone()))
+        {
+            return;
+        }
+        let glob_source =
+            self.def_map.resolve_import_path(self.db, module_id, &raw_items[import].path);
+        let glob_source = match glob_source.take_types() {
+            Some(ModuleDef::Module(it)) if it.krate == module.krate => it,
+            _ => return,
+        };
+        let source_import = self.def_map[glob_source.module_id]
+            .scope
+            .get(&name)
+            .filter(|it| !it.def.is_none())
+            .and_then(|it| it.import);
+        if let Some(source_import) = source_import {
+            self.mark_used(glob_source.module_id, source_import, name);
+        }
+    }
+}
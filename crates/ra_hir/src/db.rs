@@ -4,14 +4,15 @@ use ra_syntax::{SyntaxNode, TreeArc, SourceFile};
 use ra_db::{SourceDatabase, salsa, FileId};
 
 use crate::{
-    HirFileId, SourceFileItems, SourceItemId, Crate, Module, HirInterner,
-    Function, FnSignature, ExprScopes, TypeAlias,
+    HirFileId, MacroCallId, SourceFileItems, SourceItemId, Crate, Module, HirInterner,
+    Function, FnSignature, ExprScopes, TypeAlias, Trait,
     Struct, Enum, StructField,
     Const, ConstSignature, Static,
     nameres::{Namespace, ImportSourceMap, RawItems, CrateDefMap},
     ty::{InferenceResult, Ty, method_resolution::CrateImplBlocks, TypableDef, CallableDef, FnSig},
-    adt::{StructData, EnumData},
-    impl_block::{ModuleImplBlocks, ImplSourceMap},
+    adt::{AdtDef, StructData, EnumData},
+    impl_block::{ImplBlock, ModuleImplBlocks, ImplSourceMap},
+    traits::TraitData,
     generics::{GenericParams, GenericDef},
     type_ref::TypeRef,
 };
@@ -21,12 +22,18 @@ pub trait DefDatabase: SourceDatabase + AsRef<HirInterner> {
     #[salsa::invoke(HirFileId::hir_parse)]
     fn hir_parse(&self, file_id: HirFileId) -> TreeArc<SourceFile>;
 
+    #[salsa::invoke(crate::ids::macro_expand_query)]
+    fn macro_expand(&self, macro_call: MacroCallId) -> Option<Arc<tt::Subtree>>;
+
     #[salsa::invoke(crate::adt::StructData::struct_data_query)]
     fn struct_data(&self, s: Struct) -> Arc<StructData>;
 
     #[salsa::invoke(crate::adt::EnumData::enum_data_query)]
     fn enum_data(&self, e: Enum) -> Arc<EnumData>;
 
+    #[salsa::invoke(crate::traits::TraitData::trait_data_query)]
+    fn trait_data(&self, t: Trait) -> Arc<TraitData>;
+
     #[salsa::invoke(crate::ids::SourceFileItems::file_items_query)]
     fn file_items(&self, file_id: HirFileId) -> Arc<SourceFileItems>;
 
@@ -98,6 +105,12 @@ pub trait HirDatabase: DefDatabase {
 
     #[salsa::invoke(crate::ty::method_resolution::CrateImplBlocks::impls_in_crate_query)]
     fn impls_in_crate(&self, krate: Crate) -> Arc<CrateImplBlocks>;
+
+    #[salsa::invoke(crate::ty::method_resolution::impls_for_trait_query)]
+    fn impls_for_trait(&self, tr: Trait) -> Arc<Vec<ImplBlock>>;
+
+    #[salsa::invoke(crate::ty::method_resolution::impls_for_type_query)]
+    fn impls_for_type(&self, adt: AdtDef) -> Arc<Vec<ImplBlock>>;
 }
 
 #[test]
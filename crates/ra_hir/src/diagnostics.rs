@@ -0,0 +1,221 @@
+//! Semantic errors and warnings, found while lowering and checking the code.
+//!
+//! HIR doesn't know how to present or fix the problems it finds, that's the
+//! job of the IDE layer. Instead, diagnostics are pushed into a
+//! `DiagnosticSink`, which dispatches them to callbacks registered for the
+//! concrete diagnostic types.
+
+use std::{any::Any, fmt};
+
+use ra_syntax::{SyntaxNode, SyntaxNodePtr, TextRange, TreeArc, AstPtr, ast};
+
+use crate::{HirFileId, HirDatabase, Name, Ty, Enum, EnumVariant, ModuleDef, adt::VariantDef};
+
+pub trait Diagnostic: Any + Send + Sync + fmt::Debug + 'static {
+    fn message(&self) -> String;
+    fn file(&self) -> HirFileId;
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr;
+    fn highlight_range(&self) -> TextRange {
+        self.syntax_node_ptr().range()
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static);
+}
+
+impl dyn Diagnostic {
+    pub fn syntax_node(&self, db: &impl HirDatabase) -> TreeArc<SyntaxNode> {
+        let source_file = db.hir_parse(self.file());
+        self.syntax_node_ptr().to_node(&source_file).to_owned()
+    }
+
+    pub fn downcast_ref<D: Diagnostic>(&self) -> Option<&D> {
+        self.as_any().downcast_ref()
+    }
+}
+
+pub struct DiagnosticSink<'a> {
+    callbacks: Vec<Box<dyn FnMut(&dyn Diagnostic) -> Result<(), ()> + 'a>>,
+    default_callback: Box<dyn FnMut(&dyn Diagnostic) + 'a>,
+}
+
+impl<'a> DiagnosticSink<'a> {
+    /// Creates a sink which passes all the diagnostics without a more
+    /// specific callback to `cb`.
+    pub fn new(cb: impl FnMut(&dyn Diagnostic) + 'a) -> DiagnosticSink<'a> {
+        DiagnosticSink { callbacks: Vec::new(), default_callback: Box::new(cb) }
+    }
+
+    /// Registers a callback for diagnostics of type `D`. Callbacks registered
+    /// earlier take precedence.
+    pub fn on<D: Diagnostic, F: FnMut(&D) + 'a>(mut self, mut cb: F) -> DiagnosticSink<'a> {
+        let cb = move |diag: &dyn Diagnostic| match diag.downcast_ref::<D>() {
+            Some(d) => {
+                cb(d);
+                Ok(())
+            }
+            None => Err(()),
+        };
+        self.callbacks.push(Box::new(cb));
+        self
+    }
+
+    pub(crate) fn push(&mut self, d: impl Diagnostic) {
+        let d: &dyn Diagnostic = &d;
+        for cb in self.callbacks.iter_mut() {
+            if cb(d).is_ok() {
+                return;
+            }
+        }
+        (self.default_callback)(d)
+    }
+}
+
+/// A `macro_rules` definition couldn't be parsed, or a macro call couldn't be
+/// expanded.
+#[derive(Debug)]
+pub struct MacroError {
+    pub file: HirFileId,
+    /// The macro call or the `macro_rules` definition.
+    pub node: SyntaxNodePtr,
+    pub message: String,
+}
+
+impl Diagnostic for MacroError {
+    fn message(&self) -> String {
+        self.message.clone()
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.node
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+/// A struct literal mentions a field which the struct doesn't have.
+#[derive(Debug)]
+pub struct NoSuchField {
+    pub file: HirFileId,
+    pub field: AstPtr<ast::NamedField>,
+    /// The struct or the enum variant the literal constructs.
+    pub variant: VariantDef,
+    /// The type of the value the field is initialized with.
+    pub ty: Ty,
+}
+
+impl Diagnostic for NoSuchField {
+    fn message(&self) -> String {
+        "no such field".to_string()
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.field.syntax_node_ptr()
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+/// A `match` over an enum has no arms for some of the enum's variants, and no
+/// catch-all arm.
+#[derive(Debug)]
+pub struct MissingMatchArms {
+    pub file: HirFileId,
+    pub match_expr: SyntaxNodePtr,
+    pub enum_: Enum,
+    pub missing: Vec<EnumVariant>,
+}
+
+impl Diagnostic for MissingMatchArms {
+    fn message(&self) -> String {
+        "missing match arms".to_string()
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.match_expr
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+/// A struct literal without a `..base` doesn't initialize some of the fields.
+#[derive(Debug)]
+pub struct MissingFields {
+    pub file: HirFileId,
+    pub field_list: AstPtr<ast::NamedFieldList>,
+    pub missed_fields: Vec<Name>,
+}
+
+impl Diagnostic for MissingFields {
+    fn message(&self) -> String {
+        "missing structure fields".to_string()
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.field_list.syntax_node_ptr()
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+/// A private import which no path in the module, or in its nested modules,
+/// resolves through.
+#[derive(Debug)]
+pub struct UnusedImport {
+    pub file: HirFileId,
+    /// The leaf of the use tree which produced the import.
+    pub use_tree: AstPtr<ast::UseTree>,
+}
+
+impl Diagnostic for UnusedImport {
+    fn message(&self) -> String {
+        "unused import".to_string()
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.use_tree.syntax_node_ptr()
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+/// A path goes through an item which is not visible from the module the path
+/// is written in.
+#[derive(Debug)]
+pub struct PrivateItem {
+    pub file: HirFileId,
+    /// The segment of the path naming the item.
+    pub segment: AstPtr<ast::PathSegment>,
+    pub name: Name,
+    /// The item to make visible, or `None` if the item is in another crate or
+    /// the name comes from a private import.
+    pub item: Option<ModuleDef>,
+}
+
+impl Diagnostic for PrivateItem {
+    fn message(&self) -> String {
+        format!("`{}` is private", self.name)
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.segment.syntax_node_ptr()
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
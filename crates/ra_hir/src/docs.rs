@@ -27,5 +27,27 @@ pub trait Docs {
 }
 
 pub(crate) fn docs_from_ast(node: &impl ast::DocCommentsOwner) -> Option<Documentation> {
-    node.doc_comment_text().map(|it| Documentation::new(&it))
+    node.doc_comment_text().map(|it| Documentation::new(&unindent(&it)))
+}
+
+pub(crate) fn inner_docs_from_ast(node: &impl ast::InnerDocCommentsOwner) -> Option<Documentation> {
+    node.inner_doc_comment_text().map(|it| Documentation::new(&unindent(&it)))
+}
+
+/// Removes the indentation common to all non-blank lines, like rustdoc does.
+fn unindent(text: &str) -> String {
+    let indent_of = |line: &str| line.len() - line.trim_start_matches(&[' ', '\t'][..]).len();
+    let indent = text.lines().filter(|it| !it.trim().is_empty()).map(indent_of).min().unwrap_or(0);
+    text.lines().map(|line| line.get(indent..).unwrap_or("")).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unindent() {
+        assert_eq!(unindent("  foo\n\n    bar\n  baz"), "foo\n\n  bar\nbaz");
+        assert_eq!(unindent("foo\n  bar"), "foo\n  bar");
+    }
 }
@@ -5,7 +5,7 @@ use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
 use ra_syntax::{
-    SyntaxNodePtr, AstNode,
+    SyntaxNodePtr, AstPtr, AstNode,
     ast::{self, LoopBodyOwner, ArgListOwner, NameOwner, LiteralFlavor, TypeAscriptionOwner}
 };
 
@@ -13,12 +13,15 @@ use crate::{
     Path, Name, HirDatabase, Function, Resolver,
     name::AsName,
     type_ref::{Mutability, TypeRef},
+    builtin_macro::BuiltinMacro,
+    hygiene::Hygiene,
 };
 use crate::{ path::GenericArgs, ty::primitive::{IntTy, UncertainIntTy, FloatTy, UncertainFloatTy}};
 
 pub use self::scope::{ExprScopes, ScopesWithSourceMap, ScopeEntryWithSyntax};
 
 pub(crate) mod scope;
+pub(crate) mod validation;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExprId(RawId);
@@ -54,6 +57,7 @@ pub struct BodySourceMap {
     expr_map_back: ArenaMap<ExprId, SyntaxNodePtr>,
     pat_map: FxHashMap<SyntaxNodePtr, PatId>,
     pat_map_back: ArenaMap<PatId, SyntaxNodePtr>,
+    field_map: FxHashMap<(ExprId, usize), AstPtr<ast::NamedField>>,
 }
 
 impl Body {
@@ -138,6 +142,11 @@ impl BodySourceMap {
     pub fn node_pat(&self, node: &ast::Pat) -> Option<PatId> {
         self.pat_map.get(&SyntaxNodePtr::new(node.syntax())).cloned()
     }
+
+    /// The syntax of the `field`-th field of a struct literal.
+    pub fn field_syntax(&self, expr: ExprId, field: usize) -> AstPtr<ast::NamedField> {
+        self.field_map[&(expr, field)]
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -166,15 +175,18 @@ pub enum Expr {
     },
     Loop {
         body: ExprId,
+        label: Option<Name>,
     },
     While {
         condition: ExprId,
         body: ExprId,
+        label: Option<Name>,
     },
     For {
         iterable: ExprId,
         pat: PatId,
         body: ExprId,
+        label: Option<Name>,
     },
     Call {
         callee: ExprId,
@@ -190,9 +202,12 @@ pub enum Expr {
         expr: ExprId,
         arms: Vec<MatchArm>,
     },
-    Continue,
+    Continue {
+        label: Option<Name>,
+    },
     Break {
         expr: Option<ExprId>,
+        label: Option<Name>,
     },
     Return {
         expr: Option<ExprId>,
@@ -289,8 +304,8 @@ impl Expr {
                     f(*expr);
                 }
             }
-            Expr::Loop { body } => f(*body),
-            Expr::While { condition, body } => {
+            Expr::Loop { body, .. } => f(*body),
+            Expr::While { condition, body, .. } => {
                 f(*condition);
                 f(*body);
             }
@@ -316,8 +331,8 @@ impl Expr {
                     f(arm.expr);
                 }
             }
-            Expr::Continue => {}
-            Expr::Break { expr } | Expr::Return { expr } => {
+            Expr::Continue { .. } => {}
+            Expr::Break { expr, .. } | Expr::Return { expr } => {
                 if let Some(expr) = expr {
                     f(*expr);
                 }
@@ -460,6 +475,7 @@ impl Pat {
 
 struct ExprCollector {
     owner: Function,
+    hygiene: Hygiene,
     exprs: Arena<ExprId, Expr>,
     pats: Arena<PatId, Pat>,
     source_map: BodySourceMap,
@@ -468,9 +484,10 @@ struct ExprCollector {
 }
 
 impl ExprCollector {
-    fn new(owner: Function) -> Self {
+    fn new(owner: Function, hygiene: Hygiene) -> Self {
         ExprCollector {
             owner,
+            hygiene,
             exprs: Arena::default(),
             pats: Arena::default(),
             source_map: BodySourceMap::default(),
@@ -540,7 +557,8 @@ impl ExprCollector {
             ast::ExprKind::BlockExpr(e) => self.collect_block_opt(e.block()),
             ast::ExprKind::LoopExpr(e) => {
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::Loop { body }, syntax_ptr)
+                let label = e.label().and_then(|it| it.lifetime()).map(|it| self.lifetime_name(it));
+                self.alloc_expr(Expr::Loop { body, label }, syntax_ptr)
             }
             ast::ExprKind::WhileExpr(e) => {
                 let condition = if let Some(condition) = e.condition() {
@@ -554,13 +572,15 @@ impl ExprCollector {
                     self.exprs.alloc(Expr::Missing)
                 };
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::While { condition, body }, syntax_ptr)
+                let label = e.label().and_then(|it| it.lifetime()).map(|it| self.lifetime_name(it));
+                self.alloc_expr(Expr::While { condition, body, label }, syntax_ptr)
             }
             ast::ExprKind::ForExpr(e) => {
                 let iterable = self.collect_expr_opt(e.iterable());
                 let pat = self.collect_pat_opt(e.pat());
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::For { iterable, pat, body }, syntax_ptr)
+                let label = e.label().and_then(|it| it.lifetime()).map(|it| self.lifetime_name(it));
+                self.alloc_expr(Expr::For { iterable, pat, body, label }, syntax_ptr)
             }
             ast::ExprKind::CallExpr(e) => {
                 let callee = self.collect_expr_opt(e.expr());
@@ -605,17 +625,21 @@ impl ExprCollector {
                 self.alloc_expr(Expr::Match { expr, arms }, syntax_ptr)
             }
             ast::ExprKind::PathExpr(e) => {
-                let path =
-                    e.path().and_then(Path::from_ast).map(Expr::Path).unwrap_or(Expr::Missing);
+                let path = e
+                    .path()
+                    .and_then(|it| self.collect_path(it))
+                    .map(Expr::Path)
+                    .unwrap_or(Expr::Missing);
                 self.alloc_expr(path, syntax_ptr)
             }
-            ast::ExprKind::ContinueExpr(_e) => {
-                // FIXME: labels
-                self.alloc_expr(Expr::Continue, syntax_ptr)
+            ast::ExprKind::ContinueExpr(e) => {
+                let label = e.lifetime().map(|it| self.lifetime_name(it));
+                self.alloc_expr(Expr::Continue { label }, syntax_ptr)
             }
             ast::ExprKind::BreakExpr(e) => {
                 let expr = e.expr().map(|e| self.collect_expr(e));
-                self.alloc_expr(Expr::Break { expr }, syntax_ptr)
+                let label = e.lifetime().map(|it| self.lifetime_name(it));
+                self.alloc_expr(Expr::Break { expr, label }, syntax_ptr)
             }
             ast::ExprKind::ParenExpr(e) => {
                 let inner = self.collect_expr_opt(e.expr());
@@ -629,8 +653,10 @@ impl ExprCollector {
             }
             ast::ExprKind::StructLit(e) => {
                 let path = e.path().and_then(Path::from_ast);
+                let mut field_ptrs = Vec::new();
                 let fields = if let Some(nfl) = e.named_field_list() {
                     nfl.fields()
+                        .inspect(|field| field_ptrs.push(AstPtr::new(*field)))
                         .map(|field| StructLitField {
                             name: field
                                 .name_ref()
@@ -657,7 +683,11 @@ impl ExprCollector {
                     Vec::new()
                 };
                 let spread = e.spread().map(|s| self.collect_expr(s));
-                self.alloc_expr(Expr::StructLit { path, fields, spread }, syntax_ptr)
+                let res = self.alloc_expr(Expr::StructLit { path, fields, spread }, syntax_ptr);
+                for (i, ptr) in field_ptrs.into_iter().enumerate() {
+                    self.source_map.field_map.insert((res, i), ptr);
+                }
+                res
             }
             ast::ExprKind::FieldExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
@@ -750,6 +780,22 @@ impl ExprCollector {
                 };
                 self.alloc_expr(Expr::Literal(lit), syntax_ptr)
             }
+            ast::ExprKind::MacroCall(e) => {
+                // Built-in macros expand to a single literal, whose value we
+                // don't need to know for type inference.
+                let builtin = e
+                    .path()
+                    .and_then(Path::from_ast)
+                    .and_then(|path| BuiltinMacro::from_path(&path));
+                let lit = match builtin {
+                    Some(it) if it.expands_to_integer() => {
+                        Literal::Int(Default::default(), UncertainIntTy::Known(IntTy::u32()))
+                    }
+                    Some(_) => Literal::String(Default::default()),
+                    None => return self.alloc_expr(Expr::Missing, syntax_ptr),
+                };
+                self.alloc_expr(Expr::Literal(lit), syntax_ptr)
+            }
 
             // FIXME implement HIR for these:
             ast::ExprKind::Label(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
@@ -758,6 +804,22 @@ impl ExprCollector {
         }
     }
 
+    /// Lowers a path in an expression. Single-segment paths may refer to
+    /// local variables, so their names are hygienic.
+    fn collect_path(&self, path: &ast::Path) -> Option<Path> {
+        let mut res = Path::from_ast(path)?;
+        if res.as_ident().is_some() {
+            if let Some(name_ref) = path.segment().and_then(|it| it.name_ref()) {
+                res.segments[0].name = self.hygiene.mark(name_ref.as_name(), name_ref.syntax());
+            }
+        }
+        Some(res)
+    }
+
+    fn lifetime_name(&self, lifetime: &ast::Lifetime) -> Name {
+        self.hygiene.mark(lifetime.as_name(), lifetime.syntax())
+    }
+
     fn collect_expr_opt(&mut self, expr: Option<&ast::Expr>) -> ExprId {
         if let Some(expr) = expr {
             self.collect_expr(expr)
@@ -796,7 +858,10 @@ impl ExprCollector {
     fn collect_pat(&mut self, pat: &ast::Pat) -> PatId {
         let pattern = match pat.kind() {
             ast::PatKind::BindPat(bp) => {
-                let name = bp.name().map(|nr| nr.as_name()).unwrap_or_else(Name::missing);
+                let name = bp
+                    .name()
+                    .map(|nr| self.hygiene.mark(nr.as_name(), nr.syntax()))
+                    .unwrap_or_else(Name::missing);
                 let annotation = BindingAnnotation::new(bp.is_mutable(), bp.is_ref());
                 let subpat = bp.pat().map(|subpat| self.collect_pat(subpat));
                 Pat::Bind { name, mode: annotation, subpat }
@@ -908,10 +973,12 @@ pub(crate) fn body_with_source_map_query(
     db: &impl HirDatabase,
     func: Function,
 ) -> (Arc<Body>, Arc<BodySourceMap>) {
-    let mut collector = ExprCollector::new(func);
+    db.check_canceled();
+    let (file_id, fn_def) = func.source(db);
+    let mut collector = ExprCollector::new(func, Hygiene::new(db, file_id));
 
     // FIXME: consts, etc.
-    collector.collect_fn_body(&func.source(db).1);
+    collector.collect_fn_body(&fn_def);
 
     let (body, source_map) = collector.finish();
     (Arc::new(body), Arc::new(source_map))
@@ -923,7 +990,64 @@ pub(crate) fn body_hir_query(db: &impl HirDatabase, func: Function) -> Arc<Body>
 
 #[cfg(test)]
 fn collect_fn_body_syntax(function: Function, node: &ast::FnDef) -> (Body, BodySourceMap) {
-    let mut collector = ExprCollector::new(function);
+    let mut collector = ExprCollector::new(function, Hygiene::root());
     collector.collect_fn_body(node);
     collector.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_db::SourceDatabase;
+    use ra_syntax::{
+        SyntaxNodePtr,
+        ast::{self, AstNode},
+    };
+
+    use crate::{mock::MockDatabase, source_binder};
+
+    #[test]
+    fn body_source_map_maps_syntax_to_ids_and_back() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            fn foo(x: u32, (y, _): (u32, u32)) -> u32 {
+                let z = if x > 0 { x + y } else { y };
+                match (z, y) {
+                    (n, _) => n * 2,
+                    _ => 1,
+                }
+            }
+            ",
+        );
+        let source_file = db.parse(file_id);
+        let fn_def = source_file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let func = source_binder::function_from_source(&db, file_id, fn_def).unwrap();
+        let body = func.body(&db);
+        let source_map = func.body_source_map(&db);
+
+        let mut n_exprs = 0;
+        for expr in fn_def.syntax().descendants().filter_map(ast::Expr::cast) {
+            let id = source_map.node_expr(expr).unwrap();
+            assert_eq!(source_map.expr_syntax(id), Some(SyntaxNodePtr::new(expr.syntax())));
+            n_exprs += 1;
+        }
+        // Blocks are lowered to expressions too.
+        for block in fn_def.syntax().descendants().filter_map(ast::Block::cast) {
+            let ptr = SyntaxNodePtr::new(block.syntax());
+            let id = source_map.syntax_expr(ptr).unwrap();
+            assert_eq!(source_map.expr_syntax(id), Some(ptr));
+            n_exprs += 1;
+        }
+        let n_with_syntax =
+            body.exprs().filter(|(id, _)| source_map.expr_syntax(*id).is_some()).count();
+        assert_eq!(n_with_syntax, n_exprs);
+
+        let mut n_pats = 0;
+        for pat in fn_def.syntax().descendants().filter_map(ast::Pat::cast) {
+            let id = source_map.node_pat(pat).unwrap();
+            assert_eq!(source_map.pat_syntax(id), Some(SyntaxNodePtr::new(pat.syntax())));
+            n_pats += 1;
+        }
+        assert_eq!(body.pats().count(), n_pats);
+        assert_eq!(body.params().len(), 2);
+    }
+}
@@ -36,6 +36,8 @@ pub struct ScopeEntry {
 pub struct ScopeData {
     parent: Option<ScopeId>,
     entries: Vec<ScopeEntry>,
+    /// The label of the loop this scope is the body of, if any.
+    label: Option<(Name, ExprId)>,
 }
 
 impl ExprScopes {
@@ -73,12 +75,67 @@ impl ExprScopes {
         generate(scope, move |&scope| self.scopes[scope].parent)
     }
 
+    /// Resolves `name` to the innermost binding with this name visible from
+    /// `scope`.
+    pub fn resolve_name_in_scope(&self, scope: ScopeId, name: &Name) -> Option<&ScopeEntry> {
+        self.scope_chain_for(Some(scope)).find_map(|scope| {
+            self.entries(scope).iter().find(|entry| entry.name().hygienic_eq(name))
+        })
+    }
+
+    /// Resolves the label of a `break` or `continue` in `scope` to the loop
+    /// expression it refers to.
+    pub fn resolve_label(&self, scope: ScopeId, label: &Name) -> Option<ExprId> {
+        self.scope_chain_for(Some(scope)).find_map(|scope| match &self.scopes[scope].label {
+            Some((name, expr)) if name.hygienic_eq(label) => Some(*expr),
+            _ => None,
+        })
+    }
+
+    /// Finds all the path expressions which resolve to the binding `pat`.
+    pub fn find_references(&self, pat: PatId) -> Vec<ExprId> {
+        let name = match &self.body[pat] {
+            Pat::Bind { name, .. } => name,
+            _ => return Vec::new(),
+        };
+        self.body
+            .exprs()
+            .filter(|(expr, data)| match data {
+                Expr::Path(path) if path.as_ident() == Some(name) => {
+                    self.scope_for(*expr)
+                        .and_then(|scope| self.resolve_name_in_scope(scope, name))
+                        .map(|entry| entry.pat())
+                        == Some(pat)
+                }
+                _ => false,
+            })
+            .map(|(expr, _)| expr)
+            .collect()
+    }
+
     fn root_scope(&mut self) -> ScopeId {
-        self.scopes.alloc(ScopeData { parent: None, entries: vec![] })
+        self.scopes.alloc(ScopeData { parent: None, entries: vec![], label: None })
     }
 
     fn new_scope(&mut self, parent: ScopeId) -> ScopeId {
-        self.scopes.alloc(ScopeData { parent: Some(parent), entries: vec![] })
+        self.scopes.alloc(ScopeData { parent: Some(parent), entries: vec![], label: None })
+    }
+
+    /// Creates the scope of the body of `loop_expr` if the loop is labeled.
+    fn new_labeled_scope(
+        &mut self,
+        parent: ScopeId,
+        label: &Option<Name>,
+        loop_expr: ExprId,
+    ) -> ScopeId {
+        match label {
+            Some(label) => self.scopes.alloc(ScopeData {
+                parent: Some(parent),
+                entries: vec![],
+                label: Some((label.clone(), loop_expr)),
+            }),
+            None => parent,
+        }
     }
 
     fn add_bindings(&mut self, body: &Body, scope: ScopeId, pat: PatId) {
@@ -116,6 +173,7 @@ pub struct ScopesWithSourceMap {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScopeEntryWithSyntax {
     name: Name,
+    pat: PatId,
     ptr: SyntaxNodePtr,
 }
 
@@ -124,6 +182,10 @@ impl ScopeEntryWithSyntax {
         &self.name
     }
 
+    pub fn pat(&self) -> PatId {
+        self.pat
+    }
+
     pub fn ptr(&self) -> SyntaxNodePtr {
         self.ptr
     }
@@ -185,11 +247,20 @@ impl ScopesWithSourceMap {
         ret.and_then(|entry| {
             Some(ScopeEntryWithSyntax {
                 name: entry.name().clone(),
+                pat: entry.pat(),
                 ptr: self.source_map.pat_syntax(entry.pat())?,
             })
         })
     }
 
+    /// Resolves the label of a `break` or `continue` expression to the loop
+    /// it refers to.
+    pub fn resolve_label(&self, lifetime: &ast::Lifetime) -> Option<SyntaxNodePtr> {
+        let scope = self.scope_for(lifetime.syntax())?;
+        let loop_expr = self.scopes.resolve_label(scope, &lifetime.as_name())?;
+        self.source_map.expr_syntax(loop_expr)
+    }
+
     pub fn find_all_refs(&self, pat: &ast::BindPat) -> Vec<ReferenceDescriptor> {
         let fn_def = pat.syntax().ancestors().find_map(ast::FnDef::cast).unwrap();
         let name_ptr = SyntaxNodePtr::new(pat.syntax());
@@ -260,8 +331,18 @@ fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut ExprScopes, scope
         Expr::Block { statements, tail } => {
             compute_block_scopes(&statements, *tail, body, scopes, scope);
         }
-        Expr::For { iterable, pat, body: body_expr } => {
+        Expr::Loop { body: body_expr, label } => {
+            let scope = scopes.new_labeled_scope(scope, label, expr);
+            compute_expr_scopes(*body_expr, body, scopes, scope);
+        }
+        Expr::While { condition, body: body_expr, label } => {
+            compute_expr_scopes(*condition, body, scopes, scope);
+            let scope = scopes.new_labeled_scope(scope, label, expr);
+            compute_expr_scopes(*body_expr, body, scopes, scope);
+        }
+        Expr::For { iterable, pat, body: body_expr, label } => {
             compute_expr_scopes(*iterable, body, scopes, scope);
+            let scope = scopes.new_labeled_scope(scope, label, expr);
             let scope = scopes.new_scope(scope);
             scopes.add_bindings(body, scope, *pat);
             compute_expr_scopes(*body_expr, body, scopes, scope);
@@ -464,6 +545,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_label() {
+        let code = r"
+            fn foo() {
+                'outer: loop {
+                    'inner: for x in y {
+                        break 'outer;
+                        continue 'inner;
+                    }
+                }
+            }";
+        let file = SourceFile::parse(code);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let irrelevant_function = Function { id: crate::ids::FunctionId::from_raw(0.into()) };
+        let (body, source_map) = expr::collect_fn_body_syntax(irrelevant_function, fn_def);
+        let scopes = ExprScopes::new(Arc::new(body));
+        let scopes =
+            ScopesWithSourceMap { scopes: Arc::new(scopes), source_map: Arc::new(source_map) };
+        let resolved = file
+            .syntax()
+            .descendants()
+            .filter(|it| it.parent().map_or(false, |p| p.kind() != ra_syntax::SyntaxKind::LABEL))
+            .filter_map(ast::Lifetime::cast)
+            .map(|lifetime| {
+                let ptr = scopes.resolve_label(lifetime).unwrap();
+                ptr.to_node(&file).text().to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(resolved.len(), 2);
+        assert!(resolved[0].starts_with("'outer: loop"));
+        assert!(resolved[1].starts_with("'inner: for"));
+    }
+
+    #[test]
+    fn test_find_references() {
+        let code = r"
+            fn foo(x: i32) {
+                let y = x;
+                let x = y + x;
+                |x| x;
+                x
+            }";
+        let file = SourceFile::parse(code);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let irrelevant_function = Function { id: crate::ids::FunctionId::from_raw(0.into()) };
+        let (body, source_map) = expr::collect_fn_body_syntax(irrelevant_function, fn_def);
+        let param = body.params()[0];
+        let scopes = ExprScopes::new(Arc::new(body));
+        let refs = scopes
+            .find_references(param)
+            .into_iter()
+            .map(|expr| source_map.expr_syntax(expr).unwrap().range().start().to_usize())
+            .collect::<Vec<_>>();
+        assert_eq!(refs, [code.find("= x").unwrap() + 2, code.find("y + x").unwrap() + 4]);
+    }
+
     #[test]
     fn ref_patterns_contribute_bindings() {
         do_check_local_name(
@@ -0,0 +1,356 @@
+//! Checks of function bodies which are not a part of type inference, like
+//! exhaustiveness of `match`es or visibility of the items paths refer to.
+
+use std::sync::Arc;
+
+use ra_syntax::{
+    AstPtr, SyntaxNode,
+    SyntaxKind::MATCH_EXPR,
+    ast::{self, AstNode},
+};
+
+use crate::{
+    Function, HirDatabase, HirFileId, Enum, EnumVariant, Module, ModuleDef, ModuleSource,
+    Resolution, Path, AsName,
+    adt::{AdtDef, VariantKind},
+    diagnostics::{DiagnosticSink, MissingFields, MissingMatchArms, PrivateItem},
+    expr::{Body, Expr, ExprId, MatchArm, Pat, PatId, StructLitField, resolver_for_expr},
+    nameres::ScopeLookup,
+    ty::{ApplicationTy, InferenceResult, Ty, TypeCtor},
+};
+
+pub(crate) struct ExprValidator<'a, 'b: 'a> {
+    func: Function,
+    infer: Arc<InferenceResult>,
+    sink: &'a mut DiagnosticSink<'b>,
+}
+
+impl<'a, 'b> ExprValidator<'a, 'b> {
+    pub(crate) fn new(
+        func: Function,
+        infer: Arc<InferenceResult>,
+        sink: &'a mut DiagnosticSink<'b>,
+    ) -> ExprValidator<'a, 'b> {
+        ExprValidator { func, infer, sink }
+    }
+
+    pub(crate) fn validate_body(&mut self, db: &impl HirDatabase) {
+        let body = self.func.body(db);
+        for (id, expr) in body.exprs() {
+            match expr {
+                Expr::Match { expr: scrutinee, arms } => {
+                    self.validate_match(db, &body, id, *scrutinee, arms)
+                }
+                Expr::StructLit { fields, spread: None, .. } => {
+                    self.validate_struct_literal(db, id, fields)
+                }
+                _ => (),
+            }
+        }
+        self.validate_paths(db, &body);
+    }
+
+    /// Flags paths which go through items, or private imports, not visible
+    /// from the function's module. Only the first such segment is reported.
+    fn validate_paths(&mut self, db: &impl HirDatabase, body: &Body) {
+        let source_map = self.func.body_source_map(db);
+        let exprs = body.exprs().filter_map(|(id, expr)| {
+            let path = match expr {
+                Expr::Path(path) | Expr::StructLit { path: Some(path), .. } => path,
+                _ => return None,
+            };
+            Some((path, source_map.expr_syntax(id)?))
+        });
+        let pats = body.pats().filter_map(|(id, pat)| {
+            let path = match pat {
+                Pat::Path(path)
+                | Pat::TupleStruct { path: Some(path), .. }
+                | Pat::Struct { path: Some(path), .. } => path,
+                _ => return None,
+            };
+            Some((path, source_map.pat_syntax(id)?))
+        });
+        let paths = exprs.chain(pats).collect::<Vec<_>>();
+
+        let module = self.func.module(db);
+        let (file, _) = self.func.source(db);
+        let source_file = db.hir_parse(file);
+        for (path, ptr) in paths {
+            let lookup = match private_lookup(db, module, path, false) {
+                Some(it) => it,
+                None => continue,
+            };
+            let segment = ptr
+                .to_node(&source_file)
+                .children()
+                .find_map(ast::Path::cast)
+                .and_then(|it| path_segment(it, path, lookup.segment));
+            if let Some(segment) = segment {
+                self.sink.push(private_item(module, file, segment, path, lookup));
+            }
+        }
+    }
+
+    /// Flags struct literals without a `..base` which don't initialize all
+    /// the fields of the struct or the enum variant.
+    fn validate_struct_literal(
+        &mut self,
+        db: &impl HirDatabase,
+        id: ExprId,
+        fields: &[StructLitField],
+    ) {
+        let variant = match self.infer.variant_resolution(id) {
+            Some(it) => it,
+            None => return,
+        };
+        if variant.kind(db) != VariantKind::Named {
+            return;
+        }
+        let missed_fields = variant
+            .fields(db)
+            .into_iter()
+            .map(|field| field.name(db))
+            .filter(|name| !fields.iter().any(|it| &it.name == name))
+            .collect::<Vec<_>>();
+        if missed_fields.is_empty() {
+            return;
+        }
+        let source_map = self.func.body_source_map(db);
+        let ptr = match source_map.expr_syntax(id) {
+            Some(it) => it,
+            None => return,
+        };
+        let (file, _) = self.func.source(db);
+        let source_file = db.hir_parse(file);
+        let field_list = match ast::StructLit::cast(ptr.to_node(&source_file))
+            .and_then(|it| it.named_field_list())
+        {
+            Some(it) => AstPtr::new(it),
+            None => return,
+        };
+        self.sink.push(MissingFields { file, field_list, missed_fields });
+    }
+
+    /// Flags `match`es over an enum which don't mention some of its variants
+    /// and have no catch-all arm. Only the top-level patterns are checked, so
+    /// `Some(1)` counts as covering `Some`: this is a hint, not a proof of
+    /// exhaustiveness.
+    fn validate_match(
+        &mut self,
+        db: &impl HirDatabase,
+        body: &Arc<Body>,
+        id: ExprId,
+        scrutinee: ExprId,
+        arms: &[MatchArm],
+    ) {
+        let source_map = self.func.body_source_map(db);
+        // `if let` is lowered to a `match` too.
+        let match_expr = match source_map.expr_syntax(id) {
+            Some(ptr) if ptr.kind() == MATCH_EXPR => ptr,
+            _ => return,
+        };
+        let enum_ = match matched_enum(&self.infer[scrutinee]) {
+            Some(it) => it,
+            None => return,
+        };
+
+        let mut covered = Vec::new();
+        for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+            for &pat in arm.pats.iter() {
+                match pat_variant(db, body, id, pat) {
+                    PatCoverage::All => return,
+                    PatCoverage::Variant(variant) => covered.push(variant),
+                    PatCoverage::Unknown => return,
+                }
+            }
+        }
+        let missing = enum_
+            .variants(db)
+            .into_iter()
+            .filter(|variant| !covered.contains(variant))
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return;
+        }
+        let (file, _) = self.func.source(db);
+        self.sink.push(MissingMatchArms { file, match_expr, enum_, missing });
+    }
+}
+
+enum PatCoverage {
+    All,
+    Variant(EnumVariant),
+    /// A pattern we can't reason about, like a literal or an unresolved path.
+    Unknown,
+}
+
+fn matched_enum(ty: &Ty) -> Option<Enum> {
+    match ty {
+        Ty::Apply(ApplicationTy { ctor: TypeCtor::Adt(AdtDef::Enum(it)), .. }) => Some(*it),
+        // With default binding modes, a reference to an enum is matched with
+        // the patterns of the enum.
+        Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(_), parameters }) => {
+            matched_enum(parameters.as_single())
+        }
+        _ => None,
+    }
+}
+
+fn pat_variant(db: &impl HirDatabase, body: &Arc<Body>, expr: ExprId, pat: PatId) -> PatCoverage {
+    let path = match &body[pat] {
+        Pat::Wild | Pat::Bind { subpat: None, .. } => return PatCoverage::All,
+        Pat::Bind { subpat: Some(pat), .. } | Pat::Ref { pat, .. } => {
+            return pat_variant(db, body, expr, *pat)
+        }
+        Pat::Path(path) => Some(path),
+        Pat::TupleStruct { path, .. } | Pat::Struct { path, .. } => path.as_ref(),
+        _ => None,
+    };
+    let path = match path {
+        Some(it) => it,
+        None => return PatCoverage::Unknown,
+    };
+    let resolver = resolver_for_expr(Arc::clone(body), db, expr);
+    let resolution = resolver.resolve_path(db, path);
+    match resolution.clone().take_values().or_else(|| resolution.take_types()) {
+        Some(Resolution::Def(ModuleDef::EnumVariant(variant))) => PatCoverage::Variant(variant),
+        _ => PatCoverage::Unknown,
+    }
+}
+
+/// Flags the paths of the `use` items of the module, and the types written in
+/// it, which go through items not visible from the module. Paths in function
+/// bodies are checked by `ExprValidator`.
+pub(crate) fn validate_module_paths(
+    module: Module,
+    db: &impl HirDatabase,
+    sink: &mut DiagnosticSink,
+) {
+    let def_map = db.crate_def_map(module.krate);
+    let (file, source) = module.definition_source(db);
+    let raw_items = db.raw_items(file.original_file(db));
+    let mut imports = Vec::new();
+    for (_, resolution) in def_map[module.module_id].scope.entries_in_source_order() {
+        match resolution.import {
+            Some(import) if !imports.contains(&import) => imports.push(import),
+            _ => (),
+        }
+    }
+    for import in imports {
+        // Imports from macro expansions have no source to point to.
+        if raw_items[import].is_extern_crate || module.import_range(db, import).is_none() {
+            continue;
+        }
+        let path = &raw_items[import].path;
+        let lookup = match private_lookup(db, module, path, true) {
+            Some(it) => it,
+            None => continue,
+        };
+        let leaf = module.import_source(db, import);
+        if let Some(segment) = use_path_segment(&leaf, path, lookup.segment) {
+            sink.push(private_item(module, file, segment, path, lookup));
+        }
+    }
+
+    let node = match &source {
+        ModuleSource::SourceFile(it) => it.syntax(),
+        ModuleSource::Module(it) => it.syntax(),
+    };
+    let mut type_paths = Vec::new();
+    collect_type_paths(node, &mut type_paths);
+    for ast_path in type_paths {
+        let path = match Path::from_ast(ast_path) {
+            Some(it) => it,
+            None => continue,
+        };
+        let lookup = match private_lookup(db, module, &path, false) {
+            Some(it) => it,
+            None => continue,
+        };
+        if let Some(segment) = path_segment(ast_path, &path, lookup.segment) {
+            sink.push(private_item(module, file, segment, &path, lookup));
+        }
+    }
+}
+
+/// Collects the paths of the types, including the bounds, written in the
+/// module, but not in its inline submodules.
+fn collect_type_paths<'a>(node: &'a SyntaxNode, acc: &mut Vec<&'a ast::Path>) {
+    for child in node.children() {
+        if ast::Module::cast(child).and_then(|it| it.item_list()).is_some() {
+            continue;
+        }
+        if let Some(path) = ast::PathType::cast(child).and_then(|it| it.path()) {
+            acc.push(path);
+        }
+        collect_type_paths(child, acc);
+    }
+}
+
+/// Finds the first segment of `path` which goes through an item, or a private
+/// import, not visible from `module`.
+fn private_lookup(
+    db: &impl HirDatabase,
+    module: Module,
+    path: &Path,
+    is_import: bool,
+) -> Option<ScopeLookup> {
+    db.crate_def_map(module.krate)
+        .path_lookups(db, module.module_id, path, is_import)
+        .into_iter()
+        .find(|it| !it.resolution.visibility.is_visible_from(db, module))
+}
+
+fn private_item(
+    module: Module,
+    file: HirFileId,
+    segment: &ast::PathSegment,
+    path: &Path,
+    lookup: ScopeLookup,
+) -> PrivateItem {
+    let def = lookup.resolution.def;
+    let item = match lookup.resolution.import {
+        None if lookup.module.krate == module.krate => def.take_types().or(def.take_values()),
+        _ => None,
+    };
+    let name = path.segments[lookup.segment].name.clone();
+    PrivateItem { file, segment: AstPtr::new(segment), name, item }
+}
+
+/// Like `path_segment`, but for the path of an import, given its last
+/// segment. The prefix of the path may be written in the enclosing use trees,
+/// as in `use foo::{bar::Baz}`.
+fn use_path_segment<'p>(
+    leaf: &'p ast::PathSegment,
+    path: &Path,
+    idx: usize,
+) -> Option<&'p ast::PathSegment> {
+    let mut ast_path = leaf.syntax().parent().and_then(ast::Path::cast)?;
+    for _ in idx + 1..path.segments.len() {
+        ast_path = match ast_path.qualifier() {
+            Some(it) => it,
+            None => ast_path.syntax().ancestors().filter_map(ast::UseTree::cast).nth(1)?.path()?,
+        };
+    }
+    let segment = ast_path.segment()?;
+    // `use foo::{self}` has no segment of its own.
+    if segment.name_ref()?.as_name() != path.segments[idx].name {
+        return None;
+    }
+    Some(segment)
+}
+
+/// Finds the segment of `ast_path` corresponding to `path.segments[idx]`.
+/// Leading `self`, `super` and `crate` are not among the segments of `path`,
+/// so the segments are counted from the end.
+fn path_segment<'p>(
+    ast_path: &'p ast::Path,
+    path: &Path,
+    idx: usize,
+) -> Option<&'p ast::PathSegment> {
+    let mut ast_path = ast_path;
+    for _ in idx + 1..path.segments.len() {
+        ast_path = ast_path.qualifier()?;
+    }
+    ast_path.segment()
+}
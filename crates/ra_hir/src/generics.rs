@@ -9,6 +9,7 @@ use ra_syntax::ast::{self, NameOwner, TypeParamsOwner};
 
 use crate::{
     db::DefDatabase,
+    type_ref::TypeRef,
     Name, AsName, Function, Struct, Enum, Trait, TypeAlias, ImplBlock
 };
 
@@ -18,6 +19,16 @@ pub struct GenericParam {
     // FIXME: give generic params proper IDs
     pub(crate) idx: u32,
     pub(crate) name: Name,
+    pub(crate) bounds: Vec<TypeRef>,
+    pub(crate) lifetime_bounds: Vec<Name>,
+    pub(crate) default: Option<TypeRef>,
+}
+
+/// Data about a lifetime parameter, like `'a: 'b`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LifetimeParam {
+    pub(crate) name: Name,
+    pub(crate) bounds: Vec<Name>,
 }
 
 /// Data about the generic parameters of a function, struct, impl, etc.
@@ -25,6 +36,38 @@ pub struct GenericParam {
 pub struct GenericParams {
     pub(crate) parent_params: Option<Arc<GenericParams>>,
     pub(crate) params: Vec<GenericParam>,
+    pub(crate) lifetimes: Vec<LifetimeParam>,
+}
+
+impl GenericParam {
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    /// Type bounds declared inline, like `Clone` in `T: Clone + 'a`. Bounds
+    /// from `where` clauses are not included.
+    pub fn bounds(&self) -> &[TypeRef] {
+        &self.bounds
+    }
+
+    /// Lifetime bounds declared inline, like `'a` in `T: Clone + 'a`.
+    pub fn lifetime_bounds(&self) -> &[Name] {
+        &self.lifetime_bounds
+    }
+
+    pub fn default(&self) -> Option<&TypeRef> {
+        self.default.as_ref()
+    }
+}
+
+impl LifetimeParam {
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    pub fn bounds(&self) -> &[Name] {
+        &self.bounds
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
@@ -71,13 +114,37 @@ impl GenericParams {
     }
 
     fn fill_params(&mut self, params: &ast::TypeParamList, start: u32) {
+        for lifetime_param in params.lifetime_params() {
+            let name = match lifetime_param.lifetime() {
+                Some(it) => it.as_name(),
+                None => continue,
+            };
+            let bounds = lifetime_param.bounds().map(AsName::as_name).collect();
+            self.lifetimes.push(LifetimeParam { name, bounds });
+        }
         for (idx, type_param) in params.type_params().enumerate() {
             let name = type_param.name().map(AsName::as_name).unwrap_or_else(Name::missing);
-            let param = GenericParam { idx: idx as u32 + start, name };
+            let param = GenericParam {
+                idx: idx as u32 + start,
+                name,
+                bounds: type_param.bounds().map(TypeRef::from_ast).collect(),
+                lifetime_bounds: type_param.lifetime_bounds().map(AsName::as_name).collect(),
+                default: type_param.default_type().map(TypeRef::from_ast),
+            };
             self.params.push(param);
         }
     }
 
+    /// Type parameters declared on this item, without the parent ones.
+    pub fn params(&self) -> &[GenericParam] {
+        &self.params
+    }
+
+    /// Lifetime parameters declared on this item, without the parent ones.
+    pub fn lifetimes(&self) -> &[LifetimeParam] {
+        &self.lifetimes
+    }
+
     pub(crate) fn find_by_name(&self, name: &Name) -> Option<&GenericParam> {
         self.params.iter().find(|p| &p.name == name)
     }
@@ -104,3 +171,48 @@ impl GenericParams {
         vec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_db::SourceDatabase;
+    use ra_syntax::ast::{self, AstNode};
+
+    use crate::{mock::MockDatabase, source_binder, type_ref::TypeRef};
+
+    #[test]
+    fn lowers_bounds_defaults_and_lifetimes() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            struct S;
+            impl<'a: 'b, 'b, T: Clone + 'a> S {
+                fn foo<U: Iterator, V = T>(&self) {}
+            }
+            ",
+        );
+        let source_file = db.parse(file_id);
+        let fn_def = source_file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let func = source_binder::function_from_source(&db, file_id, fn_def).unwrap();
+
+        let generics = func.generic_params(&db);
+        let names: Vec<_> = generics.params().iter().map(|it| it.name().to_string()).collect();
+        assert_eq!(names, ["U", "V"]);
+        assert_eq!(generics.params()[0].bounds().len(), 1);
+        assert!(generics.params()[0].default().is_none());
+        match generics.params()[1].default() {
+            Some(TypeRef::Path(path)) => assert_eq!(path.as_ident().unwrap().to_string(), "T"),
+            default => panic!("unexpected default: {:?}", default),
+        }
+
+        let parent = generics.parent_params.as_ref().unwrap();
+        let lifetimes: Vec<_> = parent
+            .lifetimes()
+            .iter()
+            .map(|it| (it.name().to_string(), it.bounds().len()))
+            .collect();
+        assert_eq!(lifetimes, [("'a".to_string(), 1), ("'b".to_string(), 0)]);
+        let t = &parent.params()[0];
+        assert_eq!(t.bounds().len(), 1);
+        assert_eq!(t.lifetime_bounds().iter().map(|it| it.to_string()).collect::<Vec<_>>(), ["'a"]);
+        assert_eq!(generics.count_params_including_parent(), 3);
+    }
+}
@@ -0,0 +1,61 @@
+//! A minimal model of macro hygiene.
+//!
+//! Each identifier gets a `SyntaxContext`, which records the macro expansion
+//! the identifier was introduced by. Identifiers written by the user have the
+//! root context, as do the identifiers which a macro copies from its
+//! arguments. A local variable is visible only to the references with the
+//! same context, so that a `let x` in a macro doesn't capture a user's `x`.
+//!
+//! This doesn't model the contexts of nested expansions precisely: tokens
+//! passed through several macro calls get the context of the innermost call.
+
+use ra_syntax::{SyntaxNode, TextRange};
+
+use crate::{DefDatabase, HirFileId, MacroCallId, Name, ExpansionInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct SyntaxContext(pub(crate) Option<MacroCallId>);
+
+impl SyntaxContext {
+    pub(crate) fn root() -> SyntaxContext {
+        SyntaxContext(None)
+    }
+
+    fn of_file(file_id: HirFileId) -> SyntaxContext {
+        SyntaxContext(file_id.as_macro_call_id())
+    }
+}
+
+/// Assigns syntax contexts to the names in a file.
+pub(crate) struct Hygiene {
+    expansion: Option<(MacroCallId, ExpansionInfo)>,
+}
+
+impl Hygiene {
+    pub(crate) fn new(db: &impl DefDatabase, file_id: HirFileId) -> Hygiene {
+        let expansion = file_id
+            .as_macro_call_id()
+            .and_then(|macro_call_id| file_id.expansion_info(db).map(|info| (macro_call_id, info)));
+        Hygiene { expansion }
+    }
+
+    /// Hygiene for a file which is not a macro expansion.
+    pub(crate) fn root() -> Hygiene {
+        Hygiene { expansion: None }
+    }
+
+    /// Attaches the context of the identifier `node` to `name`.
+    pub(crate) fn mark(&self, name: Name, node: &SyntaxNode) -> Name {
+        name.with_ctx(self.ctx_of(node.range()))
+    }
+
+    fn ctx_of(&self, range: TextRange) -> SyntaxContext {
+        match &self.expansion {
+            None => SyntaxContext::root(),
+            Some((macro_call_id, info)) => match info.map_range_up(range) {
+                Some((call_file, _)) => SyntaxContext::of_file(call_file),
+                None => SyntaxContext(Some(*macro_call_id)),
+            },
+        }
+    }
+}
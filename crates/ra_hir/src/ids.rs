@@ -4,13 +4,18 @@ use std::{
     sync::Arc,
 };
 
-use ra_db::{LocationInterner, FileId};
-use ra_syntax::{TreeArc, SyntaxNode, SourceFile, AstNode, SyntaxNodePtr, ast};
+use ra_db::{LocationInterner, FileId, FileRange};
+use ra_syntax::{
+    TreeArc, SyntaxNode, SourceFile, AstNode, SyntaxNodePtr, TextRange, TextUnit, SyntaxKind, ast,
+};
 use ra_arena::{Arena, RawId, ArenaId, impl_arena_id};
+use rustc_hash::FxHashMap;
 
 use crate::{
     Module,
     DefDatabase,
+    Path,
+    builtin_macro::{BuiltinMacro, CallSite},
 };
 
 #[derive(Debug, Default)]
@@ -83,6 +88,40 @@ impl HirFileId {
         }
     }
 
+    /// Maps a range in this file to the original source file. Ranges inside
+    /// macro expansions are mapped to the whole macro call.
+    pub fn original_range(self, db: &impl DefDatabase, range: TextRange) -> FileRange {
+        match self.0 {
+            HirFileIdRepr::File(file_id) => FileRange { file_id, range },
+            HirFileIdRepr::Macro(macro_call_id) => {
+                let loc = macro_call_id.loc(db);
+                let (file_id, macro_call) = loc.source_item_id.source(db);
+                file_id.original_range(db, macro_call.range())
+            }
+        }
+    }
+
+    /// For macro-expansion files, returns the information needed to map
+    /// ranges in the expansion back to the macro call.
+    pub fn expansion_info(self, db: &impl DefDatabase) -> Option<ExpansionInfo> {
+        let macro_call_id = self.as_macro_call_id()?;
+        let loc = macro_call_id.loc(db);
+        let (tt, arg_map) = expand_macro_invocation(db, macro_call_id)?;
+        let (_, exp_map) = mbe::token_tree_to_ast_item_list_with_map(&tt);
+
+        let syntax = db.file_item(loc.source_item_id);
+        let arg_start = ast::MacroCall::cast(&syntax)?.token_tree()?.syntax().range().start();
+        Some(ExpansionInfo { call_file: loc.source_item_id.file_id, arg_start, arg_map, exp_map })
+    }
+
+    /// For macro-expansion files, returns the macro call which produced it.
+    pub(crate) fn as_macro_call_id(self) -> Option<MacroCallId> {
+        match self.0 {
+            HirFileIdRepr::File(_) => None,
+            HirFileIdRepr::Macro(macro_call_id) => Some(macro_call_id),
+        }
+    }
+
     pub(crate) fn hir_parse(db: &impl DefDatabase, file_id: HirFileId) -> TreeArc<SourceFile> {
         match file_id.0 {
             HirFileIdRepr::File(file_id) => db.parse(file_id),
@@ -95,17 +134,53 @@ impl HirFileId {
 }
 
 fn parse_macro(db: &impl DefDatabase, macro_call_id: MacroCallId) -> Option<TreeArc<SourceFile>> {
+    let tt = db.macro_expand(macro_call_id)?;
+    Some(mbe::token_tree_to_ast_item_list(&tt))
+}
+
+pub(crate) fn macro_expand_query(
+    db: &impl DefDatabase,
+    macro_call_id: MacroCallId,
+) -> Option<Arc<tt::Subtree>> {
+    let _p = ra_prof::profile("macro_expand_query");
+    db.check_canceled();
+    let (tt, _) = expand_macro_invocation(db, macro_call_id)?;
+    Some(Arc::new(tt))
+}
+
+/// Returns the expansion together with the token map of the macro call's
+/// arguments.
+fn expand_macro_invocation(
+    db: &impl DefDatabase,
+    macro_call_id: MacroCallId,
+) -> Option<(tt::Subtree, mbe::TokenMap)> {
     let loc = macro_call_id.loc(db);
     let syntax = db.file_item(loc.source_item_id);
     let macro_call = ast::MacroCall::cast(&syntax).unwrap();
-    let (macro_arg, _) = macro_call.token_tree().and_then(mbe::ast_to_token_tree)?;
+    let (macro_arg, arg_map) = macro_call.token_tree().and_then(mbe::ast_to_token_tree)?;
 
     let def_map = db.crate_def_map(loc.module.krate);
-    let (krate, macro_id) = def_map.resolve_macro(macro_call_id)?;
-    let def_map = db.crate_def_map(krate);
-    let macro_rules = &def_map[macro_id];
-    let tt = macro_rules.expand(&macro_arg).ok()?;
-    Some(mbe::token_tree_to_ast_item_list(&tt))
+    let tt = match def_map.resolve_macro(macro_call_id) {
+        Some((krate, macro_id)) => {
+            let def_map = db.crate_def_map(krate);
+            let macro_rules = &def_map[macro_id];
+            macro_rules.expand(&macro_arg).ok()?
+        }
+        // Built-in macros are not defined anywhere, so they can't be shadowed.
+        None => {
+            let builtin = macro_call.path().and_then(Path::from_ast)?;
+            let builtin = BuiltinMacro::from_path(&builtin)?;
+            let call_site = call_site(db, loc.source_item_id.file_id, macro_call);
+            builtin.expand(&call_site, &macro_arg)?
+        }
+    };
+    Some((tt, arg_map))
+}
+
+fn call_site(db: &impl DefDatabase, file_id: HirFileId, macro_call: &ast::MacroCall) -> CallSite {
+    let FileRange { file_id, range } = file_id.original_range(db, macro_call.syntax().range());
+    let file = db.file_relative_path(file_id).as_str().to_string();
+    CallSite::new(file, &db.file_text(file_id), range.start().to_usize())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -142,10 +217,111 @@ impl MacroCallId {
     pub(crate) fn loc(self, db: &impl AsRef<HirInterner>) -> MacroCallLoc {
         db.as_ref().macros.id2loc(self)
     }
+
+    /// Expands this macro call. Returns `None` if the macro can't be resolved
+    /// or if the arguments don't match any of its rules.
+    pub fn expand(self, db: &impl DefDatabase) -> Option<MacroExpansion> {
+        let tt = db.macro_expand(self)?;
+        let file_id: HirFileId = self.into();
+        let source_file = db.hir_parse(file_id);
+        let module = self.loc(db).module;
+        Some(MacroExpansion { file_id, module, tt, source_file })
+    }
+}
+
+/// The result of expanding a single macro call: the token tree produced by
+/// the macro, and the very same tokens parsed as a list of items.
+///
+/// Note that macro-generated source has no trivia: tokens are glued together,
+/// so `source_file().syntax().text()` is not necessarily valid Rust.
+#[derive(Debug)]
+pub struct MacroExpansion {
+    file_id: HirFileId,
+    module: Module,
+    tt: Arc<tt::Subtree>,
+    source_file: TreeArc<SourceFile>,
+}
+
+impl MacroExpansion {
+    /// Id of the "file" which holds the expansion.
+    pub fn file_id(&self) -> HirFileId {
+        self.file_id
+    }
+
+    /// The module containing the macro call.
+    pub fn module(&self) -> Module {
+        self.module
+    }
+
+    pub fn token_tree(&self) -> &tt::Subtree {
+        &self.tt
+    }
+
+    pub fn source_file(&self) -> &SourceFile {
+        &self.source_file
+    }
+
+    /// Macro calls which appear in the expansion, in source order. Useful for
+    /// recursive expansion.
+    pub fn macro_calls(
+        &self,
+        db: &impl DefDatabase,
+    ) -> Vec<(TreeArc<ast::MacroCall>, MacroCallId)> {
+        let file_items = db.file_items(self.file_id);
+        self.source_file
+            .syntax()
+            .descendants()
+            .filter_map(ast::MacroCall::cast)
+            .map(|macro_call| {
+                let item_id = file_items.id_of(self.file_id, macro_call.syntax());
+                let loc = MacroCallLoc {
+                    module: self.module,
+                    source_item_id: item_id.with_file_id(self.file_id),
+                };
+                (macro_call.to_owned(), loc.id(db))
+            })
+            .collect()
+    }
+}
+
+/// Maps ranges in a macro expansion to the macro call which produced it.
+#[derive(Debug)]
+pub struct ExpansionInfo {
+    /// File which contains the macro call.
+    call_file: HirFileId,
+    /// Start of the macro call's token tree in `call_file`.
+    arg_start: TextUnit,
+    arg_map: mbe::TokenMap,
+    exp_map: mbe::RevTokenMap,
+}
+
+impl ExpansionInfo {
+    /// Maps a range in the expansion to the range of the macro call tokens it
+    /// was produced from. Tokens which come from the macro definition itself
+    /// are ignored; returns `None` if there are no tokens from the call.
+    pub fn map_range_up(&self, range: TextRange) -> Option<(HirFileId, TextRange)> {
+        let mut res: Option<TextRange> = None;
+        for id in self.exp_map.tokens_in(range) {
+            let relative_range = match self.arg_map.relative_range_of(id) {
+                Some(it) => it,
+                None => continue,
+            };
+            let range = TextRange::offset_len(
+                relative_range.start() + self.arg_start,
+                relative_range.len(),
+            );
+            res = Some(match res {
+                None => range,
+                Some(prev) => {
+                    TextRange::from_to(prev.start().min(range.start()), prev.end().max(range.end()))
+                }
+            });
+        }
+        res.map(|range| (self.call_file, range))
+    }
 }
 
 impl MacroCallLoc {
-    #[allow(unused)]
     pub(crate) fn id(&self, db: &impl AsRef<HirInterner>) -> MacroCallId {
         db.as_ref().macros.loc2id(&self)
     }
@@ -154,10 +330,16 @@ impl MacroCallLoc {
 #[derive(Debug)]
 pub struct ItemLoc<N: AstNode> {
     pub(crate) module: Module,
-    raw: SourceItemId,
+    pub(crate) raw: SourceItemId,
     _ty: PhantomData<N>,
 }
 
+impl<N: AstNode> ItemLoc<N> {
+    pub(crate) fn new(module: Module, raw: SourceItemId) -> ItemLoc<N> {
+        ItemLoc { module, raw, _ty: PhantomData }
+    }
+}
+
 impl<N: AstNode> PartialEq for ItemLoc<N> {
     fn eq(&self, other: &Self) -> bool {
         self.module == other.module && self.raw == other.raw
@@ -309,11 +491,20 @@ pub struct SourceItemId {
     pub(crate) item_id: SourceFileItemId,
 }
 
+impl SourceItemId {
+    /// Returns the file containing the item, together with its syntax.
+    pub fn source(self, db: &impl DefDatabase) -> (HirFileId, TreeArc<SyntaxNode>) {
+        (self.file_id, db.file_item(self))
+    }
+}
+
 /// Maps items' `SyntaxNode`s to `SourceFileItemId`s and back.
 #[derive(Debug, PartialEq, Eq)]
 pub struct SourceFileItems {
     file_id: HirFileId,
     arena: Arena<SourceFileItemId, SyntaxNodePtr>,
+    /// Reverse mapping for `arena`, so that `id_of` is O(1) rather than O(n).
+    map: FxHashMap<SyntaxNodePtr, SourceFileItemId>,
 }
 
 impl SourceFileItems {
@@ -339,23 +530,25 @@ impl SourceFileItems {
         source_file: &SourceFile,
         file_id: HirFileId,
     ) -> SourceFileItems {
-        let mut res = SourceFileItems { file_id, arena: Arena::default() };
+        let mut res =
+            SourceFileItems { file_id, arena: Arena::default(), map: FxHashMap::default() };
         // By walking the tree in bread-first order we make sure that parents
         // get lower ids then children. That is, adding a new child does not
         // change parent's id. This means that, say, adding a new function to a
         // trait does not change ids of top-level items, which helps caching.
         bfs(source_file.syntax(), |it| {
-            if let Some(module_item) = ast::ModuleItem::cast(it) {
-                res.alloc(module_item.syntax());
-            } else if let Some(macro_call) = ast::MacroCall::cast(it) {
-                res.alloc(macro_call.syntax());
+            if it.kind().is_item() || it.kind() == SyntaxKind::MACRO_CALL {
+                res.alloc(it);
             }
         });
         res
     }
 
     fn alloc(&mut self, item: &SyntaxNode) -> SourceFileItemId {
-        self.arena.alloc(SyntaxNodePtr::new(item))
+        let ptr = SyntaxNodePtr::new(item);
+        let id = self.arena.alloc(ptr);
+        self.map.insert(ptr, id);
+        id
     }
     pub(crate) fn id_of(&self, file_id: HirFileId, item: &SyntaxNode) -> SourceFileItemId {
         assert_eq!(
@@ -367,7 +560,7 @@ impl SourceFileItems {
     }
     pub(crate) fn id_of_unchecked(&self, item: &SyntaxNode) -> SourceFileItemId {
         let ptr = SyntaxNodePtr::new(item);
-        if let Some((id, _)) = self.arena.iter().find(|(_id, i)| **i == ptr) {
+        if let Some(&id) = self.map.get(&ptr) {
             return id;
         }
         panic!(
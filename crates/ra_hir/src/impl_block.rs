@@ -4,13 +4,13 @@ use rustc_hash::FxHashMap;
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
 use ra_syntax::{
     AstPtr, SourceFile, TreeArc,
-    ast::{self, AstNode}
+    ast::{self, AstNode, NameOwner}
 };
 
 use crate::{
     Const, TypeAlias, Function, HirFileId,
     HirDatabase, DefDatabase,
-    ModuleDef, Trait, Resolution,
+    ModuleDef, Trait, Resolution, Name, AsName,
     type_ref::TypeRef,
     ids::LocationCtx,
     resolve::Resolver,
@@ -176,6 +176,43 @@ impl From<Function> for ImplItem {
     }
 }
 
+impl ImplItem {
+    /// Returns the syntax of the item.
+    pub fn source(&self, db: &impl DefDatabase) -> (HirFileId, TreeArc<ast::ImplItem>) {
+        match self {
+            ImplItem::Method(it) => {
+                let (file_id, source) = it.source(db);
+                (file_id, <&ast::ImplItem>::from(&*source).to_owned())
+            }
+            ImplItem::Const(it) => {
+                let (file_id, source) = it.source(db);
+                (file_id, <&ast::ImplItem>::from(&*source).to_owned())
+            }
+            ImplItem::TypeAlias(it) => {
+                let (file_id, source) = it.source(db);
+                (file_id, <&ast::ImplItem>::from(&*source).to_owned())
+            }
+        }
+    }
+
+    pub fn name(&self, db: &impl HirDatabase) -> Option<Name> {
+        match self {
+            ImplItem::Method(it) => Some(it.name(db)),
+            ImplItem::Const(it) => Some(it.signature(db).name().clone()),
+            ImplItem::TypeAlias(it) => it.source(db).1.name().map(|name| name.as_name()),
+        }
+    }
+
+    /// The impl block containing the item.
+    pub fn impl_block(&self, db: &impl DefDatabase) -> Option<ImplBlock> {
+        match self {
+            ImplItem::Method(it) => it.impl_block(db),
+            ImplItem::Const(it) => it.impl_block(db),
+            ImplItem::TypeAlias(it) => it.impl_block(db),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ImplId(pub RawId);
 impl_arena_id!(ImplId);
@@ -244,3 +281,49 @@ pub(crate) fn impls_in_module_source_map_query(
 ) -> Arc<ImplSourceMap> {
     db.impls_in_module_with_source_map(module).1
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_db::SourceDatabase;
+    use ra_syntax::{
+        SyntaxKind::ITEM_LIST,
+        ast::{self, AstNode},
+    };
+
+    use crate::{mock::MockDatabase, source_binder, type_ref::TypeRef};
+
+    #[test]
+    fn lowers_impl_blocks_with_items() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            trait Tr {}
+            struct S;
+            impl Tr for S {
+                fn method(&self) {}
+                const C: u32 = 92;
+                type T = u8;
+            }
+            ",
+        );
+        let source_file = db.parse(file_id);
+        let fn_def = source_file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let func = source_binder::function_from_source(&db, file_id, fn_def).unwrap();
+        let impl_block = func.impl_block(&db).unwrap();
+
+        assert!(impl_block.target_trait_ref(&db).is_some());
+        assert!(impl_block.target_trait(&db).is_some());
+        match impl_block.target_type(&db) {
+            TypeRef::Path(path) => assert_eq!(path.as_ident().unwrap().to_string(), "S"),
+            it => panic!("unexpected target type: {:?}", it),
+        }
+
+        let items = impl_block.items(&db);
+        let names = items.iter().map(|it| it.name(&db).unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(names, ["method", "C", "T"]);
+        for item in items {
+            assert_eq!(item.impl_block(&db), Some(impl_block));
+            let (_, source) = item.source(&db);
+            assert!(source.syntax().parent().map_or(false, |it| it.kind() == ITEM_LIST));
+        }
+    }
+}
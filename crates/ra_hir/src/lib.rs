@@ -22,15 +22,20 @@ pub mod db;
 pub mod mock;
 mod path;
 pub mod source_binder;
+pub mod diagnostics;
+pub mod reference_search;
 
 mod ids;
+mod builtin_macro;
 mod name;
+mod hygiene;
 mod nameres;
 mod adt;
 mod type_alias;
 mod type_ref;
 mod ty;
 mod impl_block;
+mod traits;
 mod expr;
 mod generics;
 mod docs;
@@ -45,25 +50,31 @@ mod marks;
 use crate::{
     db::{HirDatabase, DefDatabase},
     name::{AsName, KnownName},
-    ids::{SourceItemId, SourceFileItems},
+    ids::SourceFileItems,
 };
 
 pub use self::{
     path::{Path, PathKind},
     name::Name,
-    ids::{HirFileId, MacroCallId, MacroCallLoc, HirInterner},
-    nameres::{PerNs, Namespace},
+    ids::{
+        HirFileId, MacroCallId, MacroCallLoc, MacroExpansion, ExpansionInfo, HirInterner,
+        SourceItemId,
+    },
+    nameres::{PerNs, Namespace, Visibility, CrateDefMap, DefMapStats},
     ty::{Ty, ApplicationTy, TypeCtor, Substs, display::HirDisplay},
     impl_block::{ImplBlock, ImplItem},
+    traits::{TraitData, TraitItem},
     docs::{Docs, Documentation},
-    adt::AdtDef,
+    adt::{AdtDef, VariantDef, VariantKind},
+    generics::{GenericParams, GenericParam, LifetimeParam},
+    type_ref::TypeRef,
     expr::{ExprScopes, ScopesWithSourceMap, ScopeEntryWithSyntax},
-    resolve::{Resolver, Resolution},
+    resolve::{Resolver, Resolution, PathExpectation},
 };
 
 pub use self::code_model_api::{
     Crate, CrateDependency,
-    Module, ModuleDef, ModuleSource, Problem,
+    Module, ModuleDef, ModuleSource, ModuleItem, ModuleItemSource, Problem,
     Struct, Enum, EnumVariant,
     Function, FnSignature,
     StructField, FieldSource,
@@ -1,13 +1,35 @@
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-use ra_syntax::{ast, SmolStr};
+use ra_syntax::{ast::{self, AstToken}, SmolStr};
+
+use crate::hygiene::SyntaxContext;
 
 /// `Name` is a wrapper around string, which is used in hir for both references
-/// and declarations. In theory, names should also carry hygiene info, but we are
-/// not there yet!
-#[derive(Clone, PartialEq, Eq, Hash)]
+/// and declarations.
+///
+/// Names of local variables and labels also carry a minimal hygiene marker,
+/// the `SyntaxContext`. Only items are looked up by name in maps, and items
+/// are not hygienic, so the context does not take part in `==` and `Hash`;
+/// scope lookups compare names with `hygienic_eq` instead.
+#[derive(Clone)]
 pub struct Name {
     text: SmolStr,
+    ctx: SyntaxContext,
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state)
+    }
 }
 
 impl fmt::Display for Name {
@@ -27,7 +49,26 @@ impl Name {
     /// Hopefully, this should allow us to integrate hygiene cleaner in the
     /// future, and to switch to interned representation of names.
     fn new(text: SmolStr) -> Name {
-        Name { text }
+        Name { text, ctx: SyntaxContext::root() }
+    }
+
+    pub(crate) fn with_ctx(self, ctx: SyntaxContext) -> Name {
+        Name { ctx, ..self }
+    }
+
+    /// Restores a name from its parts, when loading a persisted def map.
+    pub(crate) fn from_parts(text: SmolStr, ctx: SyntaxContext) -> Name {
+        Name { text, ctx }
+    }
+
+    pub(crate) fn parts(&self) -> (&SmolStr, SyntaxContext) {
+        (&self.text, self.ctx)
+    }
+
+    /// Compares the names taking hygiene into account: a local variable
+    /// introduced by a macro can't be referred to from outside of it.
+    pub(crate) fn hygienic_eq(&self, other: &Name) -> bool {
+        self.text == other.text && self.ctx == other.ctx
     }
 
     pub(crate) fn missing() -> Name {
@@ -90,6 +131,12 @@ impl AsName for ast::Name {
     }
 }
 
+impl AsName for ast::Lifetime {
+    fn as_name(&self) -> Name {
+        Name::new(self.text().clone())
+    }
+}
+
 impl AsName for ra_db::Dependency {
     fn as_name(&self) -> Name {
         Name::new(self.name.clone())
@@ -38,8 +38,10 @@
 ///
 /// ## Resolving Macros
 ///
-/// macro_rules from the same crate use a global mutable namespace. We expand
-/// them immediately, when we collect modules.
+/// macro_rules from the same crate are scoped textually: a macro is visible
+/// after its definition, in the rest of the module and in child modules, and
+/// `#[macro_use]` on a module makes its macros visible after it in the parent
+/// too. We expand them immediately, when we collect modules.
 ///
 /// Macros from other crates (including proc-macros) can be used with
 /// `foo::bar!` syntax. We handle them similarly to imports. There's a list of
@@ -50,12 +52,13 @@
 mod per_ns;
 mod raw;
 mod collector;
+mod persistent;
 #[cfg(test)]
 mod tests;
 
 use std::sync::Arc;
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use ra_arena::{Arena, RawId, impl_arena_id};
 use ra_db::{FileId, Edition};
 use test_utils::tested_by;
@@ -66,7 +69,7 @@ use crate::{
     ids::{SourceItemId, SourceFileItemId, MacroCallId},
 };
 
-pub(crate) use self::raw::{RawItems, ImportId, ImportSourceMap};
+pub(crate) use self::raw::{RawItems, ImportId, ImportSourceMap, RawVisibility};
 
 pub use self::per_ns::{PerNs, Namespace};
 
@@ -80,11 +83,24 @@ pub struct CrateDefMap {
     /// a dependency (`std` or `core`).
     prelude: Option<Module>,
     extern_prelude: FxHashMap<Name, ModuleDef>,
+    /// Names under which the dependent crates know this crate. In 2018
+    /// edition, `use my_crate::foo` inside `my_crate` resolves like
+    /// `use crate::foo`. These are deliberately not a part of
+    /// `extern_prelude`, so that completion doesn't show the crate itself as
+    /// a dependency.
+    self_names: Vec<Name>,
     root: CrateModuleId,
     modules: Arena<CrateModuleId, ModuleData>,
-    macros: Arena<CrateMacroId, mbe::MacroRules>,
+    macros: Arena<CrateMacroId, MacroDef>,
     public_macros: FxHashMap<Name, CrateMacroId>,
     macro_resolutions: FxHashMap<MacroCallId, (Crate, CrateMacroId)>,
+    /// Imports which failed to resolve, together with the file of their
+    /// `RawItems`, so that they can be mapped back to source precisely.
+    unresolved_imports: Vec<(CrateModuleId, HirFileId, ImportId)>,
+    /// Imports which come from macro expansions. Their ids index the
+    /// `RawItems` of the expansion, so they may coincide with the ids of the
+    /// imports written in the module's file.
+    macro_imports: FxHashSet<(CrateModuleId, ImportId)>,
     problems: CrateDefMapProblems,
 }
 
@@ -98,10 +114,19 @@ impl std::ops::Index<CrateModuleId> for CrateDefMap {
 impl std::ops::Index<CrateMacroId> for CrateDefMap {
     type Output = mbe::MacroRules;
     fn index(&self, id: CrateMacroId) -> &mbe::MacroRules {
-        &self.macros[id]
+        &self.macros[id].rules
     }
 }
 
+/// A `macro_rules!` definition.
+#[derive(Debug, PartialEq, Eq)]
+struct MacroDef {
+    rules: mbe::MacroRules,
+    /// The token tree `rules` are parsed from, kept so that the def map can be
+    /// persisted.
+    body: tt::Subtree,
+}
+
 /// An ID of a macro, **local** to a specific crate
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct CrateMacroId(RawId);
@@ -123,6 +148,28 @@ pub(crate) struct ModuleData {
     ///
     /// Note that non-inline modules, by definition, live inside non-macro file.
     pub(crate) definition: Option<FileId>,
+    /// Defs of this module which come from macro expansions, grouped by the
+    /// macro call that produced them (the innermost one, for nested calls).
+    pub(crate) expansions: Vec<(MacroCallId, Vec<ModuleDef>)>,
+}
+
+/// Sizes of a `CrateDefMap`, see `CrateDefMap::stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefMapStats {
+    pub modules: usize,
+    pub scope_entries: usize,
+    pub macros: usize,
+    /// Approximate heap usage in bytes.
+    pub heap_size: usize,
+}
+
+impl std::ops::AddAssign for DefMapStats {
+    fn add_assign(&mut self, other: DefMapStats) {
+        self.modules += other.modules;
+        self.scope_entries += other.scope_entries;
+        self.macros += other.macros;
+        self.heap_size += other.heap_size;
+    }
 }
 
 #[derive(Default, Debug, PartialEq, Eq)]
@@ -152,6 +199,15 @@ impl ModuleScope {
     pub fn get(&self, name: &Name) -> Option<&Resolution> {
         self.items.get(name)
     }
+    /// Like `entries`, but ordered by `Resolution::source_order`, and then by
+    /// name, instead of the unspecified hash map order.
+    pub fn entries_in_source_order(&self) -> Vec<(&Name, &Resolution)> {
+        let mut res = self.entries().collect::<Vec<_>>();
+        res.sort_by(|(name1, res1), (name2, res2)| {
+            res1.source_order.cmp(&res2.source_order).then_with(|| name1.cmp(name2))
+        });
+        res
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -160,6 +216,52 @@ pub struct Resolution {
     pub def: PerNs<ModuleDef>,
     /// ident by which this is imported into local scope.
     pub import: Option<ImportId>,
+    /// Visibility of the item or, for imports, of the `use` itself.
+    pub visibility: Visibility,
+    /// Position of the definition or of the import among the items of the
+    /// module. Items produced by a macro from this crate come right after the
+    /// macro call, those produced by macros from other crates come last.
+    /// There may be gaps between the positions.
+    pub source_order: u32,
+}
+
+/// Resolved visibility of an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// Visible everywhere.
+    Public,
+    /// Visible inside the module and its descendants.
+    Module(Module),
+}
+
+impl Default for Visibility {
+    fn default() -> Visibility {
+        Visibility::Public
+    }
+}
+
+impl Visibility {
+    pub fn is_visible_from(self, db: &impl DefDatabase, from: Module) -> bool {
+        match self {
+            Visibility::Public => true,
+            Visibility::Module(module) => {
+                module.krate == from.krate
+                    && db
+                        .crate_def_map(from.krate)
+                        .is_descendant_of(from.module_id, module.module_id)
+            }
+        }
+    }
+}
+
+/// A segment of a path, resolved in the scope of a module.
+#[derive(Debug, Clone)]
+pub(crate) struct ScopeLookup {
+    /// The module in whose scope the segment was found.
+    pub(crate) module: Module,
+    /// The index of the segment in `Path::segments`.
+    pub(crate) segment: usize,
+    pub(crate) resolution: Resolution,
 }
 
 #[derive(Debug, Clone)]
@@ -197,6 +299,7 @@ enum ReachedFixedPoint {
 
 impl CrateDefMap {
     pub(crate) fn crate_def_map_query(db: &impl DefDatabase, krate: Crate) -> Arc<CrateDefMap> {
+        let _p = ra_prof::profile("crate_def_map");
         let start = std::time::Instant::now();
         let def_map = {
             let edition = krate.edition(db);
@@ -206,12 +309,15 @@ impl CrateDefMap {
                 krate,
                 edition,
                 extern_prelude: FxHashMap::default(),
+                self_names: Vec::new(),
                 prelude: None,
                 root,
                 modules,
                 macros: Arena::default(),
                 public_macros: FxHashMap::default(),
                 macro_resolutions: FxHashMap::default(),
+                unresolved_imports: Vec::new(),
+                macro_imports: FxHashSet::default(),
                 problems: CrateDefMapProblems::default(),
             }
         };
@@ -228,10 +334,59 @@ impl CrateDefMap {
         &self.problems
     }
 
+    pub(crate) fn unresolved_imports(&self) -> &[(CrateModuleId, HirFileId, ImportId)] {
+        &self.unresolved_imports
+    }
+
+    /// Whether an import with this id comes from a macro expansion in the
+    /// module. If it does, the id can't be mapped back to the module's file.
+    pub(crate) fn is_macro_import(&self, module_id: CrateModuleId, import: ImportId) -> bool {
+        self.macro_imports.contains(&(module_id, import))
+    }
+
     pub(crate) fn mk_module(&self, module_id: CrateModuleId) -> Module {
         Module { krate: self.krate, module_id }
     }
 
+    /// Counts the contents of this def map and estimates its heap usage. The
+    /// estimate covers the tables owned by the def map, but not the contents of
+    /// the macro definitions or of the names.
+    pub fn stats(&self) -> DefMapStats {
+        use std::mem::size_of;
+
+        fn map_size<K, V>(map: &FxHashMap<K, V>) -> usize {
+            map.capacity() * size_of::<(K, V)>()
+        }
+
+        let mut heap_size = self.modules.len() * size_of::<ModuleData>()
+            + self.macros.len() * size_of::<MacroDef>()
+            + map_size(&self.extern_prelude)
+            + map_size(&self.public_macros)
+            + map_size(&self.macro_resolutions)
+            + self.self_names.capacity() * size_of::<Name>()
+            + self.unresolved_imports.capacity()
+                * size_of::<(CrateModuleId, HirFileId, ImportId)>()
+            + self.problems.problems.capacity() * size_of::<(SourceItemId, Problem)>();
+        let mut scope_entries = 0;
+        for (_, module) in self.modules.iter() {
+            scope_entries += module.scope.items.len();
+            heap_size += map_size(&module.children)
+                + map_size(&module.scope.items)
+                + module.expansions.capacity() * size_of::<(MacroCallId, Vec<ModuleDef>)>()
+                + module
+                    .expansions
+                    .iter()
+                    .map(|(_, defs)| defs.capacity() * size_of::<ModuleDef>())
+                    .sum::<usize>();
+        }
+        DefMapStats {
+            modules: self.modules.len(),
+            scope_entries,
+            macros: self.macros.len(),
+            heap_size,
+        }
+    }
+
     pub(crate) fn prelude(&self) -> Option<Module> {
         self.prelude
     }
@@ -240,6 +395,52 @@ impl CrateDefMap {
         &self.extern_prelude
     }
 
+    /// The names which may follow `::` at the start of a path, see
+    /// `resolve_path_fp`. The crate root shadows the extern prelude, so its
+    /// items come last.
+    pub(crate) fn global_path_names(&self) -> Vec<(Name, PerNs<ModuleDef>)> {
+        let mut res: Vec<_> = self
+            .extern_prelude
+            .iter()
+            .map(|(name, def)| (name.clone(), PerNs::types(*def)))
+            .collect();
+        if self.edition == Edition::Edition2015 {
+            res.extend(self[self.root].scope.entries().map(|(name, res)| (name.clone(), res.def)));
+        }
+        res
+    }
+
+    /// Resolves visibility of an item, declared in `original_module`. Paths
+    /// which don't resolve to a module of this crate make the item private.
+    pub(crate) fn resolve_visibility(
+        &self,
+        db: &impl DefDatabase,
+        original_module: CrateModuleId,
+        visibility: &RawVisibility,
+    ) -> Visibility {
+        let path = match visibility {
+            RawVisibility::Public => return Visibility::Public,
+            RawVisibility::Module(path) => path,
+        };
+        let module = match self.resolve_path(db, original_module, path).0.take_types() {
+            Some(ModuleDef::Module(module)) if module.krate == self.krate => module,
+            _ => self.mk_module(original_module),
+        };
+        Visibility::Module(module)
+    }
+
+    fn is_descendant_of(&self, mut module: CrateModuleId, ancestor: CrateModuleId) -> bool {
+        loop {
+            if module == ancestor {
+                return true;
+            }
+            module = match self.modules[module].parent {
+                Some(it) => it,
+                None => return false,
+            };
+        }
+    }
+
     pub(crate) fn resolve_macro(
         &self,
         macro_call_id: MacroCallId,
@@ -273,6 +474,56 @@ impl CrateDefMap {
         (res.resolved_def, res.segment_index)
     }
 
+    /// Resolves the path of a `use` item of `original_module`.
+    pub(crate) fn resolve_import_path(
+        &self,
+        db: &impl DefDatabase,
+        original_module: CrateModuleId,
+        path: &Path,
+    ) -> PerNs<ModuleDef> {
+        self.resolve_path_fp(db, ResolveMode::Import, original_module, path).resolved_def
+    }
+
+    /// Returns the imports of this crate through which `path` resolves: for
+    /// `foo::Bar` after `use baz::foo;`, this is the `foo` import. Partially
+    /// resolved paths, like `Foo::new`, use their imports as well. Each import
+    /// comes with the name it was used for.
+    pub(crate) fn imports_used_by_path(
+        &self,
+        db: &impl DefDatabase,
+        original_module: CrateModuleId,
+        path: &Path,
+        is_import: bool,
+    ) -> Vec<(CrateModuleId, ImportId, Name)> {
+        let mode = if is_import { ResolveMode::Import } else { ResolveMode::Other };
+        let mut lookups = Vec::new();
+        self.resolve_path_fp_with_lookups(db, mode, original_module, path, &mut lookups);
+        lookups
+            .into_iter()
+            .filter(|it| it.module.krate == self.krate)
+            .filter_map(|it| {
+                let name = path.segments[it.segment].name.clone();
+                Some((it.module.module_id, it.resolution.import?, name))
+            })
+            .collect()
+    }
+
+    /// Returns the segments of `path` which are resolved in the scope of some
+    /// module, in order. This doesn't include the segments resolved in other
+    /// ways, like enum variants.
+    pub(crate) fn path_lookups(
+        &self,
+        db: &impl DefDatabase,
+        original_module: CrateModuleId,
+        path: &Path,
+        is_import: bool,
+    ) -> Vec<ScopeLookup> {
+        let mode = if is_import { ResolveMode::Import } else { ResolveMode::Other };
+        let mut lookups = Vec::new();
+        self.resolve_path_fp_with_lookups(db, mode, original_module, path, &mut lookups);
+        lookups
+    }
+
     // Returns Yes if we are sure that additions to `ItemMap` wouldn't change
     // the result.
     fn resolve_path_fp(
@@ -281,6 +532,19 @@ impl CrateDefMap {
         mode: ResolveMode,
         original_module: CrateModuleId,
         path: &Path,
+    ) -> ResolvePathResult {
+        self.resolve_path_fp_with_lookups(db, mode, original_module, path, &mut Vec::new())
+    }
+
+    /// Like `resolve_path_fp`, but also records the segments of the path which
+    /// are resolved in module scopes.
+    fn resolve_path_fp_with_lookups(
+        &self,
+        db: &impl DefDatabase,
+        mode: ResolveMode,
+        original_module: CrateModuleId,
+        path: &Path,
+        lookups: &mut Vec<ScopeLookup>,
     ) -> ResolvePathResult {
         let mut segments = path.segments.iter().enumerate();
         let mut curr_per_ns: PerNs<ModuleDef> = match path.kind {
@@ -303,6 +567,7 @@ impl CrateDefMap {
                     None => return ResolvePathResult::empty(ReachedFixedPoint::Yes),
                 };
                 log::debug!("resolving {:?} in crate root (+ extern prelude)", segment);
+                self.record_lookup(self.root, 0, &segment.name, lookups);
                 self.resolve_name_in_crate_root_or_extern_prelude(&segment.name)
             }
             PathKind::Plain => {
@@ -311,15 +576,21 @@ impl CrateDefMap {
                     None => return ResolvePathResult::empty(ReachedFixedPoint::Yes),
                 };
                 log::debug!("resolving {:?} in module", segment);
+                self.record_lookup(original_module, 0, &segment.name, lookups);
                 self.resolve_name_in_module(db, original_module, &segment.name)
             }
-            PathKind::Super => {
-                if let Some(p) = self.modules[original_module].parent {
-                    PerNs::types(Module { krate: self.krate, module_id: p }.into())
-                } else {
-                    log::debug!("super path in root module");
-                    return ResolvePathResult::empty(ReachedFixedPoint::Yes);
+            PathKind::Super(n) => {
+                let mut module_id = original_module;
+                for _ in 0..n {
+                    module_id = match self.modules[module_id].parent {
+                        Some(it) => it,
+                        None => {
+                            log::debug!("super path goes above the root module");
+                            return ResolvePathResult::empty(ReachedFixedPoint::Yes);
+                        }
+                    };
                 }
+                PerNs::types(Module { krate: self.krate, module_id }.into())
             }
             PathKind::Abs => {
                 // 2018-style absolute path -- only extern prelude
@@ -327,9 +598,10 @@ impl CrateDefMap {
                     Some((_, segment)) => segment,
                     None => return ResolvePathResult::empty(ReachedFixedPoint::Yes),
                 };
-                if let Some(def) = self.extern_prelude.get(&segment.name) {
+                let def = self.resolve_name_in_extern_prelude_or_self(&segment.name);
+                if !def.is_none() {
                     log::debug!("absolute path {:?} resolved to crate {:?}", path, def);
-                    PerNs::types(*def)
+                    def
                 } else {
                     return ResolvePathResult::empty(ReachedFixedPoint::No); // extern crate declarations can add to the extern prelude
                 }
@@ -359,14 +631,25 @@ impl CrateDefMap {
                         };
                         log::debug!("resolving {:?} in other crate", path);
                         let defp_map = db.crate_def_map(module.krate);
-                        let (def, s) = defp_map.resolve_path(db, module.module_id, &path);
+                        let start = lookups.len();
+                        let res = defp_map.resolve_path_fp_with_lookups(
+                            db,
+                            ResolveMode::Other,
+                            module.module_id,
+                            &path,
+                            lookups,
+                        );
+                        for lookup in lookups[start..].iter_mut() {
+                            lookup.segment += i;
+                        }
                         return ResolvePathResult::with(
-                            def,
+                            res.resolved_def,
                             ReachedFixedPoint::Yes,
-                            s.map(|s| s + i),
+                            res.segment_index.map(|s| s + i),
                         );
                     }
 
+                    self.record_lookup(module.module_id, i, &segment.name, lookups);
                     match self[module.module_id].scope.items.get(&segment.name) {
                         Some(res) if !res.def.is_none() => res.def,
                         _ => {
@@ -409,6 +692,21 @@ impl CrateDefMap {
         ResolvePathResult::with(curr_per_ns, ReachedFixedPoint::Yes, None)
     }
 
+    fn record_lookup(
+        &self,
+        module: CrateModuleId,
+        segment: usize,
+        name: &Name,
+        lookups: &mut Vec<ScopeLookup>,
+    ) {
+        if let Some(resolution) = self[module].scope.items.get(name) {
+            if !resolution.def.is_none() {
+                let module = self.mk_module(module);
+                lookups.push(ScopeLookup { module, segment, resolution: resolution.clone() });
+            }
+        }
+    }
+
     fn resolve_name_in_crate_root_or_extern_prelude(&self, name: &Name) -> PerNs<ModuleDef> {
         let from_crate_root =
             self[self.root].scope.items.get(name).map_or(PerNs::none(), |it| it.def);
@@ -428,8 +726,7 @@ impl CrateDefMap {
         //  - extern prelude
         //  - std prelude
         let from_scope = self[module].scope.items.get(name).map_or(PerNs::none(), |it| it.def);
-        let from_extern_prelude =
-            self.extern_prelude.get(name).map_or(PerNs::none(), |&it| PerNs::types(it));
+        let from_extern_prelude = self.resolve_name_in_extern_prelude_or_self(name);
         let from_prelude = self.resolve_in_prelude(db, name);
 
         from_scope.or(from_extern_prelude).or(from_prelude)
@@ -439,6 +736,14 @@ impl CrateDefMap {
         self.extern_prelude.get(name).map_or(PerNs::none(), |&it| PerNs::types(it))
     }
 
+    fn resolve_name_in_extern_prelude_or_self(&self, name: &Name) -> PerNs<ModuleDef> {
+        let from_extern_prelude = self.resolve_name_in_extern_prelude(name);
+        if !from_extern_prelude.is_none() || !self.self_names.contains(name) {
+            return from_extern_prelude;
+        }
+        PerNs::types(self.mk_module(self.root).into())
+    }
+
     fn resolve_in_prelude(&self, db: &impl DefDatabase, name: &Name) -> PerNs<ModuleDef> {
         if let Some(prelude) = self.prelude {
             let resolution = if prelude.krate == self.krate {
@@ -2,19 +2,22 @@ use arrayvec::ArrayVec;
 use rustc_hash::FxHashMap;
 use relative_path::RelativePathBuf;
 use test_utils::tested_by;
-use ra_db::FileId;
+use ra_db::{FileId, Edition};
 
 use crate::{
     Function, Module, Struct, Enum, Const, Static, Trait, TypeAlias,
     DefDatabase, HirFileId, Name, Path, Problem, Crate,
-    KnownName,
-    nameres::{Resolution, PerNs, ModuleDef, ReachedFixedPoint, ResolveMode, raw},
+    KnownName, AsName,
+    nameres::{
+        Resolution, PerNs, Namespace, Visibility, ModuleDef, ReachedFixedPoint, ResolveMode, raw,
+    },
     ids::{AstItemDef, LocationCtx, MacroCallLoc, SourceItemId, MacroCallId},
 };
 
-use super::{CrateDefMap, CrateModuleId, ModuleData, CrateMacroId};
+use super::{CrateDefMap, CrateModuleId, ModuleData, CrateMacroId, MacroDef};
 
 pub(super) fn collect_defs(db: &impl DefDatabase, mut def_map: CrateDefMap) -> CrateDefMap {
+    let _p = ra_prof::profile("collect_defs");
     // populate external prelude
     for dep in def_map.krate.dependencies(db) {
         log::debug!("crate dep {:?} -> {:?}", dep.name, dep.krate);
@@ -30,13 +33,33 @@ pub(super) fn collect_defs(db: &impl DefDatabase, mut def_map: CrateDefMap) -> C
         }
     }
 
+    // in 2018, the crate can refer to itself by the name its dependents use
+    if def_map.edition == Edition::Edition2018 {
+        let crate_graph = db.crate_graph();
+        for krate in crate_graph.iter() {
+            for dep in crate_graph.dependencies(krate) {
+                if dep.crate_id() != def_map.krate.crate_id() {
+                    continue;
+                }
+                let name = dep.as_name();
+                if !def_map.self_names.contains(&name) {
+                    def_map.self_names.push(name);
+                }
+            }
+        }
+    }
+
     let mut collector = DefCollector {
         db,
         def_map,
         glob_imports: FxHashMap::default(),
         unresolved_imports: Vec::new(),
         unexpanded_macros: Vec::new(),
-        global_macro_scope: FxHashMap::default(),
+        def_sources: FxHashMap::default(),
+        next_source_order: FxHashMap::default(),
+        macro_stack: Vec::new(),
+        expansion_count: 0,
+        expansion_limit_reached: false,
     };
     collector.collect();
     collector.finish()
@@ -46,12 +69,31 @@ pub(super) fn collect_defs(db: &impl DefDatabase, mut def_map: CrateDefMap) -> C
 struct DefCollector<DB> {
     db: DB,
     def_map: CrateDefMap,
-    glob_imports: FxHashMap<CrateModuleId, Vec<(CrateModuleId, raw::ImportId)>>,
-    unresolved_imports: Vec<(CrateModuleId, raw::ImportId, raw::ImportData)>,
-    unexpanded_macros: Vec<(CrateModuleId, MacroCallId, Path, tt::Subtree)>,
-    global_macro_scope: FxHashMap<Name, CrateMacroId>,
+    glob_imports: FxHashMap<CrateModuleId, Vec<(CrateModuleId, raw::ImportId, Visibility, u32)>>,
+    unresolved_imports: Vec<(CrateModuleId, HirFileId, raw::ImportId, raw::ImportData, u32)>,
+    /// Calls to macros from other crates, along with the textual macro scope
+    /// at the call site.
+    unexpanded_macros:
+        Vec<(CrateModuleId, MacroCallId, Path, tt::Subtree, FxHashMap<Name, CrateMacroId>)>,
+    /// Where the items in module scopes are defined, to report duplicates.
+    /// Glob imports are not tracked: they can be shadowed.
+    def_sources: FxHashMap<(CrateModuleId, Name, Namespace), SourceItemId>,
+    /// The `Resolution::source_order` of the next item collected in a module.
+    next_source_order: FxHashMap<CrateModuleId, u32>,
+    /// Macro calls which are being expanded, outermost first.
+    macro_stack: Vec<(Name, MacroCallId)>,
+    /// The number of macro expansions done so far in this crate.
+    expansion_count: u32,
+    /// The limit is reported only once, so that a runaway macro does not
+    /// produce a problem for each of its calls.
+    expansion_limit_reached: bool,
 }
 
+/// How deep macro calls may be nested inside of each other's expansions.
+const EXPANSION_DEPTH_LIMIT: usize = 64;
+/// How many macro expansions may be done in a single crate.
+const EXPANSION_COUNT_LIMIT: u32 = 10_000;
+
 impl<'a, DB> DefCollector<&'a DB>
 where
     DB: DefDatabase,
@@ -67,12 +109,16 @@ where
             module_id,
             file_id: file_id.into(),
             raw_items: &raw_items,
+            macro_scope: FxHashMap::default(),
         }
         .collect(raw_items.items());
 
         // main name resolution fixed-point loop.
         let mut i = 0;
         loop {
+            // Resolving a large crate can take a while, so don't hold up the
+            // pending changes until we are done.
+            self.db.check_canceled();
             match (self.resolve_imports(), self.resolve_macros()) {
                 (ReachedFixedPoint::Yes, ReachedFixedPoint::Yes) => break,
                 _ => i += 1,
@@ -85,28 +131,63 @@ where
 
         let unresolved_imports = std::mem::replace(&mut self.unresolved_imports, Vec::new());
         // show unresolved imports in completion, etc
-        for (module_id, import, import_data) in unresolved_imports {
-            self.record_resolved_import(module_id, PerNs::none(), import, &import_data)
+        for (module_id, file_id, import, import_data, source_order) in unresolved_imports {
+            self.record_resolved_import(
+                module_id,
+                file_id,
+                PerNs::none(),
+                import,
+                &import_data,
+                source_order,
+            );
+            self.def_map.unresolved_imports.push((module_id, file_id, import));
         }
     }
 
-    fn define_macro(&mut self, name: Name, tt: &tt::Subtree, export: bool) {
-        if let Ok(rules) = mbe::MacroRules::parse(tt) {
-            let macro_id = self.def_map.macros.alloc(rules);
-            if export {
-                self.def_map.public_macros.insert(name.clone(), macro_id);
+    fn next_source_order(&mut self, module_id: CrateModuleId) -> u32 {
+        let next = self.next_source_order.entry(module_id).or_default();
+        let res = *next;
+        *next += 1;
+        res
+    }
+
+    fn define_macro(
+        &mut self,
+        source_item_id: SourceItemId,
+        name: Name,
+        tt: &tt::Subtree,
+        export: bool,
+    ) -> Option<CrateMacroId> {
+        let rules = match mbe::MacroRules::parse(tt) {
+            Ok(it) => it,
+            Err(err) => {
+                let message = format!("invalid macro definition: {}", err);
+                self.def_map.problems.add(source_item_id, Problem::MacroError { message });
+                return None;
             }
-            self.global_macro_scope.insert(name, macro_id);
+        };
+        let macro_id = self.def_map.macros.alloc(MacroDef { rules, body: tt.clone() });
+        if export {
+            self.def_map.public_macros.insert(name, macro_id);
         }
+        Some(macro_id)
     }
 
     fn resolve_imports(&mut self) -> ReachedFixedPoint {
+        let _p = ra_prof::profile("resolve_imports");
         let mut imports = std::mem::replace(&mut self.unresolved_imports, Vec::new());
         let mut resolved = Vec::new();
-        imports.retain(|(module_id, import, import_data)| {
+        imports.retain(|(module_id, file_id, import, import_data, source_order)| {
             let (def, fp) = self.resolve_import(*module_id, import_data);
             if fp == ReachedFixedPoint::Yes {
-                resolved.push((*module_id, def, *import, import_data.clone()))
+                resolved.push((
+                    *module_id,
+                    *file_id,
+                    def,
+                    *import,
+                    import_data.clone(),
+                    *source_order,
+                ))
             }
             fp == ReachedFixedPoint::No
         });
@@ -114,8 +195,8 @@ where
         // Resolves imports, filling-in module scopes
         let result =
             if resolved.is_empty() { ReachedFixedPoint::Yes } else { ReachedFixedPoint::No };
-        for (module_id, def, import, import_data) in resolved {
-            self.record_resolved_import(module_id, def, import, &import_data)
+        for (module_id, file_id, def, import, import_data, source_order) in resolved {
+            self.record_resolved_import(module_id, file_id, def, import, &import_data, source_order)
         }
         result
     }
@@ -145,10 +226,16 @@ where
     fn record_resolved_import(
         &mut self,
         module_id: CrateModuleId,
+        file_id: HirFileId,
         def: PerNs<ModuleDef>,
         import_id: raw::ImportId,
         import: &raw::ImportData,
+        source_order: u32,
     ) {
+        if file_id != file_id.original_file(self.db).into() {
+            self.def_map.macro_imports.insert((module_id, import_id));
+        }
+        let visibility = self.def_map.resolve_visibility(self.db, module_id, &import.visibility);
         if import.is_glob {
             log::debug!("glob import: {:?}", import);
             match def.take_types() {
@@ -164,9 +251,12 @@ where
                         let items = scope
                             .items
                             .iter()
-                            .map(|(name, res)| (name.clone(), res.clone()))
+                            .map(|(name, res)| {
+                                let res = Resolution { visibility, source_order, ..res.clone() };
+                                (name.clone(), res)
+                            })
                             .collect::<Vec<_>>();
-                        self.update(module_id, Some(import_id), &items);
+                        self.update(module_id, Some(import_id), None, &items);
                     } else {
                         // glob import from same crate => we do an initial
                         // import, and then need to propagate any further
@@ -175,14 +265,19 @@ where
                         let items = scope
                             .items
                             .iter()
-                            .map(|(name, res)| (name.clone(), res.clone()))
+                            .map(|(name, res)| {
+                                let res = Resolution { visibility, source_order, ..res.clone() };
+                                (name.clone(), res)
+                            })
                             .collect::<Vec<_>>();
-                        self.update(module_id, Some(import_id), &items);
+                        self.update(module_id, Some(import_id), None, &items);
                         // record the glob import in case we add further items
-                        self.glob_imports
-                            .entry(m.module_id)
-                            .or_default()
-                            .push((module_id, import_id));
+                        self.glob_imports.entry(m.module_id).or_default().push((
+                            module_id,
+                            import_id,
+                            visibility,
+                            source_order,
+                        ));
                     }
                 }
                 Some(ModuleDef::Enum(e)) => {
@@ -195,12 +290,14 @@ where
                             let res = Resolution {
                                 def: PerNs::both(variant.into(), variant.into()),
                                 import: Some(import_id),
+                                visibility,
+                                source_order,
                             };
                             let name = variant.name(self.db)?;
                             Some((name, res))
                         })
                         .collect::<Vec<_>>();
-                    self.update(module_id, Some(import_id), &resolutions);
+                    self.update(module_id, Some(import_id), None, &resolutions);
                 }
                 Some(d) => {
                     log::debug!("glob import {:?} from non-module/enum {:?}", import, d);
@@ -221,8 +318,16 @@ where
                             self.def_map.extern_prelude.insert(name.clone(), def);
                         }
                     }
-                    let resolution = Resolution { def, import: Some(import_id) };
-                    self.update(module_id, Some(import_id), &[(name, resolution)]);
+                    // `self` imports only bind the type namespace.
+                    let def = if import.is_self {
+                        def.take_types().map_or_else(PerNs::none, PerNs::types)
+                    } else {
+                        def
+                    };
+                    let resolution =
+                        Resolution { def, import: Some(import_id), visibility, source_order };
+                    let source = import.source_item_id.with_file_id(file_id);
+                    self.update(module_id, Some(import_id), Some(source), &[(name, resolution)]);
                 }
                 None => tested_by!(bogus_paths),
             }
@@ -233,15 +338,17 @@ where
         &mut self,
         module_id: CrateModuleId,
         import: Option<raw::ImportId>,
+        source: Option<SourceItemId>,
         resolutions: &[(Name, Resolution)],
     ) {
-        self.update_recursive(module_id, import, resolutions, 0)
+        self.update_recursive(module_id, import, source, resolutions, 0)
     }
 
     fn update_recursive(
         &mut self,
         module_id: CrateModuleId,
         import: Option<raw::ImportId>,
+        source: Option<SourceItemId>,
         resolutions: &[(Name, Resolution)],
         depth: usize,
     ) {
@@ -251,17 +358,32 @@ where
         }
         let module_items = &mut self.def_map.modules[module_id].scope;
         let mut changed = false;
+        let mut duplicates = Vec::new();
         for (name, res) in resolutions {
             let existing = module_items.items.entry(name.clone()).or_default();
             if existing.def.types.is_none() && res.def.types.is_some() {
                 existing.def.types = res.def.types;
                 existing.import = import.or(res.import);
+                existing.visibility = res.visibility;
+                existing.source_order = res.source_order;
                 changed = true;
+                if let Some(source) = source {
+                    self.def_sources.insert((module_id, name.clone(), Namespace::Types), source);
+                }
+            } else if res.def.types.is_some() && existing.def.types != res.def.types {
+                duplicates.push((name.clone(), Namespace::Types));
             }
             if existing.def.values.is_none() && res.def.values.is_some() {
                 existing.def.values = res.def.values;
                 existing.import = import.or(res.import);
+                existing.visibility = res.visibility;
+                existing.source_order = res.source_order;
                 changed = true;
+                if let Some(source) = source {
+                    self.def_sources.insert((module_id, name.clone(), Namespace::Values), source);
+                }
+            } else if res.def.values.is_some() && existing.def.values != res.def.values {
+                duplicates.push((name.clone(), Namespace::Values));
             }
             if existing.def.is_none()
                 && res.def.is_none()
@@ -269,6 +391,17 @@ where
                 && res.import.is_some()
             {
                 existing.import = res.import;
+                existing.source_order = res.source_order;
+            }
+        }
+        if let Some(source) = source {
+            for (name, namespace) in duplicates {
+                let key = (module_id, name.clone(), namespace);
+                if let Some(&first_definition) = self.def_sources.get(&key) {
+                    let problem =
+                        Problem::DuplicateDefinition { name, namespace, first_definition };
+                    self.def_map.problems.add(source, problem);
+                }
             }
         }
         if !changed {
@@ -281,9 +414,21 @@ where
             .flat_map(|v| v.iter())
             .cloned()
             .collect::<Vec<_>>();
-        for (glob_importing_module, glob_import) in glob_imports {
+        for (glob_importing_module, glob_import, visibility, source_order) in glob_imports {
+            let resolutions = resolutions
+                .iter()
+                .map(|(name, res)| {
+                    (name.clone(), Resolution { visibility, source_order, ..res.clone() })
+                })
+                .collect::<Vec<_>>();
             // We pass the glob import so that the tracked import in those modules is that glob import
-            self.update_recursive(glob_importing_module, Some(glob_import), resolutions, depth + 1);
+            self.update_recursive(
+                glob_importing_module,
+                Some(glob_import),
+                None,
+                &resolutions,
+                depth + 1,
+            );
         }
     }
 
@@ -293,7 +438,7 @@ where
         let mut macros = std::mem::replace(&mut self.unexpanded_macros, Vec::new());
         let mut resolved = Vec::new();
         let mut res = ReachedFixedPoint::Yes;
-        macros.retain(|(module_id, call_id, path, tt)| {
+        macros.retain(|(module_id, call_id, path, tt, macro_scope)| {
             if path.segments.len() != 2 {
                 return true;
             }
@@ -309,13 +454,27 @@ where
             res = ReachedFixedPoint::No;
             let def_map = self.db.crate_def_map(krate);
             if let Some(macro_id) = def_map.public_macros.get(&path.segments[1].name).cloned() {
-                resolved.push((*module_id, *call_id, (krate, macro_id), tt.clone()));
+                resolved.push((
+                    *module_id,
+                    *call_id,
+                    path.segments[1].name.clone(),
+                    (krate, macro_id),
+                    tt.clone(),
+                    macro_scope.clone(),
+                ));
             }
             false
         });
 
-        for (module_id, macro_call_id, macro_def_id, arg) in resolved {
-            self.collect_macro_expansion(module_id, macro_call_id, macro_def_id, arg);
+        for (module_id, macro_call_id, name, macro_def_id, arg, macro_scope) in resolved {
+            self.collect_macro_expansion(
+                module_id,
+                macro_call_id,
+                name,
+                macro_def_id,
+                arg,
+                macro_scope,
+            );
         }
         res
     }
@@ -324,9 +483,24 @@ where
         &mut self,
         module_id: CrateModuleId,
         macro_call_id: MacroCallId,
+        macro_name: Name,
         macro_def_id: (Crate, CrateMacroId),
         macro_arg: tt::Subtree,
-    ) {
+        macro_scope: FxHashMap<Name, CrateMacroId>,
+    ) -> FxHashMap<Name, CrateMacroId> {
+        let _p = ra_prof::profile("collect_macro_expansion");
+        self.db.check_canceled();
+        if self.macro_stack.len() >= EXPANSION_DEPTH_LIMIT
+            || self.expansion_count >= EXPANSION_COUNT_LIMIT
+        {
+            if !self.expansion_limit_reached {
+                self.expansion_limit_reached = true;
+                self.report_expansion_limit(macro_call_id, &macro_name);
+            }
+            return macro_scope;
+        }
+        self.expansion_count += 1;
+
         let (macro_krate, macro_id) = macro_def_id;
         let dm;
         let rules = if macro_krate == self.def_map.krate {
@@ -335,21 +509,56 @@ where
             dm = self.db.crate_def_map(macro_krate);
             &dm[macro_id]
         };
-        if let Ok(expansion) = rules.expand(&macro_arg) {
-            self.def_map.macro_resolutions.insert(macro_call_id, macro_def_id);
-            // XXX: this **does not** go through a database, because we can't
-            // identify macro_call without adding the whole state of name resolution
-            // as a parameter to the query.
-            //
-            // So, we run the queries "manually" and we must ensure that
-            // `db.hir_parse(macro_call_id)` returns the same source_file.
-            let file_id: HirFileId = macro_call_id.into();
-            let source_file = mbe::token_tree_to_ast_item_list(&expansion);
-
-            let raw_items = raw::RawItems::from_source_file(&source_file, file_id);
-            ModCollector { def_collector: &mut *self, file_id, module_id, raw_items: &raw_items }
-                .collect(raw_items.items())
-        }
+        let expansion = match rules.expand(&macro_arg) {
+            Ok(it) => it,
+            Err(err) => {
+                let source_item_id = macro_call_id.loc(self.db).source_item_id;
+                let message = format!("failed to expand macro: {}", err);
+                self.def_map.problems.add(source_item_id, Problem::MacroError { message });
+                return macro_scope;
+            }
+        };
+        self.def_map.macro_resolutions.insert(macro_call_id, macro_def_id);
+        // XXX: this **does not** go through a database, because we can't
+        // identify macro_call without adding the whole state of name resolution
+        // as a parameter to the query.
+        //
+        // So, we run the queries "manually" and we must ensure that
+        // `db.hir_parse(macro_call_id)` returns the same source_file.
+        let file_id: HirFileId = macro_call_id.into();
+        let source_file = mbe::token_tree_to_ast_item_list(&expansion);
+
+        let raw_items = raw::RawItems::from_source_file(&source_file, file_id);
+        self.macro_stack.push((macro_name, macro_call_id));
+        let mut collector = ModCollector {
+            def_collector: &mut *self,
+            file_id,
+            module_id,
+            raw_items: &raw_items,
+            macro_scope,
+        };
+        collector.collect(raw_items.items());
+        // Macros defined by the expansion are visible after the call.
+        let macro_scope = collector.macro_scope;
+        self.macro_stack.pop();
+        macro_scope
+    }
+
+    /// Reports the chain of macro calls leading to `macro_call_id` at the
+    /// outermost call, which, unlike the nested ones, is in a real file.
+    fn report_expansion_limit(&mut self, macro_call_id: MacroCallId, macro_name: &Name) {
+        let outermost = self.macro_stack.first().map_or(macro_call_id, |&(_, id)| id);
+        let source_item_id = outermost.loc(self.db).source_item_id;
+        let chain = self
+            .macro_stack
+            .iter()
+            .map(|(name, _)| name)
+            .chain(std::iter::once(macro_name))
+            .map(|name| format!("{}!", name))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        let message = format!("macro expansion limit reached: {}", chain);
+        self.def_map.problems.add(source_item_id, Problem::MacroError { message });
     }
 
     fn finish(self) -> CrateDefMap {
@@ -363,6 +572,10 @@ struct ModCollector<'a, D> {
     module_id: CrateModuleId,
     file_id: HirFileId,
     raw_items: &'a raw::RawItems,
+    /// `macro_rules` visible at the current point of the module: those defined
+    /// earlier in this module or in its ancestors, before the module itself,
+    /// and those from earlier `#[macro_use]` child modules.
+    macro_scope: FxHashMap<Name, CrateMacroId>,
 }
 
 impl<DB> ModCollector<'_, &'_ mut DefCollector<&'_ DB>>
@@ -371,38 +584,53 @@ where
 {
     fn collect(&mut self, items: &[raw::RawItem]) {
         for item in items {
+            let source_order = self.def_collector.next_source_order(self.module_id);
             match *item {
-                raw::RawItem::Module(m) => self.collect_module(&self.raw_items[m]),
+                raw::RawItem::Module(m) => self.collect_module(&self.raw_items[m], source_order),
                 raw::RawItem::Import(import) => self.def_collector.unresolved_imports.push((
                     self.module_id,
+                    self.file_id,
                     import,
                     self.raw_items[import].clone(),
+                    source_order,
                 )),
-                raw::RawItem::Def(def) => self.define_def(&self.raw_items[def]),
+                raw::RawItem::Def(def) => self.define_def(&self.raw_items[def], source_order),
                 raw::RawItem::Macro(mac) => self.collect_macro(&self.raw_items[mac]),
             }
         }
     }
 
-    fn collect_module(&mut self, module: &raw::ModuleData) {
+    fn collect_module(&mut self, module: &raw::ModuleData, source_order: u32) {
         match module {
             // inline module, just recurse
-            raw::ModuleData::Definition { name, items, source_item_id } => {
+            raw::ModuleData::Definition {
+                name,
+                items,
+                source_item_id,
+                visibility,
+                is_macro_use,
+            } => {
                 let module_id = self.push_child_module(
                     name.clone(),
                     source_item_id.with_file_id(self.file_id),
                     None,
+                    visibility,
+                    source_order,
                 );
-                ModCollector {
+                let mut collector = ModCollector {
                     def_collector: &mut *self.def_collector,
                     module_id,
                     file_id: self.file_id,
                     raw_items: self.raw_items,
+                    macro_scope: self.macro_scope.clone(),
+                };
+                collector.collect(&*items);
+                if *is_macro_use {
+                    self.macro_scope = collector.macro_scope;
                 }
-                .collect(&*items);
             }
             // out of line module, resovle, parse and recurse
-            raw::ModuleData::Declaration { name, source_item_id } => {
+            raw::ModuleData::Declaration { name, source_item_id, visibility, is_macro_use } => {
                 let source_item_id = source_item_id.with_file_id(self.file_id);
                 let is_root = self.def_collector.def_map.modules[self.module_id].parent.is_none();
                 let (file_ids, problem) =
@@ -413,16 +641,25 @@ where
                 }
 
                 if let Some(&file_id) = file_ids.first() {
-                    let module_id =
-                        self.push_child_module(name.clone(), source_item_id, Some(file_id));
+                    let module_id = self.push_child_module(
+                        name.clone(),
+                        source_item_id,
+                        Some(file_id),
+                        visibility,
+                        source_order,
+                    );
                     let raw_items = self.def_collector.db.raw_items(file_id);
-                    ModCollector {
+                    let mut collector = ModCollector {
                         def_collector: &mut *self.def_collector,
                         module_id,
                         file_id: file_id.into(),
                         raw_items: &raw_items,
+                        macro_scope: self.macro_scope.clone(),
+                    };
+                    collector.collect(raw_items.items());
+                    if *is_macro_use {
+                        self.macro_scope = collector.macro_scope;
                     }
-                    .collect(raw_items.items())
                 }
             }
         }
@@ -433,24 +670,26 @@ where
         name: Name,
         declaration: SourceItemId,
         definition: Option<FileId>,
+        visibility: &raw::RawVisibility,
+        source_order: u32,
     ) -> CrateModuleId {
+        let visibility = self.resolve_visibility(visibility);
         let modules = &mut self.def_collector.def_map.modules;
         let res = modules.alloc(ModuleData::default());
         modules[res].parent = Some(self.module_id);
         modules[res].declaration = Some(declaration);
         modules[res].definition = definition;
         modules[self.module_id].children.insert(name.clone(), res);
-        let resolution = Resolution {
-            def: PerNs::types(
-                Module { krate: self.def_collector.def_map.krate, module_id: res }.into(),
-            ),
-            import: None,
-        };
-        self.def_collector.update(self.module_id, None, &[(name, resolution)]);
+        let def: ModuleDef =
+            Module { krate: self.def_collector.def_map.krate, module_id: res }.into();
+        self.record_expanded_def(def);
+        let resolution =
+            Resolution { def: PerNs::types(def), import: None, visibility, source_order };
+        self.def_collector.update(self.module_id, None, Some(declaration), &[(name, resolution)]);
         res
     }
 
-    fn define_def(&mut self, def: &raw::DefData) {
+    fn define_def(&mut self, def: &raw::DefData, source_order: u32) {
         let module = Module { krate: self.def_collector.def_map.krate, module_id: self.module_id };
         let ctx = LocationCtx::new(self.def_collector.db, module, self.file_id.into());
         macro_rules! id {
@@ -459,6 +698,8 @@ where
             };
         }
         let name = def.name.clone();
+        let def_source = def.source_item_id;
+        let visibility = self.resolve_visibility(&def.visibility);
         let def: PerNs<ModuleDef> = match def.kind {
             raw::DefKind::Function => PerNs::values(Function { id: id!() }.into()),
             raw::DefKind::Struct => {
@@ -471,20 +712,49 @@ where
             raw::DefKind::Trait => PerNs::types(Trait { id: id!() }.into()),
             raw::DefKind::TypeAlias => PerNs::types(TypeAlias { id: id!() }.into()),
         };
-        let resolution = Resolution { def, import: None };
-        self.def_collector.update(self.module_id, None, &[(name, resolution)])
+        if let Some(module_def) = def.take_types().or(def.take_values()) {
+            self.record_expanded_def(module_def);
+        }
+        let resolution = Resolution { def, import: None, visibility, source_order };
+        let source = def_source.with_file_id(self.file_id);
+        self.def_collector.update(self.module_id, None, Some(source), &[(name, resolution)])
+    }
+
+    fn resolve_visibility(&self, visibility: &raw::RawVisibility) -> Visibility {
+        let def_collector = &*self.def_collector;
+        def_collector.def_map.resolve_visibility(def_collector.db, self.module_id, visibility)
+    }
+
+    /// If we are collecting a macro expansion, remembers that `def` was
+    /// produced by this macro call.
+    fn record_expanded_def(&mut self, def: ModuleDef) {
+        let macro_call_id = match self.file_id.as_macro_call_id() {
+            Some(it) => it,
+            None => return,
+        };
+        let expansions = &mut self.def_collector.def_map.modules[self.module_id].expansions;
+        match expansions.iter_mut().find(|(id, _)| *id == macro_call_id) {
+            Some((_, defs)) => defs.push(def),
+            None => expansions.push((macro_call_id, vec![def])),
+        }
     }
 
     fn collect_macro(&mut self, mac: &raw::MacroData) {
-        // Case 1: macro rules, define a macro in crate-global mutable scope
+        let source_item_id = SourceItemId { file_id: self.file_id, item_id: mac.source_item_id };
+
+        // Case 1: macro rules, define a macro in the textual scope
         if is_macro_rules(&mac.path) {
             if let Some(name) = &mac.name {
-                self.def_collector.define_macro(name.clone(), &mac.arg, mac.export)
+                let def_collector = &mut *self.def_collector;
+                if let Some(macro_id) =
+                    def_collector.define_macro(source_item_id, name.clone(), &mac.arg, mac.export)
+                {
+                    self.macro_scope.insert(name.clone(), macro_id);
+                }
             }
             return;
         }
 
-        let source_item_id = SourceItemId { file_id: self.file_id, item_id: mac.source_item_id };
         let macro_call_id = MacroCallLoc {
             module: Module { krate: self.def_collector.def_map.krate, module_id: self.module_id },
             source_item_id,
@@ -493,14 +763,17 @@ where
 
         // Case 2: try to expand macro_rules from this crate, triggering
         // recursive item collection.
-        if let Some(&macro_id) =
-            mac.path.as_ident().and_then(|name| self.def_collector.global_macro_scope.get(name))
-        {
-            self.def_collector.collect_macro_expansion(
+        let local_macro =
+            mac.path.as_ident().and_then(|name| Some((name, *self.macro_scope.get(name)?)));
+        if let Some((name, macro_id)) = local_macro {
+            let macro_scope = std::mem::replace(&mut self.macro_scope, FxHashMap::default());
+            self.macro_scope = self.def_collector.collect_macro_expansion(
                 self.module_id,
                 macro_call_id,
+                name.clone(),
                 (self.def_collector.def_map.krate, macro_id),
                 mac.arg.clone(),
+                macro_scope,
             );
             return;
         }
@@ -511,6 +784,7 @@ where
             macro_call_id,
             mac.path.clone(),
             mac.arg.clone(),
+            self.macro_scope.clone(),
         ))
     }
 }
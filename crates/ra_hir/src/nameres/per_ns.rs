@@ -75,4 +75,8 @@ impl<T> PerNs<T> {
     pub fn map<U>(self, f: impl Fn(T) -> U) -> PerNs<U> {
         PerNs { types: self.types.map(&f), values: self.values.map(&f) }
     }
+
+    pub fn filter(self, f: impl Fn(&T) -> bool) -> PerNs<T> {
+        PerNs { types: self.types.filter(&f), values: self.values.filter(&f) }
+    }
 }
@@ -0,0 +1,659 @@
+//! Persists `CrateDefMap`s across IDE sessions, so that large dependencies
+//! don't have to be collected on every start.
+//!
+//! Most ids in a def map are interned and so are meaningful only in the
+//! current session. Instead of raw ids, we store the locations the ids are
+//! interned from, and intern them anew when loading the def map. Macro
+//! definitions are stored as token trees and are reparsed when loading.
+//!
+//! A persisted def map is keyed by `CrateDefMap::input_hash`, a hash of the
+//! texts of the crate's files and of its dependencies. Item ids are stable for
+//! the same file texts, so a def map with a matching hash is valid as is.
+//! File and crate ids are assigned by the client and are stored as is, so
+//! they are hashed too: a def map is not reused if the ids are assigned
+//! differently in a new session.
+
+use std::hash::Hasher;
+
+use ra_arena::{Arena, ArenaId, RawId};
+use ra_db::{CrateId, Edition, FileId};
+use ra_syntax::{
+    SmolStr, ast,
+    encoding::{Reader, StableHasher, write_str, write_u32, write_u64},
+};
+use relative_path::RelativePathBuf;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Crate, Module, ModuleDef, Name, Problem, DefDatabase, HirFileId, Function, Struct, Enum,
+    EnumVariant, Const, Static, Trait, TypeAlias,
+    hygiene::SyntaxContext,
+    ids::{AstItemDef, ItemLoc, MacroCallId, MacroCallLoc, SourceItemId, SourceFileItemId},
+    nameres::{
+        CrateDefMap, CrateDefMapProblems, CrateModuleId, CrateMacroId, MacroDef, ModuleData,
+        ModuleScope, Resolution, Visibility, PerNs, Namespace, ImportId,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"RADM";
+/// Bump this when changing the encoding or the def map itself.
+const FORMAT_VERSION: u32 = 2;
+
+impl CrateDefMap {
+    /// Hashes all the inputs of the def map of `krate`: its id, the paths,
+    /// ids and texts of the files in its source root, its edition and,
+    /// recursively, its dependencies. The hash is stable across sessions.
+    pub fn input_hash(db: &impl DefDatabase, krate: Crate) -> u64 {
+        let crate_graph = db.crate_graph();
+        let mut hasher = StableHasher::default();
+        hasher.write_u32(krate.crate_id.0);
+
+        let edition = match crate_graph.edition(krate.crate_id) {
+            Edition::Edition2015 => 0,
+            Edition::Edition2018 => 1,
+        };
+        hasher.write_u8(edition);
+
+        let crate_root = crate_graph.crate_root(krate.crate_id);
+        let source_root = db.source_root(db.file_source_root(crate_root));
+        let mut files: Vec<(&RelativePathBuf, FileId)> =
+            source_root.files.iter().map(|(path, &file_id)| (path, file_id)).collect();
+        files.sort_by(|(lhs, _), (rhs, _)| lhs.as_str().cmp(rhs.as_str()));
+        for (path, file_id) in files {
+            hasher.write_str(path.as_str());
+            hasher.write_u32(file_id.0);
+            hasher.write_str(&db.file_text(file_id));
+            hasher.write_u8(u8::from(file_id == crate_root));
+        }
+
+        let mut deps: Vec<(SmolStr, CrateId)> = crate_graph
+            .dependencies(krate.crate_id)
+            .map(|dep| (dep.name.clone(), dep.crate_id()))
+            .collect();
+        deps.sort();
+        for (name, crate_id) in deps {
+            hasher.write_str(&name);
+            hasher.write_u64(CrateDefMap::input_hash(db, Crate { crate_id }));
+        }
+        hasher.finish()
+    }
+
+    /// Encodes this def map in a binary format, to be loaded back with
+    /// `from_bytes` in a later session.
+    pub fn to_bytes(&self, db: &impl DefDatabase) -> Vec<u8> {
+        let mut enc = Encoder { db, buf: Vec::new() };
+        enc.buf.extend_from_slice(MAGIC);
+        enc.u32(FORMAT_VERSION);
+        write_u64(&mut enc.buf, CrateDefMap::input_hash(db, self.krate));
+        enc.def_map(self);
+        enc.buf
+    }
+
+    /// Loads a def map saved with `to_bytes`. Returns `None` if the data is
+    /// malformed, was written by a different version of rust-analyzer, or if
+    /// the inputs of the def map changed since it was saved.
+    pub fn from_bytes(db: &impl DefDatabase, krate: Crate, bytes: &[u8]) -> Option<CrateDefMap> {
+        let mut dec = Decoder { db, reader: Reader::new(bytes), n_modules: 0, n_macros: 0 };
+        if dec.reader.take(MAGIC.len())? != MAGIC
+            || dec.u32()? != FORMAT_VERSION
+            || dec.reader.u64()? != CrateDefMap::input_hash(db, krate)
+        {
+            return None;
+        }
+        let def_map = dec.def_map(krate)?;
+        if !dec.reader.is_empty() {
+            return None;
+        }
+        Some(def_map)
+    }
+}
+
+struct Encoder<'a, DB> {
+    db: &'a DB,
+    buf: Vec<u8>,
+}
+
+impl<'a, DB: DefDatabase> Encoder<'a, DB> {
+    fn def_map(&mut self, def_map: &CrateDefMap) {
+        self.u8(match def_map.edition {
+            Edition::Edition2015 => 0,
+            Edition::Edition2018 => 1,
+        });
+        self.opt(def_map.prelude, Self::module);
+        self.u32(def_map.extern_prelude.len() as u32);
+        for (name, &def) in def_map.extern_prelude.iter() {
+            self.name(name);
+            self.module_def(def);
+        }
+        self.u32(def_map.self_names.len() as u32);
+        for name in def_map.self_names.iter() {
+            self.name(name);
+        }
+        self.id(def_map.root);
+
+        self.u32(def_map.modules.len() as u32);
+        for (_, module) in def_map.modules.iter() {
+            self.module_data(module);
+        }
+        self.u32(def_map.macros.len() as u32);
+        for (_, macro_def) in def_map.macros.iter() {
+            self.subtree(&macro_def.body);
+        }
+        self.u32(def_map.public_macros.len() as u32);
+        for (name, &macro_id) in def_map.public_macros.iter() {
+            self.name(name);
+            self.id(macro_id);
+        }
+        self.u32(def_map.macro_resolutions.len() as u32);
+        for (&macro_call_id, &(krate, macro_id)) in def_map.macro_resolutions.iter() {
+            self.macro_call(macro_call_id);
+            self.u32(krate.crate_id.0);
+            self.id(macro_id);
+        }
+        self.u32(def_map.unresolved_imports.len() as u32);
+        for &(module_id, file_id, import) in def_map.unresolved_imports.iter() {
+            self.id(module_id);
+            self.file_id(file_id);
+            self.id(import);
+        }
+        self.u32(def_map.macro_imports.len() as u32);
+        for &(module_id, import) in def_map.macro_imports.iter() {
+            self.id(module_id);
+            self.id(import);
+        }
+        self.u32(def_map.problems.problems.len() as u32);
+        for (source_item_id, problem) in def_map.problems.problems.iter() {
+            self.source_item_id(*source_item_id);
+            self.problem(problem);
+        }
+    }
+
+    fn module_data(&mut self, module: &ModuleData) {
+        self.opt(module.parent, Self::id);
+        self.u32(module.children.len() as u32);
+        for (name, &child) in module.children.iter() {
+            self.name(name);
+            self.id(child);
+        }
+        self.u32(module.scope.items.len() as u32);
+        for (name, res) in module.scope.items.iter() {
+            self.name(name);
+            self.opt(res.def.types, Self::module_def);
+            self.opt(res.def.values, Self::module_def);
+            self.opt(res.import, Self::id);
+            match res.visibility {
+                Visibility::Public => self.u8(0),
+                Visibility::Module(module) => {
+                    self.u8(1);
+                    self.module(module);
+                }
+            }
+            self.u32(res.source_order);
+        }
+        self.opt(module.declaration, Self::source_item_id);
+        self.opt(module.definition, |enc, file_id| enc.u32(file_id.0));
+        self.u32(module.expansions.len() as u32);
+        for (macro_call_id, defs) in module.expansions.iter() {
+            self.macro_call(*macro_call_id);
+            self.u32(defs.len() as u32);
+            for &def in defs.iter() {
+                self.module_def(def);
+            }
+        }
+    }
+
+    fn problem(&mut self, problem: &Problem) {
+        match problem {
+            Problem::UnresolvedModule { candidate } => {
+                self.u8(0);
+                self.str(candidate.as_str());
+            }
+            Problem::DuplicateDefinition { name, namespace, first_definition } => {
+                self.u8(1);
+                self.name(name);
+                self.u8(match namespace {
+                    Namespace::Types => 0,
+                    Namespace::Values => 1,
+                });
+                self.source_item_id(*first_definition);
+            }
+            Problem::MacroError { message } => {
+                self.u8(2);
+                self.str(message);
+            }
+        }
+    }
+
+    fn module_def(&mut self, def: ModuleDef) {
+        match def {
+            ModuleDef::Module(it) => {
+                self.u8(0);
+                self.module(it);
+            }
+            ModuleDef::Function(it) => {
+                self.u8(1);
+                self.item_loc(it.id);
+            }
+            ModuleDef::Struct(it) => {
+                self.u8(2);
+                self.item_loc(it.id);
+            }
+            ModuleDef::Enum(it) => {
+                self.u8(3);
+                self.item_loc(it.id);
+            }
+            ModuleDef::EnumVariant(it) => {
+                self.u8(4);
+                self.item_loc(it.parent.id);
+                self.id(it.id);
+            }
+            ModuleDef::Const(it) => {
+                self.u8(5);
+                self.item_loc(it.id);
+            }
+            ModuleDef::Static(it) => {
+                self.u8(6);
+                self.item_loc(it.id);
+            }
+            ModuleDef::Trait(it) => {
+                self.u8(7);
+                self.item_loc(it.id);
+            }
+            ModuleDef::TypeAlias(it) => {
+                self.u8(8);
+                self.item_loc(it.id);
+            }
+        }
+    }
+
+    fn item_loc<N: ast::AstNode, ID: AstItemDef<N>>(&mut self, id: ID) {
+        let loc = ID::interner(self.db.as_ref()).id2loc(id);
+        self.module(loc.module);
+        self.source_item_id(loc.raw);
+    }
+
+    fn macro_call(&mut self, macro_call_id: MacroCallId) {
+        let loc = macro_call_id.loc(self.db);
+        self.module(loc.module);
+        self.source_item_id(loc.source_item_id);
+    }
+
+    fn source_item_id(&mut self, source_item_id: SourceItemId) {
+        self.file_id(source_item_id.file_id);
+        self.id(source_item_id.item_id);
+    }
+
+    fn file_id(&mut self, file_id: HirFileId) {
+        match file_id.as_macro_call_id() {
+            None => {
+                self.u8(0);
+                self.u32(file_id.as_original_file().0);
+            }
+            Some(macro_call_id) => {
+                self.u8(1);
+                self.macro_call(macro_call_id);
+            }
+        }
+    }
+
+    fn module(&mut self, module: Module) {
+        self.u32(module.krate.crate_id.0);
+        self.id(module.module_id);
+    }
+
+    fn name(&mut self, name: &Name) {
+        let (text, ctx) = name.parts();
+        self.str(text);
+        self.opt(ctx.0, Self::macro_call);
+    }
+
+    fn subtree(&mut self, subtree: &tt::Subtree) {
+        self.u8(match subtree.delimiter {
+            tt::Delimiter::Parenthesis => 0,
+            tt::Delimiter::Brace => 1,
+            tt::Delimiter::Bracket => 2,
+            tt::Delimiter::None => 3,
+        });
+        self.u32(subtree.token_trees.len() as u32);
+        for token_tree in subtree.token_trees.iter() {
+            match token_tree {
+                tt::TokenTree::Leaf(tt::Leaf::Literal(it)) => {
+                    self.u8(0);
+                    self.str(&it.text);
+                }
+                tt::TokenTree::Leaf(tt::Leaf::Punct(it)) => {
+                    self.u8(1);
+                    self.u32(it.char as u32);
+                    self.u8(match it.spacing {
+                        tt::Spacing::Alone => 0,
+                        tt::Spacing::Joint => 1,
+                    });
+                }
+                tt::TokenTree::Leaf(tt::Leaf::Ident(it)) => {
+                    self.u8(2);
+                    self.str(&it.text);
+                    self.u32(it.id.0);
+                }
+                tt::TokenTree::Subtree(it) => {
+                    self.u8(3);
+                    self.subtree(it);
+                }
+            }
+        }
+    }
+
+    fn opt<T>(&mut self, value: Option<T>, f: impl FnOnce(&mut Self, T)) {
+        match value {
+            None => self.u8(0),
+            Some(it) => {
+                self.u8(1);
+                f(self, it);
+            }
+        }
+    }
+
+    fn id<ID: ArenaId>(&mut self, id: ID) {
+        self.u32(u32::from(id.into_raw()))
+    }
+
+    fn str(&mut self, s: &str) {
+        write_str(&mut self.buf, s)
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value)
+    }
+
+    fn u32(&mut self, value: u32) {
+        write_u32(&mut self.buf, value)
+    }
+}
+
+struct Decoder<'a, DB> {
+    db: &'a DB,
+    reader: Reader<'a>,
+    /// The numbers of modules and macros of the def map, to check the local
+    /// ids against.
+    n_modules: u32,
+    n_macros: u32,
+}
+
+impl<'a, DB: DefDatabase> Decoder<'a, DB> {
+    fn def_map(&mut self, krate: Crate) -> Option<CrateDefMap> {
+        let edition = match self.u8()? {
+            0 => Edition::Edition2015,
+            1 => Edition::Edition2018,
+            _ => return None,
+        };
+        let prelude = self.opt(Self::module)?;
+        let mut extern_prelude = FxHashMap::default();
+        for _ in 0..self.u32()? {
+            let name = self.name()?;
+            extern_prelude.insert(name, self.module_def()?);
+        }
+        let mut self_names = Vec::new();
+        for _ in 0..self.u32()? {
+            self_names.push(self.name()?);
+        }
+        let root_raw = self.u32()?;
+
+        self.n_modules = self.u32()?;
+        if root_raw >= self.n_modules {
+            return None;
+        }
+        let mut modules = Arena::default();
+        for _ in 0..self.n_modules {
+            modules.alloc(self.module_data()?);
+        }
+
+        self.n_macros = self.u32()?;
+        let mut macros = Arena::default();
+        for _ in 0..self.n_macros {
+            let body = self.subtree()?;
+            let rules = mbe::MacroRules::parse(&body).ok()?;
+            macros.alloc(MacroDef { rules, body });
+        }
+        let mut public_macros = FxHashMap::default();
+        for _ in 0..self.u32()? {
+            let name = self.name()?;
+            public_macros.insert(name, self.macro_id()?);
+        }
+        let mut macro_resolutions = FxHashMap::default();
+        for _ in 0..self.u32()? {
+            let macro_call_id = self.macro_call()?;
+            let krate = Crate { crate_id: CrateId(self.u32()?) };
+            // Macros of other crates can't be checked until their def maps are
+            // computed.
+            let macro_id = CrateMacroId::from_raw(RawId::from(self.u32()?));
+            macro_resolutions.insert(macro_call_id, (krate, macro_id));
+        }
+        let mut unresolved_imports = Vec::new();
+        for _ in 0..self.u32()? {
+            let module_id = self.module_id()?;
+            let file_id = self.file_id()?;
+            let import = self.id()?;
+            unresolved_imports.push((module_id, file_id, import));
+        }
+        let mut macro_imports = FxHashSet::default();
+        for _ in 0..self.u32()? {
+            let module_id = self.module_id()?;
+            macro_imports.insert((module_id, self.id()?));
+        }
+        let mut problems = CrateDefMapProblems::default();
+        for _ in 0..self.u32()? {
+            let source_item_id = self.source_item_id()?;
+            problems.add(source_item_id, self.problem()?);
+        }
+
+        Some(CrateDefMap {
+            krate,
+            edition,
+            prelude,
+            extern_prelude,
+            self_names,
+            root: CrateModuleId::from_raw(RawId::from(root_raw)),
+            modules,
+            macros,
+            public_macros,
+            macro_resolutions,
+            unresolved_imports,
+            macro_imports,
+            problems,
+        })
+    }
+
+    fn module_data(&mut self) -> Option<ModuleData> {
+        let parent = self.opt(Self::module_id)?;
+        let mut children = FxHashMap::default();
+        for _ in 0..self.u32()? {
+            let name = self.name()?;
+            children.insert(name, self.module_id()?);
+        }
+        let mut items = FxHashMap::default();
+        for _ in 0..self.u32()? {
+            let name = self.name()?;
+            let types = self.opt(Self::module_def)?;
+            let values = self.opt(Self::module_def)?;
+            let import: Option<ImportId> = self.opt(Self::id)?;
+            let visibility = match self.u8()? {
+                0 => Visibility::Public,
+                1 => Visibility::Module(self.module()?),
+                _ => return None,
+            };
+            let source_order = self.u32()?;
+            let def = PerNs { types, values };
+            items.insert(name, Resolution { def, import, visibility, source_order });
+        }
+        let declaration = self.opt(Self::source_item_id)?;
+        let definition = self.opt(|dec| dec.u32().map(FileId))?;
+        let mut expansions = Vec::new();
+        for _ in 0..self.u32()? {
+            let macro_call_id = self.macro_call()?;
+            let mut defs = Vec::new();
+            for _ in 0..self.u32()? {
+                defs.push(self.module_def()?);
+            }
+            expansions.push((macro_call_id, defs));
+        }
+        Some(ModuleData {
+            parent,
+            children,
+            scope: ModuleScope { items },
+            declaration,
+            definition,
+            expansions,
+        })
+    }
+
+    fn problem(&mut self) -> Option<Problem> {
+        let res = match self.u8()? {
+            0 => Problem::UnresolvedModule { candidate: RelativePathBuf::from(self.str()?) },
+            1 => {
+                let name = self.name()?;
+                let namespace = match self.u8()? {
+                    0 => Namespace::Types,
+                    1 => Namespace::Values,
+                    _ => return None,
+                };
+                let first_definition = self.source_item_id()?;
+                Problem::DuplicateDefinition { name, namespace, first_definition }
+            }
+            2 => Problem::MacroError { message: self.str()?.to_string() },
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    fn module_def(&mut self) -> Option<ModuleDef> {
+        let res = match self.u8()? {
+            0 => ModuleDef::Module(self.module()?),
+            1 => Function { id: self.item_loc()? }.into(),
+            2 => Struct { id: self.item_loc()? }.into(),
+            3 => Enum { id: self.item_loc()? }.into(),
+            4 => {
+                let parent = Enum { id: self.item_loc()? };
+                EnumVariant { parent, id: self.id()? }.into()
+            }
+            5 => Const { id: self.item_loc()? }.into(),
+            6 => Static { id: self.item_loc()? }.into(),
+            7 => Trait { id: self.item_loc()? }.into(),
+            8 => TypeAlias { id: self.item_loc()? }.into(),
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    fn item_loc<N: ast::AstNode, ID: AstItemDef<N>>(&mut self) -> Option<ID> {
+        let module = self.module()?;
+        let raw = self.source_item_id()?;
+        Some(ID::interner(self.db.as_ref()).loc2id(&ItemLoc::new(module, raw)))
+    }
+
+    fn macro_call(&mut self) -> Option<MacroCallId> {
+        let module = self.module()?;
+        let source_item_id = self.source_item_id()?;
+        Some(MacroCallLoc { module, source_item_id }.id(self.db))
+    }
+
+    fn source_item_id(&mut self) -> Option<SourceItemId> {
+        let file_id = self.file_id()?;
+        let item_id: SourceFileItemId = self.id()?;
+        Some(item_id.with_file_id(file_id))
+    }
+
+    fn file_id(&mut self) -> Option<HirFileId> {
+        match self.u8()? {
+            0 => Some(FileId(self.u32()?).into()),
+            1 => Some(self.macro_call()?.into()),
+            _ => None,
+        }
+    }
+
+    fn module(&mut self) -> Option<Module> {
+        let krate = Crate { crate_id: CrateId(self.u32()?) };
+        let module_id = CrateModuleId::from_raw(RawId::from(self.u32()?));
+        Some(Module { krate, module_id })
+    }
+
+    fn module_id(&mut self) -> Option<CrateModuleId> {
+        let raw = self.u32()?;
+        if raw >= self.n_modules {
+            return None;
+        }
+        Some(CrateModuleId::from_raw(RawId::from(raw)))
+    }
+
+    fn macro_id(&mut self) -> Option<CrateMacroId> {
+        let raw = self.u32()?;
+        if raw >= self.n_macros {
+            return None;
+        }
+        Some(CrateMacroId::from_raw(RawId::from(raw)))
+    }
+
+    fn name(&mut self) -> Option<Name> {
+        let text = SmolStr::new(self.str()?);
+        let ctx = SyntaxContext(self.opt(Self::macro_call)?);
+        Some(Name::from_parts(text, ctx))
+    }
+
+    fn subtree(&mut self) -> Option<tt::Subtree> {
+        let delimiter = match self.u8()? {
+            0 => tt::Delimiter::Parenthesis,
+            1 => tt::Delimiter::Brace,
+            2 => tt::Delimiter::Bracket,
+            3 => tt::Delimiter::None,
+            _ => return None,
+        };
+        let mut token_trees = Vec::new();
+        for _ in 0..self.u32()? {
+            let token_tree: tt::TokenTree = match self.u8()? {
+                0 => tt::Leaf::from(tt::Literal { text: self.str()?.into() }).into(),
+                1 => {
+                    let char = std::char::from_u32(self.u32()?)?;
+                    let spacing = match self.u8()? {
+                        0 => tt::Spacing::Alone,
+                        1 => tt::Spacing::Joint,
+                        _ => return None,
+                    };
+                    tt::Leaf::from(tt::Punct { char, spacing }).into()
+                }
+                2 => {
+                    let text = self.str()?.into();
+                    let id = tt::TokenId(self.u32()?);
+                    tt::Leaf::from(tt::Ident { text, id }).into()
+                }
+                3 => self.subtree()?.into(),
+                _ => return None,
+            };
+            token_trees.push(token_tree);
+        }
+        Some(tt::Subtree { delimiter, token_trees })
+    }
+
+    /// Reads a value written by `Encoder::opt`. The outer `Option` is `None`
+    /// if the data is malformed.
+    fn opt<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => Some(Some(f(self)?)),
+            _ => None,
+        }
+    }
+
+    fn id<ID: ArenaId>(&mut self) -> Option<ID> {
+        self.u32().map(|raw| ID::from_raw(RawId::from(raw)))
+    }
+
+    fn str(&mut self) -> Option<&'a str> {
+        self.reader.str()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.reader.byte()
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.reader.u32()
+    }
+}
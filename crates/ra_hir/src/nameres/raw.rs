@@ -7,12 +7,13 @@ use test_utils::tested_by;
 use ra_db::FileId;
 use ra_arena::{Arena, impl_arena_id, RawId, map::ArenaMap};
 use ra_syntax::{
-    AstNode, SourceFile, AstPtr, TreeArc,
-    ast::{self, NameOwner, AttrsOwner},
+    AstNode, SourceFile, AstPtr, TreeArc, TextRange, SyntaxNode,
+    SyntaxKind::ERROR,
+    ast::{self, NameOwner, AttrsOwner, VisibilityOwner},
 };
 
 use crate::{
-    DefDatabase, Name, AsName, Path, HirFileId, ModuleSource,
+    DefDatabase, Name, AsName, Path, PathKind, HirFileId, ModuleSource,
     ids::{SourceFileItemId, SourceFileItems},
 };
 
@@ -29,6 +30,10 @@ pub struct RawItems {
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct ImportSourceMap {
     map: ArenaMap<ImportId, AstPtr<ast::PathSegment>>,
+    /// Ranges of the use-tree leaves (or whole `extern crate` items) which
+    /// produced each import. These live here rather than in `ImportData`, so
+    /// that editing whitespace does not invalidate `RawItems`.
+    ranges: ArenaMap<ImportId, TextRange>,
 }
 
 impl ImportSourceMap {
@@ -36,6 +41,17 @@ impl ImportSourceMap {
         self.map.insert(import, AstPtr::new(segment))
     }
 
+    pub(crate) fn insert_range(&mut self, import: ImportId, range: TextRange) {
+        self.ranges.insert(import, range)
+    }
+
+    /// Returns the range of the leaf of a (possibly nested) use tree which
+    /// corresponds to `import`. For `use foo::{bar, baz::*}`, these are `bar`
+    /// and `baz::*`.
+    pub fn range(&self, import: ImportId) -> Option<TextRange> {
+        self.ranges.get(import).cloned()
+    }
+
     pub fn get(&self, source: &ModuleSource, import: ImportId) -> TreeArc<ast::PathSegment> {
         let file = match source {
             ModuleSource::SourceFile(file) => &*file,
@@ -73,12 +89,22 @@ impl RawItems {
     // is a query-less variant of the above function.
     pub(crate) fn from_source_file(source_file: &SourceFile, file_id: HirFileId) -> RawItems {
         let source_file_items = SourceFileItems::from_source_file(source_file, file_id);
+        RawItems::from_item_owner(source_file, Arc::new(source_file_items))
+    }
+
+    /// Collects raw items from any node which contains items, like an impl's
+    /// or a trait's `ItemList` or a `Block`. `source_file_items` must be the
+    /// items of the file `owner` belongs to.
+    pub(crate) fn from_item_owner(
+        owner: &impl ast::ModuleItemOwner,
+        source_file_items: Arc<SourceFileItems>,
+    ) -> RawItems {
         let mut collector = RawItemsCollector {
             raw_items: RawItems::default(),
-            source_file_items: Arc::new(source_file_items),
+            source_file_items,
             source_map: ImportSourceMap::default(),
         };
-        collector.process_module(None, &*source_file);
+        collector.process_module(None, owner);
         collector.raw_items
     }
 }
@@ -125,8 +151,19 @@ impl_arena_id!(Module);
 
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum ModuleData {
-    Declaration { name: Name, source_item_id: SourceFileItemId },
-    Definition { name: Name, source_item_id: SourceFileItemId, items: Vec<RawItem> },
+    Declaration {
+        name: Name,
+        visibility: RawVisibility,
+        source_item_id: SourceFileItemId,
+        is_macro_use: bool,
+    },
+    Definition {
+        name: Name,
+        visibility: RawVisibility,
+        source_item_id: SourceFileItemId,
+        is_macro_use: bool,
+        items: Vec<RawItem>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -138,8 +175,14 @@ pub struct ImportData {
     pub(crate) path: Path,
     pub(crate) alias: Option<Name>,
     pub(crate) is_glob: bool,
+    /// A `self` import in a use tree, like `use foo::{self}`, which binds the
+    /// module `foo` itself.
+    pub(crate) is_self: bool,
     pub(crate) is_prelude: bool,
     pub(crate) is_extern_crate: bool,
+    pub(crate) visibility: RawVisibility,
+    /// The `use` or `extern crate` item this import comes from.
+    pub(crate) source_item_id: SourceFileItemId,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -151,6 +194,7 @@ pub(crate) struct DefData {
     pub(crate) source_item_id: SourceFileItemId,
     pub(crate) name: Name,
     pub(crate) kind: DefKind,
+    pub(crate) visibility: RawVisibility,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -164,6 +208,42 @@ pub(crate) enum DefKind {
     TypeAlias,
 }
 
+/// Visibility as written in the source. It is resolved to a module during
+/// name resolution, see `CrateDefMap::resolve_visibility`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RawVisibility {
+    /// `pub`
+    Public,
+    /// Visible inside the module this path points to. `pub(crate)` is
+    /// `pub(in crate)`, and no visibility at all is `pub(in self)`.
+    Module(Path),
+}
+
+impl RawVisibility {
+    fn from_ast(node: Option<&ast::Visibility>) -> RawVisibility {
+        let node = match node {
+            Some(it) => it,
+            None => return RawVisibility::private(),
+        };
+        let kind = match node.kind() {
+            ast::VisibilityKind::In(path) => {
+                return Path::from_ast(path)
+                    .map(RawVisibility::Module)
+                    .unwrap_or_else(RawVisibility::private)
+            }
+            ast::VisibilityKind::PubCrate => PathKind::Crate,
+            ast::VisibilityKind::PubSuper => PathKind::Super(1),
+            ast::VisibilityKind::PubSelf => PathKind::Self_,
+            ast::VisibilityKind::Pub => return RawVisibility::Public,
+        };
+        RawVisibility::Module(Path { kind, segments: Vec::new() })
+    }
+
+    fn private() -> RawVisibility {
+        RawVisibility::Module(Path { kind: PathKind::Self_, segments: Vec::new() })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) struct Macro(RawId);
 impl_arena_id!(Macro);
@@ -185,16 +265,28 @@ struct RawItemsCollector {
 
 impl RawItemsCollector {
     fn process_module(&mut self, current_module: Option<Module>, body: &impl ast::ModuleItemOwner) {
-        for item_or_macro in body.items_with_macros() {
-            match item_or_macro {
-                ast::ItemOrMacro::Macro(m) => self.add_macro(current_module, m),
-                ast::ItemOrMacro::Item(item) => self.add_item(current_module, item),
+        self.process_items(current_module, body.syntax())
+    }
+
+    fn process_items(&mut self, current_module: Option<Module>, node: &SyntaxNode) {
+        for child in node.children() {
+            if let Some(item) = ast::ModuleItem::cast(child) {
+                self.add_item(current_module, item);
+            } else if let Some(m) = ast::MacroCall::cast(child) {
+                self.add_macro(current_module, m);
+            } else if child.kind() == ERROR {
+                // The parser puts items which follow a syntax error into an
+                // error node. They are collected anyway, so that a temporary
+                // error doesn't change the resolution of the rest of the
+                // module while the user is typing.
+                tested_by!(raw_items_inside_error_node);
+                self.process_items(current_module, child);
             }
         }
     }
 
     fn add_item(&mut self, current_module: Option<Module>, item: &ast::ModuleItem) {
-        let (kind, name) = match item.kind() {
+        let (kind, name, visibility) = match item.kind() {
             ast::ModuleItemKind::Module(module) => {
                 self.add_module(current_module, module);
                 return;
@@ -211,18 +303,21 @@ impl RawItemsCollector {
                 // impls don't participate in name resolution
                 return;
             }
-            ast::ModuleItemKind::StructDef(it) => (DefKind::Struct, it.name()),
-            ast::ModuleItemKind::EnumDef(it) => (DefKind::Enum, it.name()),
-            ast::ModuleItemKind::FnDef(it) => (DefKind::Function, it.name()),
-            ast::ModuleItemKind::TraitDef(it) => (DefKind::Trait, it.name()),
-            ast::ModuleItemKind::TypeAliasDef(it) => (DefKind::TypeAlias, it.name()),
-            ast::ModuleItemKind::ConstDef(it) => (DefKind::Const, it.name()),
-            ast::ModuleItemKind::StaticDef(it) => (DefKind::Static, it.name()),
+            ast::ModuleItemKind::StructDef(it) => (DefKind::Struct, it.name(), it.visibility()),
+            ast::ModuleItemKind::EnumDef(it) => (DefKind::Enum, it.name(), it.visibility()),
+            ast::ModuleItemKind::FnDef(it) => (DefKind::Function, it.name(), it.visibility()),
+            ast::ModuleItemKind::TraitDef(it) => (DefKind::Trait, it.name(), it.visibility()),
+            ast::ModuleItemKind::TypeAliasDef(it) => {
+                (DefKind::TypeAlias, it.name(), it.visibility())
+            }
+            ast::ModuleItemKind::ConstDef(it) => (DefKind::Const, it.name(), it.visibility()),
+            ast::ModuleItemKind::StaticDef(it) => (DefKind::Static, it.name(), it.visibility()),
         };
         if let Some(name) = name {
             let name = name.as_name();
+            let visibility = RawVisibility::from_ast(visibility);
             let source_item_id = self.source_file_items.id_of_unchecked(item.syntax());
-            let def = self.raw_items.defs.alloc(DefData { name, kind, source_item_id });
+            let def = self.raw_items.defs.alloc(DefData { name, kind, visibility, source_item_id });
             self.push_item(current_module, RawItem::Def(def))
         }
     }
@@ -232,10 +327,16 @@ impl RawItemsCollector {
             Some(it) => it.as_name(),
             None => return,
         };
+        let visibility = RawVisibility::from_ast(module.visibility());
         let source_item_id = self.source_file_items.id_of_unchecked(module.syntax());
+        let is_macro_use = module.has_atom_attr("macro_use");
         if module.has_semi() {
-            let item =
-                self.raw_items.modules.alloc(ModuleData::Declaration { name, source_item_id });
+            let item = self.raw_items.modules.alloc(ModuleData::Declaration {
+                name,
+                visibility,
+                source_item_id,
+                is_macro_use,
+            });
             self.push_item(current_module, RawItem::Module(item));
             return;
         }
@@ -243,7 +344,9 @@ impl RawItemsCollector {
         if let Some(item_list) = module.item_list() {
             let item = self.raw_items.modules.alloc(ModuleData::Definition {
                 name,
+                visibility,
                 source_item_id,
+                is_macro_use,
                 items: Vec::new(),
             });
             self.process_module(Some(item), item_list);
@@ -255,18 +358,25 @@ impl RawItemsCollector {
 
     fn add_use_item(&mut self, current_module: Option<Module>, use_item: &ast::UseItem) {
         let is_prelude = use_item.has_atom_attr("prelude_import");
+        let visibility = RawVisibility::from_ast(use_item.visibility());
+        let source_item_id = self.source_file_items.id_of_unchecked(use_item.syntax());
 
-        Path::expand_use_item(use_item, |path, segment, alias| {
+        Path::expand_use_item(use_item, |path, use_tree, segment, alias| {
             let import = self.raw_items.imports.alloc(ImportData {
                 path,
                 alias,
                 is_glob: segment.is_none(),
+                is_self: segment
+                    .map_or(false, |it| it.kind() == Some(ast::PathSegmentKind::SelfKw)),
                 is_prelude,
                 is_extern_crate: false,
+                visibility: visibility.clone(),
+                source_item_id,
             });
             if let Some(segment) = segment {
                 self.source_map.insert(import, segment)
             }
+            self.source_map.insert_range(import, use_tree.syntax().range());
             self.push_item(current_module, RawItem::Import(import))
         })
     }
@@ -279,13 +389,18 @@ impl RawItemsCollector {
         if let Some(name_ref) = extern_crate.name_ref() {
             let path = Path::from_name_ref(name_ref);
             let alias = extern_crate.alias().and_then(|a| a.name()).map(AsName::as_name);
+            let source_item_id = self.source_file_items.id_of_unchecked(extern_crate.syntax());
             let import = self.raw_items.imports.alloc(ImportData {
                 path,
                 alias,
                 is_glob: false,
+                is_self: false,
                 is_prelude: false,
                 is_extern_crate: true,
+                visibility: RawVisibility::from_ast(extern_crate.visibility()),
+                source_item_id,
             });
+            self.source_map.insert_range(import, extern_crate.syntax().range());
             self.push_item(current_module, RawItem::Import(import))
         }
     }
@@ -22,6 +22,18 @@ fn compute_crate_def_map(fixture: &str, graph: Option<CrateGraphFixture>) -> Arc
     db.crate_def_map(krate)
 }
 
+fn compute_crate_def_map_for_root(
+    fixture: &str,
+    graph: CrateGraphFixture,
+    crate_root: &str,
+) -> Arc<CrateDefMap> {
+    let mut db = MockDatabase::with_files(fixture);
+    db.set_crate_graph_from_fixture(graph);
+    let file_id = db.file_id_of(crate_root);
+    let crate_id = db.crate_graph().crate_id_for_crate_root(file_id).unwrap();
+    db.crate_def_map(Crate { crate_id })
+}
+
 fn render_crate_def_map(map: &CrateDefMap) -> String {
     let mut buf = String::new();
     go(&mut buf, map, "\ncrate", map.root);
@@ -59,6 +71,26 @@ fn def_map_with_crate_graph(fixtute: &str, graph: CrateGraphFixture) -> String {
     render_crate_def_map(&dm)
 }
 
+#[test]
+fn items_after_syntax_errors_are_collected() {
+    covers!(raw_items_inside_error_node);
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        struct S;
+        {
+            fn inner() {}
+        }
+        enum E {}
+        ",
+        None,
+    );
+    let names = map[map.root].scope.entries().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+    for name in ["S", "inner", "E"].iter() {
+        assert!(names.contains(&name.to_string()), "{} is missing", name);
+    }
+}
+
 #[test]
 fn crate_def_map_smoke_test() {
     let map = def_map(
@@ -181,6 +213,57 @@ Baz: t v
     );
 }
 
+#[test]
+fn nested_use_trees_with_self_and_globs() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        mod foo;
+        mod user;
+
+        //- /user.rs
+        use crate::foo::{self, bar::{self, baz::{Baz, *}}, Quux as Renamed};
+
+        //- /foo/mod.rs
+        pub mod bar;
+        pub fn bar() {}
+        pub struct Quux;
+
+        //- /foo/bar/mod.rs
+        pub mod baz;
+
+        //- /foo/bar/baz.rs
+        pub struct Baz;
+        pub struct Unit;
+        pub fn qux() {}
+        ",
+        None,
+    );
+    let user = map[map.root]
+        .children
+        .iter()
+        .find(|(name, _)| name.to_string() == "user")
+        .map(|(_, &id)| id)
+        .unwrap();
+    let namespaces = |name: &str| {
+        map[user]
+            .scope
+            .entries()
+            .find(|(n, _)| n.to_string() == name)
+            .map(|(_, res)| (res.def.types.is_some(), res.def.values.is_some()))
+    };
+
+    assert_eq!(namespaces("foo"), Some((true, false)));
+    // `self` doesn't bring the function `foo::bar` into scope.
+    assert_eq!(namespaces("bar"), Some((true, false)));
+    assert_eq!(namespaces("Baz"), Some((true, true)));
+    assert_eq!(namespaces("Unit"), Some((true, true)));
+    assert_eq!(namespaces("qux"), Some((false, true)));
+    assert_eq!(namespaces("Renamed"), Some((true, true)));
+    assert_eq!(namespaces("Quux"), None);
+    assert_eq!(namespaces("baz"), None);
+}
+
 #[test]
 fn re_exports() {
     let map = def_map(
@@ -301,6 +384,112 @@ Bar: t v
     );
 }
 
+#[test]
+fn plain_and_absolute_imports_depend_on_edition() {
+    let fixture = "
+        //- /2015/main.rs
+        mod foo;
+        mod bar;
+
+        //- /2015/bar.rs
+        pub struct Plain;
+        pub struct Absolute;
+        pub struct CrateRelative;
+
+        //- /2015/foo.rs
+        use bar::Plain;
+        use ::bar::Absolute;
+        use crate::bar::CrateRelative;
+        use ::dep::Dep;
+
+        //- /2018/main.rs
+        mod foo;
+        mod bar;
+
+        //- /2018/bar.rs
+        pub struct Plain;
+        pub struct Absolute;
+        pub struct CrateRelative;
+
+        //- /2018/foo.rs
+        use bar::Plain;
+        use ::bar::Absolute;
+        use crate::bar::CrateRelative;
+        use ::dep::Dep;
+
+        //- /dep.rs
+        pub struct Dep;
+    ";
+    let resolved = |edition: &str, name: &str| {
+        let graph = crate_graph! {
+            "main2015": ("/2015/main.rs", "2015", ["dep"]),
+            "main2018": ("/2018/main.rs", "2018", ["dep"]),
+            "dep": ("/dep.rs", []),
+        };
+        let map = compute_crate_def_map_for_root(fixture, graph, &format!("/{}/main.rs", edition));
+        let foo = map[map.root]
+            .children
+            .iter()
+            .find(|(name, _)| name.to_string() == "foo")
+            .map(|(_, &id)| id)
+            .unwrap();
+        let (_, res) = map[foo].scope.entries().find(|(n, _)| n.to_string() == name).unwrap();
+        !res.def.is_none()
+    };
+
+    // In 2015, plain imports and absolute paths start at the crate root.
+    assert!(resolved("2015", "Plain"));
+    assert!(resolved("2015", "Absolute"));
+    assert!(resolved("2015", "CrateRelative"));
+    assert!(resolved("2015", "Dep"));
+
+    // In 2018, plain imports are relative to the current module and absolute
+    // paths start with a crate name.
+    assert!(!resolved("2018", "Plain"));
+    assert!(!resolved("2018", "Absolute"));
+    assert!(resolved("2018", "CrateRelative"));
+    assert!(resolved("2018", "Dep"));
+}
+
+#[test]
+fn super_paths_of_any_depth() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        mod a;
+        pub struct Root;
+
+        //- /a.rs
+        pub mod b;
+        pub struct A;
+
+        //- /a/b.rs
+        pub mod c;
+
+        //- /a/b/c.rs
+        use super::super::A;
+        use super::super::super::Root;
+        use self::super::super::A as SelfA;
+        use super::super::super::super::TooFar;
+        pub(in super::super) struct Vis;
+        ",
+        None,
+    );
+    let child =
+        |module_id: CrateModuleId| map[module_id].children.values().cloned().next().unwrap();
+    let a = child(map.root);
+    let c = child(child(a));
+    let resolution = |name: &str| {
+        map[c].scope.entries().find(|(n, _)| n.to_string() == name).map(|(_, res)| res).unwrap()
+    };
+
+    assert!(!resolution("A").def.is_none());
+    assert!(!resolution("Root").def.is_none());
+    assert!(!resolution("SelfA").def.is_none());
+    assert!(resolution("TooFar").def.is_none());
+    assert_eq!(resolution("Vis").visibility, Visibility::Module(map.mk_module(a)));
+}
+
 #[test]
 fn module_resolution_works_for_non_standard_filenames() {
     let map = def_map_with_crate_graph(
@@ -552,3 +741,504 @@ foo: v
 "###
     );
 }
+
+#[test]
+fn use_own_crate_name_2018() {
+    let map = compute_crate_def_map_for_root(
+        "
+        //- /main.rs
+        use my_lib::Baz;
+
+        //- /lib.rs
+        mod foo;
+        use my_lib::foo::Baz;
+
+        //- /foo.rs
+        pub struct Baz;
+        ",
+        crate_graph! {
+            "main": ("/main.rs", ["my_lib"]),
+            "my_lib": ("/lib.rs", []),
+        },
+        "/lib.rs",
+    );
+    let (_, baz) =
+        map[map.root].scope.entries().find(|(name, _)| name.to_string() == "Baz").unwrap();
+    assert!(baz.def.take_types().is_some());
+}
+
+#[test]
+fn use_own_crate_name_2015() {
+    let map = compute_crate_def_map_for_root(
+        "
+        //- /main.rs
+        use my_lib::Baz;
+
+        //- /lib.rs
+        mod foo;
+        use my_lib::foo::Baz;
+
+        //- /foo.rs
+        pub struct Baz;
+        ",
+        crate_graph! {
+            "main": ("/main.rs", ["my_lib"]),
+            "my_lib": ("/lib.rs", "2015", []),
+        },
+        "/lib.rs",
+    );
+    let (_, baz) =
+        map[map.root].scope.entries().find(|(name, _)| name.to_string() == "Baz").unwrap();
+    assert!(baz.def.is_none());
+}
+
+#[test]
+fn own_crate_name_is_not_in_extern_prelude() {
+    let map = compute_crate_def_map_for_root(
+        "
+        //- /main.rs
+        use my_lib::*;
+
+        //- /lib.rs
+        struct S;
+        ",
+        crate_graph! {
+            "main": ("/main.rs", ["my_lib"]),
+            "my_lib": ("/lib.rs", []),
+        },
+        "/lib.rs",
+    );
+    assert!(map.extern_prelude().is_empty());
+}
+
+#[test]
+fn duplicate_definitions_are_recorded() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        mod foo;
+        struct S;
+        fn f() {}
+        use self::foo::S;
+        use self::foo::*;
+
+        //- /foo.rs
+        pub struct S;
+        pub fn f() {}
+        ",
+        None,
+    );
+    let problems = map
+        .problems()
+        .iter()
+        .map(|(_, problem)| match problem {
+            Problem::DuplicateDefinition { name, namespace, .. } => (name.to_string(), *namespace),
+            _ => panic!("unexpected problem: {:?}", problem),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(problems.len(), 2);
+    assert!(problems.contains(&("S".to_string(), Namespace::Types)));
+    assert!(problems.contains(&("S".to_string(), Namespace::Values)));
+}
+
+#[test]
+fn visibility_is_recorded() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        mod foo;
+        pub use self::foo::bar::Reexported;
+
+        //- /foo.rs
+        pub mod bar;
+        pub(crate) struct CrateVisible;
+        struct Private;
+
+        //- /foo/bar.rs
+        pub(super) fn super_visible() {}
+        pub struct Reexported;
+        ",
+        None,
+    );
+    let module = |id| map.mk_module(id);
+    let visibility = |module_id: CrateModuleId, name: &str| {
+        map[module_id]
+            .scope
+            .entries()
+            .find(|(n, _)| n.to_string() == name)
+            .map(|(_, res)| res.visibility)
+            .unwrap()
+    };
+    let foo = map[map.root].children.values().cloned().next().unwrap();
+    let bar = map[foo].children.values().cloned().next().unwrap();
+
+    assert_eq!(visibility(map.root, "Reexported"), Visibility::Public);
+    assert_eq!(visibility(map.root, "foo"), Visibility::Module(module(map.root)));
+    assert_eq!(visibility(foo, "bar"), Visibility::Public);
+    assert_eq!(visibility(foo, "CrateVisible"), Visibility::Module(module(map.root)));
+    assert_eq!(visibility(foo, "Private"), Visibility::Module(module(foo)));
+    assert_eq!(visibility(bar, "super_visible"), Visibility::Module(module(foo)));
+    assert_eq!(visibility(bar, "Reexported"), Visibility::Public);
+}
+
+#[test]
+fn unresolved_imports_are_mapped_to_use_tree_leaves() {
+    let db = MockDatabase::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        use self::foo::{Bar, Baz, nested::{Qux, Missing as Renamed}};
+
+        //- /foo.rs
+        pub struct Bar;
+        pub mod nested { pub struct Qux; }
+        ",
+    );
+    let file_id = db.file_id_of("/lib.rs");
+    let crate_id = db.crate_graph().iter().next().unwrap();
+    let map = db.crate_def_map(Crate { crate_id });
+
+    let text = db.file_text(file_id);
+    let mut unresolved = map
+        .unresolved_imports()
+        .iter()
+        .map(|&(_, hir_file_id, import)| {
+            assert_eq!(hir_file_id.original_file(&db), file_id);
+            let (_, source_map) = db.raw_items_with_source_map(file_id);
+            let range = source_map.range(import).unwrap();
+            text[range.start().to_usize()..range.end().to_usize()].to_string()
+        })
+        .collect::<Vec<_>>();
+    unresolved.sort();
+    assert_eq!(unresolved, vec!["Baz".to_string(), "Missing as Renamed".to_string()]);
+}
+
+fn unused_imports(fixture: &str) -> Vec<String> {
+    let db = MockDatabase::with_files(fixture);
+    let file_id = db.file_id_of("/lib.rs");
+    let module = crate::source_binder::module_from_file_id(&db, file_id).unwrap();
+
+    let text = db.file_text(file_id);
+    let mut ranges = module
+        .unused_imports(&db)
+        .into_iter()
+        .map(|import| module.import_range(&db, import).unwrap())
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|range| range.start());
+    ranges
+        .into_iter()
+        .map(|range| text[range.start().to_usize()..range.end().to_usize()].to_string())
+        .collect()
+}
+
+#[test]
+fn unused_imports_are_found() {
+    let unused = unused_imports(
+        "
+        //- /lib.rs
+        mod foo;
+        use self::foo::{Bar, Baz, shadowed, nested, Unused, UsedInTests};
+        use nested::Qux;
+        macro_rules! import_unused {
+            () => { use self::foo::Unused as FromMacro; }
+        }
+        import_unused!();
+        mod child {
+            fn f() { super::Baz; }
+        }
+        mod tests {
+            use super::*;
+            fn t(_: UsedInTests) {}
+        }
+        fn f(_: Bar) -> Qux {
+            let shadowed = 92;
+            shadowed
+        }
+
+        //- /foo.rs
+        pub struct Bar;
+        pub struct Baz;
+        pub fn shadowed() {}
+        pub struct Unused;
+        pub struct UsedInTests;
+        pub mod nested { pub struct Qux; }
+        ",
+    );
+    assert_eq!(unused, vec!["shadowed".to_string(), "Unused".to_string()]);
+}
+
+#[test]
+fn unused_imports_sees_bounds_and_macro_arguments() {
+    let unused = unused_imports(
+        "
+        //- /lib.rs
+        mod foo;
+        use self::foo::{
+            Bound, Where, ImplTrait, DynTrait, Binding, Turbofish, Derived, MacroArg, Unused,
+        };
+        fn bound<T: Tr<Bound>>() {}
+        fn where_clause<T>() where T: Tr<Where> {}
+        fn impl_trait(_: impl Tr<ImplTrait>) {}
+        fn dyn_trait(_: &dyn Tr<DynTrait>) {}
+        fn binding(_: &dyn Iterator<Item = Binding>) {}
+        fn turbofish(x: u32) { x.convert::<Turbofish>(); }
+        #[derive(Derived)]
+        struct S;
+        items!(MacroArg);
+        macro_rules! m {
+            () => { Unused }
+        }
+
+        //- /foo.rs
+        pub struct Bound;
+        pub struct Where;
+        pub struct ImplTrait;
+        pub struct DynTrait;
+        pub struct Binding;
+        pub struct Turbofish;
+        pub struct Derived;
+        pub struct MacroArg;
+        pub struct Unused;
+        ",
+    );
+    assert_eq!(unused, vec!["Unused".to_string()]);
+}
+
+#[test]
+fn scope_entries_are_ordered_as_in_source() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        macro_rules! structs {
+            ($($i:ident),*) => { $(struct $i;)* }
+        }
+        fn zzz() {}
+        use self::m::*;
+        structs!(Yyy, Aaa);
+        mod m { pub struct Glob; }
+        use self::m::Glob as Renamed;
+        ",
+        None,
+    );
+    let names = map[map.root]
+        .scope
+        .entries_in_source_order()
+        .into_iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["zzz", "Glob", "Yyy", "Aaa", "m", "Renamed"]);
+}
+
+#[test]
+fn path_expectation_trims_resolutions() {
+    use crate::resolve::PathExpectation;
+
+    let db = MockDatabase::with_files(
+        "
+        //- /lib.rs
+        mod m {}
+        struct Unit;
+        struct Record {}
+        fn func() {}
+        ",
+    );
+    let crate_id = db.crate_graph().iter().next().unwrap();
+    let module = Crate { crate_id }.root_module(&db).unwrap();
+    let resolver = module.resolver(&db);
+
+    let names = |expectation| {
+        let mut names = resolver
+            .all_names_expecting(&db, expectation)
+            .keys()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    assert_eq!(names(PathExpectation::Type), vec!["Record", "Unit", "m"]);
+    assert_eq!(names(PathExpectation::Value), vec!["Record", "Unit", "func", "m"]);
+    assert_eq!(names(PathExpectation::Macro), vec!["m"]);
+
+    let resolves = |name: &str, expectation: PathExpectation| {
+        let name =
+            resolver.all_names(&db).keys().find(|it| it.to_string() == name).unwrap().clone();
+        !expectation.filter(resolver.resolve_name(&db, &name)).is_none()
+    };
+    assert!(resolves("Unit", PathExpectation::Value));
+    assert!(!resolves("Record", PathExpectation::Value));
+    assert!(!resolves("m", PathExpectation::Value));
+    assert!(!resolves("m", PathExpectation::Type));
+    assert!(!resolves("func", PathExpectation::Type));
+}
+
+#[test]
+fn raw_items_from_block_and_item_list() {
+    use ra_syntax::ast::{self, AstNode};
+
+    let (db, _, file_id) = MockDatabase::with_single_file(
+        "
+        fn outer() { fn inner() {} struct S; let x = 92; }
+        trait Tr { fn method(); const C: u32; }
+        ",
+    );
+    let source_file = db.parse(file_id);
+    let file_items = db.file_items(file_id.into());
+    let def_names = |raw_items: &raw::RawItems| -> Vec<String> {
+        raw_items
+            .items()
+            .iter()
+            .filter_map(|it| match it {
+                raw::RawItem::Def(def) => Some(raw_items[*def].name.to_string()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let block = source_file.syntax().descendants().find_map(ast::Block::cast).unwrap();
+    let raw_items = raw::RawItems::from_item_owner(block, file_items.clone());
+    assert_eq!(def_names(&raw_items), ["inner", "S"]);
+
+    let trait_def = source_file.syntax().descendants().find_map(ast::TraitDef::cast).unwrap();
+    let raw_items = raw::RawItems::from_item_owner(trait_def.item_list().unwrap(), file_items);
+    assert_eq!(def_names(&raw_items), ["method", "C"]);
+}
+
+#[test]
+fn def_map_stats() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        mod foo;
+        struct S;
+        macro_rules! m { () => {} }
+
+        //- /foo.rs
+        fn f() {}
+        enum E {}
+        ",
+        None,
+    );
+    let stats = map.stats();
+    assert_eq!(stats.modules, 2);
+    assert_eq!(stats.scope_entries, 4);
+    assert_eq!(stats.macros, 1);
+    assert!(stats.heap_size > 0);
+}
+
+#[test]
+fn def_map_roundtrips_through_persistent_encoding() {
+    let mut db = MockDatabase::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        use crate::foo::bar::{Baz, E::V};
+        use crate::foo::Missing;
+
+        macro_rules! structs {
+            ($($i:ident),*) => { $(struct $i;)* }
+        }
+        structs!(S, T);
+
+        //- /foo/mod.rs
+        pub mod bar;
+        mod missing;
+
+        //- /foo/bar.rs
+        pub struct Baz;
+        pub enum E { V }
+        ",
+    );
+    let krate = Crate { crate_id: db.crate_graph().iter().next().unwrap() };
+    let def_map = db.crate_def_map(krate);
+    let bytes = def_map.to_bytes(&db);
+
+    let loaded = CrateDefMap::from_bytes(&db, krate, &bytes).unwrap();
+    assert_eq!(*def_map, loaded);
+    assert!(CrateDefMap::from_bytes(&db, krate, &bytes[..bytes.len() - 1]).is_none());
+
+    // The same files, with the ids of `mod.rs` and `bar.rs` swapped.
+    let mod_rs = db.file_id_of("/foo/mod.rs");
+    let bar_rs = db.file_id_of("/foo/bar.rs");
+    let (mod_text, bar_text) = (db.file_text(mod_rs), db.file_text(bar_rs));
+    let (mod_path, bar_path) = (db.file_relative_path(mod_rs), db.file_relative_path(bar_rs));
+    let source_root_id = db.file_source_root(mod_rs);
+    let mut source_root = (*db.source_root(source_root_id)).clone();
+    source_root.files.insert(mod_path.clone(), bar_rs);
+    source_root.files.insert(bar_path.clone(), mod_rs);
+    db.set_file_text(mod_rs, bar_text);
+    db.set_file_text(bar_rs, mod_text);
+    db.set_file_relative_path(mod_rs, bar_path);
+    db.set_file_relative_path(bar_rs, mod_path);
+    db.set_source_root(source_root_id, Arc::new(source_root));
+    assert!(CrateDefMap::from_bytes(&db, krate, &bytes).is_none());
+    let bytes = db.crate_def_map(krate).to_bytes(&db);
+    assert!(CrateDefMap::from_bytes(&db, krate, &bytes).is_some());
+
+    // `bar.rs` has the id of `mod.rs` now.
+    db.set_file_text(mod_rs, Arc::new("pub struct Baz;".to_string()));
+    assert!(CrateDefMap::from_bytes(&db, krate, &bytes).is_none());
+}
+
+/// Poor man's benchmark: collecting items used to be quadratic in the number
+/// of items in a file. Run with
+/// `cargo test -p ra_hir --release -- --ignored --nocapture def_map_for_large_file`.
+#[test]
+#[ignore]
+fn def_map_for_large_file() {
+    let mut fixture = String::from("//- /lib.rs\n");
+    for i in 0..10_000 {
+        fixture.push_str(&format!("fn f{}() {{}}\n", i));
+    }
+    let start = std::time::Instant::now();
+    let map = compute_crate_def_map(&fixture, None);
+    eprintln!("crate_def_map for 10k items: {:?}", start.elapsed());
+    assert_eq!(map[map.root].scope.entries().count(), 10_000);
+}
+
+#[test]
+fn declarations_are_listed_in_source_order() {
+    use crate::{ModuleDef, ModuleItem};
+
+    let db = MockDatabase::with_files(
+        "
+        //- /lib.rs
+        impl S {}
+        struct S;
+        mod m {}
+        macro_rules! gen {
+            () => { fn gen_b() {} fn gen_a() {} }
+        }
+        fn z() {}
+        gen!();
+        fn a() {}
+        ",
+    );
+    let crate_id = db.crate_graph().iter().next().unwrap();
+    let module = Crate { crate_id }.root_module(&db).unwrap();
+    let file_id = db.file_id_of("/lib.rs");
+    let text = db.file_text(file_id);
+
+    let items = module.declarations_in_order(&db);
+    let names = items
+        .iter()
+        .map(|it| match it.item {
+            ModuleItem::ImplBlock(_) => "impl".to_string(),
+            ModuleItem::Def(ModuleDef::Struct(it)) => it.name(&db).unwrap().to_string(),
+            ModuleItem::Def(ModuleDef::Module(it)) => it.name(&db).unwrap().to_string(),
+            ModuleItem::Def(ModuleDef::Function(it)) => it.name(&db).to_string(),
+            ModuleItem::Def(it) => panic!("unexpected item: {:?}", it),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["impl", "S", "m", "z", "gen_b", "gen_a", "a"]);
+
+    let texts = items
+        .iter()
+        .map(|it| {
+            assert_eq!(it.range.file_id, file_id);
+            &text[it.range.range]
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(texts[1], "struct S;");
+    assert!(texts[4].starts_with("gen!()"));
+    assert_eq!(texts[4], texts[5]);
+}
@@ -1,3 +1,5 @@
+use ra_syntax::{AstNode, SyntaxKind};
+
 use super::*;
 
 #[test]
@@ -92,3 +94,139 @@ Foo: t v
 Bar: t v
 "###);
 }
+
+#[test]
+fn macro_rules_are_scoped_textually() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        mod before;
+        #[macro_use]
+        mod macros;
+        mod after;
+        mod plain;
+        mod after_plain;
+        mod order;
+
+        //- /macros.rs
+        macro_rules! structs {
+            ($i:ident) => { struct $i; }
+        }
+
+        //- /plain.rs
+        macro_rules! hidden {
+            ($i:ident) => { struct $i; }
+        }
+
+        //- /before.rs
+        structs!(Before);
+
+        //- /after.rs
+        structs!(After);
+
+        //- /after_plain.rs
+        hidden!(Hidden);
+
+        //- /order.rs
+        local!(Early);
+        macro_rules! local {
+            ($i:ident) => { struct $i; }
+        }
+        local!(Late);
+        ",
+        None,
+    );
+    let items = |module: &str| {
+        let module_id = map[map.root]
+            .children
+            .iter()
+            .find(|(name, _)| name.to_string() == module)
+            .map(|(_, &id)| id)
+            .unwrap();
+        map[module_id].scope.entries().map(|(name, _)| name.to_string()).collect::<Vec<_>>()
+    };
+    assert!(items("before").is_empty());
+    assert_eq!(items("after"), ["After"]);
+    assert!(items("after_plain").is_empty());
+    assert_eq!(items("order"), ["Late"]);
+}
+
+#[test]
+fn macro_expansions_are_attributed_to_macro_calls() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+        struct NotFromMacro;
+        structs!(Foo);
+        structs!(Bar, Baz);
+        ",
+        None,
+    );
+    let expansions = &map[map.root].expansions;
+    let n_defs = expansions.iter().map(|(_, defs)| defs.len()).collect::<Vec<_>>();
+    assert_eq!(n_defs, vec![1, 2]);
+}
+
+#[test]
+fn expansion_info_maps_ranges_to_macro_call() {
+    let db = MockDatabase::with_files(
+        "
+        //- /lib.rs
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+        structs!(Foo);
+        ",
+    );
+    let crate_id = db.crate_graph().iter().next().unwrap();
+    let map = db.crate_def_map(Crate { crate_id });
+    let (macro_call_id, _) = map[map.root].expansions[0];
+    let file_id: HirFileId = macro_call_id.into();
+
+    let expansion = db.hir_parse(file_id);
+    let name = expansion.syntax().descendants().find(|it| it.kind() == SyntaxKind::NAME).unwrap();
+    let info = file_id.expansion_info(&db).unwrap();
+    let (call_file, range) = info.map_range_up(name.range()).unwrap();
+
+    let lib_rs = db.file_id_of("/lib.rs");
+    assert_eq!(call_file, lib_rs.into());
+    let text = db.file_text(lib_rs);
+    let (start, end) = (u32::from(range.start()) as usize, u32::from(range.end()) as usize);
+    assert_eq!(&text[start..end], "Foo");
+}
+
+#[test]
+fn recursive_macro_expansion_is_limited() {
+    let map = compute_crate_def_map(
+        "
+        //- /lib.rs
+        macro_rules! foo {
+            () => { bar!(); }
+        }
+        macro_rules! bar {
+            () => { foo!(); foo!(); }
+        }
+        foo!();
+        struct S;
+        ",
+        None,
+    );
+    assert!(map[map.root].scope.entries().any(|(name, _)| name.to_string() == "S"));
+    let messages = map
+        .problems()
+        .iter()
+        .map(|(_, problem)| match problem {
+            Problem::MacroError { message } => message.clone(),
+            _ => panic!("unexpected problem: {:?}", problem),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("macro expansion limit reached: foo! -> bar! -> foo!"));
+}
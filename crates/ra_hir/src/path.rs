@@ -36,7 +36,8 @@ pub enum GenericArg {
 pub enum PathKind {
     Plain,
     Self_,
-    Super,
+    /// `super::`, repeated the given number of times.
+    Super(u8),
     Crate,
     // Absolute path
     Abs,
@@ -46,7 +47,7 @@ impl Path {
     /// Calls `cb` with all paths, represented by this use item.
     pub fn expand_use_item<'a>(
         item: &'a ast::UseItem,
-        mut cb: impl FnMut(Path, Option<&'a ast::PathSegment>, Option<Name>),
+        mut cb: impl FnMut(Path, &'a ast::UseTree, Option<&'a ast::PathSegment>, Option<Name>),
     ) {
         if let Some(tree) = item.use_tree() {
             expand_use_tree(None, tree, &mut cb);
@@ -66,6 +67,10 @@ impl Path {
 
             match segment.kind()? {
                 ast::PathSegmentKind::Name(name) => {
+                    if let PathKind::Super(_) = kind {
+                        // E.g. `foo::super::bar`
+                        return None;
+                    }
                     let args =
                         segment.type_arg_list().and_then(GenericArgs::from_ast).map(Arc::new);
                     let segment = PathSegment { name: name.as_name(), args_and_bindings: args };
@@ -76,12 +81,20 @@ impl Path {
                     break;
                 }
                 ast::PathSegmentKind::SelfKw => {
+                    // `self::super::foo` is the same as `super::foo`
+                    if let PathKind::Super(_) = kind {
+                        break;
+                    }
                     kind = PathKind::Self_;
                     break;
                 }
+                // The segments are visited from the last one, so we see the
+                // `super`s of `super::super::foo` one by one.
                 ast::PathSegmentKind::SuperKw => {
-                    kind = PathKind::Super;
-                    break;
+                    kind = match kind {
+                        PathKind::Super(n) => PathKind::Super(n + 1),
+                        _ => PathKind::Super(1),
+                    };
                 }
             }
             path = match qualifier(path) {
@@ -156,7 +169,7 @@ impl From<Name> for Path {
 fn expand_use_tree<'a>(
     prefix: Option<Path>,
     tree: &'a ast::UseTree,
-    cb: &mut impl FnMut(Path, Option<&'a ast::PathSegment>, Option<Name>),
+    cb: &mut impl FnMut(Path, &'a ast::UseTree, Option<&'a ast::PathSegment>, Option<Name>),
 ) {
     if let Some(use_tree_list) = tree.use_tree_list() {
         let prefix = match tree.path() {
@@ -181,7 +194,7 @@ fn expand_use_tree<'a>(
                 if let Some(segment) = ast_path.segment() {
                     if segment.kind() == Some(ast::PathSegmentKind::SelfKw) {
                         if let Some(prefix) = prefix {
-                            cb(prefix, Some(segment), alias);
+                            cb(prefix, tree, Some(segment), alias);
                             return;
                         }
                     }
@@ -189,13 +202,18 @@ fn expand_use_tree<'a>(
             }
             if let Some(path) = convert_path(prefix, ast_path) {
                 if tree.has_star() {
-                    cb(path, None, alias)
+                    cb(path, tree, None, alias)
                 } else if let Some(segment) = ast_path.segment() {
-                    cb(path, Some(segment), alias)
+                    cb(path, tree, Some(segment), alias)
                 };
             }
             // FIXME: report errors somewhere
             // We get here if we do
+        } else if tree.has_star() {
+            // E.g. `use something::{inner, *}`
+            if let Some(prefix) = prefix {
+                cb(prefix, tree, None, alias)
+            }
         }
     }
 }
@@ -226,12 +244,18 @@ fn convert_path(prefix: Option<Path>, path: &ast::Path) -> Option<Path> {
             }
             Path { kind: PathKind::Self_, segments: Vec::new() }
         }
-        ast::PathSegmentKind::SuperKw => {
-            if prefix.is_some() {
-                return None;
+        ast::PathSegmentKind::SuperKw => match prefix {
+            None => Path { kind: PathKind::Super(1), segments: Vec::new() },
+            // E.g. `use self::super::foo`
+            Some(Path { kind: PathKind::Self_, ref segments }) if segments.is_empty() => {
+                Path { kind: PathKind::Super(1), segments: Vec::new() }
             }
-            Path { kind: PathKind::Super, segments: Vec::new() }
-        }
+            // E.g. `use super::super::foo`
+            Some(Path { kind: PathKind::Super(n), ref segments }) if segments.is_empty() => {
+                Path { kind: PathKind::Super(n + 1), segments: Vec::new() }
+            }
+            Some(_) => return None,
+        },
     };
     Some(res)
 }
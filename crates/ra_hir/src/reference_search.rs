@@ -0,0 +1,116 @@
+//! Finds the references to a definition.
+//!
+//! There's no index from definitions to their uses: instead, we look at all
+//! the name references in the crate of the definition which have the same
+//! text as its name, and resolve each of them, using the def map for paths
+//! and type inference for methods.
+use ra_db::{FileId, FileRange};
+use ra_syntax::{
+    AstNode,
+    ast::{self, NameOwner},
+};
+
+use crate::{
+    HirDatabase, Module, ModuleDef, Crate, Name, Path, ImplItem, AsName,
+    resolve::Resolution,
+    source_binder::{function_from_child_node, resolver_for_node},
+};
+
+/// Returns the ranges of all the name references resolving to `def` in its
+/// crate. The definition itself is not included.
+pub fn find_references(db: &impl HirDatabase, def: ModuleDef) -> Vec<FileRange> {
+    let (krate, name) = match (def_module(db, def).krate(db), def_name(db, def)) {
+        (Some(krate), Some(name)) => (krate, name.to_string()),
+        _ => return Vec::new(),
+    };
+    let mut res = Vec::new();
+    for file_id in crate_files(db, krate) {
+        let source_file = db.parse(file_id);
+        for name_ref in source_file.syntax().descendants().filter_map(ast::NameRef::cast) {
+            if name_ref.text().as_str() != name {
+                continue;
+            }
+            if resolve_name_ref(db, file_id, name_ref) == Some(def) {
+                res.push(FileRange { file_id, range: name_ref.syntax().range() });
+            }
+        }
+    }
+    res
+}
+
+/// Resolves a name reference to the item it refers to, if it refers to an
+/// item. References to locals, fields and generic params resolve to `None`.
+pub fn resolve_name_ref(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+) -> Option<ModuleDef> {
+    let function = function_from_child_node(db, file_id, name_ref.syntax());
+
+    if let Some(method_call) = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast) {
+        let function = function?;
+        let expr = function.body_source_map(db).node_expr(method_call.into())?;
+        return function.infer(db).method_resolution(expr).map(ModuleDef::Function);
+    }
+
+    let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+    let hir_path = Path::from_ast(path)?;
+    let resolved = resolver_for_node(db, file_id, name_ref.syntax()).resolve_path(db, &hir_path);
+    match resolved.clone().take_types().or_else(|| resolved.take_values()) {
+        Some(Resolution::Def(def)) => Some(def),
+        Some(_) => None,
+        // Associated items can only be resolved by type inference.
+        None => {
+            let function = function?;
+            let path_expr = path.syntax().parent().and_then(ast::PathExpr::cast)?;
+            let expr = function.body_source_map(db).node_expr(path_expr.into())?;
+            match function.infer(db).assoc_resolutions_for_expr(expr)? {
+                ImplItem::Method(it) => Some(ModuleDef::Function(it)),
+                ImplItem::Const(it) => Some(ModuleDef::Const(it)),
+                ImplItem::TypeAlias(it) => Some(ModuleDef::TypeAlias(it)),
+            }
+        }
+    }
+}
+
+fn def_module(db: &impl HirDatabase, def: ModuleDef) -> Module {
+    match def {
+        ModuleDef::Module(it) => it,
+        ModuleDef::Function(it) => it.module(db),
+        ModuleDef::Struct(it) => it.module(db),
+        ModuleDef::Enum(it) => it.module(db),
+        ModuleDef::EnumVariant(it) => it.module(db),
+        ModuleDef::Const(it) => it.module(db),
+        ModuleDef::Static(it) => it.module(db),
+        ModuleDef::Trait(it) => it.module(db),
+        ModuleDef::TypeAlias(it) => it.module(db),
+    }
+}
+
+fn def_name(db: &impl HirDatabase, def: ModuleDef) -> Option<Name> {
+    match def {
+        ModuleDef::Module(it) => it.name(db),
+        ModuleDef::Function(it) => Some(it.name(db)),
+        ModuleDef::Struct(it) => it.name(db),
+        ModuleDef::Enum(it) => it.name(db),
+        ModuleDef::EnumVariant(it) => it.name(db),
+        ModuleDef::Const(it) => it.source(db).1.name().map(|it| it.as_name()),
+        ModuleDef::Static(it) => it.source(db).1.name().map(|it| it.as_name()),
+        ModuleDef::Trait(it) => it.source(db).1.name().map(|it| it.as_name()),
+        ModuleDef::TypeAlias(it) => it.source(db).1.name().map(|it| it.as_name()),
+    }
+}
+
+/// All the files with modules of `krate`, in the order of the module tree.
+fn crate_files(db: &impl HirDatabase, krate: Crate) -> Vec<FileId> {
+    let mut res = Vec::new();
+    let mut stack: Vec<Module> = krate.root_module(db).into_iter().collect();
+    while let Some(module) = stack.pop() {
+        let file_id = module.definition_source(db).0.original_file(db);
+        if !res.contains(&file_id) {
+            res.push(file_id);
+        }
+        stack.extend(module.children(db));
+    }
+    res
+}
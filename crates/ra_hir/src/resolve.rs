@@ -116,6 +116,52 @@ pub enum Resolution {
     SelfType(ImplBlock),
 }
 
+/// What a path is syntactically expected to resolve to, judging by where it
+/// occurs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathExpectation {
+    /// A type, as in `let x: Foo;`.
+    Type,
+    /// A value, as in `let x = foo;`.
+    Value,
+    /// A macro, as in `foo!()`.
+    Macro,
+}
+
+impl PathExpectation {
+    /// Trims `res` to resolutions which make sense at the end of a path with
+    /// this expectation. For example, modules are neither types nor values.
+    pub fn filter(self, res: PerNs<Resolution>) -> PerNs<Resolution> {
+        match self {
+            PathExpectation::Type => PerNs {
+                types: res.types.filter(|it| match it {
+                    Resolution::Def(ModuleDef::Module(_)) => false,
+                    _ => true,
+                }),
+                values: None,
+            },
+            PathExpectation::Value => PerNs { types: None, values: res.values },
+            // Macros don't live in `PerNs`, so nothing there can be a macro.
+            PathExpectation::Macro => PerNs::none(),
+        }
+    }
+
+    /// Trims `res` to resolutions which may qualify a path with this
+    /// expectation, like `Foo` in `Foo::new()`.
+    pub fn filter_qualifier(self, res: PerNs<Resolution>) -> PerNs<Resolution> {
+        let types = res.types.filter(|it| match (self, it) {
+            (_, Resolution::Def(ModuleDef::Module(_))) => true,
+            (PathExpectation::Macro, _) => false,
+            (_, Resolution::Def(ModuleDef::Function(_)))
+            | (_, Resolution::Def(ModuleDef::Const(_)))
+            | (_, Resolution::Def(ModuleDef::Static(_))) => false,
+            (_, Resolution::LocalBinding(_)) => false,
+            _ => true,
+        });
+        PerNs { types, values: None }
+    }
+}
+
 impl Resolver {
     pub fn resolve_name(&self, db: &impl HirDatabase, name: &Name) -> PerNs<Resolution> {
         let mut resolution = PerNs::none();
@@ -159,6 +205,37 @@ impl Resolver {
         self.resolve_path_segments(db, path).into_fully_resolved()
     }
 
+    /// Like `resolve_path`, but trims the result to what is allowed by
+    /// `expectation`.
+    pub fn resolve_path_expecting(
+        &self,
+        db: &impl HirDatabase,
+        path: &Path,
+        expectation: PathExpectation,
+    ) -> PerNs<Resolution> {
+        expectation.filter(self.resolve_path(db, path))
+    }
+
+    /// Like `all_names`, but only keeps names which may be used as the whole
+    /// path with the given expectation or as its first segment.
+    pub fn all_names_expecting(
+        &self,
+        db: &impl HirDatabase,
+        expectation: PathExpectation,
+    ) -> FxHashMap<Name, PerNs<Resolution>> {
+        self.all_names(db)
+            .into_iter()
+            .filter_map(|(name, res)| {
+                let res = expectation.filter(res.clone()).or(expectation.filter_qualifier(res));
+                if res.is_none() {
+                    None
+                } else {
+                    Some((name, res))
+                }
+            })
+            .collect()
+    }
+
     pub fn all_names(&self, db: &impl HirDatabase) -> FxHashMap<Name, PerNs<Resolution>> {
         let mut names = FxHashMap::default();
         for scope in self.scopes.iter().rev() {
@@ -175,6 +252,21 @@ impl Resolver {
         names
     }
 
+    /// The names which may follow `::` at the start of a path: the crates of
+    /// the extern prelude and, in the 2015 edition, the items of the crate
+    /// root.
+    pub fn global_path_names(&self) -> FxHashMap<Name, PerNs<Resolution>> {
+        let (item_map, _) = match self.module() {
+            Some(it) => it,
+            None => return FxHashMap::default(),
+        };
+        item_map
+            .global_path_names()
+            .into_iter()
+            .map(|(name, def)| (name, def.map(Resolution::Def)))
+            .collect()
+    }
+
     fn module(&self) -> Option<(&CrateDefMap, CrateModuleId)> {
         self.scopes.iter().rev().find_map(|scope| match scope {
             Scope::ModuleScope(m) => Some((&*m.crate_def_map, m.module_id)),
@@ -247,8 +339,11 @@ impl Scope {
                 }
             }
             Scope::ExprScope(e) => {
-                let entry =
-                    e.expr_scopes.entries(e.scope_id).iter().find(|entry| entry.name() == name);
+                let entry = e
+                    .expr_scopes
+                    .entries(e.scope_id)
+                    .iter()
+                    .find(|entry| entry.name().hygienic_eq(name));
                 match entry {
                     Some(e) => PerNs::values(Resolution::LocalBinding(e.pat())),
                     None => PerNs::none(),
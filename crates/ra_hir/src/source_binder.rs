@@ -14,8 +14,8 @@ use ra_syntax::{
 
 use crate::{
     HirDatabase, Function, Struct, Enum,
-    AsName, Module, HirFileId, Crate, Trait, Resolver,
-    ids::{LocationCtx, SourceFileItemId},
+    AsName, Module, HirFileId, Crate, Trait, Resolver, MacroCallId, MacroExpansion,
+    ids::{LocationCtx, SourceFileItemId, MacroCallLoc},
     expr
 };
 
@@ -72,6 +72,21 @@ pub fn module_from_child_node(
     }
 }
 
+/// Locates the macro call by its syntax node. Note that the call might still
+/// fail to expand, if the macro itself is not resolved.
+pub fn macro_call_from_source(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    macro_call: &ast::MacroCall,
+) -> Option<MacroCallId> {
+    let module = module_from_child_node(db, file_id, macro_call.syntax())?;
+    let file_id = file_id.into();
+    let file_items = db.file_items(file_id);
+    let item_id = file_items.id_of(file_id, macro_call.syntax());
+    let loc = MacroCallLoc { module, source_item_id: item_id.with_file_id(file_id) };
+    Some(loc.id(db))
+}
+
 fn module_from_source(
     db: &impl HirDatabase,
     file_id: HirFileId,
@@ -103,6 +118,17 @@ pub fn function_from_source(
     Some(res)
 }
 
+/// Locates a function defined by a macro. `fn_def` must be a node of
+/// `expansion.source_file()`.
+pub fn function_from_expansion(
+    db: &impl HirDatabase,
+    expansion: &MacroExpansion,
+    fn_def: &ast::FnDef,
+) -> Function {
+    let ctx = LocationCtx::new(db, expansion.module(), expansion.file_id());
+    Function { id: ctx.to_def(fn_def) }
+}
+
 pub fn function_from_module(
     db: &impl HirDatabase,
     module: Module,
@@ -0,0 +1,159 @@
+//! HIR for trait definitions.
+
+use std::sync::Arc;
+
+use rustc_hash::FxHashSet;
+use ra_syntax::{
+    TreeArc,
+    ast::{self, AstNode, NameOwner},
+};
+
+use crate::{
+    Function, Const, TypeAlias, Name, AsName, Trait, HirFileId,
+    DefDatabase, HirDatabase,
+    ids::LocationCtx,
+};
+
+/// The associated items of a trait, and which of them have a default
+/// implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraitData {
+    name: Option<Name>,
+    items: Vec<TraitItem>,
+    with_default_body: FxHashSet<TraitItem>,
+}
+
+impl TraitData {
+    pub(crate) fn trait_data_query(db: &impl DefDatabase, tr: Trait) -> Arc<TraitData> {
+        let (file_id, node) = tr.source(db);
+        let name = node.name().map(|name| name.as_name());
+        let ctx = LocationCtx::new(db, tr.module(db), file_id);
+        let mut items = Vec::new();
+        let mut with_default_body = FxHashSet::default();
+        for item_node in node.item_list().into_iter().flat_map(|it| it.impl_items()) {
+            let (item, has_default_body) = match item_node.kind() {
+                ast::ImplItemKind::FnDef(it) => {
+                    (Function { id: ctx.to_def(it) }.into(), it.body().is_some())
+                }
+                ast::ImplItemKind::ConstDef(it) => {
+                    (Const { id: ctx.to_def(it) }.into(), it.body().is_some())
+                }
+                ast::ImplItemKind::TypeAliasDef(it) => {
+                    (TypeAlias { id: ctx.to_def(it) }.into(), it.type_ref().is_some())
+                }
+            };
+            items.push(item);
+            if has_default_body {
+                with_default_body.insert(item);
+            }
+        }
+        Arc::new(TraitData { name, items, with_default_body })
+    }
+
+    pub fn name(&self) -> Option<&Name> {
+        self.name.as_ref()
+    }
+
+    /// The items of the trait, in source order.
+    pub fn items(&self) -> &[TraitItem] {
+        &self.items
+    }
+
+    /// Whether `item` has a default implementation, that is, a method with a
+    /// body, a const with a value or a type alias with a type. Such items
+    /// don't have to be implemented by impls of the trait.
+    pub fn has_default_body(&self, item: TraitItem) -> bool {
+        self.with_default_body.contains(&item)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraitItem {
+    Function(Function),
+    Const(Const),
+    TypeAlias(TypeAlias),
+    // Existential
+}
+impl_froms!(TraitItem: Function, Const, TypeAlias);
+
+impl TraitItem {
+    /// Returns the syntax of the item.
+    pub fn source(&self, db: &impl DefDatabase) -> (HirFileId, TreeArc<ast::ImplItem>) {
+        match self {
+            TraitItem::Function(it) => {
+                let (file_id, source) = it.source(db);
+                (file_id, <&ast::ImplItem>::from(&*source).to_owned())
+            }
+            TraitItem::Const(it) => {
+                let (file_id, source) = it.source(db);
+                (file_id, <&ast::ImplItem>::from(&*source).to_owned())
+            }
+            TraitItem::TypeAlias(it) => {
+                let (file_id, source) = it.source(db);
+                (file_id, <&ast::ImplItem>::from(&*source).to_owned())
+            }
+        }
+    }
+
+    pub fn name(&self, db: &impl HirDatabase) -> Option<Name> {
+        match self {
+            TraitItem::Function(it) => Some(it.name(db)),
+            TraitItem::Const(it) => Some(it.signature(db).name().clone()),
+            TraitItem::TypeAlias(it) => it.source(db).1.name().map(|name| name.as_name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_db::SourceDatabase;
+    use ra_syntax::ast::{self, AstNode};
+
+    use crate::{mock::MockDatabase, source_binder};
+
+    #[test]
+    fn lowers_trait_items() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            trait Tr {
+                fn required(&self);
+                fn provided(&self) {}
+                const REQUIRED: u32;
+                const PROVIDED: u32 = 92;
+                type Required;
+                type Provided = u8;
+            }
+            ",
+        );
+        let source_file = db.parse(file_id);
+        let trait_def = source_file.syntax().descendants().find_map(ast::TraitDef::cast).unwrap();
+        let module = source_binder::module_from_file_id(&db, file_id).unwrap();
+        let tr = source_binder::trait_from_module(&db, module, trait_def);
+        let data = tr.trait_data(&db);
+        assert_eq!(data.name().unwrap().to_string(), "Tr");
+
+        let items = tr.items(&db);
+        let names = items
+            .iter()
+            .map(|&it| {
+                let name = it.name(&db).unwrap().to_string();
+                format!("{}{}", name, if data.has_default_body(it) { " (default)" } else { "" })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "required",
+                "provided (default)",
+                "REQUIRED",
+                "PROVIDED (default)",
+                "Required",
+                "Provided (default)"
+            ]
+        );
+        for item in items {
+            let (_, source) = item.source(&db);
+            assert_eq!(source.syntax().ancestors().find_map(ast::TraitDef::cast), Some(trait_def));
+        }
+    }
+}
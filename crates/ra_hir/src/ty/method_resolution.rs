@@ -7,7 +7,7 @@ use std::sync::Arc;
 use rustc_hash::FxHashMap;
 
 use crate::{
-    HirDatabase, Module, Crate, Name, Function, Trait,
+    HirDatabase, Module, Crate, Name, Function, Trait, AdtDef,
     ids::TraitId,
     impl_block::{ImplId, ImplBlock, ImplItem},
     ty::{Ty, TypeCtor},
@@ -109,6 +109,34 @@ impl CrateImplBlocks {
     }
 }
 
+/// Impls of `tr` in all crates which can see it: the crate defining it and
+/// its dependents.
+pub(crate) fn impls_for_trait_query(db: &impl HirDatabase, tr: Trait) -> Arc<Vec<ImplBlock>> {
+    let mut res = Vec::new();
+    if let Some(krate) = tr.module(db).krate(db) {
+        for krate in dependents(db, krate) {
+            res.extend(db.impls_in_crate(krate).lookup_impl_blocks_for_trait(&tr));
+        }
+    }
+    Arc::new(res)
+}
+
+/// Inherent and trait impls for `adt` in all crates which can see it.
+pub(crate) fn impls_for_type_query(db: &impl HirDatabase, adt: AdtDef) -> Arc<Vec<ImplBlock>> {
+    let ty = Ty::simple(TypeCtor::Adt(adt));
+    let mut res = Vec::new();
+    if let Some(krate) = adt.krate(db) {
+        for krate in dependents(db, krate) {
+            res.extend(db.impls_in_crate(krate).lookup_impl_blocks(&ty));
+        }
+    }
+    Arc::new(res)
+}
+
+fn dependents(db: &impl HirDatabase, krate: Crate) -> impl Iterator<Item = Crate> {
+    db.crate_graph().dependents(krate.crate_id).into_iter().map(|crate_id| Crate { crate_id })
+}
+
 fn def_crate(db: &impl HirDatabase, ty: &Ty) -> Option<Crate> {
     match ty {
         Ty::Apply(a_ty) => match a_ty.ctor {
@@ -8,8 +8,9 @@ use ra_syntax::{algo, ast::{self, AstNode}};
 use test_utils::covers;
 
 use crate::{
-    source_binder,
+    source_binder, ModuleDef,
     mock::MockDatabase,
+    expr::Pat,
     ty::display::HirDisplay,
 };
 
@@ -239,6 +240,26 @@ fn test() {
     );
 }
 
+#[test]
+fn infer_builtin_macros() {
+    assert_snapshot_matches!(
+        infer(r#"
+fn test() {
+    let l = line!();
+    let s = concat!("a", 1);
+    foo!();
+}
+"#),
+        @r###"
+[11; 76) '{     ...!(); }': ()
+[21; 22) 'l': u32
+[25; 32) 'line!()': u32
+[42; 43) 's': &str
+[46; 61) 'concat!("a", 1)': &str
+[67; 73) 'foo!()': {unknown}"###
+    );
+}
+
 #[test]
 fn infer_unary_op() {
     assert_snapshot_matches!(
@@ -2189,6 +2210,46 @@ fn test<T: Iterable<Item=u32>>() {
     );
 }
 
+#[test]
+fn macro_locals_are_hygienic() {
+    let (db, _, file_id) = MockDatabase::with_single_file(
+        r#"
+macro_rules! m {
+    ($e:ident) => {
+        fn f() {
+            let x = 1u32;
+            let y = $e;
+            let z = x;
+        }
+    }
+}
+m!(x);
+"#,
+    );
+    let module = source_binder::module_from_file_id(&db, file_id).unwrap();
+    let func = module
+        .declarations(&db)
+        .into_iter()
+        .find_map(|it| match it {
+            ModuleDef::Function(it) => Some(it),
+            _ => None,
+        })
+        .unwrap();
+    let body = func.body(&db);
+    let inference_result = func.infer(&db);
+    let mut bindings = body
+        .pats()
+        .filter_map(|(pat, it)| match it {
+            Pat::Bind { name, .. } => {
+                Some(format!("{}: {}", name, inference_result[pat].display(&db)))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    bindings.sort();
+    assert_eq!(bindings, ["x: u32", "y: {unknown}", "z: u32"]);
+}
+
 fn type_at_pos(db: &MockDatabase, pos: FilePosition) -> String {
     let func = source_binder::function_from_position(db, pos).unwrap();
     let body_source_map = func.body_source_map(db);
@@ -24,6 +24,7 @@ pub(crate) fn assists(db: &RootDatabase, frange: FileRange) -> Vec<Assist> {
                 cursor_position: action
                     .cursor_position
                     .map(|offset| FilePosition { offset, file_id }),
+                typing: None,
             };
             Assist { id, change }
         })
@@ -0,0 +1,206 @@
+//! Call hierarchy: the functions which call a function, and the functions it
+//! calls.
+
+use hir::{ModuleDef, source_binder, reference_search};
+use ra_db::SourceDatabase;
+use ra_syntax::{AstNode, SmolStr, ast, algo::find_covering_node};
+
+use crate::{
+    FilePosition, FileRange, NavigationTarget,
+    call_info::{FnCallNode, resolve_callee},
+    db::RootDatabase,
+    references::find_def,
+};
+
+/// The function on the other side of a call. Outgoing method calls may be
+/// unresolved, for example because the type of the receiver is unknown.
+#[derive(Debug, Clone)]
+pub enum CallTarget {
+    Function(NavigationTarget),
+    UnresolvedMethod { name: SmolStr },
+}
+
+/// A caller or a callee of a function.
+#[derive(Debug, Clone)]
+pub struct CallItem {
+    pub target: CallTarget,
+    /// Ranges of the names of the called function at the call sites.
+    pub ranges: Vec<FileRange>,
+}
+
+pub(crate) fn incoming_calls(db: &RootDatabase, position: FilePosition) -> Option<Vec<CallItem>> {
+    let function = function_at(db, position)?;
+    let mut callers: Vec<(hir::Function, Vec<FileRange>)> = Vec::new();
+    for reference in reference_search::find_references(db, function.into()) {
+        let file = db.parse(reference.file_id);
+        let name_ref = find_covering_node(file.syntax(), reference.range)
+            .ancestors()
+            .find_map(ast::NameRef::cast);
+        let name_ref = match name_ref {
+            Some(it) if is_call(it) => it,
+            _ => continue,
+        };
+        let caller =
+            source_binder::function_from_child_node(db, reference.file_id, name_ref.syntax());
+        let caller = match caller {
+            Some(it) => it,
+            None => continue,
+        };
+        match callers.iter_mut().find(|(it, _)| *it == caller) {
+            Some((_, ranges)) => ranges.push(reference),
+            None => callers.push((caller, vec![reference])),
+        }
+    }
+    let res = callers
+        .into_iter()
+        .map(|(caller, ranges)| CallItem {
+            target: CallTarget::Function(NavigationTarget::from_function(db, caller)),
+            ranges,
+        })
+        .collect();
+    Some(res)
+}
+
+pub(crate) fn outgoing_calls(db: &RootDatabase, position: FilePosition) -> Option<Vec<CallItem>> {
+    let function = function_at(db, position)?;
+    let (hir_file_id, fn_def) = function.source(db);
+    // Ranges inside of macro expansions can't be shown to the user.
+    let file_id = hir_file_id.as_original_file();
+    if hir::HirFileId::from(file_id) != hir_file_id {
+        return Some(Vec::new());
+    }
+
+    let mut callees: Vec<(CallKey, Vec<FileRange>)> = Vec::new();
+    for node in fn_def.body()?.syntax().descendants() {
+        let call = if let Some(it) = ast::CallExpr::cast(node) {
+            FnCallNode::CallExpr(it)
+        } else if let Some(it) = ast::MethodCallExpr::cast(node) {
+            FnCallNode::MethodCallExpr(it)
+        } else {
+            continue;
+        };
+        let name_ref = match call.name_ref() {
+            Some(it) => it,
+            None => continue,
+        };
+        let key = match (resolve_callee(db, file_id, &call), &call) {
+            (Some(callee), _) => CallKey::Function(callee),
+            (None, FnCallNode::MethodCallExpr(_)) => CallKey::Method(name_ref.text().clone()),
+            // Calls of closures and of function pointers.
+            (None, FnCallNode::CallExpr(_)) => continue,
+        };
+        let range = FileRange { file_id, range: name_ref.syntax().range() };
+        match callees.iter_mut().find(|(it, _)| *it == key) {
+            Some((_, ranges)) => ranges.push(range),
+            None => callees.push((key, vec![range])),
+        }
+    }
+    let res = callees
+        .into_iter()
+        .map(|(key, ranges)| {
+            let target = match key {
+                CallKey::Function(callee) => {
+                    CallTarget::Function(NavigationTarget::from_function(db, callee))
+                }
+                CallKey::Method(name) => CallTarget::UnresolvedMethod { name },
+            };
+            CallItem { target, ranges }
+        })
+        .collect();
+    Some(res)
+}
+
+#[derive(PartialEq, Eq)]
+enum CallKey {
+    Function(hir::Function),
+    Method(SmolStr),
+}
+
+fn function_at(db: &RootDatabase, position: FilePosition) -> Option<hir::Function> {
+    let file = db.parse(position.file_id);
+    match find_def(db, &file, position)? {
+        ModuleDef::Function(it) => Some(it),
+        _ => None,
+    }
+}
+
+/// Whether the name is the called function of a call expression, and not,
+/// for example, a function passed as an argument.
+fn is_call(name_ref: &ast::NameRef) -> bool {
+    if name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast).is_some() {
+        return true;
+    }
+    let path_expr = name_ref
+        .syntax()
+        .ancestors()
+        .find_map(ast::Path::cast)
+        .and_then(|it| it.syntax().parent())
+        .and_then(ast::PathExpr::cast);
+    let path_expr = match path_expr {
+        Some(it) => it,
+        None => return false,
+    };
+    match path_expr.syntax().parent().and_then(ast::CallExpr::cast) {
+        Some(call) => call.expr().map(|it| it.syntax()) == Some(path_expr.syntax()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file_with_position;
+
+    use super::*;
+
+    fn calls(items: Vec<CallItem>) -> Vec<(String, Vec<String>)> {
+        items
+            .into_iter()
+            .map(|it| {
+                let name = match it.target {
+                    CallTarget::Function(nav) => nav.name().to_string(),
+                    CallTarget::UnresolvedMethod { name } => format!("?{}", name),
+                };
+                let ranges = it.ranges.iter().map(|it| format!("{:?}", it.range)).collect();
+                (name, ranges)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_incoming_calls() {
+        let (analysis, position) = single_file_with_position(
+            "
+fn callee<|>() {}
+fn caller1() { callee(); callee(); }
+fn caller2() { let f = callee; callee() }
+fn not_a_caller() {}
+",
+        );
+        let items = analysis.incoming_calls(position).unwrap().unwrap();
+        assert_eq!(
+            calls(items),
+            vec![
+                ("caller1".to_string(), vec!["[31; 37)".to_string(), "[41; 47)".to_string()]),
+                ("caller2".to_string(), vec!["[84; 90)".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outgoing_calls() {
+        let (analysis, position) = single_file_with_position(
+            "
+struct S;
+impl S { fn method(&self) {} }
+fn foo() {}
+fn caller<|>(s: S, x: Unknown) { foo(); s.method(); x.unknown(); foo(); }
+",
+        );
+        let items = analysis.outgoing_calls(position).unwrap().unwrap();
+        let names = calls(items).into_iter().map(|(name, ranges)| (name, ranges.len()));
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            vec![("foo".to_string(), 2), ("method".to_string(), 1), ("?unknown".to_string(), 1)]
+        );
+    }
+}
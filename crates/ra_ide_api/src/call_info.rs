@@ -1,12 +1,12 @@
 use test_utils::tested_by;
-use ra_db::SourceDatabase;
+use ra_db::{FileId, SourceDatabase};
 use ra_syntax::{
     AstNode, SyntaxNode, TextUnit,
-    SyntaxKind::FN_DEF,
-    ast::{self, ArgListOwner},
+    SyntaxKind::{FN_DEF, COMMA},
+    ast::{self, ArgListOwner, TypeAscriptionOwner},
     algo::find_node_at_offset,
 };
-use hir::Docs;
+use hir::{Docs, ImplItem, ModuleDef, Resolution};
 
 use crate::{FilePosition, CallInfo, db::RootDatabase};
 
@@ -17,48 +17,95 @@ pub(crate) fn call_info(db: &RootDatabase, position: FilePosition) -> Option<Cal
 
     // Find the calling expression and it's NameRef
     let calling_node = FnCallNode::with_node(syntax, position.offset)?;
-    let name_ref = calling_node.name_ref()?;
-
-    // Resolve the function's NameRef (NOTE: this isn't entirely accurate).
-    let file_symbols = crate::symbol_index::index_resolve(db, name_ref);
-    let symbol = file_symbols.into_iter().find(|it| it.ptr.kind() == FN_DEF)?;
-    let fn_file = db.parse(symbol.file_id);
-    let fn_def = symbol.ptr.to_node(&fn_file);
-    let fn_def = ast::FnDef::cast(fn_def).unwrap();
-    let function = hir::source_binder::function_from_source(db, symbol.file_id, fn_def)?;
-
-    let mut call_info = CallInfo::new(db, function, fn_def)?;
-    // If we have a calling expression let's find which argument we are on
-    let num_params = call_info.parameters.len();
-    let has_self = fn_def.param_list().and_then(|l| l.self_param()).is_some();
-
-    if num_params == 1 {
-        if !has_self {
-            call_info.active_parameter = Some(0);
+    let function = match resolve_callee(db, position.file_id, &calling_node) {
+        Some(it) => it,
+        None => {
+            // Resolve the function's NameRef (NOTE: this isn't entirely accurate).
+            tested_by!(call_info_approximate_callee);
+            let name_ref = calling_node.name_ref()?;
+            let file_symbols = crate::symbol_index::index_resolve(db, name_ref);
+            let symbol = file_symbols.into_iter().find(|it| it.ptr.kind() == FN_DEF)?;
+            let fn_file = db.parse(symbol.file_id);
+            // The symbol may come from the cache of a previous session.
+            let fn_def = symbol.ptr.try_to_node(&fn_file).and_then(ast::FnDef::cast)?;
+            hir::source_binder::function_from_source(db, symbol.file_id, fn_def)?
         }
-    } else if num_params > 1 {
-        // Count how many parameters into the call we are.
-        if let Some(ref arg_list) = calling_node.arg_list() {
-            let arg_list_range = arg_list.syntax().range();
-            if !arg_list_range.contains_inclusive(position.offset) {
-                tested_by!(call_info_bad_offset);
-                return None;
-            }
-
-            let param = arg_list
-                .args()
-                .position(|arg| arg.syntax().range().contains(position.offset))
-                .or(Some(num_params - 1))
-                .unwrap();
+    };
+    let (_, fn_def) = function.source(db);
+
+    let mut call_info = CallInfo::new(db, function, &fn_def)?;
+    let arg_list = match calling_node.arg_list() {
+        Some(it) => it,
+        None => return Some(call_info),
+    };
+    if !arg_list.syntax().range().contains_inclusive(position.offset) {
+        tested_by!(call_info_bad_offset);
+        return None;
+    }
 
-            call_info.active_parameter = Some(param);
+    // Count the commas before the cursor. Commas of the nested calls and
+    // tuples are inside of the argument nodes, so only the direct children of
+    // the argument list are counted.
+    let mut param = arg_list
+        .syntax()
+        .children()
+        .filter(|it| it.kind() == COMMA && it.range().end() <= position.offset)
+        .count();
+    // With the method call syntax, `self` is not in the argument list.
+    if let FnCallNode::MethodCallExpr(_) = calling_node {
+        if function.signature(db).has_self_param() {
+            param += 1;
         }
     }
+    if param < call_info.parameters.len() {
+        call_info.active_parameter = Some(param);
+    }
 
     Some(call_info)
 }
 
-enum FnCallNode<'a> {
+/// Resolves the called function precisely, using name resolution and type
+/// inference of the enclosing function.
+pub(crate) fn resolve_callee(
+    db: &RootDatabase,
+    file_id: FileId,
+    calling_node: &FnCallNode,
+) -> Option<hir::Function> {
+    let function = hir::source_binder::function_from_child_node(db, file_id, calling_node.syntax());
+    match calling_node {
+        FnCallNode::CallExpr(call_expr) => {
+            let callee = call_expr.expr()?;
+            if let Some(function) = function {
+                let infer_result = function.infer(db);
+                let source_map = function.body_source_map(db);
+                let assoc_item = source_map
+                    .node_expr(callee)
+                    .and_then(|it| infer_result.assoc_resolutions_for_expr(it));
+                if let Some(ImplItem::Method(func)) = assoc_item {
+                    return Some(func);
+                }
+            }
+            let path = match callee.kind() {
+                ast::ExprKind::PathExpr(path_expr) => hir::Path::from_ast(path_expr.path()?)?,
+                _ => return None,
+            };
+            let resolver = hir::source_binder::resolver_for_node(db, file_id, callee.syntax());
+            match resolver.resolve_path(db, &path).take_values()? {
+                Resolution::Def(ModuleDef::Function(func)) => Some(func),
+                _ => None,
+            }
+        }
+        FnCallNode::MethodCallExpr(method_call) => {
+            let function = function?;
+            let infer_result = function.infer(db);
+            let source_map = function.body_source_map(db);
+            let expr = ast::Expr::cast(method_call.syntax()).unwrap();
+            source_map.node_expr(expr).and_then(|it| infer_result.method_resolution(it))
+        }
+    }
+}
+
+pub(crate) enum FnCallNode<'a> {
     CallExpr(&'a ast::CallExpr),
     MethodCallExpr(&'a ast::MethodCallExpr),
 }
@@ -87,6 +134,13 @@ impl<'a> FnCallNode<'a> {
         }
     }
 
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        match *self {
+            FnCallNode::CallExpr(expr) => expr.syntax(),
+            FnCallNode::MethodCallExpr(expr) => expr.syntax(),
+        }
+    }
+
     pub fn arg_list(&self) -> Option<&'a ast::ArgList> {
         match *self {
             FnCallNode::CallExpr(expr) => expr.arg_list(),
@@ -99,23 +153,32 @@ impl CallInfo {
     fn new(db: &RootDatabase, function: hir::Function, node: &ast::FnDef) -> Option<Self> {
         let label = crate::completion::function_label(node)?;
         let doc = function.docs(db);
+        let (parameters, parameter_types) = param_list(node).into_iter().unzip();
 
-        Some(CallInfo { parameters: param_list(node), label, doc, active_parameter: None })
+        Some(CallInfo { parameters, parameter_types, label, doc, active_parameter: None })
     }
 }
 
-fn param_list(node: &ast::FnDef) -> Vec<String> {
+fn param_list(node: &ast::FnDef) -> Vec<(String, String)> {
     let mut res = vec![];
     if let Some(param_list) = node.param_list() {
         if let Some(self_param) = param_list.self_param() {
-            res.push(self_param.syntax().text().to_string())
+            let ty = match self_param.ascribed_type() {
+                Some(ty) => ty.syntax().text().to_string(),
+                None => match self_param.flavor() {
+                    ast::SelfParamFlavor::Owned => "Self".to_string(),
+                    ast::SelfParamFlavor::Ref => "&Self".to_string(),
+                    ast::SelfParamFlavor::MutRef => "&mut Self".to_string(),
+                },
+            };
+            res.push((self_param.syntax().text().to_string(), ty))
         }
 
-        // Maybe use param.pat here? See if we can just extract the name?
-        //res.extend(param_list.params().map(|p| p.syntax().text().to_string()));
-        res.extend(
-            param_list.params().filter_map(|p| p.pat()).map(|pat| pat.syntax().text().to_string()),
-        );
+        res.extend(param_list.params().filter_map(|p| {
+            let pat = p.pat()?.syntax().text().to_string();
+            let ty = p.ascribed_type().map(|it| it.syntax().text().to_string());
+            Some((pat, ty.unwrap_or_default()))
+        }));
     }
     res
 }
@@ -320,8 +383,43 @@ assert_eq!(6, my_crate::add_one(5));
         );
     }
 
+    #[test]
+    fn test_fn_signature_nested_calls() {
+        let info = call_info(
+            r#"fn foo(x: u32, y: (u32, u32), z: u32) -> u32 { x }
+fn bar(a: u32, b: u32) -> u32 { a }
+fn baz() { foo(bar(1, 2), (3, 4), <|>); }"#,
+        );
+
+        assert_eq!(info.parameters, vec!["x".to_string(), "y".to_string(), "z".to_string()]);
+        assert_eq!(info.parameter_types, vec!["u32", "(u32, u32)", "u32"]);
+        assert_eq!(info.active_parameter, Some(2));
+
+        let info = call_info(
+            r#"fn foo(x: u32, y: u32) -> u32 { x }
+fn bar(a: u32, b: u32) -> u32 { a }
+fn baz() { foo(1, bar(2, <|>)); }"#,
+        );
+        assert_eq!(info.label, "fn bar(a: u32, b: u32) -> u32".to_string());
+        assert_eq!(info.active_parameter, Some(1));
+    }
+
+    #[test]
+    fn test_fn_signature_resolves_callee() {
+        let info = call_info(
+            r#"mod a { pub fn foo(x: u32) {} }
+mod b { pub fn foo(y: u32, z: u32) {} }
+use b::foo;
+fn bar() { foo(1, <|>); }"#,
+        );
+
+        assert_eq!(info.parameters, vec!["y".to_string(), "z".to_string()]);
+        assert_eq!(info.active_parameter, Some(1));
+    }
+
     #[test]
     fn test_fn_signature_with_docs_from_actix() {
+        covers!(call_info_approximate_callee);
         let info = call_info(
             r#"
 pub trait WriteHandler<E>
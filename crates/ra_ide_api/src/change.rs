@@ -1,21 +1,17 @@
-use std::{
-    fmt, time,
-    sync::Arc,
-};
+use std::{fmt, time, iter::FromIterator, sync::Arc};
 
 use rustc_hash::FxHashMap;
 use ra_db::{
     SourceRootId, FileId, CrateGraph, SourceDatabase, SourceRoot,
-    salsa::{Database, SweepStrategy},
+    salsa::{Database, SweepStrategy, debug::{DebugQueryTable, TableEntry}},
 };
-use ra_syntax::SourceFile;
 use relative_path::RelativePathBuf;
 use rayon::prelude::*;
 
 use crate::{
     db::RootDatabase,
+    symbol_cache::SymbolCache,
     symbol_index::{SymbolIndex, SymbolsDatabase},
-    status::syntax_tree_stats,
 };
 
 #[derive(Default)]
@@ -136,10 +132,20 @@ impl LibraryData {
         root_id: SourceRootId,
         files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
     ) -> LibraryData {
-        let symbol_index = SymbolIndex::for_files(files.par_iter().map(|(file_id, _, text)| {
-            let file = SourceFile::parse(text);
-            (*file_id, file)
-        }));
+        LibraryData::prepare_with_cache(root_id, files, &SymbolCache::default())
+    }
+
+    /// Like `prepare`, but takes the symbols of unchanged files from `cache`
+    /// instead of parsing them.
+    pub fn prepare_with_cache(
+        root_id: SourceRootId,
+        files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
+        cache: &SymbolCache,
+    ) -> LibraryData {
+        let symbol_index = SymbolIndex::for_files(
+            files.par_iter().map(|(file_id, _, text)| (*file_id, text.as_str())),
+            cache,
+        );
         let mut root_change = RootChange::default();
         root_change.added = files
             .into_iter()
@@ -151,6 +157,29 @@ impl LibraryData {
 
 const GC_COOLDOWN: time::Duration = time::Duration::from_millis(100);
 
+/// How many values the queries which are cheap to recompute, but take a lot
+/// of memory, may retain.
+///
+/// Salsa can't evict individual values, so the limits are thresholds rather
+/// than capacities: when a query goes over its limit, the values of all the
+/// syntax-related queries which were not used in the current revision are
+/// discarded, and the recently used ones are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionLimits {
+    /// Syntax trees of the files.
+    pub parse: usize,
+    /// Syntax trees of the files and of the macro expansions.
+    pub hir_parse: usize,
+    /// Token trees produced by macro calls.
+    pub macro_expand: usize,
+}
+
+impl Default for RetentionLimits {
+    fn default() -> RetentionLimits {
+        RetentionLimits { parse: 128, hir_parse: 128, macro_expand: 512 }
+    }
+}
+
 impl RootDatabase {
     pub(crate) fn apply_change(&mut self, change: AnalysisChange) {
         log::info!("apply_change {:?}", change);
@@ -204,22 +233,30 @@ impl RootDatabase {
     pub(crate) fn maybe_collect_garbage(&mut self) {
         if self.last_gc_check.elapsed() > GC_COOLDOWN {
             self.last_gc_check = time::Instant::now();
-            let retained_trees = syntax_tree_stats(self).retained;
-            if retained_trees > 100 {
-                log::info!("automatic garbadge collection, {} retained trees", retained_trees);
-                self.collect_garbage();
+            if self.is_over_retention_limits() {
+                log::info!("automatic garbage collection");
+                self.sweep(SweepStrategy::default().discard_values());
             }
         }
     }
 
+    fn is_over_retention_limits(&self) -> bool {
+        let limits = self.retention_limits;
+        self.query(ra_db::ParseQuery).entries::<Retained>().0 > limits.parse
+            || self.query(hir::db::HirParseQuery).entries::<Retained>().0 > limits.hir_parse
+            || self.query(hir::db::MacroExpandQuery).entries::<Retained>().0 > limits.macro_expand
+    }
+
     pub(crate) fn collect_garbage(&mut self) {
         self.last_gc = time::Instant::now();
+        self.sweep(SweepStrategy::default().discard_values().sweep_all_revisions());
+    }
 
-        let sweep = SweepStrategy::default().discard_values().sweep_all_revisions();
-
+    fn sweep(&mut self, sweep: SweepStrategy) {
         self.query(ra_db::ParseQuery).sweep(sweep);
 
         self.query(hir::db::HirParseQuery).sweep(sweep);
+        self.query(hir::db::MacroExpandQuery).sweep(sweep);
         self.query(hir::db::FileItemsQuery).sweep(sweep);
         self.query(hir::db::FileItemQuery).sweep(sweep);
 
@@ -227,3 +264,73 @@ impl RootDatabase {
         self.query(hir::db::BodyWithSourceMapQuery).sweep(sweep);
     }
 }
+
+/// The number of values a query retains. Counting doesn't allocate, unlike
+/// collecting the entries into a `Vec`.
+struct Retained(usize);
+
+impl<K, V> FromIterator<TableEntry<K, V>> for Retained {
+    fn from_iter<T>(iter: T) -> Retained
+    where
+        T: IntoIterator<Item = TableEntry<K, V>>,
+    {
+        Retained(iter.into_iter().filter(|it| it.value.is_some()).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_db::Edition::Edition2018;
+    use hir::{HirFileId, db::DefDatabase};
+
+    use super::*;
+
+    /// The keys of the entries which retain a value.
+    fn retained<K, V>(entries: Vec<TableEntry<K, V>>) -> Vec<K> {
+        entries.into_iter().filter(|it| it.value.is_some()).map(|it| it.key).collect()
+    }
+
+    #[test]
+    fn garbage_collection_keeps_values_of_the_current_revision() {
+        let mut db = RootDatabase::default();
+        db.retention_limits = RetentionLimits { parse: 2, hir_parse: 2, macro_expand: 2 };
+
+        let root_id = SourceRootId(0);
+        let mut change = AnalysisChange::new();
+        change.add_root(root_id, true);
+        let lib = FileId(1);
+        let lib_text = "macro_rules! m { () => { fn f() {} } } mod m1; mod m2; mod m3; mod m4;";
+        change.add_file(root_id, lib, "lib.rs".into(), Arc::new(lib_text.to_string()));
+        for i in 1..=4 {
+            let path = RelativePathBuf::from(format!("m{}.rs", i));
+            change.add_file(root_id, FileId(i + 1), path, Arc::new("m!();".to_string()));
+        }
+        let mut crate_graph = CrateGraph::default();
+        crate_graph.add_crate_root(lib, Edition2018);
+        change.set_crate_graph(crate_graph);
+        db.apply_change(change);
+
+        let krate = hir::source_binder::module_from_file_id(&db, lib).unwrap().krate(&db).unwrap();
+        db.crate_def_map(krate);
+        assert!(db.is_over_retention_limits());
+        assert_eq!(retained(db.query(ra_db::ParseQuery).entries()).len(), 5);
+        assert_eq!(retained(db.query(hir::db::MacroExpandQuery).entries()).len(), 4);
+
+        // Only the root file is used after the change.
+        let mut change = AnalysisChange::new();
+        change.change_file(FileId(5), Arc::new("fn g() {}".to_string()));
+        db.apply_change(change);
+        db.parse(lib);
+        db.hir_parse(lib.into());
+
+        db.last_gc_check -= GC_COOLDOWN * 2;
+        db.maybe_collect_garbage();
+
+        assert_eq!(retained(db.query(ra_db::ParseQuery).entries()), vec![lib]);
+        assert_eq!(
+            retained(db.query(hir::db::HirParseQuery).entries()),
+            vec![HirFileId::from(lib)]
+        );
+        assert!(retained(db.query(hir::db::MacroExpandQuery).entries()).is_empty());
+    }
+}
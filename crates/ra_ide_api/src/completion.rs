@@ -1,5 +1,6 @@
 mod completion_item;
 mod completion_context;
+mod syntactic_context;
 mod presentation;
 
 mod complete_dot;
@@ -27,7 +28,10 @@ use crate::{
 #[cfg(test)]
 use crate::completion::completion_item::{do_completion, check_completion};
 
-pub use crate::completion::completion_item::{CompletionItem, CompletionItemKind, InsertTextFormat};
+pub use crate::completion::{
+    completion_item::{CompletionItem, CompletionItemKind, InsertTextFormat},
+    syntactic_context::SyntacticCompletionContext,
+};
 
 /// Main entry point for completion. We run completion as a two-phase process.
 ///
@@ -59,6 +63,7 @@ pub(crate) fn completions(db: &db::RootDatabase, position: FilePosition) -> Opti
 
     complete_fn_param::complete_fn_param(&mut acc, &ctx);
     complete_keyword::complete_expr_keyword(&mut acc, &ctx);
+    complete_keyword::complete_item_keyword(&mut acc, &ctx);
     complete_keyword::complete_use_tree_keyword(&mut acc, &ctx);
     complete_snippet::complete_expr_snippet(&mut acc, &ctx);
     complete_snippet::complete_item_snippet(&mut acc, &ctx);
@@ -10,8 +10,8 @@ use crate::completion::{CompletionContext, CompletionItem, Completions, Completi
 pub(super) fn complete_use_tree_keyword(acc: &mut Completions, ctx: &CompletionContext) {
     // complete keyword "crate" in use stmt
     let source_range = ctx.source_range();
-    match (ctx.use_item_syntax.as_ref(), ctx.path_prefix.as_ref()) {
-        (Some(_), None) => {
+    match (ctx.syntactic.in_use_tree, ctx.path_prefix.as_ref()) {
+        (true, None) => {
             CompletionItem::new(CompletionKind::Keyword, source_range, "crate")
                 .kind(CompletionItemKind::Keyword)
                 .insert_text("crate::")
@@ -24,7 +24,7 @@ pub(super) fn complete_use_tree_keyword(acc: &mut Completions, ctx: &CompletionC
                 .insert_text("super::")
                 .add_to(acc);
         }
-        (Some(_), Some(_)) => {
+        (true, Some(_)) => {
             CompletionItem::new(CompletionKind::Keyword, source_range, "self")
                 .kind(CompletionItemKind::Keyword)
                 .add_to(acc);
@@ -44,6 +44,45 @@ fn keyword(ctx: &CompletionContext, kw: &str, snippet: &str) -> CompletionItem {
         .build()
 }
 
+/// Keywords which start an item, with the snippets they expand to.
+const ITEM_KEYWORDS: &[(&str, &str)] = &[
+    ("const", "const $0"),
+    ("enum", "enum $0 {}"),
+    ("fn", "fn $0() {}"),
+    ("impl", "impl $0 {}"),
+    ("mod", "mod $0"),
+    ("static", "static $0"),
+    ("struct", "struct $0"),
+    ("trait", "trait $0 {}"),
+    ("type", "type $0"),
+    ("use", "use $0;"),
+];
+
+/// Keywords which start an associated item, in an impl or a trait.
+const ASSOC_ITEM_KEYWORDS: &[(&str, &str)] =
+    &[("const", "const $0"), ("fn", "fn $0() {}"), ("type", "type $0")];
+
+pub(super) fn complete_item_keyword(acc: &mut Completions, ctx: &CompletionContext) {
+    if !ctx.syntactic.in_item_position {
+        return;
+    }
+    let container = ctx.leaf.ancestors().map(|it| it.kind()).find(|&kind| match kind {
+        IMPL_BLOCK | TRAIT_DEF | MODULE | SOURCE_FILE => true,
+        _ => false,
+    });
+    let keywords = match container {
+        Some(IMPL_BLOCK) | Some(TRAIT_DEF) => ASSOC_ITEM_KEYWORDS,
+        _ => ITEM_KEYWORDS,
+    };
+    for &(kw, snippet) in keywords {
+        acc.add(keyword(ctx, kw, snippet));
+    }
+    // Items of a trait are as visible as the trait itself.
+    if container != Some(TRAIT_DEF) {
+        acc.add(keyword(ctx, "pub", "pub $0"));
+    }
+}
+
 pub(super) fn complete_expr_keyword(acc: &mut Completions, ctx: &CompletionContext) {
     if !ctx.is_trivial_path {
         return;
@@ -54,10 +93,22 @@ pub(super) fn complete_expr_keyword(acc: &mut Completions, ctx: &CompletionConte
         None => return,
     };
     acc.add(keyword(ctx, "if", "if $0 {}"));
+    acc.add(keyword(ctx, "if let", "if let $1 = $0 {}"));
     acc.add(keyword(ctx, "match", "match $0 {}"));
     acc.add(keyword(ctx, "while", "while $0 {}"));
+    acc.add(keyword(ctx, "while let", "while let $1 = $0 {}"));
+    acc.add(keyword(ctx, "for", "for $1 in $0 {}"));
     acc.add(keyword(ctx, "loop", "loop {$0}"));
 
+    // Items may be declared inside of a function body as well, but there is
+    // no point in making them `pub`.
+    if ctx.can_be_stmt {
+        acc.add(keyword(ctx, "let", "let $1 = $0;"));
+        for &(kw, snippet) in ITEM_KEYWORDS {
+            acc.add(keyword(ctx, kw, snippet));
+        }
+    }
+
     if ctx.after_if {
         acc.add(keyword(ctx, "else", "else {$0}"));
         acc.add(keyword(ctx, "else if", "else if $0 {}"));
@@ -109,7 +160,7 @@ fn complete_return(
 
 #[cfg(test)]
 mod tests {
-    use crate::completion::{check_completion, CompletionKind};
+    use crate::completion::{check_completion, do_completion, CompletionKind};
 
     fn check_keyword_completion(name: &str, code: &str) {
         check_completion(name, code, CompletionKind::Keyword);
@@ -151,6 +202,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn completes_item_keywords_by_position() {
+        let labels = |code| {
+            let completions = do_completion(code, CompletionKind::Keyword);
+            completions.into_iter().map(|it| it.label().to_string()).collect::<Vec<_>>()
+        };
+
+        let top_level = labels("struct S;\n<|>");
+        assert!(top_level.contains(&"pub".to_string()));
+        assert!(top_level.contains(&"struct".to_string()));
+        assert!(!top_level.contains(&"let".to_string()));
+
+        let in_function = labels("fn quux() { <|> }");
+        assert!(in_function.contains(&"struct".to_string()));
+        assert!(in_function.contains(&"let".to_string()));
+        assert!(!in_function.contains(&"pub".to_string()));
+
+        assert_eq!(labels("trait T { <|> }"), vec!["const", "fn", "type"]);
+        assert_eq!(labels("impl S { <|> }"), vec!["const", "fn", "pub", "type"]);
+    }
+
     #[test]
     fn completes_else_after_if() {
         check_keyword_completion(
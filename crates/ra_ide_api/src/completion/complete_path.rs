@@ -1,4 +1,4 @@
-use hir::Resolution;
+use hir::{PathKind, PerNs, Resolution};
 use ra_syntax::AstNode;
 use test_utils::tested_by;
 
@@ -9,6 +9,15 @@ pub(super) fn complete_path(acc: &mut Completions, ctx: &CompletionContext) {
         Some(path) => path.clone(),
         _ => return,
     };
+    if path.kind == PathKind::Abs && path.segments.is_empty() {
+        for (name, res) in ctx.resolver.global_path_names() {
+            let res = filter_namespace(ctx, res);
+            if !res.is_none() {
+                acc.add_resolution(ctx, name.to_string(), &res);
+            }
+        }
+        return;
+    }
     let def = match ctx.resolver.resolve_path(ctx.db, &path).take_types() {
         Some(Resolution::Def(def)) => def,
         _ => return,
@@ -16,7 +25,7 @@ pub(super) fn complete_path(acc: &mut Completions, ctx: &CompletionContext) {
     match def {
         hir::ModuleDef::Module(module) => {
             let module_scope = module.scope(ctx.db);
-            for (name, res) in module_scope.entries() {
+            for (name, res) in module_scope.entries_in_source_order() {
                 if Some(module) == ctx.module {
                     if let Some(import) = res.import {
                         let path = module.import_source(ctx.db, import);
@@ -27,7 +36,10 @@ pub(super) fn complete_path(acc: &mut Completions, ctx: &CompletionContext) {
                         }
                     }
                 }
-                acc.add_resolution(ctx, name.to_string(), &res.def.map(hir::Resolution::Def));
+                let res = filter_namespace(ctx, res.def.map(hir::Resolution::Def));
+                if !res.is_none() {
+                    acc.add_resolution(ctx, name.to_string(), &res);
+                }
             }
         }
         hir::ModuleDef::Enum(e) => {
@@ -55,6 +67,15 @@ pub(super) fn complete_path(acc: &mut Completions, ctx: &CompletionContext) {
     };
 }
 
+/// Keeps only the resolutions which fit at the end of the path, or which
+/// may qualify a longer one, like a module in type position.
+fn filter_namespace(ctx: &CompletionContext, res: PerNs<Resolution>) -> PerNs<Resolution> {
+    match ctx.path_expectation {
+        Some(expectation) => expectation.filter(res.clone()).or(expectation.filter_qualifier(res)),
+        None => res,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use test_utils::covers;
@@ -244,6 +265,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn completes_only_fitting_namespaces() {
+        let labels = |code| {
+            let completions = do_completion(code, CompletionKind::Reference);
+            completions.iter().map(|it| it.label().to_string()).collect::<Vec<_>>()
+        };
+        let module = "mod m { pub struct S; pub fn f() {} pub mod inner {} }";
+        assert_eq!(labels(&format!("{} fn foo(x: m::<|>) {{}}", module)), vec!["S", "inner"]);
+        assert_eq!(labels(&format!("{} fn foo() {{ m::<|> }}", module)), vec!["S", "f", "inner"]);
+        assert_eq!(labels(&format!("{} use m::<|>;", module)), vec!["S", "f", "inner"]);
+    }
+
+    #[test]
+    fn completes_extern_prelude_after_leading_colons() {
+        let completions = do_completion(
+            "
+            //- /main.rs
+            fn foo() { let _ = ::<|> }
+
+            //- /foo/lib.rs
+            pub struct S;
+            ",
+            CompletionKind::Reference,
+        );
+        let labels = completions.iter().map(|it| it.label()).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["foo"]);
+    }
+
     #[test]
     fn completes_use_paths_across_crates() {
         check_reference_completion(
@@ -7,7 +7,7 @@ use ra_syntax::{
 };
 use hir::{source_binder, Resolver};
 
-use crate::{db, FilePosition};
+use crate::{db, FilePosition, completion::SyntacticCompletionContext};
 
 /// `CompletionContext` is created early during completion to figure out, where
 /// exactly is the cursor, syntax-wise.
@@ -20,7 +20,6 @@ pub(crate) struct CompletionContext<'a> {
     pub(super) module: Option<hir::Module>,
     pub(super) function: Option<hir::Function>,
     pub(super) function_syntax: Option<&'a ast::FnDef>,
-    pub(super) use_item_syntax: Option<&'a ast::UseItem>,
     pub(super) struct_lit_syntax: Option<&'a ast::StructLit>,
     pub(super) is_param: bool,
     /// If a name-binding or reference to a const in a pattern.
@@ -30,15 +29,19 @@ pub(crate) struct CompletionContext<'a> {
     pub(super) is_trivial_path: bool,
     /// If not a trivial, path, the prefix (qualifier).
     pub(super) path_prefix: Option<hir::Path>,
+    /// What the path with the prefix should resolve to. `None` in `use`
+    /// items, where anything goes.
+    pub(super) path_expectation: Option<hir::PathExpectation>,
     pub(super) after_if: bool,
     /// `true` if we are a statement or a last expr in the block.
     pub(super) can_be_stmt: bool,
-    /// Something is typed at the "top" level, in module or impl/trait.
-    pub(super) is_new_item: bool,
     /// The receiver if this is a field or method access, i.e. writing something.<|>
     pub(super) dot_receiver: Option<&'a ast::Expr>,
     /// If this is a call (method or function) in particular, i.e. the () are already there.
     pub(super) is_call: bool,
+    /// The purely syntactic part of the context, like being in a `use` item
+    /// or at the "top" level, in module or impl/trait.
+    pub(super) syntactic: SyntacticCompletionContext,
 }
 
 impl<'a> CompletionContext<'a> {
@@ -58,17 +61,17 @@ impl<'a> CompletionContext<'a> {
             module,
             function: None,
             function_syntax: None,
-            use_item_syntax: None,
             struct_lit_syntax: None,
             is_param: false,
             is_pat_binding: false,
             is_trivial_path: false,
             path_prefix: None,
+            path_expectation: None,
             after_if: false,
             can_be_stmt: false,
-            is_new_item: false,
             dot_receiver: None,
             is_call: false,
+            syntactic: SyntacticCompletionContext::default(),
         };
         ctx.fill(original_file, position.offset);
         Some(ctx)
@@ -91,6 +94,7 @@ impl<'a> CompletionContext<'a> {
             let edit = AtomTextEdit::insert(offset, "intellijRulezz".to_string());
             original_file.reparse(&edit)
         };
+        self.syntactic = SyntacticCompletionContext::with_fake_ident(original_file, &file, offset);
 
         // First, let's try to complete a reference to some declaration.
         if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(file.syntax(), offset) {
@@ -123,24 +127,14 @@ impl<'a> CompletionContext<'a> {
     }
 
     fn classify_name_ref(&mut self, original_file: &'a SourceFile, name_ref: &ast::NameRef) {
-        let name_range = name_ref.syntax().range();
         if name_ref.syntax().parent().and_then(ast::NamedField::cast).is_some() {
             self.struct_lit_syntax = find_node_at_offset(original_file.syntax(), self.offset);
         }
 
-        let top_node =
-            name_ref.syntax().ancestors().take_while(|it| it.range() == name_range).last().unwrap();
-
-        match top_node.parent().map(|it| it.kind()) {
-            Some(SOURCE_FILE) | Some(ITEM_LIST) => {
-                self.is_new_item = true;
-                return;
-            }
-            _ => (),
+        if self.syntactic.in_item_position {
+            return;
         }
 
-        self.use_item_syntax = self.leaf.ancestors().find_map(ast::UseItem::cast);
-
         self.function_syntax = self
             .leaf
             .ancestors()
@@ -164,10 +158,11 @@ impl<'a> CompletionContext<'a> {
                 .and_then(|it| it.syntax().parent().and_then(ast::CallExpr::cast))
                 .is_some();
 
-            if let Some(mut path) = hir::Path::from_ast(path) {
-                if !path.is_ident() {
-                    path.segments.pop().unwrap();
-                    self.path_prefix = Some(path);
+            if let Some(mut hir_path) = hir::Path::from_ast(path) {
+                if !hir_path.is_ident() {
+                    hir_path.segments.pop().unwrap();
+                    self.path_prefix = Some(hir_path);
+                    self.path_expectation = path_expectation(path);
                     return;
                 }
             }
@@ -223,6 +218,17 @@ impl<'a> CompletionContext<'a> {
     }
 }
 
+fn path_expectation(path: &ast::Path) -> Option<hir::PathExpectation> {
+    let top_path = path.syntax().ancestors().take_while(|it| it.kind() == PATH).last().unwrap();
+    let res = match top_path.parent()?.kind() {
+        PATH_TYPE => hir::PathExpectation::Type,
+        PATH_EXPR | PATH_PAT | TUPLE_STRUCT_PAT => hir::PathExpectation::Value,
+        MACRO_CALL => hir::PathExpectation::Macro,
+        _ => return None,
+    };
+    Some(res)
+}
+
 fn find_node_with_range<N: AstNode>(syntax: &SyntaxNode, range: TextRange) -> Option<&N> {
     let node = find_covering_node(syntax, range);
     node.ancestors().find_map(N::cast)
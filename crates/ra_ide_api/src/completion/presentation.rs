@@ -103,7 +103,7 @@ impl Completions {
             .set_documentation(func.docs(ctx.db))
             .set_detail(detail);
         // If not an import, add parenthesis automatically.
-        if ctx.use_item_syntax.is_none() && !ctx.is_call {
+        if !ctx.syntactic.in_use_tree && !ctx.is_call {
             tested_by!(inserts_parens_for_function_calls);
             let snippet =
                 if sig.params().is_empty() || sig.has_self_param() && sig.params().len() == 1 {
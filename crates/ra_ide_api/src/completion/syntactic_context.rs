@@ -0,0 +1,138 @@
+use ra_text_edit::AtomTextEdit;
+use ra_syntax::{
+    AstNode, SyntaxNode, SourceFile, TextUnit, ast,
+    algo::{find_leaf_at_offset, find_node_at_offset},
+    SyntaxKind::*,
+};
+
+/// Classifies the position of the cursor using only the syntax tree. Unlike
+/// `CompletionContext`, this doesn't need a database, so completion providers
+/// built on top of it can be tested on bare source files. `CompletionContext`
+/// computes one as well and keeps it in its `syntactic` field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyntacticCompletionContext {
+    /// A field or a method: `foo.<|>`.
+    pub after_dot: bool,
+    /// A qualified path: `foo::<|>` or `::<|>`.
+    pub after_colon_colon: bool,
+    /// Inside of a `use` item.
+    pub in_use_tree: bool,
+    pub in_pattern: bool,
+    /// Where a new item can start, in a module, impl or trait.
+    pub in_item_position: bool,
+    pub in_type_position: bool,
+    /// Inside of a string or char literal, or of a comment. Usually, nothing
+    /// should be completed there.
+    pub in_string_or_comment: bool,
+    /// Inside of an attribute: `#[<|>]`.
+    pub in_attr: bool,
+}
+
+impl SyntacticCompletionContext {
+    pub fn new(file: &SourceFile, offset: TextUnit) -> SyntacticCompletionContext {
+        // As in `CompletionContext`, insert a fake ident to get a valid parse
+        // tree.
+        let fake_file = file.reparse(&AtomTextEdit::insert(offset, "intellijRulezz".to_string()));
+        SyntacticCompletionContext::with_fake_ident(file, &fake_file, offset)
+    }
+
+    /// Like `new`, but reuses `fake_file`, the `original_file` with an
+    /// identifier inserted at the `offset`.
+    pub(super) fn with_fake_ident(
+        original_file: &SourceFile,
+        fake_file: &SourceFile,
+        offset: TextUnit,
+    ) -> SyntacticCompletionContext {
+        let mut ctx = SyntacticCompletionContext::default();
+        ctx.in_string_or_comment = ra_ide_api_light::is_in_string_or_comment(original_file, offset);
+        if ctx.in_string_or_comment {
+            return ctx;
+        }
+
+        let file = fake_file;
+        let node = match find_leaf_at_offset(file.syntax(), offset).right_biased() {
+            Some(it) => it,
+            None => return ctx,
+        };
+        ctx.in_attr = node.ancestors().any(|it| it.kind() == ATTR);
+        ctx.in_use_tree = node.ancestors().any(|it| it.kind() == USE_ITEM);
+
+        if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(file.syntax(), offset) {
+            ctx.classify_name_ref(name_ref);
+        } else if let Some(name) = find_node_at_offset::<ast::Name>(file.syntax(), offset) {
+            ctx.in_pattern = ast::BindPat::cast(name.syntax().parent().unwrap()).is_some();
+        }
+        ctx
+    }
+
+    fn classify_name_ref(&mut self, name_ref: &ast::NameRef) {
+        let parent = match name_ref.syntax().parent() {
+            Some(it) => it,
+            None => return,
+        };
+        if parent.kind() == FIELD_EXPR || parent.kind() == METHOD_CALL_EXPR {
+            self.after_dot = true;
+            return;
+        }
+        let segment = match ast::PathSegment::cast(parent) {
+            Some(it) => it,
+            None => return,
+        };
+        let path = segment.parent_path();
+        self.after_colon_colon = path.qualifier().is_some()
+            || segment.syntax().children().any(|it| it.kind() == COLONCOLON);
+
+        let top_path = path.syntax().ancestors().take_while(|it| it.kind() == PATH).last().unwrap();
+        let kind = top_path.parent().map(SyntaxNode::kind);
+        match kind {
+            Some(PATH_TYPE) => self.in_type_position = true,
+            Some(PATH_PAT) | Some(TUPLE_STRUCT_PAT) | Some(STRUCT_PAT) => self.in_pattern = true,
+            // A path on its own at the item level is parsed as a macro call
+            // missing its `!`.
+            Some(MACRO_CALL) => {
+                let parent = top_path.parent().and_then(SyntaxNode::parent);
+                self.in_item_position = match parent.map(SyntaxNode::kind) {
+                    Some(SOURCE_FILE) | Some(ITEM_LIST) => true,
+                    _ => false,
+                };
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::extract_offset;
+
+    use super::*;
+
+    fn context(code: &str) -> SyntacticCompletionContext {
+        let (offset, code) = extract_offset(code);
+        let file = SourceFile::parse(&code);
+        SyntacticCompletionContext::new(&file, offset)
+    }
+
+    #[test]
+    fn classifies_completion_positions() {
+        assert!(context("fn f() { foo.<|> }").after_dot);
+        assert!(context("fn f() { foo::<|> }").after_colon_colon);
+        assert!(!context("fn f() { foo<|> }").after_colon_colon);
+
+        let use_tree = context("use foo::<|>;");
+        assert!(use_tree.in_use_tree && use_tree.after_colon_colon);
+
+        assert!(context("fn f() { match x { Some(<|>) => () } }").in_pattern);
+        assert!(context("fn f() { let <|> = 92; }").in_pattern);
+        assert!(context("fn f(x: <|>) {}").in_type_position);
+        assert!(context("struct S { f: Vec<<|>> }").in_type_position);
+        assert!(context("<|>").in_item_position);
+        assert!(context("impl S { <|> }").in_item_position);
+        assert!(!context("fn f() { <|> }").in_item_position);
+        assert!(context("#[<|>] fn f() {}").in_attr);
+
+        assert!(context(r#"fn f() { "foo<|>" }"#).in_string_or_comment);
+        assert!(context("fn f() { // foo<|>\n }").in_string_or_comment);
+        assert!(!context(r#"fn f() { "foo"<|> }"#).in_string_or_comment);
+    }
+}
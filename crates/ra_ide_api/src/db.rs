@@ -5,10 +5,10 @@ use std::{
 
 use ra_db::{
     CheckCanceled, FileId, Canceled, SourceDatabase,
-    salsa,
+    salsa, profile,
 };
 
-use crate::{LineIndex, symbol_index::{self, SymbolsDatabase}};
+use crate::{LineIndex, RetentionLimits, symbol_index::{self, SymbolsDatabase}};
 
 #[salsa::database(
     ra_db::SourceDatabaseStorage,
@@ -23,6 +23,7 @@ pub(crate) struct RootDatabase {
     interner: Arc<hir::HirInterner>,
     pub(crate) last_gc: time::Instant,
     pub(crate) last_gc_check: time::Instant,
+    pub(crate) retention_limits: RetentionLimits,
 }
 
 impl salsa::Database for RootDatabase {
@@ -32,6 +33,11 @@ impl salsa::Database for RootDatabase {
     fn on_propagated_panic(&self) -> ! {
         Canceled::throw()
     }
+    fn salsa_event(&self, event: impl Fn() -> salsa::Event<RootDatabase>) {
+        if profile::is_profiling() {
+            profile::record_event(&event());
+        }
+    }
 }
 
 impl Default for RootDatabase {
@@ -41,10 +47,12 @@ impl Default for RootDatabase {
             interner: Default::default(),
             last_gc: time::Instant::now(),
             last_gc_check: time::Instant::now(),
+            retention_limits: RetentionLimits::default(),
         };
         db.set_crate_graph(Default::default());
         db.set_local_roots(Default::default());
         db.set_library_roots(Default::default());
+        db.set_symbol_cache(Default::default());
         db
     }
 }
@@ -56,6 +64,7 @@ impl salsa::ParallelDatabase for RootDatabase {
             interner: Arc::clone(&self.interner),
             last_gc: self.last_gc.clone(),
             last_gc_check: self.last_gc_check.clone(),
+            retention_limits: self.retention_limits,
         })
     }
 }
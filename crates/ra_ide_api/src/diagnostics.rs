@@ -1,11 +1,27 @@
+use std::{cell::RefCell, iter};
+
 use itertools::Itertools;
-use hir::{Problem, source_binder};
+use hir::{
+    Problem, HirDisplay, Ty, VariantDef, VariantKind, source_binder,
+    diagnostics::{
+        Diagnostic as _, DiagnosticSink, NoSuchField, MissingFields, MissingMatchArms,
+        UnusedImport, PrivateItem,
+    },
+};
 use ra_ide_api_light::Severity;
-use ra_db::SourceDatabase;
+use ra_db::{SourceDatabase, Edition};
 use ra_syntax::{
-    Location, SourceFile, SyntaxKind, TextRange, SyntaxNode,
-    ast::{self, AstNode},
-
+    Location, SourceFile, SyntaxKind, TextRange, SyntaxNode, Direction, SmolStr,
+    SyntaxKind::{
+        COMMA, EXCL, FALSE_KW, FLOAT_NUMBER, IDENT, INT_NUMBER, MINUS, RAW_STRING, R_BRACK,
+        R_CURLY, R_PAREN, STRING, TOKEN_TREE, TRUE_KW, USE_ITEM, WHITESPACE, BYTE, BYTE_STRING,
+        RAW_BYTE_STRING, FN_DEF, BIND_PAT, STRUCT_DEF, ENUM_DEF, ENUM_VARIANT, TRAIT_DEF,
+        TYPE_ALIAS_DEF, EXTERN_ITEM_LIST, PATH_SEGMENT, NAMED_FIELD, PATH_EXPR, LET_STMT,
+        PARAM_LIST, MATCH_ARM, IF_EXPR, WHILE_EXPR, FOR_EXPR, LAMBDA_EXPR, DYN_TRAIT_TYPE,
+        IMPL_TRAIT_TYPE, TYPE_PARAM, WHERE_PRED, IMPL_BLOCK, COLON, PLUS, QUESTION, L_PAREN,
+        COMMENT, CONST_DEF, STATIC_DEF, SOURCE_FILE, ITEM_LIST, MODULE, BLOCK, FIELD_PAT_LIST,
+    },
+    ast::{self, AstNode, edit, make},
 };
 use ra_text_edit::{TextEdit, TextEditBuilder};
 
@@ -18,17 +34,41 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
     syntax_errors(&mut res, &source_file);
 
     for node in source_file.syntax().descendants() {
-        check_unnecessary_braces_in_use_statement(&mut res, file_id, node);
-        check_struct_shorthand_initialization(&mut res, file_id, node);
+        check_node(&mut res, file_id, node);
     }
+    check_file(&mut res, file_id, &source_file);
 
+    semantic_diagnostics(&mut res, db, file_id, &source_file);
+    res
+}
+
+/// Syntactic checks of a single node, which don't need name resolution.
+pub(crate) fn check_node(acc: &mut Vec<Diagnostic>, file_id: FileId, node: &SyntaxNode) {
+    check_unnecessary_braces_in_use_statement(acc, file_id, node);
+    check_struct_shorthand_initialization(acc, file_id, node);
+    check_builtin_macro_call(acc, file_id, node);
+    check_naming_conventions(acc, file_id, node);
+}
+
+/// Syntactic checks which need to look at the whole file.
+pub(crate) fn check_file(acc: &mut Vec<Diagnostic>, file_id: FileId, source_file: &SourceFile) {
+    check_misspelled_names(acc, file_id, source_file);
+}
+
+pub(crate) fn semantic_diagnostics(
+    acc: &mut Vec<Diagnostic>,
+    db: &RootDatabase,
+    file_id: FileId,
+    source_file: &SourceFile,
+) {
     if let Some(m) = source_binder::module_from_file_id(db, file_id) {
-        check_module(&mut res, db, file_id, m);
+        check_module(acc, db, file_id, m);
+        check_hir_diagnostics(acc, db, file_id, m);
+        check_bare_trait_objects(acc, db, file_id, source_file, m);
     };
-    res
 }
 
-fn syntax_errors(acc: &mut Vec<Diagnostic>, source_file: &SourceFile) {
+pub(crate) fn syntax_errors(acc: &mut Vec<Diagnostic>, source_file: &SourceFile) {
     fn location_to_range(location: Location) -> TextRange {
         match location {
             Location::Offset(offset) => TextRange::offset_len(offset, 1.into()),
@@ -71,6 +111,7 @@ fn check_unnecessary_braces_in_use_statement(
                 source_file_edits: vec![SourceFileEdit { file_id, edit }],
                 file_system_edits: Vec::new(),
                 cursor_position: None,
+                typing: None,
             }),
         });
     }
@@ -119,6 +160,7 @@ fn check_struct_shorthand_initialization(
                         source_file_edits: vec![SourceFileEdit { file_id, edit }],
                         file_system_edits: Vec::new(),
                         cursor_position: None,
+                        typing: None,
                     }),
                 });
             }
@@ -127,6 +169,509 @@ fn check_struct_shorthand_initialization(
     Some(())
 }
 
+/// Checks the shape of the arguments of well-known built-in macros, like
+/// `env!("VAR")` or `include_str!("path")`. The macros are not expanded, so
+/// arguments which are themselves macro calls are accepted as is.
+fn check_builtin_macro_call(
+    acc: &mut Vec<Diagnostic>,
+    _file_id: FileId,
+    node: &SyntaxNode,
+) -> Option<()> {
+    let macro_call = ast::MacroCall::cast(node)?;
+    let path = macro_call.path()?;
+    if let Some(qualifier) = path.qualifier() {
+        // Other paths might refer to user-defined macros with the same name.
+        let krate = qualifier.segment()?.name_ref()?.text();
+        if qualifier.qualifier().is_some() || (krate.as_str() != "std" && krate.as_str() != "core")
+        {
+            return None;
+        }
+    }
+    let name = path.segment()?.name_ref()?.text().clone();
+    let token_tree = macro_call.token_tree()?;
+    let args = macro_args(token_tree);
+    let args_range = token_tree.syntax().range();
+
+    let mut errors = Vec::new();
+    match name.as_str() {
+        "env" | "option_env" | "include" | "include_str" | "include_bytes" => {
+            let max_args = if name == "env" { 2 } else { 1 };
+            if args.is_empty() || args.len() > max_args {
+                let expected = if max_args == 2 { "1 or 2 arguments" } else { "1 argument" };
+                let range = match args.get(max_args) {
+                    Some(extra) => TextRange::from_to(
+                        args_range_of(extra, args_range).start(),
+                        args_range_of(args.last().unwrap(), args_range).end(),
+                    ),
+                    None => args_range,
+                };
+                errors.push((range, format!("`{}!` takes {}", name, expected)));
+            }
+            for arg in args.iter().take(max_args) {
+                if !is_string_literal(arg) && !is_macro_call(arg) {
+                    errors.push((args_range_of(arg, args_range), "expected string literal".into()));
+                }
+            }
+        }
+        "concat" => {
+            for arg in args.iter() {
+                if !is_concat_literal(arg) && !is_macro_call(arg) {
+                    errors.push((args_range_of(arg, args_range), "expected a literal".into()));
+                }
+            }
+        }
+        "format_args" | "format" | "print" | "println" | "eprint" | "eprintln" | "write"
+        | "writeln" => {
+            // The first argument of `write!` is the destination.
+            let format_pos = if name.starts_with("write") { 1 } else { 0 };
+            match args.get(format_pos) {
+                Some(arg) => {
+                    if !is_string_literal(arg) && !is_macro_call(arg) {
+                        let message = "format argument must be a string literal".to_string();
+                        errors.push((args_range_of(arg, args_range), message));
+                    }
+                }
+                None if !name.ends_with("ln") => {
+                    let message = format!("`{}!` requires at least a format string argument", name);
+                    errors.push((args_range, message));
+                }
+                None => (),
+            }
+        }
+        _ => return None,
+    }
+
+    acc.extend(errors.into_iter().map(|(range, message)| Diagnostic {
+        range,
+        message,
+        severity: Severity::Error,
+        fix: None,
+    }));
+    Some(())
+}
+
+/// Splits the token tree of a macro call into comma-separated arguments,
+/// ignoring trivia and a trailing comma.
+fn macro_args(token_tree: &ast::TokenTree) -> Vec<Vec<&SyntaxNode>> {
+    let mut tokens: Vec<&SyntaxNode> =
+        token_tree.syntax().children().skip(1).filter(|it| !it.kind().is_trivia()).collect();
+    if tokens.last().map_or(false, |it| [R_PAREN, R_BRACK, R_CURLY].contains(&it.kind())) {
+        tokens.pop();
+    }
+    let mut args: Vec<Vec<&SyntaxNode>> =
+        tokens.split(|it| it.kind() == COMMA).map(|arg| arg.to_vec()).collect();
+    if args.last().map_or(false, |it| it.is_empty()) {
+        args.pop();
+    }
+    args
+}
+
+/// The range of an argument, or `default` for an empty argument like in
+/// `env!(, "foo")`.
+fn args_range_of(arg: &[&SyntaxNode], default: TextRange) -> TextRange {
+    match (arg.first(), arg.last()) {
+        (Some(first), Some(last)) => TextRange::from_to(first.range().start(), last.range().end()),
+        _ => default,
+    }
+}
+
+fn is_string_literal(arg: &[&SyntaxNode]) -> bool {
+    match arg {
+        [token] => token.kind() == STRING || token.kind() == RAW_STRING,
+        _ => false,
+    }
+}
+
+fn is_concat_literal(arg: &[&SyntaxNode]) -> bool {
+    match arg {
+        [token] => match token.kind() {
+            BYTE | BYTE_STRING | RAW_BYTE_STRING => false,
+            TRUE_KW | FALSE_KW => true,
+            kind => kind.is_literal(),
+        },
+        [minus, number] => {
+            minus.kind() == MINUS && [INT_NUMBER, FLOAT_NUMBER].contains(&number.kind())
+        }
+        _ => false,
+    }
+}
+
+/// Whether the argument looks like `foo!(...)` or `path::to::foo!(...)`.
+fn is_macro_call(arg: &[&SyntaxNode]) -> bool {
+    let n = arg.len();
+    n >= 3
+        && arg[n - 3].kind() == IDENT
+        && arg[n - 2].kind() == EXCL
+        && arg[n - 1].kind() == TOKEN_TREE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NamingConvention {
+    SnakeCase,
+    CamelCase,
+}
+
+/// Flags functions and variables whose names are not in snake_case and types
+/// whose names are not in CamelCase. This is purely syntactic, so the fix
+/// renames the references in the same file only, finding them by text.
+fn check_naming_conventions(
+    acc: &mut Vec<Diagnostic>,
+    file_id: FileId,
+    node: &SyntaxNode,
+) -> Option<()> {
+    let (convention, lint) = match node.kind() {
+        FN_DEF | BIND_PAT => (NamingConvention::SnakeCase, "non_snake_case"),
+        STRUCT_DEF | ENUM_DEF | ENUM_VARIANT | TRAIT_DEF | TYPE_ALIAS_DEF => {
+            (NamingConvention::CamelCase, "non_camel_case_types")
+        }
+        _ => return None,
+    };
+    let name = node.children().find_map(ast::Name::cast)?;
+    let text = name.text().as_str();
+    // Foreign functions follow the conventions of the foreign code, and
+    // capitalized bindings are most likely constants or unit variants.
+    if node.ancestors().any(|it| it.kind() == EXTERN_ITEM_LIST)
+        || (node.kind() == BIND_PAT && text.starts_with(|c: char| c.is_uppercase()))
+    {
+        return None;
+    }
+    let new_name = match convention {
+        NamingConvention::SnakeCase => to_snake_case(text),
+        NamingConvention::CamelCase => to_camel_case(text),
+    };
+    if new_name == text || new_name.is_empty() || is_lint_allowed(node, lint) {
+        return None;
+    }
+
+    let (kind, convention_name) = match (node.kind(), convention) {
+        (FN_DEF, _) => ("Function", "snake_case"),
+        (BIND_PAT, _) => ("Variable", "snake_case"),
+        (_, _) => ("Type", "CamelCase"),
+    };
+    let mut edit = TextEditBuilder::default();
+    // A binding in a struct pattern shorthand, like `S { x }`, keeps the
+    // field name.
+    if node.parent().map(|it| it.kind()) == Some(FIELD_PAT_LIST) {
+        edit.replace(name.syntax().range(), format!("{}: {}", text, new_name));
+    } else {
+        edit.replace(name.syntax().range(), new_name.clone());
+    }
+    for name_ref in references_in_file(node, text) {
+        let range = name_ref.syntax().range();
+        // Field shorthands, like `S { x }`, keep the field name.
+        if name_ref.syntax().parent().map(|it| it.kind()) == Some(NAMED_FIELD) {
+            edit.replace(range, format!("{}: {}", text, new_name))
+        } else {
+            edit.replace(range, new_name.clone())
+        }
+    }
+    acc.push(Diagnostic {
+        range: name.syntax().range(),
+        message: format!("{} `{}` should have a {} name", kind, text, convention_name),
+        severity: Severity::WeakWarning,
+        fix: Some(SourceChange {
+            label: format!("Rename to `{}`", new_name),
+            source_file_edits: vec![SourceFileEdit { file_id, edit: edit.finish() }],
+            file_system_edits: Vec::new(),
+            cursor_position: None,
+            typing: None,
+        }),
+    });
+    Some(())
+}
+
+/// Whether `node` or any of its ancestors has an `#[allow(lint)]` attribute.
+fn is_lint_allowed(node: &SyntaxNode, lint: &str) -> bool {
+    let allows_lint = |attr: &ast::Attr| match attr.as_call() {
+        Some((name, args)) if name.as_str() == "allow" => {
+            args.syntax().children().any(|it| it.leaf_text().map(|it| it.as_str()) == Some(lint))
+        }
+        _ => false,
+    };
+    node.ancestors().flat_map(|it| it.children().filter_map(ast::Attr::cast)).any(allows_lint)
+}
+
+/// Finds the name references which probably refer to the definition `def`
+/// named `name`. For variables, these are the plain paths in the scope of
+/// the binding, up to the first shadowing `let`. For items, these are the
+/// plain paths in the scope the item is declared in.
+fn references_in_file<'a>(def: &'a SyntaxNode, name: &str) -> Vec<&'a ast::NameRef> {
+    let is_path_to_name = |name_ref: &ast::NameRef| {
+        if name_ref.text().as_str() != name {
+            return false;
+        }
+        let parent = match name_ref.syntax().parent() {
+            Some(it) => it,
+            None => return false,
+        };
+        match parent.kind() {
+            PATH_SEGMENT => true,
+            // A field shorthand, like `S { x }`.
+            NAMED_FIELD => {
+                def.kind() == BIND_PAT
+                    && ast::NamedField::cast(parent).map_or(false, |it| it.expr().is_none())
+            }
+            _ => false,
+        }
+    };
+    let name_refs_in = |node: &'a SyntaxNode| {
+        node.descendants().filter_map(ast::NameRef::cast).filter(|it| is_path_to_name(*it))
+    };
+
+    if def.kind() != BIND_PAT {
+        let mut res = Vec::new();
+        // Methods and associated items are only referred to by qualified
+        // paths or method calls, which can't be told apart without types.
+        let scope = def.parent().filter(|it| match it.kind() {
+            SOURCE_FILE | BLOCK => true,
+            ITEM_LIST => it.parent().map(|it| it.kind()) == Some(MODULE),
+            _ => false,
+        });
+        if let Some(scope) = scope {
+            item_references(scope, def, name, &mut res);
+        }
+        return res;
+    }
+    let is_plain_path = |name_ref: &&ast::NameRef| {
+        match name_ref.syntax().ancestors().find_map(ast::Path::cast) {
+            Some(path) => {
+                path.qualifier().is_none()
+                    && path.syntax().parent().map(|it| it.kind()) == Some(PATH_EXPR)
+            }
+            // A field shorthand.
+            None => true,
+        }
+    };
+    let scope = def.ancestors().find(|it| match it.kind() {
+        LET_STMT | PARAM_LIST | MATCH_ARM | IF_EXPR | WHILE_EXPR | FOR_EXPR | LAMBDA_EXPR => true,
+        _ => false,
+    });
+    let scope = match scope {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    match scope.kind() {
+        LET_STMT => {
+            let mut res = Vec::new();
+            for sibling in scope.siblings(Direction::Next).skip(1) {
+                res.extend(name_refs_in(sibling).filter(is_plain_path));
+                let shadows =
+                    ast::LetStmt::cast(sibling).and_then(|it| it.pat()).map_or(false, |pat| {
+                        pat.syntax()
+                            .descendants()
+                            .filter_map(ast::BindPat::cast)
+                            .any(|it| it.name().map(|it| it.text().as_str() == name) == Some(true))
+                    });
+                if shadows {
+                    break;
+                }
+            }
+            res
+        }
+        // Parameters are visible in the whole function.
+        PARAM_LIST => name_refs_in(scope.parent().unwrap()).filter(is_plain_path).collect(),
+        _ => name_refs_in(scope).filter(is_plain_path).collect(),
+    }
+}
+
+/// Collects the unqualified paths named `name` in `scope`. Nested modules have
+/// scopes of their own, and functions which define another `name` might
+/// refer to it instead, so both are skipped.
+fn item_references<'a>(
+    scope: &'a SyntaxNode,
+    def: &SyntaxNode,
+    name: &str,
+    acc: &mut Vec<&'a ast::NameRef>,
+) {
+    for child in scope.children() {
+        if child.kind() == MODULE {
+            continue;
+        }
+        if child.kind() == FN_DEF {
+            let redefines = child
+                .descendants()
+                .filter_map(ast::Name::cast)
+                .any(|it| it.text().as_str() == name && it.syntax().parent() != Some(def));
+            if redefines {
+                continue;
+            }
+        }
+        let name_ref = ast::NameRef::cast(child).filter(|it| it.text().as_str() == name);
+        if let Some(name_ref) = name_ref {
+            let is_plain_path = child
+                .parent()
+                .and_then(ast::PathSegment::cast)
+                .and_then(|it| it.syntax().parent())
+                .and_then(ast::Path::cast)
+                .map_or(false, |it| it.qualifier().is_none());
+            if is_plain_path {
+                acc.push(name_ref);
+            }
+        }
+        item_references(child, def, name, acc);
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut res = String::new();
+    let mut prev: Option<char> = None;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_uppercase() {
+            // Insert an underscore at word boundaries, like in `fooBar` and
+            // in `HTTPServer`, but not inside acronyms.
+            let next_is_lower = chars.peek().map_or(false, |it| it.is_lowercase());
+            let is_boundary = match prev {
+                Some(p) => {
+                    p.is_lowercase() || p.is_numeric() || (p.is_uppercase() && next_is_lower)
+                }
+                None => false,
+            };
+            if is_boundary {
+                res.push('_');
+            }
+            res.extend(c.to_lowercase());
+        } else {
+            res.push(c);
+        }
+        prev = Some(c);
+    }
+    res
+}
+
+fn to_camel_case(name: &str) -> String {
+    // Leading and trailing underscores mark unused items, keep them.
+    let trimmed = name.trim_matches('_');
+    let prefix = &name[..name.len() - name.trim_start_matches('_').len()];
+    let suffix = &name[name.trim_end_matches('_').len()..];
+    let mut res = String::from(prefix);
+    for word in trimmed.split('_').filter(|it| !it.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            res.extend(first.to_uppercase());
+            res.push_str(chars.as_str());
+        }
+    }
+    if trimmed.is_empty() {
+        return name.to_string();
+    }
+    res.push_str(suffix);
+    res
+}
+
+/// Suggests a similar name for a plain path which doesn't refer to any of
+/// the earlier local variables or to the functions, constants, statics and
+/// imports of the file. This is a purely syntactic guess, so it gives up on
+/// files where names may come from elsewhere: from glob imports or macros.
+fn check_misspelled_names(
+    acc: &mut Vec<Diagnostic>,
+    file_id: FileId,
+    source_file: &SourceFile,
+) -> Option<()> {
+    let mut items: Vec<&SmolStr> = Vec::new();
+    for node in source_file.syntax().descendants() {
+        if let Some(use_tree) = ast::UseTree::cast(node) {
+            if use_tree.has_star() {
+                return None;
+            }
+            let name = match use_tree.alias() {
+                Some(alias) => alias.name().map(|it| it.text()),
+                None => use_tree.path().and_then(|it| it.segment()?.name_ref()).map(|it| it.text()),
+            };
+            items.extend(name);
+        } else if let Some(macro_call) = ast::MacroCall::cast(node) {
+            let is_item = node.parent().map_or(false, |it| match it.kind() {
+                SOURCE_FILE | ITEM_LIST => true,
+                _ => false,
+            });
+            let is_macro_rules = macro_call
+                .path()
+                .and_then(|it| it.segment()?.name_ref())
+                .map_or(false, |it| it.text().as_str() == "macro_rules");
+            if is_item && !is_macro_rules {
+                return None;
+            }
+        } else if node.kind() == FN_DEF || node.kind() == CONST_DEF || node.kind() == STATIC_DEF {
+            items.extend(node.children().find_map(ast::Name::cast).map(|it| it.text()));
+        }
+    }
+
+    for path_expr in source_file.syntax().descendants().filter_map(ast::PathExpr::cast) {
+        let path = match path_expr.path() {
+            Some(it) if it.qualifier().is_none() => it,
+            _ => continue,
+        };
+        let name_ref = match path.segment().and_then(|it| it.name_ref()) {
+            Some(it) => it,
+            None => continue,
+        };
+        let text = name_ref.text();
+        // Capitalized values are usually variants or constants from other
+        // modules, and short names are too easy to confuse.
+        let n_chars = text.chars().count();
+        if n_chars < 3 || !text.chars().all(|c| c.is_lowercase() || c.is_numeric() || c == '_') {
+            continue;
+        }
+        let fn_def = match path_expr.syntax().ancestors().find(|it| it.kind() == FN_DEF) {
+            Some(it) => it,
+            None => continue,
+        };
+        let offset = path_expr.syntax().range().start();
+        let locals = fn_def
+            .descendants()
+            .filter_map(ast::BindPat::cast)
+            .filter(|it| it.syntax().range().end() <= offset)
+            .filter_map(|it| it.name())
+            .map(|it| it.text());
+        let names = locals.chain(items.iter().cloned()).unique().collect::<Vec<_>>();
+        if names.contains(&text) {
+            continue;
+        }
+        let mut suggestions = names
+            .into_iter()
+            .map(|name| (edit_distance(text, name), name))
+            .filter(|&(distance, _)| distance <= n_chars / 3)
+            .collect::<Vec<_>>();
+        suggestions.sort_by_key(|&(distance, _)| distance);
+        let suggestion = match (suggestions.get(0), suggestions.get(1)) {
+            (Some((d1, _)), Some((d2, _))) if d1 == d2 => continue,
+            (Some((_, name)), _) => name.to_string(),
+            (None, _) => continue,
+        };
+
+        let mut edit = TextEditBuilder::default();
+        edit.replace(name_ref.syntax().range(), suggestion.clone());
+        acc.push(Diagnostic {
+            range: name_ref.syntax().range(),
+            message: format!("did you mean `{}`?", suggestion),
+            severity: Severity::WeakWarning,
+            fix: Some(SourceChange {
+                label: format!("Rename to `{}`", suggestion),
+                source_file_edits: vec![SourceFileEdit { file_id, edit: edit.finish() }],
+                file_system_edits: Vec::new(),
+                cursor_position: None,
+                typing: None,
+            }),
+        });
+    }
+    Some(())
+}
+
+/// The Levenshtein distance between `lhs` and `rhs`, in chars.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs = rhs.chars().collect::<Vec<_>>();
+    let mut prev = (0..=rhs.len()).collect::<Vec<_>>();
+    for (i, l) in lhs.chars().enumerate() {
+        let mut curr = vec![i + 1];
+        for (j, &r) in rhs.iter().enumerate() {
+            let substitution = if l == r { prev[j] } else { prev[j] + 1 };
+            curr.push(substitution.min(prev[j + 1] + 1).min(curr[j] + 1));
+        }
+        prev = curr;
+    }
+    prev[rhs.len()]
+}
+
 fn check_module(
     acc: &mut Vec<Diagnostic>,
     db: &RootDatabase,
@@ -136,6 +681,23 @@ fn check_module(
     let source_root = db.file_source_root(file_id);
     for (name_node, problem) in module.problems(db) {
         let diag = match problem {
+            Problem::DuplicateDefinition { name, .. } => {
+                let range = name_node
+                    .children()
+                    .find(|it| it.kind() == SyntaxKind::NAME)
+                    .unwrap_or(&*name_node)
+                    .range();
+                // The same item might clash both in types and values
+                if acc.iter().any(|it| it.range == range) {
+                    continue;
+                }
+                Diagnostic {
+                    range,
+                    message: format!("the name `{}` is defined multiple times", name),
+                    severity: Severity::Error,
+                    fix: None,
+                }
+            }
             Problem::UnresolvedModule { candidate } => {
                 let create_file =
                     FileSystemEdit::CreateFile { source_root, path: candidate.clone() };
@@ -144,6 +706,7 @@ fn check_module(
                     source_file_edits: Vec::new(),
                     file_system_edits: vec![create_file],
                     cursor_position: None,
+                    typing: None,
                 };
                 Diagnostic {
                     range: name_node.range(),
@@ -152,15 +715,511 @@ fn check_module(
                     fix: Some(fix),
                 }
             }
+            // Reported with the rest of HIR diagnostics.
+            Problem::MacroError { .. } => continue,
         };
         acc.push(diag)
     }
 }
 
+/// Reports trait objects without `dyn`, like `Box<Write>`. They are deprecated
+/// only since the 2018 edition, so 2015 crates are not checked.
+fn check_bare_trait_objects(
+    acc: &mut Vec<Diagnostic>,
+    db: &RootDatabase,
+    file_id: FileId,
+    source_file: &SourceFile,
+    module: hir::Module,
+) {
+    match module.krate(db) {
+        Some(krate) if krate.edition(db) == Edition::Edition2018 => (),
+        _ => return,
+    }
+    for path_type in source_file.syntax().descendants().filter_map(ast::PathType::cast) {
+        if !is_bare_trait_object(db, file_id, path_type) {
+            continue;
+        }
+        let range = path_type.syntax().range();
+        let mut edit_builder = TextEditBuilder::default();
+        edit_builder.insert(range.start(), "dyn ".to_string());
+        acc.push(Diagnostic {
+            range,
+            message: "trait objects without an explicit `dyn` are deprecated".to_string(),
+            severity: Severity::WeakWarning,
+            fix: Some(SourceChange {
+                label: "Insert `dyn`".to_string(),
+                source_file_edits: vec![SourceFileEdit { file_id, edit: edit_builder.finish() }],
+                file_system_edits: Vec::new(),
+                cursor_position: None,
+                typing: None,
+            }),
+        })
+    }
+}
+
+fn is_bare_trait_object(db: &RootDatabase, file_id: FileId, path_type: &ast::PathType) -> bool {
+    // Bounds and the trait of an impl name the trait itself, not a trait
+    // object.
+    let parent = match path_type.syntax().parent() {
+        Some(it) => it,
+        None => return false,
+    };
+    match parent.kind() {
+        DYN_TRAIT_TYPE | IMPL_TRAIT_TYPE => return false,
+        TYPE_PARAM | WHERE_PRED | TRAIT_DEF | TYPE_ALIAS_DEF => {
+            let prev = path_type
+                .syntax()
+                .siblings(Direction::Prev)
+                .skip(1)
+                .find(|it| it.kind() != WHITESPACE && it.kind() != COMMENT);
+            match prev.map(|it| it.kind()) {
+                Some(COLON) | Some(PLUS) | Some(QUESTION) | Some(L_PAREN) => return false,
+                _ => (),
+            }
+        }
+        IMPL_BLOCK => {
+            let impl_block = ast::ImplBlock::cast(parent).unwrap();
+            if impl_block.target_trait().map(|it| it.syntax()) == Some(path_type.syntax()) {
+                return false;
+            }
+        }
+        _ => (),
+    }
+    let path = match path_type.path().and_then(hir::Path::from_ast) {
+        Some(it) => it,
+        None => return false,
+    };
+    let resolver = source_binder::resolver_for_node(db, file_id, path_type.syntax());
+    match resolver.resolve_path(db, &path).take_types() {
+        Some(hir::Resolution::Def(hir::ModuleDef::Trait(_))) => true,
+        _ => false,
+    }
+}
+
+/// Reports the problems HIR finds in the module: macros which fail to expand,
+/// unused imports and problems in the bodies of the module's functions.
+fn check_hir_diagnostics(
+    acc: &mut Vec<Diagnostic>,
+    db: &RootDatabase,
+    file_id: FileId,
+    module: hir::Module,
+) {
+    // Diagnostics inside of macro expansions can't be mapped back to the file.
+    let hir_file_id: hir::HirFileId = file_id.into();
+    let source_file = db.parse(file_id);
+    let errors = acc
+        .iter()
+        .filter_map(|it| match it.severity {
+            Severity::Error => Some(it.range),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let res = RefCell::new(Vec::new());
+    let mut sink = DiagnosticSink::new(|d| {
+        if d.file() == hir_file_id {
+            res.borrow_mut().push(Diagnostic {
+                range: d.highlight_range(),
+                message: d.message(),
+                severity: Severity::Error,
+                fix: None,
+            })
+        }
+    })
+    .on::<NoSuchField, _>(|d| {
+        if d.file == hir_file_id {
+            res.borrow_mut().push(Diagnostic {
+                range: d.highlight_range(),
+                message: d.message(),
+                severity: Severity::Error,
+                fix: add_field_fix(db, file_id, &source_file, d),
+            })
+        }
+    })
+    .on::<MissingFields, _>(|d| {
+        if d.file == hir_file_id {
+            res.borrow_mut().push(Diagnostic {
+                range: d.highlight_range(),
+                message: d.message(),
+                severity: Severity::Error,
+                fix: fill_fields_fix(file_id, &source_file, d),
+            })
+        }
+    })
+    .on::<UnusedImport, _>(|d| {
+        if d.file != hir_file_id {
+            return;
+        }
+        let use_tree = d.use_tree.to_node(&source_file);
+        let range = use_tree.syntax().range();
+        // Don't pile up on top of errors, like duplicate definitions.
+        if errors.iter().any(|it| it.is_subrange(&range) || range.is_subrange(it)) {
+            return;
+        }
+        let mut edit = TextEditBuilder::default();
+        edit.delete(range_to_remove_use_tree(use_tree));
+        res.borrow_mut().push(Diagnostic {
+            range,
+            message: d.message(),
+            severity: Severity::WeakWarning,
+            fix: Some(SourceChange {
+                label: "Remove unused import".to_string(),
+                source_file_edits: vec![SourceFileEdit { file_id, edit: edit.finish() }],
+                file_system_edits: Vec::new(),
+                cursor_position: None,
+                typing: None,
+            }),
+        })
+    })
+    .on::<PrivateItem, _>(|d| {
+        if d.file == hir_file_id {
+            res.borrow_mut().push(Diagnostic {
+                range: d.highlight_range(),
+                message: d.message(),
+                severity: Severity::Error,
+                fix: make_visible_fix(db, d),
+            })
+        }
+    })
+    .on::<MissingMatchArms, _>(|d| {
+        if d.file == hir_file_id {
+            res.borrow_mut().push(Diagnostic {
+                range: d.highlight_range(),
+                message: d.message(),
+                severity: Severity::WeakWarning,
+                fix: add_match_arms_fix(db, file_id, &source_file, d),
+            })
+        }
+    });
+    module.diagnostics(db, &mut sink);
+    drop(sink);
+    acc.extend(res.into_inner());
+}
+
+/// Appends the field a struct literal mentions to the definition of the
+/// struct, typed with the type of the value if it is known. The field is
+/// `pub(crate)` if the literal is outside of the struct's module.
+fn add_field_fix(
+    db: &RootDatabase,
+    file_id: FileId,
+    source_file: &SourceFile,
+    d: &NoSuchField,
+) -> Option<SourceChange> {
+    let field = d.field.to_node(source_file);
+    let name = field.name_ref()?.text().clone();
+    let literal_module = source_binder::module_from_child_node(db, file_id, field.syntax())?;
+    let ty = match &d.ty {
+        Ty::Unknown => "()".to_string(),
+        ty => {
+            let text = ty.display(db).to_string();
+            // Partially known types, like `{integer}` or `Vec<{unknown}>`,
+            // and references, which would need a lifetime.
+            if text.contains('{') || text.contains('&') {
+                "()".to_string()
+            } else {
+                text
+            }
+        }
+    };
+    let field = format!("{}: {}", name, ty);
+
+    let (def_file_id, edit) = match d.variant {
+        VariantDef::Struct(s) => {
+            let (file_id, def) = s.source(db);
+            let field = if s.module(db) == literal_module {
+                field
+            } else {
+                format!("pub(crate) {}", field)
+            };
+            (file_id, add_field_edit(def.flavor(), &field)?)
+        }
+        // The fields of enum variants are as visible as the enum.
+        VariantDef::EnumVariant(v) => {
+            let (file_id, def) = v.source(db);
+            (file_id, add_field_edit(def.flavor(), &field)?)
+        }
+    };
+    Some(SourceChange {
+        label: format!("add `{}` field", name),
+        source_file_edits: vec![SourceFileEdit { file_id: def_file_id.original_file(db), edit }],
+        file_system_edits: Vec::new(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+/// Makes a private item `pub(crate)`, which is enough for any path in the same
+/// crate.
+fn make_visible_fix(db: &RootDatabase, d: &PrivateItem) -> Option<SourceChange> {
+    let (file_id, edit) = match d.item? {
+        hir::ModuleDef::Module(it) => {
+            let (file_id, node) = it.declaration_source(db)?;
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::Function(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::Struct(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::Enum(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::Const(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::Static(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::Trait(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        hir::ModuleDef::TypeAlias(it) => {
+            let (file_id, node) = it.source(db);
+            (file_id, make_visible_edit(&*node))
+        }
+        // Variants are as visible as their enum.
+        hir::ModuleDef::EnumVariant(_) => return None,
+    };
+    Some(SourceChange {
+        label: format!("make `{}` pub(crate)", d.name),
+        source_file_edits: vec![SourceFileEdit { file_id: file_id.original_file(db), edit }],
+        file_system_edits: Vec::new(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+fn make_visible_edit(item: &impl ast::VisibilityOwner) -> TextEdit {
+    let mut edit = TextEditBuilder::default();
+    match item.visibility() {
+        Some(vis) => edit.replace(vis.syntax().range(), "pub(crate)".to_string()),
+        None => {
+            // Doc comments and attributes stay in front.
+            let start = item
+                .syntax()
+                .children()
+                .find(|it| !it.kind().is_trivia() && it.kind() != SyntaxKind::ATTR)
+                .map_or(item.syntax().range().start(), |it| it.range().start());
+            edit.insert(start, "pub(crate) ".to_string());
+        }
+    }
+    edit.finish()
+}
+
+/// Initializes each of the missing fields with a `()` placeholder.
+fn fill_fields_fix(
+    file_id: FileId,
+    source_file: &SourceFile,
+    d: &MissingFields,
+) -> Option<SourceChange> {
+    let field_list = d.field_list.to_node(source_file);
+    let unit = make::expr_unit();
+    let mut new_field_list = field_list.to_owned();
+    for name in d.missed_fields.iter() {
+        let field = make::named_field(&name.to_string(), &unit);
+        new_field_list = edit::add_named_field(&new_field_list, &field);
+    }
+    let mut edit = TextEditBuilder::default();
+    edit.replace(field_list.syntax().range(), new_field_list.syntax().text().to_string());
+    Some(SourceChange {
+        label: "fill struct fields".to_string(),
+        source_file_edits: vec![SourceFileEdit { file_id, edit: edit.finish() }],
+        file_system_edits: Vec::new(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+/// Adds an arm doing nothing for each missing variant, as in
+/// `Enum::Variant(..) => (),`.
+fn add_match_arms_fix(
+    db: &RootDatabase,
+    file_id: FileId,
+    source_file: &SourceFile,
+    d: &MissingMatchArms,
+) -> Option<SourceChange> {
+    let match_expr = ast::MatchExpr::cast(d.match_expr.to_node(source_file))?;
+    let arm_list = match_expr.match_arm_list()?;
+    let prefix = match arm_variant_prefix(arm_list) {
+        Some(it) => it,
+        None => visible_enum_prefix(db, file_id, match_expr.syntax(), d.enum_)?,
+    };
+    let unit = make::expr_unit();
+    let mut new_arm_list = arm_list.to_owned();
+    for variant in d.missing.iter() {
+        let pat = match variant.kind(db) {
+            VariantKind::Named => format!("{}{} {{ .. }}", prefix, variant.name(db)?),
+            VariantKind::Tuple => format!("{}{}(..)", prefix, variant.name(db)?),
+            VariantKind::Unit => format!("{}{}", prefix, variant.name(db)?),
+        };
+        let arm = make::match_arm(iter::once(&*make::pat_from_text(&pat)), &unit);
+        new_arm_list = edit::add_match_arm(&new_arm_list, &arm);
+    }
+    let mut edit = TextEditBuilder::default();
+    edit.replace(arm_list.syntax().range(), new_arm_list.syntax().text().to_string());
+    Some(SourceChange {
+        label: "add missing match arms".to_string(),
+        source_file_edits: vec![SourceFileEdit { file_id, edit: edit.finish() }],
+        file_system_edits: Vec::new(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+/// The prefix of the variants in the existing arms, like `foo::E::` for
+/// `foo::E::A => ()`, or nothing if the variants are imported.
+fn arm_variant_prefix(arm_list: &ast::MatchArmList) -> Option<String> {
+    arm_list.arms().flat_map(|arm| arm.pats()).find_map(|pat| {
+        let path = match pat.kind() {
+            ast::PatKind::PathPat(it) => it.path(),
+            ast::PatKind::TupleStructPat(it) => it.path(),
+            ast::PatKind::StructPat(it) => it.path(),
+            _ => None,
+        }?;
+        let prefix = match path.qualifier() {
+            Some(qualifier) => format!("{}::", qualifier.syntax().text()),
+            None => String::new(),
+        };
+        Some(prefix)
+    })
+}
+
+/// The prefix of the variants of `enum_` in the module of `node`: just the
+/// name of the enum if it is in scope, and the absolute path otherwise.
+fn visible_enum_prefix(
+    db: &RootDatabase,
+    file_id: FileId,
+    node: &SyntaxNode,
+    enum_: hir::Enum,
+) -> Option<String> {
+    let name = enum_.name(db)?;
+    let resolver = source_binder::resolver_for_node(db, file_id, node);
+    if let Some(hir::Resolution::Def(hir::ModuleDef::Enum(it))) =
+        resolver.resolve_name(db, &name).take_types()
+    {
+        if it == enum_ {
+            return Some(format!("{}::", name));
+        }
+    }
+    let module = source_binder::module_from_child_node(db, file_id, node)?;
+    let enum_module = enum_.module(db);
+    let krate = enum_module.krate(db)?;
+    let mut segments = if module.krate(db) == Some(krate) {
+        vec!["crate".to_string()]
+    } else {
+        let deps = module.krate(db)?.dependencies(db);
+        let dep = deps.into_iter().find(|it| it.krate == krate)?;
+        vec![format!("::{}", dep.name)]
+    };
+    // The path to the root starts with the module itself and ends with the
+    // nameless crate root.
+    for module in enum_module.path_to_root(db).iter().rev().skip(1) {
+        segments.push(module.name(db)?.to_string());
+    }
+    segments.push(name.to_string());
+    Some(format!("{}::", segments.join("::")))
+}
+
+fn add_field_edit(flavor: ast::StructFlavor, field: &str) -> Option<TextEdit> {
+    let field_list = match flavor {
+        ast::StructFlavor::Named(it) => it,
+        _ => return None,
+    };
+    let mut edit = TextEditBuilder::default();
+    let last_field = match field_list.fields().last() {
+        Some(it) => it.syntax(),
+        None => {
+            edit.replace(field_list.syntax().range(), format!("{{ {} }}", field));
+            return Some(edit.finish());
+        }
+    };
+    let comma = last_field
+        .siblings(Direction::Next)
+        .skip(1)
+        .find(|it| it.kind() != WHITESPACE)
+        .filter(|it| it.kind() == COMMA);
+    let anchor = comma.unwrap_or(last_field);
+    let separator = if comma.is_some() { "" } else { "," };
+    let is_multiline = anchor
+        .next_sibling()
+        .filter(|it| it.kind() == WHITESPACE)
+        .map_or(false, |it| it.leaf_text().map_or(false, |text| text.contains('\n')));
+    let text = if is_multiline {
+        let indent = match last_field.prev_sibling().and_then(|it| it.leaf_text()) {
+            Some(ws) => ws.rsplit('\n').next().unwrap_or("").to_string(),
+            None => String::new(),
+        };
+        format!("{}\n{}{},", separator, indent, field)
+    } else {
+        format!("{} {}", separator, field)
+    };
+    edit.insert(anchor.range().end(), text);
+    Some(edit.finish())
+}
+
+/// Computes the range to delete to remove a single use-tree leaf, taking the
+/// separating comma with it. If the leaf is the only one in its braces, the
+/// enclosing tree is removed instead, up to the whole `use` item.
+fn range_to_remove_use_tree(use_tree: &ast::UseTree) -> TextRange {
+    let syntax = use_tree.syntax();
+    let use_tree_list = match syntax.parent().and_then(ast::UseTreeList::cast) {
+        Some(it) => it,
+        None => {
+            let use_item = match syntax.parent().filter(|it| it.kind() == USE_ITEM) {
+                Some(it) => it,
+                None => return syntax.range(),
+            };
+            let end = match use_item.next_sibling().filter(|it| it.kind() == WHITESPACE) {
+                Some(ws) => ws.range().end(),
+                None => use_item.range().end(),
+            };
+            return TextRange::from_to(use_item.range().start(), end);
+        }
+    };
+    if use_tree_list.use_trees().count() == 1 {
+        if let Some(parent) = use_tree_list.syntax().parent().and_then(ast::UseTree::cast) {
+            return range_to_remove_use_tree(parent);
+        }
+        return syntax.range();
+    }
+
+    let adjacent_comma = |direction| {
+        syntax
+            .siblings(direction)
+            .skip(1)
+            .find(|it: &&SyntaxNode| it.kind() != WHITESPACE)
+            .filter(|it| it.kind() == COMMA)
+    };
+    if let Some(comma) = adjacent_comma(Direction::Next) {
+        let end = match comma.next_sibling().filter(|it| it.kind() == WHITESPACE) {
+            Some(ws) => ws.range().end(),
+            None => comma.range().end(),
+        };
+        return TextRange::from_to(syntax.range().start(), end);
+    }
+    match adjacent_comma(Direction::Prev) {
+        Some(comma) => TextRange::from_to(comma.range().start(), syntax.range().end()),
+        None => syntax.range(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use test_utils::assert_eq_text;
 
+    use std::sync::Arc;
+
+    use crate::{
+        AnalysisChange, AnalysisHost, CrateGraph, SourceRootId,
+        mock_analysis::{single_file, MockAnalysis},
+    };
+
     use super::*;
 
     type DiagnosticChecker = fn(&mut Vec<Diagnostic>, FileId, &SyntaxNode) -> Option<()>;
@@ -286,4 +1345,523 @@ fn main() {
             check_struct_shorthand_initialization,
         );
     }
+
+    #[test]
+    fn test_check_misspelled_names() {
+        fn check(code: &str) -> Vec<(String, String, String)> {
+            let file = SourceFile::parse(code);
+            let mut diagnostics = Vec::new();
+            check_misspelled_names(&mut diagnostics, FileId(0), &file);
+            diagnostics
+                .into_iter()
+                .map(|it| {
+                    let edit = it.fix.unwrap().source_file_edits.pop().unwrap().edit;
+                    (code[it.range].to_string(), it.message, edit.apply(code))
+                })
+                .collect()
+        }
+
+        assert_eq!(
+            check("fn main() { let foo_bar = 1; foo_baz; }"),
+            vec![(
+                "foo_baz".to_string(),
+                "did you mean `foo_bar`?".to_string(),
+                "fn main() { let foo_bar = 1; foo_bar; }".to_string(),
+            )]
+        );
+        assert_eq!(
+            check("fn compute() {} fn main() { compte(); }")[0].1,
+            "did you mean `compute`?"
+        );
+        assert!(check("fn main() { let foo = 1; bar; }").is_empty());
+        assert!(check("fn main() { foo_baz; let foo_bar = 1; }").is_empty());
+        assert!(check("fn foo_a() {} fn foo_b() {} fn main() { foo_c(); }").is_empty());
+        assert!(check("use m::*; fn main() { let foo_bar = 1; foo_baz; }").is_empty());
+        assert!(check("m!(); fn main() { let foo_bar = 1; foo_baz; }").is_empty());
+    }
+
+    #[test]
+    fn test_check_naming_conventions() {
+        check_not_applicable(
+            r#"
+            #![allow(non_camel_case_types)]
+            #[allow(non_snake_case)]
+            fn fooBar() {}
+            extern "C" { fn fooBar(); }
+            fn f() { match x { None => () } }
+            struct foo_bar;
+            "#,
+            check_naming_conventions,
+        );
+        check_apply(
+            "fn fooBar() {} fn main() { fooBar(); }",
+            "fn foo_bar() {} fn main() { foo_bar(); }",
+            check_naming_conventions,
+        );
+        check_apply(
+            "fn f() { let fooBar = 1; let y = fooBar + 1; S { fooBar }; }",
+            "fn f() { let foo_bar = 1; let y = foo_bar + 1; S { fooBar: foo_bar }; }",
+            check_naming_conventions,
+        );
+        check_apply(
+            "fn f() { let x = 1; let fooBar = x; fooBar; let fooBar = 2; fooBar; }",
+            "fn f() { let x = 1; let fooBar = x; fooBar; let foo_bar = 2; foo_bar; }",
+            check_naming_conventions,
+        );
+        check_apply(
+            "struct foo_bar; fn f(_: foo_bar) {}",
+            "struct FooBar; fn f(_: FooBar) {}",
+            check_naming_conventions,
+        );
+        check_apply(
+            "fn f(s: S) { let S { fooBar } = s; fooBar; }",
+            "fn f(s: S) { let S { fooBar: foo_bar } = s; foo_bar; }",
+            check_naming_conventions,
+        );
+        // Qualified paths, method calls and the paths in functions which
+        // define another `fooBar` keep the name.
+        check_apply(
+            "fn fooBar() {} fn f() { fooBar(); m::fooBar(); x.fooBar(); } \
+             #[allow(non_snake_case)] fn g() { let fooBar = 1; fooBar; } \
+             #[allow(non_snake_case)] mod m { pub fn fooBar() {} }",
+            "fn foo_bar() {} fn f() { foo_bar(); m::fooBar(); x.fooBar(); } \
+             #[allow(non_snake_case)] fn g() { let fooBar = 1; fooBar; } \
+             #[allow(non_snake_case)] mod m { pub fn fooBar() {} }",
+            check_naming_conventions,
+        );
+        assert_eq!(to_snake_case("HTTPServer2Go"), "http_server2_go");
+        assert_eq!(to_camel_case("_foo_bar_"), "_FooBar_");
+    }
+
+    fn check_messages(code: &str, func: DiagnosticChecker) -> Vec<(String, String)> {
+        let file = SourceFile::parse(code);
+        let mut diagnostics = Vec::new();
+        for node in file.syntax().descendants() {
+            func(&mut diagnostics, FileId(0), node);
+        }
+        diagnostics.into_iter().map(|it| (code[it.range].to_string(), it.message)).collect()
+    }
+
+    /// Runs all diagnostics on `code` and, for each one whose message ends
+    /// with `message`, returns the text of its range and the fixed code.
+    fn check_fixes(code: &str, message: &str) -> Vec<(String, String)> {
+        let (analysis, file_id) = single_file(code);
+        analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.message.ends_with(message))
+            .map(|it| {
+                let mut fix = it.fix.unwrap_or_else(|| panic!("no fix for `{}`", it.message));
+                let fixed = fix.source_file_edits.pop().unwrap().edit.apply(code);
+                (code[it.range].to_string(), fixed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_builtin_macro_call() {
+        check_not_applicable(
+            r#"
+            const A: &str = env!("A");
+            const B: &str = std::env!("B", "B is not set");
+            const C: &str = include_str!(concat!(env!("OUT_DIR"), "/c.txt"));
+            const D: &str = concat!("a", 1, -2.0, true, 'c', stringify!(d),);
+            fn main() {
+                println!();
+                println!("{}", A);
+                let _ = format!(r"{}", B);
+                write!(f, "{}", C);
+                foo::env!(1, 2, 3);
+            }
+        "#,
+            check_builtin_macro_call,
+        );
+
+        let messages = check_messages(
+            r#"
+            const A: &str = env!(A);
+            const B: &str = env!("A", "B", "C", "D");
+            const C: &str = include_str!();
+            const D: &str = concat!("a", b"b", x);
+            fn main() {
+                print!();
+                println!(A);
+            }
+        "#,
+            check_builtin_macro_call,
+        );
+        assert_eq!(
+            messages,
+            [
+                ("A", "expected string literal"),
+                (r#""C", "D""#, "`env!` takes 1 or 2 arguments"),
+                ("()", "`include_str!` takes 1 argument"),
+                (r#"b"b""#, "expected a literal"),
+                ("x", "expected a literal"),
+                ("()", "`print!` requires at least a format string argument"),
+                ("A", "format argument must be a string literal"),
+            ]
+            .iter()
+            .map(|&(text, message)| (text.to_string(), message.to_string()))
+            .collect::<Vec<_>>()
+        );
+    }
+
+    fn check_unused_imports_fix(before: &str, after: &str) {
+        let (analysis, file_id) = single_file(before);
+        let diagnostic = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .find(|it| it.message == "unused import")
+            .unwrap_or_else(|| panic!("no unused imports in:\n{}\n", before));
+        let mut fix = diagnostic.fix.unwrap();
+        let edit = fix.source_file_edits.pop().unwrap().edit;
+        let actual = edit.apply(&before);
+        assert_eq_text!(after, &actual);
+    }
+
+    #[test]
+    fn test_check_unused_imports() {
+        let (analysis, file_id) = single_file(
+            "
+mod foo {
+    pub struct Used;
+    pub struct Unused;
+    pub trait Trait {}
+    pub mod bar { pub struct Baz; }
+}
+pub use self::foo::Unused as Reexported;
+use self::foo::{Used, Unused, Trait, bar::*};
+fn f(_: Used) {}
+",
+        );
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        let unused = diagnostics
+            .iter()
+            .filter(|it| it.message == "unused import")
+            .map(|it| it.range)
+            .collect::<Vec<_>>();
+        assert_eq!(unused, vec![TextRange::from_to(179.into(), 185.into())]);
+
+        check_unused_imports_fix(
+            "mod foo { pub struct A; pub struct B; }\nuse self::foo::{A, B};\nfn f(_: A) {}\n",
+            "mod foo { pub struct A; pub struct B; }\nuse self::foo::{A};\nfn f(_: A) {}\n",
+        );
+        check_unused_imports_fix(
+            "mod foo { pub struct A; pub struct B; }\nuse self::foo::{A, B};\nfn f(_: B) {}\n",
+            "mod foo { pub struct A; pub struct B; }\nuse self::foo::{B};\nfn f(_: B) {}\n",
+        );
+        check_unused_imports_fix(
+            "mod foo { pub mod bar { pub struct A; } pub struct B; }\nuse self::foo::{bar::{A}, B};\nfn f(_: B) {}\n",
+            "mod foo { pub mod bar { pub struct A; } pub struct B; }\nuse self::foo::{B};\nfn f(_: B) {}\n",
+        );
+        check_unused_imports_fix(
+            "mod foo { pub struct A; }\nuse self::foo::A;\nfn f() {}\n",
+            "mod foo { pub struct A; }\nfn f() {}\n",
+        );
+    }
+
+    #[test]
+    fn test_no_such_field() {
+        let mock = MockAnalysis::with_files(
+            "
+//- /lib.rs
+mod foo;
+fn main() {
+    foo::S { foo: 92, bar: 92u8 };
+    foo::S { foo: 92, baz: \"hello\" };
+}
+//- /foo.rs
+pub struct S {
+    pub foo: u32,
+}
+",
+        );
+        let lib = mock.id_of("/lib.rs");
+        let foo = mock.id_of("/foo.rs");
+        let analysis = mock.analysis();
+        let lib_text = analysis.file_text(lib);
+        let foo_text = analysis.file_text(foo);
+        let diagnostics = analysis
+            .diagnostics(lib)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.message == "no such field")
+            .collect::<Vec<_>>();
+        let fields = diagnostics
+            .iter()
+            .map(|it| &lib_text[it.range.start().to_usize()..it.range.end().to_usize()])
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["bar: 92u8", "baz: \"hello\""]);
+
+        let fixed = diagnostics
+            .into_iter()
+            .map(|it| {
+                let mut fix = it.fix.unwrap();
+                let edit = fix.source_file_edits.pop().unwrap();
+                assert_eq!(edit.file_id, foo);
+                edit.edit.apply(&foo_text)
+            })
+            .collect::<Vec<_>>();
+        assert_eq_text!(
+            "pub struct S {\n    pub foo: u32,\n    pub(crate) bar: u8,\n}\n",
+            &fixed[0]
+        );
+        assert_eq_text!(
+            "pub struct S {\n    pub foo: u32,\n    pub(crate) baz: (),\n}\n",
+            &fixed[1]
+        );
+
+        // The literal is in the struct's module.
+        let code = "struct S { foo: u32 }\nfn f() { S { foo: 92, bar: 92u8 }; }\n";
+        let (analysis, file_id) = single_file(code);
+        let diagnostic = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .find(|it| it.message == "no such field")
+            .unwrap();
+        let fixed = diagnostic.fix.unwrap().source_file_edits.pop().unwrap().edit.apply(code);
+        assert_eq_text!(
+            "struct S { foo: u32, bar: u8 }\nfn f() { S { foo: 92, bar: 92u8 }; }\n",
+            &fixed
+        );
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let code = "
+struct S { foo: i32, bar: (), baz: u8 }
+enum E { V { x: u32 } }
+fn f(s: S) {
+    S { foo: 92 };
+    S { foo: 92, ..s };
+    S { foo: 92, bar: (), baz: 1 };
+    E::V {};
+}
+";
+        let fixes = check_fixes(code, "missing structure fields");
+        let ranges = fixes.iter().map(|(range, _)| range.as_str()).collect::<Vec<_>>();
+        assert_eq!(ranges, vec!["{ foo: 92 }", "{}"]);
+        assert!(fixes[0].1.contains("S { foo: 92, bar: (), baz: () };"));
+        assert!(fixes[1].1.contains("E::V { x: () };"));
+    }
+
+    #[test]
+    fn test_private_item() {
+        let code = "
+mod foo {
+    fn private() {}
+    pub(super) fn visible() {}
+    /// Docs.
+    mod inner { pub struct S; }
+    pub struct Pub;
+}
+fn f() {
+    foo::private();
+    foo::visible();
+    foo::inner::S;
+    foo::Pub;
+}
+";
+        let (analysis, file_id) = single_file(code);
+        let messages = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .map(|it| it.message)
+            .filter(|it| it.ends_with("is private"))
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["`private` is private", "`inner` is private"]);
+
+        let fixes = check_fixes(code, "is private");
+        let ranges = fixes.iter().map(|(range, _)| range.as_str()).collect::<Vec<_>>();
+        assert_eq!(ranges, vec!["private", "inner"]);
+        assert!(fixes[0].1.contains("    pub(crate) fn private() {}"));
+        assert!(fixes[1].1.contains("    /// Docs.\n    pub(crate) mod inner"));
+    }
+
+    #[test]
+    fn test_private_item_in_use_and_types() {
+        let code = "
+struct Bar;
+mod foo {
+    use super::Bar;
+    struct Private;
+    mod inner { pub struct S; }
+}
+use foo::Bar as FooBar;
+use foo::{inner::S, Private};
+struct Wrapper(foo::Private);
+impl foo::Private {}
+fn f(_: foo::inner::S) {}
+";
+        let (analysis, file_id) = single_file(code);
+        let diagnostics = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.message.ends_with("is private"))
+            .collect::<Vec<_>>();
+        let ranges = diagnostics.iter().map(|it| &code[it.range]).collect::<Vec<_>>();
+        assert_eq!(ranges, vec!["Bar", "inner", "Private", "Private", "Private", "inner"]);
+
+        let mut fixes = diagnostics.into_iter().map(|it| it.fix);
+        // A private import can't be made visible.
+        assert!(fixes.next().unwrap().is_none());
+        let mut fix = fixes.nth(1).unwrap().unwrap();
+        let fixed = fix.source_file_edits.pop().unwrap().edit.apply(code);
+        assert!(fixed.contains("    pub(crate) struct Private;"));
+    }
+
+    #[test]
+    fn test_missing_match_arms() {
+        let code = "
+enum E { A, B(u32), C { x: u32 } }
+fn f(e: E, r: &E) {
+    match e {
+        E::A => (),
+    }
+    match r {
+        E::A | E::B(_) => (),
+        E::C { .. } => (),
+    }
+    match e {
+        E::A => (),
+        _ => (),
+    }
+    if let E::A = e {}
+}
+";
+        let fixes = check_fixes(code, "missing match arms");
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].0, "match e {\n        E::A => (),\n    }");
+        assert_eq_text!(
+            "
+enum E { A, B(u32), C { x: u32 } }
+fn f(e: E, r: &E) {
+    match e {
+        E::A => (),
+        E::B(..) => (),
+        E::C { .. } => (),
+    }
+    match r {
+        E::A | E::B(_) => (),
+        E::C { .. } => (),
+    }
+    match e {
+        E::A => (),
+        _ => (),
+    }
+    if let E::A = e {}
+}
+",
+            &fixes[0].1
+        );
+    }
+
+    #[test]
+    fn test_missing_match_arms_paths() {
+        let code = "
+mod foo { pub enum E { A, B(u32) } }
+use foo::E::*;
+fn f(e: foo::E) {
+    match e {
+        foo::E::A => (),
+    }
+    match e {
+        B(_) => (),
+    }
+    match e {}
+}
+";
+        let fixed = check_fixes(code, "missing match arms")
+            .into_iter()
+            .map(|(_, fixed)| fixed)
+            .collect::<Vec<_>>();
+        assert_eq!(fixed.len(), 3);
+        assert!(fixed[0].contains("foo::E::A => (),\n        foo::E::B(..) => (),"));
+        assert!(fixed[1].contains("B(_) => (),\n        A => (),"));
+        assert!(fixed[2].contains("crate::foo::E::A => ()"));
+        assert!(fixed[2].contains("crate::foo::E::B(..) => ()"));
+    }
+
+    fn bare_trait_object_fixes(code: &str, edition: Edition) -> Vec<String> {
+        let mut host = AnalysisHost::default();
+        let source_root = SourceRootId(0);
+        let file_id = FileId(1);
+        let mut change = AnalysisChange::new();
+        change.add_root(source_root, true);
+        change.add_file(source_root, file_id, "lib.rs".into(), Arc::new(code.to_string()));
+        let mut crate_graph = CrateGraph::default();
+        crate_graph.add_crate_root(file_id, edition);
+        change.set_crate_graph(crate_graph);
+        host.apply_change(change);
+
+        host.analysis()
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.message.contains("`dyn`"))
+            .map(|it| {
+                let mut fix = it.fix.unwrap();
+                fix.source_file_edits.pop().unwrap().edit.apply(code)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_bare_trait_objects() {
+        let code = "
+trait Write {}
+impl Write for u8 {}
+fn f<T: Write>(x: Box<Write>, y: &dyn Write, z: impl Write) where T: ?Sized + Write {}
+";
+        assert_eq!(
+            bare_trait_object_fixes(code, Edition::Edition2018),
+            vec![code.replace("Box<Write>", "Box<dyn Write>")]
+        );
+        assert!(bare_trait_object_fixes(code, Edition::Edition2015).is_empty());
+    }
+
+    #[test]
+    fn test_macro_errors() {
+        let (analysis, file_id) = single_file(
+            "
+macro_rules! m {
+    ($i:ident) => { fn $i() {} }
+}
+m!(foo);
+m!(92);
+macro_rules! broken {
+    ($i:ident) ()
+}
+",
+        );
+        let text = analysis.file_text(file_id);
+        let diagnostics = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .map(|it| {
+                let range = it.range.start().to_usize()..it.range.end().to_usize();
+                (text[range].to_string(), it.message)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "m!(92);".to_string(),
+                    "failed to expand macro: no rule matches the input".to_string()
+                ),
+                (
+                    "macro_rules! broken {\n    ($i:ident) ()\n}".to_string(),
+                    "invalid macro definition: expected `=`".to_string()
+                ),
+            ]
+        );
+    }
 }
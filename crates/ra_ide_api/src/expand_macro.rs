@@ -0,0 +1,205 @@
+use hir::{MacroCallId, source_binder};
+use ra_db::FilePosition;
+use ra_syntax::{
+    AstNode, SmolStr,
+    SyntaxKind::{self, *},
+    SyntaxNode, TextRange,
+    algo::find_node_at_offset,
+    ast,
+};
+
+use crate::{RangeInfo, db::RootDatabase};
+
+/// Result of the "expand macro" command.
+#[derive(Debug)]
+pub struct ExpandedMacro {
+    /// Name of the macro which was expanded, like `vec`.
+    pub name: String,
+    /// Pretty-printed expansion.
+    pub expansion: String,
+}
+
+/// Expands the macro call under cursor. Macro calls inside the expansion are
+/// expanded as well, up to `max_depth` levels of nesting.
+pub(crate) fn expand_macro(
+    db: &RootDatabase,
+    position: FilePosition,
+    max_depth: u32,
+) -> Option<RangeInfo<ExpandedMacro>> {
+    let file = db.parse(position.file_id);
+    let macro_call = find_node_at_offset::<ast::MacroCall>(file.syntax(), position.offset)?;
+    let name = macro_call.path()?.segment()?.name_ref()?.text().to_string();
+    let macro_call_id = source_binder::macro_call_from_source(db, position.file_id, macro_call)?;
+
+    let mut tokens = Vec::new();
+    expand_macro_recur(db, macro_call_id, max_depth, &mut tokens)?;
+    let expansion = insert_whitespaces(&tokens);
+    Some(RangeInfo::new(macro_call.syntax().range(), ExpandedMacro { name, expansion }))
+}
+
+fn expand_macro_recur(
+    db: &RootDatabase,
+    macro_call_id: MacroCallId,
+    depth: u32,
+    tokens: &mut Vec<(SyntaxKind, SmolStr)>,
+) -> Option<()> {
+    let expansion = macro_call_id.expand(db)?;
+    let nested: Vec<(TextRange, MacroCallId)> = if depth > 0 {
+        expansion
+            .macro_calls(db)
+            .into_iter()
+            .map(|(macro_call, id)| (macro_call.syntax().range(), id))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    collect_tokens(db, expansion.source_file().syntax(), &nested, depth, tokens);
+    Some(())
+}
+
+fn collect_tokens(
+    db: &RootDatabase,
+    node: &SyntaxNode,
+    nested: &[(TextRange, MacroCallId)],
+    depth: u32,
+    tokens: &mut Vec<(SyntaxKind, SmolStr)>,
+) {
+    if let Some(text) = node.leaf_text() {
+        if !node.kind().is_trivia() {
+            tokens.push((node.kind(), text.clone()));
+        }
+        return;
+    }
+    if node.kind() == MACRO_CALL {
+        if let Some(&(_, id)) = nested.iter().find(|(range, _)| *range == node.range()) {
+            let mut expanded = Vec::new();
+            // If the nested call fails to expand, we fall back to printing it
+            // verbatim.
+            if expand_macro_recur(db, id, depth - 1, &mut expanded).is_some() {
+                tokens.extend(expanded);
+                return;
+            }
+        }
+    }
+    for child in node.children() {
+        collect_tokens(db, child, nested, depth, tokens);
+    }
+}
+
+/// Macro expansions don't have any whitespace between the tokens, so we
+/// re-create some, putting each item and statement on a separate line.
+fn insert_whitespaces(tokens: &[(SyntaxKind, SmolStr)]) -> String {
+    let mut res = String::new();
+    let mut indent = 0usize;
+    let mut pending_newline = false;
+    let mut last: Option<SyntaxKind> = None;
+
+    for (kind, text) in tokens {
+        let kind = *kind;
+        if kind == R_CURLY {
+            indent = indent.saturating_sub(1);
+            // Keep empty blocks on a single line: `{}`
+            if last == Some(L_CURLY) {
+                pending_newline = false;
+            }
+        }
+        if pending_newline && last == Some(R_CURLY) && is_glued_to_block(kind) {
+            pending_newline = false;
+        }
+
+        if pending_newline {
+            res.push('\n');
+            res.push_str(&"    ".repeat(indent));
+            pending_newline = false;
+        } else if last.map_or(false, |last| needs_space(last, kind)) {
+            res.push(' ');
+        }
+        res.push_str(text);
+
+        match kind {
+            L_CURLY => {
+                indent += 1;
+                pending_newline = true;
+            }
+            R_CURLY | SEMI => pending_newline = true,
+            _ => (),
+        }
+        last = Some(kind);
+    }
+    res
+}
+
+/// Tokens which stay on the same line after a `}`, like in `S {};`
+fn is_glued_to_block(kind: SyntaxKind) -> bool {
+    match kind {
+        SEMI | COMMA | DOT | R_PAREN | R_BRACK | QUESTION => true,
+        _ => false,
+    }
+}
+
+fn needs_space(prev: SyntaxKind, next: SyntaxKind) -> bool {
+    match (prev, next) {
+        (L_PAREN, _) | (L_BRACK, _) | (DOT, _) | (COLONCOLON, _) | (POUND, _) => false,
+        (_, R_PAREN) | (_, R_BRACK) | (_, COMMA) | (_, SEMI) | (_, COLON) | (_, DOT) => false,
+        (_, COLONCOLON) | (_, QUESTION) => false,
+        (IDENT, L_PAREN) | (IDENT, L_BRACK) | (IDENT, EXCL) => false,
+        (EXCL, L_PAREN) | (EXCL, L_BRACK) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::assert_eq_text;
+
+    use crate::mock_analysis::analysis_and_position;
+
+    fn check_expand_macro(fixture: &str, max_depth: u32, expected: (&str, &str)) {
+        let (analysis, pos) = analysis_and_position(fixture);
+        let result = analysis.expand_macro(pos, max_depth).unwrap().unwrap();
+        assert_eq!(result.info.name, expected.0);
+        assert_eq_text!(expected.1, &result.info.expansion);
+    }
+
+    #[test]
+    fn macro_expand_items() {
+        check_expand_macro(
+            r#"
+            //- /lib.rs
+            macro_rules! foo {
+                () => { fn some_thing() -> u32 { let a = 0; a + 10 } }
+            }
+            f<|>oo!();
+            "#,
+            0,
+            ("foo", "fn some_thing() -> u32 {\n    let a = 0;\n    a + 10\n}"),
+        );
+    }
+
+    #[test]
+    fn macro_expand_respects_depth() {
+        let fixture = r#"
+            //- /lib.rs
+            macro_rules! bar {
+                () => { fn b() {} }
+            }
+            macro_rules! foo {
+                () => { bar!(); }
+            }
+            f<|>oo!();
+            "#;
+        check_expand_macro(fixture, 0, ("foo", "bar!();"));
+        check_expand_macro(fixture, 1, ("foo", "fn b() {}"));
+    }
+
+    #[test]
+    fn macro_expand_unresolved_macro() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+            //- /lib.rs
+            f<|>oo!();
+            "#,
+        );
+        assert!(analysis.expand_macro(pos, 0).unwrap().is_none());
+    }
+}
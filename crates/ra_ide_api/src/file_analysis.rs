@@ -0,0 +1,133 @@
+//! Computes several single-file analyses in a single traversal of the syntax
+//! tree. Editors typically ask for most of them after each change, and, for
+//! large files, walking the tree once per analysis is noticeable.
+
+use ra_db::SourceDatabase;
+use ra_ide_api_light::{FoldCollector, Highlighter, StructureCollector};
+use ra_syntax::{AstNode, WalkEvent};
+
+use crate::{Diagnostic, FileId, Fold, HighlightedRange, StructureNode, db::RootDatabase, diagnostics};
+
+/// Which analyses `analyze_file` should compute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeatureSet {
+    pub highlight: bool,
+    pub structure: bool,
+    pub folding_ranges: bool,
+    pub diagnostics: bool,
+}
+
+impl FeatureSet {
+    pub fn all() -> FeatureSet {
+        FeatureSet { highlight: true, structure: true, folding_ranges: true, diagnostics: true }
+    }
+}
+
+/// The results of `analyze_file`. Analyses which were not requested are
+/// left empty.
+#[derive(Debug, Default)]
+pub struct FileAnalysis {
+    pub highlight: Vec<HighlightedRange>,
+    pub structure: Vec<StructureNode>,
+    pub folding_ranges: Vec<Fold>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub(crate) fn analyze_file(db: &RootDatabase, file_id: FileId, wanted: FeatureSet) -> FileAnalysis {
+    let source_file = db.parse(file_id);
+    let mut highlighter = Highlighter::default();
+    let mut structure = StructureCollector::default();
+    let mut folds = FoldCollector::default();
+    let mut res = FileAnalysis::default();
+
+    if wanted.diagnostics {
+        diagnostics::syntax_errors(&mut res.diagnostics, &source_file);
+    }
+    for event in source_file.syntax().preorder() {
+        match event {
+            WalkEvent::Enter(node) => {
+                if wanted.highlight {
+                    highlighter.visit(node);
+                }
+                if wanted.structure {
+                    structure.enter(node);
+                }
+                if wanted.folding_ranges {
+                    folds.visit(node);
+                }
+                if wanted.diagnostics {
+                    diagnostics::check_node(&mut res.diagnostics, file_id, node);
+                }
+            }
+            WalkEvent::Leave(node) => {
+                if wanted.structure {
+                    structure.leave(node);
+                }
+            }
+        }
+    }
+    if wanted.diagnostics {
+        diagnostics::check_file(&mut res.diagnostics, file_id, &source_file);
+        diagnostics::semantic_diagnostics(&mut res.diagnostics, db, file_id, &source_file);
+    }
+
+    res.highlight = highlighter.finish();
+    res.structure = structure.finish();
+    res.folding_ranges = folds.finish(None);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file;
+
+    use super::*;
+
+    #[test]
+    fn analyze_file_matches_separate_analyses() {
+        let (analysis, file_id) = single_file(
+            r#"
+// A comment
+// spanning two lines
+use std::fmt;
+use std::{io};
+
+struct Foo {
+    x: u32,
+}
+
+impl Foo {
+    fn new() -> Foo {
+        Foo { x: 92 }
+    }
+}
+
+macro_rules! m { () => {} }
+m!();
+"#,
+        );
+        let combined = analysis.analyze_file(file_id, FeatureSet::all()).unwrap();
+
+        assert_eq!(combined.highlight, analysis.highlight(file_id).unwrap());
+        assert_eq!(
+            format!("{:?}", combined.structure),
+            format!("{:?}", analysis.file_structure(file_id))
+        );
+        assert_eq!(
+            format!("{:?}", combined.folding_ranges),
+            format!("{:?}", analysis.folding_ranges(file_id))
+        );
+        assert_eq!(
+            format!("{:?}", combined.diagnostics),
+            format!("{:?}", analysis.diagnostics(file_id).unwrap())
+        );
+        assert!(!combined.diagnostics.is_empty());
+
+        let only_folds = FeatureSet { folding_ranges: true, ..FeatureSet::default() };
+        let partial = analysis.analyze_file(file_id, only_folds).unwrap();
+        assert!(partial.highlight.is_empty());
+        assert!(partial.structure.is_empty());
+        assert!(partial.diagnostics.is_empty());
+        assert_eq!(partial.folding_ranges.len(), combined.folding_ranges.len());
+    }
+}
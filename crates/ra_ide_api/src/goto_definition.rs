@@ -242,6 +242,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_definition_works_for_macro_generated_items() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! foo { () => { fn bar() {} } }
+            foo!();
+            fn baz() { bar<|>(); }
+            ",
+            "bar FN_DEF FileId(1) [43; 50) [43; 50)",
+        );
+    }
+
     #[test]
     fn goto_definition_resolves_correct_name() {
         check_goto(
@@ -1,15 +1,50 @@
 use ra_db::SourceDatabase;
 use ra_syntax::{
-    AstNode, SyntaxNode, TreeArc, ast::{self, NameOwner, VisibilityOwner, TypeAscriptionOwner},
+    AstNode, SyntaxNode, TreeArc, TextRange,
+    SyntaxKind::MODULE,
+    ast::{self, NameOwner, VisibilityOwner, TypeAscriptionOwner},
     algo::{find_covering_node, find_node_at_offset, find_leaf_at_offset, visit::{visitor, Visitor}},
 };
-use hir::HirDisplay;
+use hir::{HirDisplay, Ty, source_binder};
 
 use crate::{db::RootDatabase, RangeInfo, FilePosition, FileRange, NavigationTarget};
 
+/// A single item shown on hover. The parts are kept apart, so that clients
+/// can lay them out as they see fit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HoverItem {
+    /// The signature of an item, or the type of an expression.
+    pub signature: Option<String>,
+    pub docs: Option<String>,
+    /// The path of the module containing the item, like `crate::foo`.
+    pub module_path: Option<String>,
+}
+
+impl HoverItem {
+    pub fn to_markup(&self) -> Option<String> {
+        match (&self.signature, &self.docs) {
+            (Some(signature), docs) => Some(rust_code_markup_with_doc(signature, docs.as_ref())),
+            (None, Some(docs)) => Some(docs.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The type of an expression or a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeInfo {
+    /// The range of the expression or the pattern, which might be larger than
+    /// the requested one.
+    pub range: TextRange,
+    pub ty: Ty,
+    /// `ty`, rendered for display.
+    pub label: String,
+}
+
 /// Contains the results when hovering over an item
 #[derive(Debug, Clone)]
 pub struct HoverResult {
+    items: Vec<HoverItem>,
     results: Vec<String>,
     exact: bool,
 }
@@ -17,14 +52,23 @@ pub struct HoverResult {
 impl HoverResult {
     pub fn new() -> HoverResult {
         HoverResult {
+            items: Vec::new(),
             results: Vec::new(),
             // We assume exact by default
             exact: true,
         }
     }
 
-    pub fn extend(&mut self, item: Option<String>) {
-        self.results.extend(item);
+    /// Adds `item`, unless there is nothing to show for it.
+    pub fn push(&mut self, item: HoverItem) {
+        if let Some(markup) = item.to_markup() {
+            self.items.push(item);
+            self.results.push(markup);
+        }
+    }
+
+    pub fn items(&self) -> &[HoverItem] {
+        &self.items
     }
 
     pub fn is_exact(&self) -> bool {
@@ -76,13 +120,13 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
         use crate::goto_definition::{ReferenceResult::*, reference_definition};
         let ref_result = reference_definition(db, position.file_id, name_ref);
         match ref_result {
-            Exact(nav) => res.extend(doc_text_for(db, nav)),
+            Exact(nav) => res.push(hover_item_for(db, nav)),
             Approximate(navs) => {
                 // We are no longer exact
                 res.exact = false;
 
                 for nav in navs {
-                    res.extend(doc_text_for(db, nav))
+                    res.push(hover_item_for(db, nav))
                 }
             }
         }
@@ -94,7 +138,7 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
 
         if let Some(navs) = navs {
             for nav in navs {
-                res.extend(doc_text_for(db, nav))
+                res.push(hover_item_for(db, nav))
             }
         }
 
@@ -108,7 +152,7 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
             leaf.ancestors().find(|n| ast::Expr::cast(*n).is_some() || ast::Pat::cast(*n).is_some())
         })?;
         let frange = FileRange { file_id: position.file_id, range: node.range() };
-        res.extend(type_of(db, frange).map(rust_code_markup));
+        res.push(HoverItem { signature: type_of(db, frange), ..HoverItem::default() });
         range = Some(node.range());
     }
 
@@ -121,9 +165,22 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
 }
 
 pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Option<String> {
+    type_info(db, frange).map(|it| it.label)
+}
+
+pub(crate) fn type_info(db: &RootDatabase, frange: FileRange) -> Option<TypeInfo> {
     let file = db.parse(frange.file_id);
     let syntax = file.syntax();
     let leaf_node = find_covering_node(syntax, frange.range);
+    let macro_call = leaf_node.ancestors().find_map(ast::MacroCall::cast).filter(|it| {
+        it.token_tree().map_or(false, |tt| {
+            let tt_range = tt.syntax().range();
+            tt_range != frange.range && frange.range.is_subrange(&tt_range)
+        })
+    });
+    if let Some(macro_call) = macro_call {
+        return type_info_in_expansion(db, frange, macro_call);
+    }
     // if we picked identifier, expand to pattern/expression
     let node = leaf_node
         .ancestors()
@@ -131,20 +188,51 @@ pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Option<String> {
         .find(|&it| ast::Expr::cast(it).is_some() || ast::Pat::cast(it).is_some())
         .unwrap_or(leaf_node);
     let parent_fn = node.ancestors().find_map(ast::FnDef::cast)?;
-    let function = hir::source_binder::function_from_source(db, frange.file_id, parent_fn)?;
+    let function = source_binder::function_from_source(db, frange.file_id, parent_fn)?;
+    type_info_in_function(db, function, node, node.range())
+}
+
+/// Infers the type of the expression or the pattern in the expansion of
+/// `macro_call` which maps back exactly to `frange`. Only the bodies of
+/// functions defined by macros are lowered, so arguments of macros in
+/// expression position have no types.
+fn type_info_in_expansion(
+    db: &RootDatabase,
+    frange: FileRange,
+    macro_call: &ast::MacroCall,
+) -> Option<TypeInfo> {
+    let macro_call_id = source_binder::macro_call_from_source(db, frange.file_id, macro_call)?;
+    let expansion = macro_call_id.expand(db)?;
+    let info = expansion.file_id().expansion_info(db)?;
+    let original = Some((frange.file_id.into(), frange.range));
+    let node = expansion
+        .source_file()
+        .syntax()
+        .descendants()
+        .filter(|&it| ast::Expr::cast(it).is_some() || ast::Pat::cast(it).is_some())
+        .find(|it| info.map_range_up(it.range()) == original)?;
+    let parent_fn = node.ancestors().find_map(ast::FnDef::cast)?;
+    let function = source_binder::function_from_expansion(db, &expansion, parent_fn);
+    type_info_in_function(db, function, node, frange.range)
+}
+
+fn type_info_in_function(
+    db: &RootDatabase,
+    function: hir::Function,
+    node: &SyntaxNode,
+    range: TextRange,
+) -> Option<TypeInfo> {
     let infer = function.infer(db);
     let source_map = function.body_source_map(db);
-    if let Some(expr) = ast::Expr::cast(node).and_then(|e| source_map.node_expr(e)) {
-        Some(infer[expr].display(db).to_string())
+    let ty = if let Some(expr) = ast::Expr::cast(node).and_then(|e| source_map.node_expr(e)) {
+        infer[expr].clone()
     } else if let Some(pat) = ast::Pat::cast(node).and_then(|p| source_map.node_pat(p)) {
-        Some(infer[pat].display(db).to_string())
+        infer[pat].clone()
     } else {
-        None
-    }
-}
-
-fn rust_code_markup<CODE: AsRef<str>>(val: CODE) -> String {
-    rust_code_markup_with_doc::<_, &str>(val, None)
+        return None;
+    };
+    let label = ty.display(db).to_string();
+    Some(TypeInfo { range, ty, label })
 }
 
 fn rust_code_markup_with_doc<CODE, DOC>(val: CODE, doc: Option<DOC>) -> String
@@ -161,11 +249,11 @@ where
 
 // FIXME: this should not really use navigation target. Rather, approximately
 // resolved symbol should return a `DefId`.
-fn doc_text_for(db: &RootDatabase, nav: NavigationTarget) -> Option<String> {
-    match (nav.description(db), nav.docs(db)) {
-        (Some(desc), docs) => Some(rust_code_markup_with_doc(desc, docs)),
-        (None, Some(docs)) => Some(docs),
-        _ => None,
+fn hover_item_for(db: &RootDatabase, nav: NavigationTarget) -> HoverItem {
+    HoverItem {
+        signature: nav.description(db),
+        docs: nav.docs(db),
+        module_path: nav.module_path(db),
     }
 }
 
@@ -180,6 +268,28 @@ impl NavigationTarget {
         Some(node)
     }
 
+    /// The path of the module containing this item, computed from the
+    /// module tree. The crate root is written as `crate`.
+    fn module_path(&self, db: &RootDatabase) -> Option<String> {
+        if !self.kind().is_item() {
+            return None;
+        }
+        let position = FilePosition { file_id: self.file_id(), offset: self.full_range().start() };
+        let mut module = source_binder::module_from_position(db, position)?;
+        if self.kind() == MODULE {
+            module = module.parent(db)?;
+        }
+        let mut segments = module
+            .path_to_root(db)
+            .into_iter()
+            .filter_map(|it| it.name(db))
+            .map(|it| it.to_string())
+            .collect::<Vec<_>>();
+        segments.push("crate".to_string());
+        segments.reverse();
+        Some(segments.join("::"))
+    }
+
     fn docs(&self, db: &RootDatabase) -> Option<String> {
         let node = self.node(db)?;
         fn doc_comments<N: ast::DocCommentsOwner>(node: &N) -> Option<String> {
@@ -251,6 +361,7 @@ impl NavigationTarget {
 mod tests {
     use ra_syntax::TextRange;
     use crate::mock_analysis::{single_file_with_position, single_file_with_range, analysis_and_position};
+    use crate::HoverItem;
 
     fn trim_markup(s: &str) -> &str {
         s.trim_start_matches("```rust\n").trim_end_matches("\n```")
@@ -289,6 +400,32 @@ mod tests {
         assert_eq!(trim_markup_opt(hover.info.first()), Some("u32"));
     }
 
+    #[test]
+    fn hover_result_is_structured() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /main.rs
+            mod a;
+            fn main() { a::b::fo<|>o(); }
+
+            //- /a.rs
+            pub mod b {
+                /// Does foo.
+                pub fn foo() -> u32 { 1 }
+            }
+            ",
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        assert_eq!(
+            hover.info.items(),
+            &[HoverItem {
+                signature: Some("pub fn foo() -> u32".to_string()),
+                docs: Some("Does foo.".to_string()),
+                module_path: Some("crate::a::b".to_string()),
+            }]
+        );
+    }
+
     #[test]
     fn hover_shows_fn_signature() {
         // Single file with result
@@ -416,6 +553,17 @@ mod tests {
         "#,
             &["static foo: u32"],
         );
+
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const MAX: u32 = 92;
+            fn main() {
+                let x = MAX<|>;
+            }
+        "#,
+            &["const MAX: u32"],
+        );
     }
 
     #[test]
@@ -532,6 +680,37 @@ The Some variant
         assert_eq!("usize", &type_name);
     }
 
+    #[test]
+    fn test_type_info_for_expr() {
+        let (analysis, range) = single_file_with_range(
+            "
+            fn main() {
+                let foo = <|>92u8<|>;
+            }
+            ",
+        );
+
+        let info = analysis.type_info(range).unwrap().unwrap();
+        assert_eq!(info.label, "u8");
+        assert_eq!(info.range, range.range);
+    }
+
+    #[test]
+    fn test_type_of_inside_macro_call() {
+        let (analysis, range) = single_file_with_range(
+            "
+            macro_rules! gen {
+                ($f:ident, $x:ident) => { fn $f($x: u32) { let y = $x; } }
+            }
+            gen!(foo, <|>arg<|>);
+            ",
+        );
+
+        let info = analysis.type_info(range).unwrap().unwrap();
+        assert_eq!(info.label, "u32");
+        assert_eq!(info.range, range.range);
+    }
+
     #[test]
     fn test_hover_infer_associated_method_result() {
         let (analysis, position) = single_file_with_position(
@@ -36,24 +36,17 @@ fn impls_for_def(
     node: &ast::NominalDef,
     module: hir::Module,
 ) -> Option<Vec<NavigationTarget>> {
-    let ty = match node.kind() {
+    let adt: hir::AdtDef = match node.kind() {
         ast::NominalDefKind::StructDef(def) => {
-            source_binder::struct_from_module(db, module, &def).ty(db)
+            source_binder::struct_from_module(db, module, &def).into()
         }
         ast::NominalDefKind::EnumDef(def) => {
-            source_binder::enum_from_module(db, module, &def).ty(db)
+            source_binder::enum_from_module(db, module, &def).into()
         }
     };
 
-    let krate = module.krate(db)?;
-    let impls = db.impls_in_crate(krate);
-
-    Some(
-        impls
-            .lookup_impl_blocks(&ty)
-            .map(|imp| NavigationTarget::from_impl_block(db, imp))
-            .collect(),
-    )
+    let impls = db.impls_for_type(adt);
+    Some(impls.iter().map(|&imp| NavigationTarget::from_impl_block(db, imp)).collect())
 }
 
 fn impls_for_trait(
@@ -63,15 +56,8 @@ fn impls_for_trait(
 ) -> Option<Vec<NavigationTarget>> {
     let tr = source_binder::trait_from_module(db, module, node);
 
-    let krate = module.krate(db)?;
-    let impls = db.impls_in_crate(krate);
-
-    Some(
-        impls
-            .lookup_impl_blocks_for_trait(&tr)
-            .map(|imp| NavigationTarget::from_impl_block(db, imp))
-            .collect(),
-    )
+    let impls = db.impls_for_trait(tr);
+    Some(impls.iter().map(|&imp| NavigationTarget::from_impl_block(db, imp)).collect())
 }
 
 #[cfg(test)]
@@ -176,4 +162,18 @@ mod tests {
             &["impl IMPL_BLOCK FileId(2) [0; 31)", "impl IMPL_BLOCK FileId(3) [0; 31)"],
         );
     }
+
+    #[test]
+    fn goto_implementation_for_trait_in_dependent_crate() {
+        check_goto(
+            "
+            //- /lib.rs
+            struct Bar;
+            impl foo::T for Bar {}
+            //- /foo/lib.rs
+            pub trait T<|> {}
+            ",
+            &["impl IMPL_BLOCK FileId(1) [12; 34)"],
+        );
+    }
 }
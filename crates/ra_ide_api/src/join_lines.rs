@@ -6,12 +6,10 @@ use ra_syntax::{
     ast,
     Direction,
 };
-use ra_fmt::{
-    compute_ws, extract_trivial_expression
-};
+use ra_fmt::{compute_ws, extract_trivial_expression, IndentStyle};
 use ra_text_edit::{TextEdit, TextEditBuilder};
 
-pub fn join_lines(file: &SourceFile, range: TextRange) -> TextEdit {
+pub fn join_lines(file: &SourceFile, range: TextRange, style: &IndentStyle) -> TextEdit {
     let range = if range.is_empty() {
         let syntax = file.syntax();
         let text = syntax.text().slice(range.start()..);
@@ -39,7 +37,7 @@ pub fn join_lines(file: &SourceFile, range: TextRange) -> TextEdit {
             let pos: TextUnit = (pos as u32).into();
             let off = node.range().start() + range.start() + pos;
             if !edit.invalidates_offset(off) {
-                remove_newline(&mut edit, node, text.as_str(), off);
+                remove_newline(&mut edit, node, text.as_str(), off, style);
             }
         }
     }
@@ -52,6 +50,7 @@ fn remove_newline(
     node: &SyntaxNode,
     node_text: &str,
     offset: TextUnit,
+    style: &IndentStyle,
 ) {
     if node.kind() != WHITESPACE || node_text.bytes().filter(|&b| b == b'\n').count() != 1 {
         // The node is either the first or the last in the file
@@ -59,9 +58,11 @@ fn remove_newline(
             offset - node.range().start() + TextUnit::of_char('\n'),
             TextUnit::of_str(node_text),
         )];
-        let spaces = suff.bytes().take_while(|&b| b == b' ').count();
+        // The indentation of the next line.
+        let indent_char = if style.use_tabs { b'\t' } else { b' ' };
+        let indent = suff.bytes().take_while(|&b| b == indent_char).count();
 
-        edit.replace(TextRange::offset_len(offset, ((spaces + 1) as u32).into()), " ".to_string());
+        edit.replace(TextRange::offset_len(offset, ((indent + 1) as u32).into()), " ".to_string());
         return;
     }
 
@@ -156,14 +157,14 @@ fn is_trailing_comma(left: SyntaxKind, right: SyntaxKind) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utils::{assert_eq_text, check_action, extract_range};
+    use crate::test_utils::{assert_eq_text, check_action, extract_offset, extract_range};
 
     use super::*;
 
     fn check_join_lines(before: &str, after: &str) {
         check_action(before, after, |file, offset| {
             let range = TextRange::offset_len(offset, 0.into());
-            let res = join_lines(file, range);
+            let res = join_lines(file, range, &IndentStyle::default());
             Some(res)
         })
     }
@@ -505,7 +506,7 @@ fn foo() {
     fn check_join_lines_sel(before: &str, after: &str) {
         let (sel, before) = extract_range(before);
         let file = SourceFile::parse(&before);
-        let result = join_lines(&file, sel);
+        let result = join_lines(&file, sel, &IndentStyle::default());
         let actual = result.apply(&before);
         assert_eq_text!(after, &actual);
     }
@@ -578,4 +579,13 @@ pub fn handle_find_matching_brace() {
 }",
         );
     }
+
+    #[test]
+    fn test_join_lines_follows_indent_style() {
+        let (offset, before) = extract_offset("fn foo() {\n\tlet s = \"a<|>\n\t\tb\";\n}\n");
+        let file = SourceFile::parse(&before);
+        let style = IndentStyle { use_tabs: true, width: 4 };
+        let result = join_lines(&file, TextRange::offset_len(offset, 0.into()), &style);
+        assert_eq_text!("fn foo() {\n\tlet s = \"a b\";\n}\n", &result.apply(&before));
+    }
 }
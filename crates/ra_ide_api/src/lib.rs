@@ -16,6 +16,7 @@
 mod db;
 pub mod mock_analysis;
 mod symbol_index;
+mod symbol_cache;
 mod navigation_target;
 mod change;
 
@@ -26,7 +27,10 @@ mod goto_definition;
 mod extend_selection;
 mod hover;
 mod call_info;
+mod call_hierarchy;
 mod syntax_highlighting;
+mod single_file;
+mod file_analysis;
 mod parent_module;
 mod references;
 mod impls;
@@ -34,9 +38,11 @@ mod assists;
 mod diagnostics;
 mod syntax_tree;
 mod line_index;
-mod folding_ranges;
 mod line_index_utils;
 mod join_lines;
+mod expand_macro;
+mod prime_caches;
+mod source_change;
 
 #[cfg(test)]
 mod marks;
@@ -54,30 +60,42 @@ use ra_db::{
 use relative_path::RelativePathBuf;
 
 use crate::{
-    symbol_index::FileSymbol,
+    symbol_index::{FileSymbol, SymbolsDatabase},
     db::LineIndexDatabase,
 };
 
 pub use crate::{
-    change::{AnalysisChange, LibraryData},
-    completion::{CompletionItem, CompletionItemKind, InsertTextFormat},
+    change::{AnalysisChange, LibraryData, RetentionLimits},
+    completion::{
+        CompletionItem, CompletionItemKind, InsertTextFormat, SyntacticCompletionContext,
+    },
     runnables::{Runnable, RunnableKind},
     navigation_target::NavigationTarget,
-    references::ReferenceSearchResult,
+    parent_module::{RelatedFile, RelatedFileKind},
+    references::{ReferenceSearchResult, RenameConflict},
+    call_hierarchy::{CallItem, CallTarget},
     assists::{Assist, AssistId},
-    hover::{HoverResult},
+    hover::{HoverItem, HoverResult, TypeInfo},
     line_index::{LineIndex, LineCol},
     line_index_utils::translate_offset_with_edit,
-    folding_ranges::{Fold, FoldKind},
+    expand_macro::ExpandedMacro,
+    syntax_highlighting::{HighlightDelta, HighlightOptions, diff_highlights},
+    single_file::SingleFileAnalysis,
+    file_analysis::{FeatureSet, FileAnalysis},
+    source_change::{ContentHashes, EditConflict, EditConflictKind, content_hash},
+    symbol_cache::SymbolCache,
+    syntax_tree::SyntaxTreeOptions,
 };
 pub use ra_ide_api_light::{
-    HighlightedRange, Severity, StructureNode, LocalEdit,
+    HighlightedRange, Severity, StructureNode, StructureNodeKind, LocalEdit, TypingEdit,
+    TypingHandler, IndentStyle, Fold, FoldKind,
 };
 pub use ra_db::{
-    Canceled, CrateGraph, CrateId, FileId, FilePosition, FileRange, SourceRootId,
-    Edition
+    Canceled, CrateGraph, CrateId, CyclicDependencies, FileId, FilePosition, FileRange,
+    SourceRootId, Edition,
+    profile::{Profiled, QueryProfile},
 };
-pub use hir::Documentation;
+pub use hir::{Documentation, DefMapStats, Ty};
 
 // We use jemalloc mainly to get heap usage statistics, actual performance
 // difference is not measures.
@@ -93,6 +111,8 @@ pub struct SourceChange {
     pub source_file_edits: Vec<SourceFileEdit>,
     pub file_system_edits: Vec<FileSystemEdit>,
     pub cursor_position: Option<FilePosition>,
+    /// Set for edits produced by typing handlers, like `on_enter`.
+    pub typing: Option<TypingEdit>,
 }
 
 #[derive(Debug)]
@@ -138,10 +158,26 @@ impl Query {
         }
     }
 
+    /// Parses a query typed by the user. The query may contain modifiers:
+    /// `#` searches only for types, and `*` searches in the libraries too.
+    pub fn parse(input: &str) -> Query {
+        let query: String = input.chars().filter(|&c| c != '#' && c != '*').collect();
+        let mut res = Query::new(query);
+        if input.contains('#') {
+            res.only_types();
+        }
+        if input.contains('*') {
+            res.libs();
+        }
+        res
+    }
+
     pub fn only_types(&mut self) {
         self.only_types = true;
     }
 
+    /// Searches in all the crates, including the libraries, and not only in
+    /// the workspace.
     pub fn libs(&mut self) {
         self.libs = true;
     }
@@ -172,6 +208,8 @@ pub struct CallInfo {
     pub label: String,
     pub doc: Option<Documentation>,
     pub parameters: Vec<String>,
+    /// Types of `parameters`, as written in the source.
+    pub parameter_types: Vec<String>,
     pub active_parameter: Option<usize>,
 }
 
@@ -194,13 +232,30 @@ impl AnalysisHost {
         self.db.apply_change(change)
     }
 
+    /// Sets how many parse trees and macro expansions may be kept in memory
+    /// before `maybe_collect_garbage` discards them.
+    pub fn set_retention_limits(&mut self, limits: RetentionLimits) {
+        self.db.retention_limits = limits;
+    }
+
+    /// Discards the values which were not used in the current revision, if
+    /// any of the queries is over its retention limit. This is cheap if
+    /// nothing needs to be discarded, so it can be called after every change.
     pub fn maybe_collect_garbage(&mut self) {
         self.db.maybe_collect_garbage();
     }
 
+    /// Discards all the values which are cheap to recompute. Hosts can call
+    /// this when the editor is idle.
     pub fn collect_garbage(&mut self) {
         self.db.collect_garbage();
     }
+
+    /// Uses the symbols of `cache`, saved in a previous session, for the files
+    /// whose contents didn't change since.
+    pub fn set_symbol_cache(&mut self, cache: SymbolCache) {
+        self.db.set_symbol_cache(Arc::new(cache));
+    }
 }
 
 /// Analysis is a snapshot of a world state at a moment in time. It is the main
@@ -236,16 +291,52 @@ impl Analysis {
         (host.analysis(), file_id)
     }
 
+    /// Computes name resolution for all crates in parallel. Useful to make
+    /// the first requests after loading a large workspace fast.
+    pub fn prime_def_maps(&self) -> Cancelable<()> {
+        self.with_db(|db| prime_caches::prime_def_maps(db))
+    }
+
     /// Debug info about the current state of the analysis
     pub fn status(&self) -> String {
         status::status(&*self.db)
     }
 
+    /// Def map statistics of the crates whose def maps are currently
+    /// computed, the largest ones first.
+    pub fn def_map_stats(&self) -> Vec<(CrateId, DefMapStats)> {
+        let stats = status::def_maps_stats(&*self.db);
+        stats.per_crate.into_iter().map(|(krate, stats)| (krate.crate_id(), stats)).collect()
+    }
+
+    /// Computes the def map of the crate and returns its statistics. If
+    /// `profile` is set, also returns what was recomputed to get the def map,
+    /// which helps to find out why name resolution is slow.
+    pub fn crate_def_map_stats(
+        &self,
+        crate_id: CrateId,
+        profile: bool,
+    ) -> Cancelable<Profiled<Option<DefMapStats>>> {
+        self.with_db(|db| {
+            ra_db::profile::profile_if(profile, || status::crate_def_map_stats(db, crate_id))
+        })
+    }
+
     /// Gets the text of the source file.
     pub fn file_text(&self, file_id: FileId) -> Arc<String> {
         self.db.file_text(file_id)
     }
 
+    /// Hashes the current texts of the files edited by `change`. Keep them
+    /// with the change to check for conflicts in `SourceChange::apply`.
+    pub fn content_hashes(&self, change: &SourceChange) -> ContentHashes {
+        let mut res = ContentHashes::default();
+        for file_edit in change.source_file_edits.iter() {
+            res.insert(file_edit.file_id, &self.db.file_text(file_edit.file_id));
+        }
+        res
+    }
+
     /// Gets the syntax tree of the file.
     pub fn parse(&self, file_id: FileId) -> TreeArc<SourceFile> {
         self.db.parse(file_id).clone()
@@ -269,25 +360,52 @@ impl Analysis {
         ra_ide_api_light::matching_brace(&file, position.offset)
     }
 
+    /// Checks if the position is inside of a string or char literal, or of a
+    /// comment, where editors shouldn't auto-close brackets or trigger
+    /// completion.
+    pub fn is_in_string_or_comment(&self, position: FilePosition) -> bool {
+        let file = self.db.parse(position.file_id);
+        ra_ide_api_light::is_in_string_or_comment(&file, position.offset)
+    }
+
+    /// Checks if the whole range is inside of a single literal or comment.
+    pub fn is_range_in_string_or_comment(&self, frange: FileRange) -> bool {
+        let file = self.db.parse(frange.file_id);
+        ra_ide_api_light::is_range_in_string_or_comment(&file, frange.range)
+    }
+
     /// Returns a syntax tree represented as `String`, for debug purposes.
     // FIXME: use a better name here.
     pub fn syntax_tree(&self, file_id: FileId, text_range: Option<TextRange>) -> String {
         syntax_tree::syntax_tree(&self.db, file_id, text_range)
     }
 
+    /// Returns the syntax tree of the file, or of the smallest node covering
+    /// `text_range`, rendered according to `options`.
+    pub fn syntax_tree_with_options(
+        &self,
+        file_id: FileId,
+        text_range: Option<TextRange>,
+        options: SyntaxTreeOptions,
+    ) -> String {
+        syntax_tree::syntax_tree_with_options(&self.db, file_id, text_range, options)
+    }
+
     /// Returns an edit to remove all newlines in the range, cleaning up minor
     /// stuff like trailing commas.
     pub fn join_lines(&self, frange: FileRange) -> SourceChange {
         let file = self.db.parse(frange.file_id);
+        let style = self.db.indent_style(frange.file_id);
         let file_edit = SourceFileEdit {
             file_id: frange.file_id,
-            edit: join_lines::join_lines(&file, frange.range),
+            edit: join_lines::join_lines(&file, frange.range, &style),
         };
         SourceChange {
             label: "join lines".to_string(),
             source_file_edits: vec![file_edit],
             file_system_edits: vec![],
             cursor_position: None,
+            typing: None,
         }
     }
 
@@ -295,7 +413,8 @@ impl Analysis {
     /// up minor stuff like continuing the comment.
     pub fn on_enter(&self, position: FilePosition) -> Option<SourceChange> {
         let file = self.db.parse(position.file_id);
-        let edit = ra_ide_api_light::on_enter(&file, position.offset)?;
+        let style = self.db.indent_style(position.file_id);
+        let edit = ra_ide_api_light::on_enter(&file, position.offset, &style)?;
         Some(SourceChange::from_local_edit(position.file_id, edit))
     }
 
@@ -311,21 +430,45 @@ impl Analysis {
     /// Returns an edit which should be applied when a dot ('.') is typed on a blank line, indenting the line appropriately.
     pub fn on_dot_typed(&self, position: FilePosition) -> Option<SourceChange> {
         let file = self.db.parse(position.file_id);
-        let edit = ra_ide_api_light::on_dot_typed(&file, position.offset)?;
+        let style = self.db.indent_style(position.file_id);
+        let edit = ra_ide_api_light::on_dot_typed(&file, position.offset, &style)?;
         Some(SourceChange::from_local_edit(position.file_id, edit))
     }
 
     /// Returns a tree representation of symbols in the file. Useful to draw a
     /// file outline.
     pub fn file_structure(&self, file_id: FileId) -> Vec<StructureNode> {
+        self.file_structure_in_range(file_id, None)
+    }
+
+    /// Like `file_structure`, but if `range` is given, only the symbols
+    /// intersecting it are returned. Cheaper for huge files, as the rest of
+    /// the file is not traversed.
+    pub fn file_structure_in_range(
+        &self,
+        file_id: FileId,
+        range: Option<TextRange>,
+    ) -> Vec<StructureNode> {
         let file = self.db.parse(file_id);
-        ra_ide_api_light::file_structure(&file)
+        ra_ide_api_light::file_structure_in_range(&file, range)
     }
 
     /// Returns the set of folding ranges.
     pub fn folding_ranges(&self, file_id: FileId) -> Vec<Fold> {
+        self.folding_ranges_in_range(file_id, None)
+    }
+
+    /// Like `folding_ranges`, but if `range` is given, only the folds
+    /// intersecting it are returned.
+    pub fn folding_ranges_in_range(&self, file_id: FileId, range: Option<TextRange>) -> Vec<Fold> {
         let file = self.db.parse(file_id);
-        folding_ranges::folding_ranges(&file)
+        ra_ide_api_light::folding_ranges_in_range(&file, range)
+    }
+
+    /// Collects the symbols of all files, so that the host can save them and
+    /// pass to `AnalysisHost::set_symbol_cache` in the next session.
+    pub fn symbol_cache(&self) -> Cancelable<SymbolCache> {
+        self.with_db(|db| symbol_cache::collect(db))
     }
 
     /// Fuzzy searches for a symbol.
@@ -365,11 +508,33 @@ impl Analysis {
         self.with_db(|db| hover::hover(db, position))
     }
 
+    /// Expands the macro call at the given position. Nested macro calls are
+    /// expanded as well, up to `max_depth` levels deep.
+    pub fn expand_macro(
+        &self,
+        position: FilePosition,
+        max_depth: u32,
+    ) -> Cancelable<Option<RangeInfo<ExpandedMacro>>> {
+        self.with_db(|db| expand_macro::expand_macro(db, position, max_depth))
+    }
+
     /// Computes parameter information for the given call expression.
     pub fn call_info(&self, position: FilePosition) -> Cancelable<Option<CallInfo>> {
         self.with_db(|db| call_info::call_info(db, position))
     }
 
+    /// Returns the functions which call the function at the position, with
+    /// the ranges of the calls.
+    pub fn incoming_calls(&self, position: FilePosition) -> Cancelable<Option<Vec<CallItem>>> {
+        self.with_db(|db| call_hierarchy::incoming_calls(db, position))
+    }
+
+    /// Returns the functions called by the function at the position, with the
+    /// ranges of the calls.
+    pub fn outgoing_calls(&self, position: FilePosition) -> Cancelable<Option<Vec<CallItem>>> {
+        self.with_db(|db| call_hierarchy::outgoing_calls(db, position))
+    }
+
     /// Returns a `mod name;` declaration which created the current module.
     pub fn parent_module(&self, position: FilePosition) -> Cancelable<Vec<NavigationTarget>> {
         self.with_db(|db| parent_module::parent_module(db, position))
@@ -380,6 +545,12 @@ impl Analysis {
         self.with_db(|db| parent_module::crate_for(db, file_id))
     }
 
+    /// Returns the files related to the given one through the module tree:
+    /// its parent and child modules, and the roots of the sibling crates.
+    pub fn related_files(&self, file_id: FileId) -> Cancelable<Vec<RelatedFile>> {
+        self.with_db(|db| parent_module::related_files(db, file_id))
+    }
+
     /// Returns the root file of the given crate.
     pub fn crate_root(&self, crate_id: CrateId) -> Cancelable<FileId> {
         self.with_db(|db| db.crate_graph().crate_root(crate_id))
@@ -392,7 +563,17 @@ impl Analysis {
 
     /// Computes syntax highlighting for the given file.
     pub fn highlight(&self, file_id: FileId) -> Cancelable<Vec<HighlightedRange>> {
-        self.with_db(|db| syntax_highlighting::highlight(db, file_id))
+        self.highlight_with_options(file_id, HighlightOptions::default())
+    }
+
+    /// Computes syntax highlighting for the given file, with non-default
+    /// `options`.
+    pub fn highlight_with_options(
+        &self,
+        file_id: FileId,
+        options: HighlightOptions,
+    ) -> Cancelable<Vec<HighlightedRange>> {
+        self.with_db(|db| syntax_highlighting::highlight(db, file_id, options))
     }
 
     /// Computes completions at the given position.
@@ -411,18 +592,32 @@ impl Analysis {
         self.with_db(|db| diagnostics::diagnostics(db, file_id))
     }
 
+    /// Computes the `wanted` subset of highlighting, file structure, folding
+    /// ranges and diagnostics in a single traversal of the file.
+    pub fn analyze_file(&self, file_id: FileId, wanted: FeatureSet) -> Cancelable<FileAnalysis> {
+        self.with_db(|db| file_analysis::analyze_file(db, file_id, wanted))
+    }
+
     /// Computes the type of the expression at the given position.
     pub fn type_of(&self, frange: FileRange) -> Cancelable<Option<String>> {
         self.with_db(|db| hover::type_of(db, frange))
     }
 
+    /// Like `type_of`, but also returns the type itself and the range of the
+    /// expression. Expressions inside of macro calls are supported if they
+    /// map back to the call unambiguously.
+    pub fn type_info(&self, frange: FileRange) -> Cancelable<Option<TypeInfo>> {
+        self.with_db(|db| hover::type_info(db, frange))
+    }
+
     /// Returns the edit required to rename reference at the position to the new
-    /// name.
+    /// name, or a conflict if the new name is already bound where the renamed
+    /// entity is declared or used.
     pub fn rename(
         &self,
         position: FilePosition,
         new_name: &str,
-    ) -> Cancelable<Option<SourceChange>> {
+    ) -> Cancelable<Result<Option<SourceChange>, RenameConflict>> {
         self.with_db(|db| references::rename(db, position, new_name))
     }
 
@@ -442,6 +637,7 @@ impl SourceChange {
             source_file_edits: vec![file_edit],
             file_system_edits: vec![],
             cursor_position: edit.cursor_position.map(|offset| FilePosition { offset, file_id }),
+            typing: edit.typing,
         }
     }
 }
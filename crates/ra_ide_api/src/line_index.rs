@@ -1,7 +1,12 @@
+//! Conversions between offsets and the line/column positions of LSP, which
+//! counts columns in UTF-16 code units.
+
 use crate::TextUnit;
 use rustc_hash::FxHashMap;
 use superslice::Ext;
 
+/// The start offsets of the lines of a text, together with the positions of
+/// the multibyte characters, whose UTF-8 and UTF-16 lengths differ.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LineIndex {
     pub(crate) newlines: Vec<TextUnit>,
@@ -24,6 +29,18 @@ impl Utf16Char {
     fn len(&self) -> TextUnit {
         self.end - self.start
     }
+
+    /// How many more UTF-8 bytes than UTF-16 code units the char takes.
+    pub(crate) fn col_adjust(&self) -> TextUnit {
+        utf8_utf16_diff(self.len())
+    }
+}
+
+/// Only the chars outside of the Basic Multilingual Plane take four bytes in
+/// UTF-8, and those are encoded with a surrogate pair in UTF-16.
+pub(crate) fn utf8_utf16_diff(utf8_len: TextUnit) -> TextUnit {
+    let utf16_len = if utf8_len == TextUnit::from(4) { 2 } else { 1 };
+    utf8_len - TextUnit::from(utf16_len)
 }
 
 impl LineIndex {
@@ -87,7 +104,7 @@ impl LineIndex {
             let mut correction = TextUnit::from_usize(0);
             for c in utf16_chars {
                 if col >= c.end {
-                    correction += c.len() - TextUnit::from_usize(1);
+                    correction += c.col_adjust();
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
@@ -106,7 +123,7 @@ impl LineIndex {
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             for c in utf16_chars {
                 if col >= c.start {
-                    col += c.len() - TextUnit::from_usize(1);
+                    col += c.col_adjust();
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
@@ -133,7 +150,7 @@ pub fn to_line_col(text: &str, offset: TextUnit) -> LineCol {
             res.line += 1;
             res.col_utf16 = 0;
         } else {
-            res.col_utf16 += 1;
+            res.col_utf16 += c.len_utf16() as u32;
         }
     }
     res
@@ -184,7 +201,7 @@ mod test_line_index {
                 res.line += 1;
                 res.col_utf16 = 0;
             } else {
-                res.col_utf16 += 1;
+                res.col_utf16 += c.len_utf16() as u32;
             }
         }
         res
@@ -199,6 +216,7 @@ mod test_line_index {
 
             assert_eq!(actual, expected);
         }
+
     }
 }
 
@@ -247,6 +265,13 @@ const C: char = 'メ';
         assert_eq!(col_index.utf16_to_utf8_col(1, 19), TextUnit::from(21));
     }
 
+    #[test]
+    fn test_surrogate_pair() {
+        let col_index = LineIndex::new("'😀'x");
+        assert_eq!(col_index.line_col(6.into()), LineCol { line: 0, col_utf16: 4 });
+        assert_eq!(col_index.offset(LineCol { line: 0, col_utf16: 4 }), 6.into());
+    }
+
     #[test]
     fn test_string() {
         let col_index = LineIndex::new(
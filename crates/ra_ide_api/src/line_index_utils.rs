@@ -1,6 +1,9 @@
 use ra_text_edit::{AtomTextEdit, TextEdit};
 use ra_syntax::{TextUnit, TextRange};
-use crate::{LineIndex, LineCol, line_index::Utf16Char};
+use crate::{
+    LineIndex, LineCol,
+    line_index::{self, Utf16Char},
+};
 
 #[derive(Debug, Clone)]
 enum Step {
@@ -215,7 +218,7 @@ impl RunningLineCol {
     }
 
     fn adjust_col(&mut self, range: &TextRange) {
-        self.col_adjust += range.len() - TextUnit::from(1);
+        self.col_adjust += line_index::utf8_utf16_diff(range.len());
     }
 }
 
@@ -293,7 +296,6 @@ pub fn translate_offset_with_edit(
 mod test {
     use super::*;
     use proptest::{prelude::*, proptest};
-    use crate::line_index;
     use ra_text_edit::test_utils::{arb_offset, arb_text_with_edit};
     use ra_text_edit::TextEdit;
 
@@ -4,5 +4,6 @@ test_utils::marks!(
     goto_definition_works_for_fields
     goto_definition_works_for_named_fields
     call_info_bad_offset
+    call_info_approximate_callee
     dont_complete_current_use
 );
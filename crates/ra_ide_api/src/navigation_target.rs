@@ -3,7 +3,7 @@ use ra_syntax::{
     SyntaxNode, SyntaxNodePtr, AstNode, SmolStr, TextRange, ast,
     SyntaxKind::{self, NAME},
 };
-use hir::{ModuleSource, FieldSource, Name, ImplItem};
+use hir::{ModuleSource, FieldSource, Name, ImplItem, HirFileId};
 
 use crate::{FileSymbol, db::RootDatabase};
 
@@ -112,7 +112,7 @@ impl NavigationTarget {
 
     pub(crate) fn from_function(db: &RootDatabase, func: hir::Function) -> NavigationTarget {
         let (file_id, fn_def) = func.source(db);
-        NavigationTarget::from_named(file_id.original_file(db), &*fn_def)
+        NavigationTarget::from_hir_named(db, file_id, &*fn_def)
     }
 
     pub(crate) fn from_field(db: &RootDatabase, field: hir::StructField) -> NavigationTarget {
@@ -130,11 +130,11 @@ impl NavigationTarget {
         match adt_def {
             hir::AdtDef::Struct(s) => {
                 let (file_id, node) = s.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::AdtDef::Enum(s) => {
                 let (file_id, node) = s.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
         }
     }
@@ -145,31 +145,31 @@ impl NavigationTarget {
             hir::ModuleDef::Function(func) => NavigationTarget::from_function(db, func),
             hir::ModuleDef::Struct(s) => {
                 let (file_id, node) = s.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::ModuleDef::Const(s) => {
                 let (file_id, node) = s.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::ModuleDef::Static(s) => {
                 let (file_id, node) = s.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::ModuleDef::Enum(e) => {
                 let (file_id, node) = e.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::ModuleDef::EnumVariant(var) => {
                 let (file_id, node) = var.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::ModuleDef::Trait(e) => {
                 let (file_id, node) = e.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             hir::ModuleDef::TypeAlias(e) => {
                 let (file_id, node) = e.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
         }
     }
@@ -192,11 +192,11 @@ impl NavigationTarget {
             ImplItem::Method(f) => NavigationTarget::from_function(db, f),
             ImplItem::Const(c) => {
                 let (file_id, node) = c.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
             ImplItem::TypeAlias(a) => {
                 let (file_id, node) = a.source(db);
-                NavigationTarget::from_named(file_id.original_file(db), &*node)
+                NavigationTarget::from_hir_named(db, file_id, &*node)
             }
         }
     }
@@ -232,6 +232,27 @@ impl NavigationTarget {
         NavigationTarget::from_syntax(file_id, name, focus_range, node.syntax())
     }
 
+    /// Like `from_named`, but maps the ranges of nodes produced by macros to
+    /// the original source file.
+    fn from_hir_named(
+        db: &RootDatabase,
+        file_id: HirFileId,
+        node: &impl ast::NameOwner,
+    ) -> NavigationTarget {
+        let name = node.name().map(|it| it.text().clone()).unwrap_or_default();
+        let full_range = file_id.original_range(db, node.syntax().range());
+        let focus_range =
+            node.name().map(|it| file_id.original_range(db, it.syntax().range()).range);
+        NavigationTarget {
+            file_id: full_range.file_id,
+            name,
+            kind: node.syntax().kind(),
+            full_range: full_range.range,
+            focus_range,
+            container_name: None,
+        }
+    }
+
     fn from_syntax(
         file_id: FileId,
         name: SmolStr,
@@ -1,4 +1,4 @@
-use ra_db::{FilePosition, FileId, CrateId};
+use ra_db::{FilePosition, FileId, CrateId, SourceDatabase};
 
 use crate::{NavigationTarget, db::RootDatabase};
 
@@ -26,9 +26,73 @@ pub(crate) fn crate_for(db: &RootDatabase, file_id: FileId) -> Vec<CrateId> {
     vec![krate.crate_id()]
 }
 
+/// How a file returned by `related_files` is related to the original one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelatedFileKind {
+    /// The file which declares the module of the original file.
+    ParentModule,
+    /// A file of a submodule declared in the original file.
+    ChildModule,
+    /// The root of another crate in the same source root, like `main.rs`
+    /// for `lib.rs`.
+    SiblingCrateRoot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedFile {
+    pub file_id: FileId,
+    pub kind: RelatedFileKind,
+}
+
+/// Lists the files related to `file_id` through the module tree, for
+/// switching to an "alternate file".
+pub(crate) fn related_files(db: &RootDatabase, file_id: FileId) -> Vec<RelatedFile> {
+    let mut res = Vec::new();
+    let mut push = |file_id, kind| {
+        if !res.iter().any(|it: &RelatedFile| it.file_id == file_id) {
+            res.push(RelatedFile { file_id, kind })
+        }
+    };
+
+    if let Some(module) = hir::source_binder::module_from_file_id(db, file_id) {
+        if let Some(parent) = module.parent(db) {
+            push(parent.definition_source(db).0.original_file(db), RelatedFileKind::ParentModule);
+        }
+        // Inline modules may declare submodules in files of their own.
+        let mut stack = vec![module];
+        while let Some(module) = stack.pop() {
+            for child in module.children(db) {
+                let child_file = child.definition_source(db).0.original_file(db);
+                if child_file == file_id {
+                    stack.push(child);
+                } else {
+                    push(child_file, RelatedFileKind::ChildModule);
+                }
+            }
+        }
+    }
+
+    let crate_graph = db.crate_graph();
+    let is_crate_root = crate_graph.iter().any(|it| crate_graph.crate_root(it) == file_id);
+    if is_crate_root {
+        let source_root = db.file_source_root(file_id);
+        for &krate in db.source_root_crates(source_root).iter() {
+            let root = crate_graph.crate_root(krate);
+            if root != file_id {
+                push(root, RelatedFileKind::SiblingCrateRoot);
+            }
+        }
+    }
+    res.retain(|it| it.file_id != file_id);
+    res
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::mock_analysis::analysis_and_position;
+    use crate::{
+        mock_analysis::{analysis_and_position, MockAnalysis},
+        RelatedFile, RelatedFileKind,
+    };
 
     #[test]
     fn test_resolve_parent_module() {
@@ -59,4 +123,44 @@ mod tests {
         let nav = analysis.parent_module(pos).unwrap().pop().unwrap();
         nav.assert_match("baz MODULE FileId(1) [32; 44)");
     }
+
+    #[test]
+    fn test_related_files() {
+        let mock = MockAnalysis::with_files(
+            "
+            //- /lib.rs
+            mod foo;
+            //- /main.rs
+            fn main() {}
+            //- /foo.rs
+            mod bar { mod baz; }
+            //- /foo/bar/baz.rs
+            // empty
+            ",
+        );
+        let lib = mock.id_of("/lib.rs");
+        let main = mock.id_of("/main.rs");
+        let foo = mock.id_of("/foo.rs");
+        let baz = mock.id_of("/foo/bar/baz.rs");
+        let analysis = mock.analysis();
+
+        assert_eq!(
+            analysis.related_files(lib).unwrap(),
+            vec![
+                RelatedFile { file_id: foo, kind: RelatedFileKind::ChildModule },
+                RelatedFile { file_id: main, kind: RelatedFileKind::SiblingCrateRoot },
+            ]
+        );
+        assert_eq!(
+            analysis.related_files(foo).unwrap(),
+            vec![
+                RelatedFile { file_id: lib, kind: RelatedFileKind::ParentModule },
+                RelatedFile { file_id: baz, kind: RelatedFileKind::ChildModule },
+            ]
+        );
+        assert_eq!(
+            analysis.related_files(baz).unwrap(),
+            vec![RelatedFile { file_id: foo, kind: RelatedFileKind::ParentModule }]
+        );
+    }
 }
@@ -0,0 +1,29 @@
+//! Eagerly computes expensive queries, so that the first requests after
+//! loading a workspace are fast.
+
+use rayon::prelude::*;
+use ra_db::salsa::{self, ParallelDatabase};
+use hir::db::DefDatabase;
+
+use crate::db::RootDatabase;
+
+/// Computes `crate_def_map` for all crates, using all the cores. Crates are
+/// processed in dependency order: def map of a crate needs the def maps of
+/// its dependencies, so we process crates wave by wave, and crates inside a
+/// wave in parallel.
+pub(crate) fn prime_def_maps(db: &RootDatabase) {
+    /// Need to wrap Snapshot to provide `Clone` impl for `for_each_with`
+    struct Snap(salsa::Snapshot<RootDatabase>);
+    impl Clone for Snap {
+        fn clone(&self) -> Snap {
+            Snap(self.0.snapshot())
+        }
+    }
+
+    for wave in hir::Crate::dependency_waves(db) {
+        let snap = Snap(db.snapshot());
+        wave.par_iter().for_each_with(snap, |db, &krate| {
+            db.0.crate_def_map(krate);
+        });
+    }
+}
@@ -1,10 +1,15 @@
+use std::fmt;
+
 use relative_path::{RelativePath, RelativePathBuf};
-use hir::{ModuleSource, source_binder};
+use hir::{ModuleDef, ModuleSource, Namespace, source_binder, reference_search};
 use ra_db::{SourceDatabase};
 use ra_syntax::{
     AstNode, SyntaxNode, SourceFile,
-    ast,
-    algo::find_node_at_offset,
+    SyntaxKind::{
+        ENUM_DEF, IDENT, IMPL_BLOCK, ITEM_LIST, MODULE, NAME, STRUCT_DEF, TRAIT_DEF, TYPE_ALIAS_DEF,
+    },
+    ast::{self, NameOwner},
+    algo::{find_node_at_offset, find_covering_node},
 };
 
 use crate::{
@@ -61,16 +66,20 @@ pub(crate) fn find_all_refs(
     position: FilePosition,
 ) -> Option<ReferenceSearchResult> {
     let file = db.parse(position.file_id);
-    let (binding, descr) = find_binding(db, &file, position)?;
-    let declaration = NavigationTarget::from_bind_pat(position.file_id, binding);
-
-    let references = descr
-        .scopes(db)
-        .find_all_refs(binding)
-        .into_iter()
-        .map(move |ref_desc| FileRange { file_id: position.file_id, range: ref_desc.range })
-        .collect::<Vec<_>>();
+    if let Some((binding, descr)) = find_binding(db, &file, position) {
+        let declaration = NavigationTarget::from_bind_pat(position.file_id, binding);
+        let references = descr
+            .scopes(db)
+            .find_all_refs(binding)
+            .into_iter()
+            .map(move |ref_desc| FileRange { file_id: position.file_id, range: ref_desc.range })
+            .collect::<Vec<_>>();
+        return Some(ReferenceSearchResult { declaration, references });
+    }
 
+    let def = find_def(db, &file, position)?;
+    let declaration = NavigationTarget::from_def(db, def);
+    let references = reference_search::find_references(db, def);
     return Some(ReferenceSearchResult { declaration, references });
 
     fn find_binding<'a>(
@@ -95,19 +104,66 @@ pub(crate) fn find_all_refs(
     }
 }
 
+pub(crate) fn find_def(
+    db: &RootDatabase,
+    source_file: &SourceFile,
+    position: FilePosition,
+) -> Option<ModuleDef> {
+    let syntax = source_file.syntax();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(syntax, position.offset) {
+        return reference_search::resolve_name_ref(db, position.file_id, name_ref);
+    }
+    let name = find_node_at_offset::<ast::Name>(syntax, position.offset)?;
+    let item = name.syntax().parent()?;
+    let module = source_binder::module_from_child_node(db, position.file_id, item)?;
+    if let Some(fn_def) = ast::FnDef::cast(item) {
+        return Some(source_binder::function_from_module(db, module, fn_def).into());
+    }
+    if let Some(struct_def) = ast::StructDef::cast(item) {
+        return Some(source_binder::struct_from_module(db, module, struct_def).into());
+    }
+    if let Some(enum_def) = ast::EnumDef::cast(item) {
+        return Some(source_binder::enum_from_module(db, module, enum_def).into());
+    }
+    if let Some(trait_def) = ast::TraitDef::cast(item) {
+        return Some(source_binder::trait_from_module(db, module, trait_def).into());
+    }
+    None
+}
+
+/// The new name of a rename is already bound at one of the renamed locations,
+/// so the rename would change the meaning of the code.
+#[derive(Debug, Clone)]
+pub struct RenameConflict {
+    pub new_name: String,
+    /// The location where `new_name` is already bound.
+    pub range: FileRange,
+}
+
+impl fmt::Display for RenameConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` is already defined in this scope", self.new_name)
+    }
+}
+
 pub(crate) fn rename(
     db: &RootDatabase,
     position: FilePosition,
     new_name: &str,
-) -> Option<SourceChange> {
+) -> Result<Option<SourceChange>, RenameConflict> {
     let source_file = db.parse(position.file_id);
     let syntax = source_file.syntax();
 
-    if let Some((ast_name, ast_module)) = find_name_and_module_at_offset(syntax, position) {
-        rename_mod(db, ast_name, ast_module, position, new_name)
-    } else {
-        rename_reference(db, position, new_name)
+    if let Some((_, ast_module)) = find_name_and_module_at_offset(syntax, position) {
+        return rename_mod(db, position.file_id, ast_module, new_name);
     }
+    // Renaming a module at one of its uses renames its declaration too.
+    if let Some(ModuleDef::Module(module)) = find_def(db, &source_file, position) {
+        if let Some((file_id, ast_module)) = module.declaration_source(db) {
+            return rename_mod(db, file_id.original_file(db), &ast_module, new_name);
+        }
+    }
+    rename_reference(db, position, new_name)
 }
 
 fn find_name_and_module_at_offset(
@@ -140,20 +196,24 @@ fn source_edit_from_fileid_range(
 
 fn rename_mod(
     db: &RootDatabase,
-    ast_name: &ast::Name,
+    file_id: FileId,
     ast_module: &ast::Module,
-    position: FilePosition,
     new_name: &str,
-) -> Option<SourceChange> {
+) -> Result<Option<SourceChange>, RenameConflict> {
+    let ast_name = match ast_module.name() {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let declaration = FileRange { file_id, range: ast_name.syntax().range() };
+    let mut references = Vec::new();
     let mut source_file_edits = Vec::new();
     let mut file_system_edits = Vec::new();
-    if let Some(module) = source_binder::module_from_declaration(db, position.file_id, &ast_module)
-    {
-        let (file_id, module_source) = module.definition_source(db);
-        let file_id = file_id.as_original_file();
+    if let Some(module) = source_binder::module_from_declaration(db, file_id, &ast_module) {
+        let (mod_file_id, module_source) = module.definition_source(db);
+        let mod_file_id = mod_file_id.as_original_file();
         match module_source {
             ModuleSource::SourceFile(..) => {
-                let mod_path: RelativePathBuf = db.file_relative_path(file_id);
+                let mod_path: RelativePathBuf = db.file_relative_path(mod_file_id);
                 // mod is defined in path/to/dir/mod.rs
                 let dst_path = if mod_path.file_stem() == Some("mod") {
                     mod_path
@@ -166,8 +226,8 @@ fn rename_mod(
                 };
                 if let Some(path) = dst_path {
                     let move_file = FileSystemEdit::MoveFile {
-                        src: file_id,
-                        dst_source_root: db.file_source_root(position.file_id),
+                        src: mod_file_id,
+                        dst_source_root: db.file_source_root(file_id),
                         dst_path: path,
                     };
                     file_system_edits.push(move_file);
@@ -175,32 +235,42 @@ fn rename_mod(
             }
             ModuleSource::Module(..) => {}
         }
+        references = reference_search::find_references(db, ModuleDef::Module(module));
     }
 
-    let edit = SourceFileEdit {
-        file_id: position.file_id,
-        edit: {
-            let mut builder = ra_text_edit::TextEditBuilder::default();
-            builder.replace(ast_name.syntax().range(), new_name.into());
-            builder.finish()
-        },
-    };
-    source_file_edits.push(edit);
+    check_rename_conflicts(db, declaration, &references, Namespace::Types, new_name)?;
+    source_file_edits.push(source_edit_from_fileid_range(file_id, declaration.range, new_name));
+    source_file_edits.extend(
+        references
+            .into_iter()
+            .map(|range| source_edit_from_fileid_range(range.file_id, range.range, new_name)),
+    );
 
-    Some(SourceChange {
+    Ok(Some(SourceChange {
         label: "rename".to_string(),
         source_file_edits,
         file_system_edits,
         cursor_position: None,
-    })
+        typing: None,
+    }))
 }
 
 fn rename_reference(
     db: &RootDatabase,
     position: FilePosition,
     new_name: &str,
-) -> Option<SourceChange> {
-    let refs = find_all_refs(db, position)?;
+) -> Result<Option<SourceChange>, RenameConflict> {
+    let refs = match find_all_refs(db, position) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let declaration =
+        FileRange { file_id: refs.declaration().file_id(), range: refs.declaration().range() };
+    let namespace = match refs.declaration().kind() {
+        STRUCT_DEF | ENUM_DEF | TRAIT_DEF | TYPE_ALIAS_DEF | MODULE => Namespace::Types,
+        _ => Namespace::Values,
+    };
+    check_rename_conflicts(db, declaration, refs.references(), namespace, new_name)?;
 
     let edit = refs
         .into_iter()
@@ -208,15 +278,63 @@ fn rename_reference(
         .collect::<Vec<_>>();
 
     if edit.is_empty() {
-        return None;
+        return Ok(None);
     }
 
-    Some(SourceChange {
+    Ok(Some(SourceChange {
         label: "rename".to_string(),
         source_file_edits: edit,
         file_system_edits: Vec::new(),
         cursor_position: None,
-    })
+        typing: None,
+    }))
+}
+
+/// Checks that `new_name` is not visible in `namespace` where the renamed
+/// entity is declared, nor at any of its references.
+fn check_rename_conflicts(
+    db: &RootDatabase,
+    declaration: FileRange,
+    references: &[FileRange],
+    namespace: Namespace,
+    new_name: &str,
+) -> Result<(), RenameConflict> {
+    let locations =
+        std::iter::once((declaration, true)).chain(references.iter().map(|&range| (range, false)));
+    for (location, is_declaration) in locations {
+        let source_file = db.parse(location.file_id);
+        let mut node = find_covering_node(source_file.syntax(), location.range);
+        if is_declaration {
+            // Names visible inside of the declared item itself don't matter:
+            // the resolver has to start from the item's container.
+            let item = match node.ancestors().find(|it| it.kind() != NAME && it.kind() != IDENT) {
+                Some(it) => it,
+                None => continue,
+            };
+            let container = match item.parent() {
+                Some(it) => it,
+                None => continue,
+            };
+            // Associated items live in the namespace of their impl or trait.
+            let is_assoc_item = container.kind() == ITEM_LIST
+                && container
+                    .parent()
+                    .map_or(false, |it| it.kind() == IMPL_BLOCK || it.kind() == TRAIT_DEF);
+            if is_assoc_item {
+                continue;
+            }
+            node = container;
+        }
+        let resolver = source_binder::resolver_for_node(db, location.file_id, node);
+        let is_bound = resolver
+            .all_names(db)
+            .into_iter()
+            .any(|(name, res)| name.to_string() == new_name && res.get(namespace).is_some());
+        if is_bound {
+            return Err(RenameConflict { new_name: new_name.to_string(), range: location });
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -320,7 +438,7 @@ mod tests {
             ",
         );
         let new_name = "foo2";
-        let source_change = analysis.rename(position, new_name).unwrap();
+        let source_change = analysis.rename(position, new_name).unwrap().unwrap();
         assert_debug_snapshot_matches!("rename_mod", &source_change);
     }
 
@@ -335,13 +453,99 @@ mod tests {
             ",
         );
         let new_name = "foo2";
-        let source_change = analysis.rename(position, new_name).unwrap();
+        let source_change = analysis.rename(position, new_name).unwrap().unwrap();
         assert_debug_snapshot_matches!("rename_mod_in_dir", &source_change);
     }
 
+    #[test]
+    fn test_rename_fn() {
+        test_rename(
+            r#"
+    fn foo<|>() {}
+    fn main() {
+        foo();
+    }"#,
+            "bar",
+            r#"
+    fn bar() {}
+    fn main() {
+        bar();
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_struct() {
+        test_rename(
+            r#"
+    struct Foo;
+    fn main() {
+        let _: Foo<|> = Foo;
+    }"#,
+            "Bar",
+            r#"
+    struct Bar;
+    fn main() {
+        let _: Bar = Bar;
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_mod_updates_paths() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            use crate::foo::S;
+            fn main() {
+                foo<|>::bar();
+            }
+
+            //- /foo.rs
+            pub struct S;
+            pub fn bar() {}
+            ",
+        );
+        let source_change = analysis.rename(position, "baz").unwrap().unwrap().unwrap();
+        assert_eq!(source_change.file_system_edits.len(), 1);
+        let mut text_edit_builder = ra_text_edit::TextEditBuilder::default();
+        for edit in source_change.source_file_edits {
+            assert_eq!(edit.file_id, position.file_id);
+            for atom in edit.edit.as_atoms() {
+                text_edit_builder.replace(atom.delete, atom.insert.clone());
+            }
+        }
+        let result = text_edit_builder.finish().apply(&*analysis.file_text(position.file_id));
+        let expected = analysis.file_text(position.file_id).replace("foo", "baz");
+        assert_eq_text!(&expected, &*result);
+    }
+
+    #[test]
+    fn test_rename_conflicts() {
+        let (analysis, position) = single_file_with_position(
+            r#"
+    fn foo() {}
+    fn bar<|>() {}
+    "#,
+        );
+        assert!(analysis.rename(position, "foo").unwrap().is_err());
+
+        let (analysis, position) = single_file_with_position(
+            r#"
+    fn main() {
+        let x = 1;
+        let y<|> = 2;
+        x + y;
+    }"#,
+        );
+        assert!(analysis.rename(position, "x").unwrap().is_err());
+        assert!(analysis.rename(position, "z").unwrap().is_ok());
+    }
+
     fn test_rename(text: &str, new_name: &str, expected: &str) {
         let (analysis, position) = single_file_with_position(text);
-        let source_change = analysis.rename(position, new_name).unwrap();
+        let source_change = analysis.rename(position, new_name).unwrap().unwrap();
         let mut text_edit_builder = ra_text_edit::TextEditBuilder::default();
         let mut file_id: Option<FileId> = None;
         if let Some(change) = source_change {
@@ -13,11 +13,13 @@ pub struct Runnable {
     pub kind: RunnableKind,
 }
 
+/// For tests and benchmarks, `path` is the full path of the function, like
+/// `tests::test_foo`, suitable for `cargo test -- --exact`.
 #[derive(Debug)]
 pub enum RunnableKind {
-    Test { name: String },
+    Test { path: String },
     TestMod { path: String },
-    Bench { name: String },
+    Bench { path: String },
     Bin,
 }
 
@@ -28,7 +30,7 @@ pub(crate) fn runnables(db: &RootDatabase, file_id: FileId) -> Vec<Runnable> {
 
 fn runnable(db: &RootDatabase, file_id: FileId, item: &SyntaxNode) -> Option<Runnable> {
     if let Some(fn_def) = ast::FnDef::cast(item) {
-        runnable_fn(fn_def)
+        runnable_fn(db, file_id, fn_def)
     } else if let Some(m) = ast::Module::cast(item) {
         runnable_mod(db, file_id, m)
     } else {
@@ -36,20 +38,31 @@ fn runnable(db: &RootDatabase, file_id: FileId, item: &SyntaxNode) -> Option<Run
     }
 }
 
-fn runnable_fn(fn_def: &ast::FnDef) -> Option<Runnable> {
+fn runnable_fn(db: &RootDatabase, file_id: FileId, fn_def: &ast::FnDef) -> Option<Runnable> {
     let name = fn_def.name()?.text();
     let kind = if name == "main" {
         RunnableKind::Bin
     } else if fn_def.has_atom_attr("test") {
-        RunnableKind::Test { name: name.to_string() }
+        RunnableKind::Test { path: fn_path(db, file_id, fn_def, name) }
     } else if fn_def.has_atom_attr("bench") {
-        RunnableKind::Bench { name: name.to_string() }
+        RunnableKind::Bench { path: fn_path(db, file_id, fn_def, name) }
     } else {
         return None;
     };
     Some(Runnable { range: fn_def.syntax().range(), kind })
 }
 
+/// The path of the function from the crate root, like `foo::tests::test_bar`.
+/// If the file is not a part of any crate, this is just the name.
+fn fn_path(db: &RootDatabase, file_id: FileId, fn_def: &ast::FnDef, name: &str) -> String {
+    let module = hir::source_binder::module_from_child_node(db, file_id, fn_def.syntax());
+    let module_path = match module {
+        Some(module) => module.path_to_root(db).into_iter().rev().filter_map(|it| it.name(db)),
+        None => return name.to_string(),
+    };
+    module_path.map(|it| it.to_string()).chain(Some(name.to_string())).join("::")
+}
+
 fn runnable_mod(db: &RootDatabase, file_id: FileId, module: &ast::Module) -> Option<Runnable> {
     let has_test_function = module
         .item_list()?
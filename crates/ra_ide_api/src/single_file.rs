@@ -0,0 +1,125 @@
+//! Features which need only the text of a single file, packaged behind one
+//! object. Handy for tools which don't have (or don't need) a workspace, like
+//! formatters, bots or web demos.
+
+use std::cell::Cell;
+
+use ra_syntax::{AstNode, SourceFile, TextRange, TextUnit, TreeArc};
+use ra_text_edit::TextEdit;
+
+use crate::{
+    Diagnostic, Fold, HighlightedRange, IndentStyle, LocalEdit, StructureNode, diagnostics,
+    join_lines,
+};
+
+/// Analysis of a single, standalone file. Unlike `Analysis`, this doesn't
+/// know anything about other files and crates, so only syntax-based
+/// features are available.
+#[derive(Debug)]
+pub struct SingleFileAnalysis {
+    file: TreeArc<SourceFile>,
+    /// Detected on first use after a change.
+    indent_style: Cell<Option<IndentStyle>>,
+}
+
+impl SingleFileAnalysis {
+    pub fn new(text: &str) -> SingleFileAnalysis {
+        SingleFileAnalysis { file: SourceFile::parse(text), indent_style: Cell::new(None) }
+    }
+
+    /// Applies an edit to the file, reparsing incrementally when possible.
+    pub fn change(&mut self, edit: &TextEdit) {
+        // Atoms refer to offsets in the original text, so applying them back
+        // to front keeps the offsets of the remaining ones valid.
+        for atom in edit.as_atoms().iter().rev() {
+            self.file = self.file.reparse(atom);
+        }
+        self.indent_style.set(None);
+    }
+
+    pub fn text(&self) -> String {
+        self.file.syntax().text().to_string()
+    }
+
+    pub fn file(&self) -> &SourceFile {
+        &self.file
+    }
+
+    pub fn highlight(&self) -> Vec<HighlightedRange> {
+        ra_ide_api_light::highlight(self.file.syntax())
+    }
+
+    pub fn file_structure(&self) -> Vec<StructureNode> {
+        ra_ide_api_light::file_structure(&self.file)
+    }
+
+    pub fn folding_ranges(&self) -> Vec<Fold> {
+        ra_ide_api_light::folding_ranges(&self.file)
+    }
+
+    pub fn matching_brace(&self, offset: TextUnit) -> Option<TextUnit> {
+        ra_ide_api_light::matching_brace(&self.file, offset)
+    }
+
+    pub fn join_lines(&self, range: TextRange) -> TextEdit {
+        join_lines::join_lines(&self.file, range, &self.indent_style())
+    }
+
+    pub fn on_enter(&self, offset: TextUnit) -> Option<LocalEdit> {
+        ra_ide_api_light::on_enter(&self.file, offset, &self.indent_style())
+    }
+
+    pub fn on_eq_typed(&self, offset: TextUnit) -> Option<LocalEdit> {
+        ra_ide_api_light::on_eq_typed(&self.file, offset)
+    }
+
+    pub fn on_dot_typed(&self, offset: TextUnit) -> Option<LocalEdit> {
+        ra_ide_api_light::on_dot_typed(&self.file, offset, &self.indent_style())
+    }
+
+    fn indent_style(&self) -> IndentStyle {
+        if let Some(style) = self.indent_style.get() {
+            return style;
+        }
+        let style = IndentStyle::detect(&self.text());
+        self.indent_style.set(Some(style));
+        style
+    }
+
+    /// Syntax errors in the file. Semantic diagnostics need a workspace and
+    /// are not available here.
+    pub fn syntax_diagnostics(&self) -> Vec<Diagnostic> {
+        let mut res = Vec::new();
+        diagnostics::syntax_errors(&mut res, &self.file);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_text_edit::TextEditBuilder;
+
+    use super::*;
+
+    #[test]
+    fn single_file_analysis_tracks_changes() {
+        let mut analysis = SingleFileAnalysis::new("fn foo() { 1 }\nfn bar() {}\n");
+        assert!(analysis.syntax_diagnostics().is_empty());
+        assert_eq!(analysis.file_structure().len(), 2);
+
+        let mut edit = TextEditBuilder::default();
+        edit.replace(TextRange::from_to(11.into(), 12.into()), "\"hello\"".to_string());
+        edit.insert(27.into(), "struct S;\n".to_string());
+        analysis.change(&edit.finish());
+
+        assert_eq!(analysis.text(), "fn foo() { \"hello\" }\nfn bar() {}\nstruct S;\n");
+        assert!(analysis.highlight().iter().any(|it| it.tag == "string"));
+        assert_eq!(analysis.file_structure().len(), 3);
+    }
+
+    #[test]
+    fn single_file_analysis_reports_syntax_errors() {
+        let analysis = SingleFileAnalysis::new("fn foo( {}");
+        assert!(!analysis.syntax_diagnostics().is_empty());
+    }
+}
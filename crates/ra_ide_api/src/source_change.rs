@@ -0,0 +1,174 @@
+//! Applies `SourceChange`s to file texts held by the host.
+//!
+//! A `SourceChange` is computed for a particular snapshot of the files, but
+//! the host usually applies it later, when the user might have already edited
+//! some of them. To detect this, the host remembers the `ContentHashes` of the
+//! snapshot together with the change, and the edits of a file are applied only
+//! if the current text of the file still has the same hash.
+
+use std::hash::Hasher;
+
+use rustc_hash::{FxHashMap, FxHasher};
+
+use crate::{FileId, SourceChange};
+
+/// A hash of a file's text. It is deterministic, but may differ between
+/// platforms, so it should not be persisted.
+pub fn content_hash(text: &str) -> u64 {
+    let mut hasher = FxHasher::default();
+    hasher.write(text.as_bytes());
+    hasher.finish()
+}
+
+/// Hashes of the texts the edits of a `SourceChange` were computed for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentHashes {
+    hashes: FxHashMap<FileId, u64>,
+}
+
+impl ContentHashes {
+    pub fn insert(&mut self, file_id: FileId, text: &str) {
+        self.hashes.insert(file_id, content_hash(text));
+    }
+
+    pub fn get(&self, file_id: FileId) -> Option<u64> {
+        self.hashes.get(&file_id).cloned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditConflict {
+    pub file_id: FileId,
+    pub kind: EditConflictKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditConflictKind {
+    /// The edited file is not among the given texts.
+    MissingFile,
+    /// The file was changed after the edit had been computed.
+    StaleContent,
+    /// The edit does not fit the text: it is out of bounds or splits a
+    /// character.
+    InvalidRange,
+}
+
+impl SourceChange {
+    /// Applies the source file edits of the change to `texts`. Edits of a file
+    /// are checked against its hash in `base`, if there is one. Several edits
+    /// of the same file are applied one after another.
+    ///
+    /// Either all edits are applied, or, if there are any conflicts, `texts`
+    /// are left untouched and all conflicts are returned. File system edits
+    /// are not applied: they are up to the host.
+    pub fn apply(
+        &self,
+        texts: &mut FxHashMap<FileId, String>,
+        base: &ContentHashes,
+    ) -> Result<(), Vec<EditConflict>> {
+        let mut new_texts: FxHashMap<FileId, String> = FxHashMap::default();
+        let mut conflicts = Vec::new();
+        for file_edit in self.source_file_edits.iter() {
+            let file_id = file_edit.file_id;
+            if conflicts.iter().any(|it: &EditConflict| it.file_id == file_id) {
+                continue;
+            }
+            let text = match new_texts.get(&file_id).or_else(|| texts.get(&file_id)) {
+                Some(it) => it,
+                None => {
+                    conflicts.push(EditConflict { file_id, kind: EditConflictKind::MissingFile });
+                    continue;
+                }
+            };
+            // Only the original text is checked: later edits of the same file
+            // apply on top of the earlier ones.
+            let is_stale = !new_texts.contains_key(&file_id)
+                && base.get(file_id).map_or(false, |hash| hash != content_hash(text));
+            if is_stale {
+                conflicts.push(EditConflict { file_id, kind: EditConflictKind::StaleContent });
+                continue;
+            }
+            let fits = file_edit.edit.as_atoms().iter().all(|atom| {
+                let (start, end) = (atom.delete.start().to_usize(), atom.delete.end().to_usize());
+                end <= text.len() && text.is_char_boundary(start) && text.is_char_boundary(end)
+            });
+            if !fits {
+                conflicts.push(EditConflict { file_id, kind: EditConflictKind::InvalidRange });
+                continue;
+            }
+            let new_text = file_edit.edit.apply(text);
+            new_texts.insert(file_id, new_text);
+        }
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        texts.extend(new_texts);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::{TextRange, TextUnit};
+    use ra_text_edit::TextEditBuilder;
+
+    use super::*;
+    use crate::SourceFileEdit;
+
+    fn insert(file_id: u32, offset: u32, text: &str) -> SourceFileEdit {
+        let mut edit = TextEditBuilder::default();
+        edit.insert(TextUnit::from(offset), text.to_string());
+        SourceFileEdit { file_id: FileId(file_id), edit: edit.finish() }
+    }
+
+    fn change(source_file_edits: Vec<SourceFileEdit>) -> SourceChange {
+        SourceChange {
+            label: "test".to_string(),
+            source_file_edits,
+            file_system_edits: vec![],
+            cursor_position: None,
+            typing: None,
+        }
+    }
+
+    #[test]
+    fn applies_edits_of_several_files() {
+        let mut texts = FxHashMap::default();
+        texts.insert(FileId(1), "fn foo() {}".to_string());
+        texts.insert(FileId(2), "foo();".to_string());
+        let mut base = ContentHashes::default();
+        base.insert(FileId(1), "fn foo() {}");
+
+        let change = change(vec![insert(1, 6, "_bar"), insert(2, 3, "_bar"), insert(2, 0, "x")]);
+        change.apply(&mut texts, &base).unwrap();
+        assert_eq!(texts[&FileId(1)], "fn foo_bar() {}");
+        assert_eq!(texts[&FileId(2)], "xfoo_bar();");
+    }
+
+    #[test]
+    fn reports_conflicts_without_applying_anything() {
+        let mut texts = FxHashMap::default();
+        texts.insert(FileId(1), "fn foo() {}".to_string());
+        texts.insert(FileId(2), "foo();".to_string());
+        let mut base = ContentHashes::default();
+        base.insert(FileId(1), "fn foo() { 92 }");
+
+        let mut delete = TextEditBuilder::default();
+        delete.delete(TextRange::from_to(0.into(), 100.into()));
+        let delete = SourceFileEdit { file_id: FileId(2), edit: delete.finish() };
+        let change = change(vec![insert(1, 6, "_bar"), delete, insert(3, 0, "")]);
+
+        let conflicts = change.apply(&mut texts, &base).unwrap_err();
+        let kinds = conflicts.iter().map(|it| (it.file_id, it.kind)).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (FileId(1), EditConflictKind::StaleContent),
+                (FileId(2), EditConflictKind::InvalidRange),
+                (FileId(3), EditConflictKind::MissingFile),
+            ]
+        );
+        assert_eq!(texts[&FileId(1)], "fn foo() {}");
+        assert_eq!(texts[&FileId(2)], "foo();");
+    }
+}
@@ -5,8 +5,9 @@ use std::{
 };
 
 use ra_syntax::{AstNode, TreeArc, SourceFile};
+use hir::{Crate, CrateDefMap, DefMapStats, db::{CrateDefMapQuery, DefDatabase}};
 use ra_db::{
-    ParseQuery, FileTextQuery, SourceRootId,
+    ParseQuery, FileTextQuery, SourceRootId, CrateId, SourceDatabase,
     salsa::{Database, debug::{DebugQueryTable, TableEntry}},
 };
 
@@ -23,15 +24,17 @@ pub(crate) fn status(db: &RootDatabase) -> String {
     let files_stats = db.query(FileTextQuery).entries::<FilesStats>();
     let syntax_tree_stats = syntax_tree_stats(db);
     let symbols_stats = db.query(LibrarySymbolsQuery).entries::<LibrarySymbolsStats>();
+    let def_maps_stats = def_maps_stats(db);
     let n_defs = {
         let interner: &hir::HirInterner = db.as_ref();
         interner.len()
     };
     format!(
-        "{}\n{}\n{}\n{} defs\n\nmemory:\n{}\ngc {:?} seconds ago",
+        "{}\n{}\n{}\n{}\n{} defs\n\nmemory:\n{}\ngc {:?} seconds ago",
         files_stats,
         symbols_stats,
         syntax_tree_stats,
+        def_maps_stats,
         n_defs,
         MemoryStats::current(),
         db.last_gc.elapsed().as_secs(),
@@ -94,6 +97,66 @@ impl FromIterator<TableEntry<FileId, TreeArc<SourceFile>>> for SyntaxTreeStats {
     }
 }
 
+pub(crate) fn def_maps_stats(db: &RootDatabase) -> DefMapsStats {
+    db.query(CrateDefMapQuery).entries::<DefMapsStats>()
+}
+
+/// Def map statistics of a single crate, computing the def map if needed.
+pub(crate) fn crate_def_map_stats(db: &RootDatabase, crate_id: CrateId) -> Option<DefMapStats> {
+    let crate_root = db.crate_graph().crate_root(crate_id);
+    let krate = hir::source_binder::module_from_file_id(db, crate_root)?.krate(db)?;
+    Some(db.crate_def_map(krate).stats())
+}
+
+/// Def map statistics of all crates, the largest crates first.
+#[derive(Default)]
+pub(crate) struct DefMapsStats {
+    pub(crate) total: DefMapStats,
+    pub(crate) per_crate: Vec<(Crate, DefMapStats)>,
+}
+
+impl fmt::Display for DefMapsStats {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{} def maps, {} modules, {} scope entries, {} macros ({})",
+            self.per_crate.len(),
+            self.total.modules,
+            self.total.scope_entries,
+            self.total.macros,
+            Bytes(self.total.heap_size),
+        )?;
+        for (krate, stats) in self.per_crate.iter().take(5) {
+            write!(
+                fmt,
+                "\n    crate {}: {} modules ({})",
+                krate.crate_id().0,
+                stats.modules,
+                Bytes(stats.heap_size),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<TableEntry<Crate, Arc<CrateDefMap>>> for DefMapsStats {
+    fn from_iter<T>(iter: T) -> DefMapsStats
+    where
+        T: IntoIterator<Item = TableEntry<Crate, Arc<CrateDefMap>>>,
+    {
+        let mut res = DefMapsStats::default();
+        for entry in iter {
+            if let Some(value) = entry.value {
+                let stats = value.stats();
+                res.total += stats;
+                res.per_crate.push((entry.key, stats));
+            }
+        }
+        res.per_crate.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.heap_size));
+        res
+    }
+}
+
 #[derive(Default)]
 struct LibrarySymbolsStats {
     total: usize,
@@ -171,3 +234,25 @@ impl std::ops::AddAssign<usize> for Bytes {
         self.0 += x;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file;
+
+    #[test]
+    fn profiles_crate_def_map() {
+        let (analysis, file_id) = single_file("mod foo {}");
+        let crate_id = analysis.crate_for(file_id).unwrap()[0];
+
+        let res = analysis.crate_def_map_stats(crate_id, true).unwrap();
+        assert_eq!(res.value.unwrap().modules, 2);
+        let profile = res.profile.unwrap();
+        assert!(profile.executed.iter().any(|it| it.contains("crate_def_map")), "{}", profile);
+        assert_eq!(profile.spans[0].label, "crate_def_map");
+        assert_eq!(profile.spans[0].children[0].label, "collect_defs");
+
+        let res = analysis.crate_def_map_stats(crate_id, true).unwrap();
+        assert!(res.profile.unwrap().executed.is_empty());
+        assert!(analysis.crate_def_map_stats(crate_id, false).unwrap().profile.is_none());
+    }
+}
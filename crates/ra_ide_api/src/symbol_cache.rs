@@ -0,0 +1,249 @@
+//! Persists the symbol index across IDE sessions.
+//!
+//! Extracting symbols requires parsing every file of the workspace and of its
+//! dependencies, which, for large workspaces, makes the first symbol search
+//! painfully slow. Instead, the host can save a `SymbolCache` on exit and load
+//! it on the next start: only the files which changed in the meantime are
+//! parsed again.
+//!
+//! Symbols are keyed by a hash of the contents of their file, rather than by
+//! `FileId`s, which are meaningful only in the current session.
+
+use std::sync::Arc;
+
+use ra_db::{FileId, SourceDatabase};
+use ra_syntax::{
+    SmolStr, SyntaxKind, SyntaxNodePtr, TextRange,
+    encoding::{
+        Reader, stable_hash, syntax_kinds_fingerprint, write_range, write_str, write_u32, write_u64,
+    },
+};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    db::RootDatabase,
+    symbol_index::{FileSymbol, SymbolsDatabase, text_to_file_symbols},
+};
+
+const MAGIC: &[u8; 4] = b"RASC";
+/// Bump this when changing the encoding. Changes of the `SyntaxKind`s are
+/// detected by `syntax_kinds_fingerprint`.
+const FORMAT_VERSION: u32 = 2;
+
+/// The symbols of a set of files, which can be saved to and loaded from disk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolCache {
+    files: FxHashMap<ContentKey, Arc<Vec<CachedSymbol>>>,
+}
+
+/// The hash and the length of the file contents.
+type ContentKey = (u64, u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CachedSymbol {
+    name: SmolStr,
+    kind: SyntaxKind,
+    range: TextRange,
+    name_range: Option<TextRange>,
+    container_name: Option<SmolStr>,
+}
+
+impl SymbolCache {
+    /// The number of distinct file contents in the cache.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, text: &str, symbols: &[FileSymbol]) {
+        let symbols = symbols
+            .iter()
+            .map(|it| CachedSymbol {
+                name: it.name.clone(),
+                kind: it.ptr.kind(),
+                range: it.ptr.range(),
+                name_range: it.name_range,
+                container_name: it.container_name.clone(),
+            })
+            .collect();
+        self.files.insert(content_key(text), Arc::new(symbols));
+    }
+
+    /// The symbols of the file `file_id`, if its contents are `text`.
+    pub(crate) fn get(&self, text: &str, file_id: FileId) -> Option<Vec<FileSymbol>> {
+        let symbols = self.files.get(&content_key(text))?;
+        let res = symbols
+            .iter()
+            .map(|it| FileSymbol {
+                file_id,
+                name: it.name.clone(),
+                ptr: SyntaxNodePtr::from_raw(it.range, it.kind),
+                name_range: it.name_range,
+                container_name: it.container_name.clone(),
+            })
+            .collect();
+        Some(res)
+    }
+
+    /// Encodes the cache in a binary format, to be loaded back with
+    /// `from_bytes` in a later session.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        write_u32(&mut buf, FORMAT_VERSION);
+        write_u64(&mut buf, syntax_kinds_fingerprint());
+        // Sort the files to make the encoding deterministic.
+        let mut files = self.files.iter().collect::<Vec<_>>();
+        files.sort_by_key(|(key, _)| **key);
+        write_u32(&mut buf, files.len() as u32);
+        for ((hash, len), symbols) in files {
+            write_u64(&mut buf, *hash);
+            write_u32(&mut buf, *len);
+            write_u32(&mut buf, symbols.len() as u32);
+            for symbol in symbols.iter() {
+                write_str(&mut buf, &symbol.name);
+                write_u32(&mut buf, symbol.kind as u32);
+                write_range(&mut buf, symbol.range);
+                match symbol.name_range {
+                    None => buf.push(0),
+                    Some(range) => {
+                        buf.push(1);
+                        write_range(&mut buf, range);
+                    }
+                }
+                match &symbol.container_name {
+                    None => buf.push(0),
+                    Some(name) => {
+                        buf.push(1);
+                        write_str(&mut buf, name);
+                    }
+                }
+            }
+        }
+        buf
+    }
+
+    /// Loads a cache encoded with `to_bytes`. Returns `None` if the data is
+    /// malformed or was written by a different version of rust-analyzer.
+    pub fn from_bytes(bytes: &[u8]) -> Option<SymbolCache> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(MAGIC.len())? != MAGIC
+            || reader.u32()? != FORMAT_VERSION
+            || reader.u64()? != syntax_kinds_fingerprint()
+        {
+            return None;
+        }
+        let mut files = FxHashMap::default();
+        for _ in 0..reader.u32()? {
+            let key = (reader.u64()?, reader.u32()?);
+            let mut symbols = Vec::new();
+            for _ in 0..reader.u32()? {
+                let name = SmolStr::new(reader.str()?);
+                let kind = reader.kind()?;
+                let range = reader.range()?;
+                let name_range = match reader.byte()? {
+                    0 => None,
+                    1 => Some(reader.range()?),
+                    _ => return None,
+                };
+                let container_name = match reader.byte()? {
+                    0 => None,
+                    1 => Some(SmolStr::new(reader.str()?)),
+                    _ => return None,
+                };
+                // Ranges past the end of the file would panic when used.
+                let is_in_file = |range: TextRange| range.end().to_usize() as u32 <= key.1;
+                if !is_in_file(range) || !name_range.map_or(true, is_in_file) {
+                    return None;
+                }
+                symbols.push(CachedSymbol { name, kind, range, name_range, container_name });
+            }
+            files.insert(key, Arc::new(symbols));
+        }
+        if !reader.is_empty() {
+            return None;
+        }
+        Some(SymbolCache { files })
+    }
+}
+
+/// Collects the symbols of all files in the current state of the world.
+///
+/// The library indices are deduplicated by name across the whole library, so
+/// the symbols of library files are extracted again, file by file, unless
+/// they are already in the cache of the previous session.
+pub(crate) fn collect(db: &RootDatabase) -> SymbolCache {
+    let mut cache = SymbolCache::default();
+    for &root in db.local_roots().iter() {
+        for &file_id in db.source_root(root).files.values() {
+            cache.insert(&db.file_text(file_id), db.file_symbols(file_id).symbols());
+        }
+    }
+    let previous = db.symbol_cache();
+    for &root in db.library_roots().iter() {
+        for &file_id in db.source_root(root).files.values() {
+            let text = db.file_text(file_id);
+            cache.insert(&text, &text_to_file_symbols(&text, file_id, &previous));
+        }
+    }
+    cache
+}
+
+fn content_key(text: &str) -> ContentKey {
+    (stable_hash(text.as_bytes()), text.len() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AnalysisHost, Query, mock_analysis::MockAnalysis};
+
+    use super::*;
+
+    #[test]
+    fn loads_symbols_from_cache() {
+        let code = "mod foo { fn bar() {} }\nstruct Baz;\n";
+        let analysis = MockAnalysis::with_files(&format!("//- /lib.rs\n{}", code)).analysis();
+        let cache = analysis.symbol_cache().unwrap();
+        assert_eq!(cache.len(), 1);
+
+        let bytes = cache.to_bytes();
+        let loaded = SymbolCache::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, cache);
+        assert!(SymbolCache::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(SymbolCache::from_bytes(b"garbage").is_none());
+        // The `SyntaxKind` fingerprint follows the magic and the version.
+        let mut other_kinds = bytes.clone();
+        other_kinds[MAGIC.len() + 1] ^= 1;
+        assert!(SymbolCache::from_bytes(&other_kinds).is_none());
+        // Both ranges must fit into the file.
+        let mut out_of_file = SymbolCache::default();
+        let symbol = FileSymbol {
+            file_id: FileId(0),
+            name: "x".into(),
+            ptr: SyntaxNodePtr::from_raw(
+                TextRange::offset_len(0.into(), 1.into()),
+                SyntaxKind::FN_DEF,
+            ),
+            name_range: Some(TextRange::offset_len(0.into(), 2.into())),
+            container_name: None,
+        };
+        out_of_file.insert("x", &[symbol]);
+        assert!(SymbolCache::from_bytes(&out_of_file.to_bytes()).is_none());
+
+        let symbols = loaded.get(code, FileId(92)).unwrap();
+        let bar = symbols.iter().find(|it| it.name.as_str() == "bar").unwrap();
+        assert_eq!(bar.file_id, FileId(92));
+        assert_eq!(bar.container_name, Some("foo".into()));
+        assert!(loaded.get("struct Baz;", FileId(92)).is_none());
+
+        let mut host: AnalysisHost =
+            MockAnalysis::with_files(&format!("//- /lib.rs\n{}", code)).analysis_host();
+        host.set_symbol_cache(loaded);
+        let navs = host.analysis().symbol_search(Query::new("Baz".to_string())).unwrap();
+        assert_eq!(navs.len(), 1);
+        assert_eq!(navs[0].name().as_str(), "Baz");
+    }
+}
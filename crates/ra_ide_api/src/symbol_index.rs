@@ -29,7 +29,7 @@ use std::{
 
 use fst::{self, Streamer};
 use ra_syntax::{
-    SyntaxNode, SyntaxNodePtr, SourceFile, SmolStr, TreeArc, AstNode,
+    SyntaxNode, SyntaxNodePtr, SourceFile, SmolStr, AstNode,
     algo::{visit::{visitor, Visitor}},
     SyntaxKind::{self, *},
     ast::{self, NameOwner},
@@ -45,6 +45,7 @@ use rayon::prelude::*;
 use crate::{
     FileId, Query,
     db::RootDatabase,
+    symbol_cache::SymbolCache,
 };
 
 #[salsa::query_group(SymbolsDatabaseStorage)]
@@ -60,10 +61,16 @@ pub(crate) trait SymbolsDatabase: hir::db::HirDatabase {
     /// Files in libraries are assumed to never change.
     #[salsa::input]
     fn library_roots(&self) -> Arc<Vec<SourceRootId>>;
+    /// Symbols from the previous session, see `SymbolCache`.
+    #[salsa::input]
+    fn symbol_cache(&self) -> Arc<SymbolCache>;
 }
 
 fn file_symbols(db: &impl SymbolsDatabase, file_id: FileId) -> Arc<SymbolIndex> {
     db.check_canceled();
+    if let Some(symbols) = db.symbol_cache().get(&db.file_text(file_id), file_id) {
+        return Arc::new(SymbolIndex::new(symbols));
+    }
     let source_file = db.parse(file_id);
 
     let symbols = source_file_to_file_symbols(&source_file, file_id);
@@ -82,22 +89,24 @@ pub(crate) fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol>
         }
     }
 
-    let buf: Vec<Arc<SymbolIndex>> = if query.libs {
+    let mut files = Vec::new();
+    for &root in db.local_roots().iter() {
+        let sr = db.source_root(root);
+        files.extend(sr.files.values().map(|&it| it))
+    }
+
+    let snap = Snap(db.snapshot());
+    let mut buf: Vec<Arc<SymbolIndex>> =
+        files.par_iter().map_with(snap, |db, &file_id| db.0.file_symbols(file_id)).collect();
+    if query.libs {
         let snap = Snap(db.snapshot());
-        db.library_roots()
+        let libs = db
+            .library_roots()
             .par_iter()
             .map_with(snap, |db, &lib_id| db.0.library_symbols(lib_id))
-            .collect()
-    } else {
-        let mut files = Vec::new();
-        for &root in db.local_roots().iter() {
-            let sr = db.source_root(root);
-            files.extend(sr.files.values().map(|&it| it))
-        }
-
-        let snap = Snap(db.snapshot());
-        files.par_iter().map_with(snap, |db, &file_id| db.0.file_symbols(file_id)).collect()
-    };
+            .collect::<Vec<_>>();
+        buf.extend(libs);
+    }
     query.search(&buf)
 }
 
@@ -155,11 +164,17 @@ impl SymbolIndex {
         self.map.as_fst().size() + self.symbols.len() * mem::size_of::<FileSymbol>()
     }
 
-    pub(crate) fn for_files(
-        files: impl ParallelIterator<Item = (FileId, TreeArc<SourceFile>)>,
+    pub(crate) fn symbols(&self) -> &[FileSymbol] {
+        &self.symbols
+    }
+
+    /// Indexes `files`, parsing only those whose symbols are not in `cache`.
+    pub(crate) fn for_files<'a>(
+        files: impl ParallelIterator<Item = (FileId, &'a str)>,
+        cache: &SymbolCache,
     ) -> SymbolIndex {
         let symbols = files
-            .flat_map(|(file_id, file)| source_file_to_file_symbols(&file, file_id))
+            .flat_map(|(file_id, text)| text_to_file_symbols(text, file_id, cache))
             .collect::<Vec<_>>();
         SymbolIndex::new(symbols)
     }
@@ -214,6 +229,19 @@ pub(crate) struct FileSymbol {
     pub(crate) container_name: Option<SmolStr>,
 }
 
+/// The symbols of the file with the contents `text`, taken from `cache` if
+/// possible.
+pub(crate) fn text_to_file_symbols(
+    text: &str,
+    file_id: FileId,
+    cache: &SymbolCache,
+) -> Vec<FileSymbol> {
+    match cache.get(text, file_id) {
+        Some(symbols) => symbols,
+        None => source_file_to_file_symbols(&SourceFile::parse(text), file_id),
+    }
+}
+
 fn source_file_to_file_symbols(source_file: &SourceFile, file_id: FileId) -> Vec<FileSymbol> {
     let mut symbols = Vec::new();
     let mut stack = Vec::new();
@@ -1,4 +1,5 @@
-use ra_syntax::AstNode;
+use hir::{HirFileId, source_binder};
+use ra_syntax::{AstNode, TextRange, ast};
 use ra_db::SourceDatabase;
 
 use crate::{
@@ -6,7 +7,171 @@ use crate::{
     db::RootDatabase,
 };
 
-pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRange> {
+/// Options for `Analysis::highlight_with_options`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HighlightOptions {
+    /// Highlight the arguments of macro calls as they are used in the
+    /// expansion, and mark such ranges as `macro_generated`.
+    pub macro_generated: bool,
+}
+
+pub(crate) fn highlight(
+    db: &RootDatabase,
+    file_id: FileId,
+    options: HighlightOptions,
+) -> Vec<HighlightedRange> {
     let source_file = db.parse(file_id);
-    ra_ide_api_light::highlight(source_file.syntax())
+    let mut res = ra_ide_api_light::highlight(source_file.syntax());
+    if options.macro_generated {
+        for macro_call in source_file.syntax().descendants().filter_map(ast::MacroCall::cast) {
+            highlight_expansion(db, file_id, macro_call, &mut res);
+        }
+        res.sort_by_key(|it| it.range.start());
+    }
+    res
+}
+
+/// Highlights the expansion of `macro_call` and maps the highlights back to
+/// the tokens of the call they come from. Tokens produced by the macro
+/// definition itself have no place in the call, so they are skipped.
+fn highlight_expansion(
+    db: &RootDatabase,
+    file_id: FileId,
+    macro_call: &ast::MacroCall,
+    res: &mut Vec<HighlightedRange>,
+) -> Option<()> {
+    let macro_call_id = source_binder::macro_call_from_source(db, file_id, macro_call)?;
+    let expansion = macro_call_id.expand(db)?;
+    let info = expansion.file_id().expansion_info(db)?;
+    for hl in ra_ide_api_light::highlight(expansion.source_file().syntax()) {
+        let range = match info.map_range_up(hl.range) {
+            Some((call_file, range)) if call_file == HirFileId::from(file_id) => range,
+            _ => continue,
+        };
+        match res.iter_mut().find(|it| it.range == range) {
+            // The expansion knows better than the heuristic parse of the
+            // macro call.
+            Some(existing) => {
+                if existing.heuristic {
+                    existing.tag = hl.tag;
+                    existing.heuristic = false;
+                }
+                existing.macro_generated = true;
+            }
+            None => res.push(HighlightedRange {
+                range,
+                tag: hl.tag,
+                heuristic: false,
+                macro_generated: true,
+            }),
+        }
+    }
+    Some(())
+}
+
+/// Difference between two highlighting snapshots of the same file.
+///
+/// The new snapshot consists of the first `unchanged` highlights of the old
+/// one, followed by `added`, followed by the last `moved` highlights of the
+/// old one, shifted by `shift`. Highlights at `removed` ranges of the old
+/// snapshot are dropped.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HighlightDelta {
+    pub unchanged: usize,
+    pub removed: Vec<TextRange>,
+    pub added: Vec<HighlightedRange>,
+    pub moved: usize,
+    pub shift: i64,
+}
+
+/// Computes a delta between two highlighting snapshots, so that the client
+/// doesn't have to receive the whole set of highlights on each keystroke.
+/// Both snapshots must be sorted by the start of the range.
+pub fn diff_highlights(old: &[HighlightedRange], new: &[HighlightedRange]) -> HighlightDelta {
+    let unchanged = old.iter().zip(new.iter()).take_while(|(old, new)| old == new).count();
+    let old_rest = &old[unchanged..];
+    let new_rest = &new[unchanged..];
+
+    let shift = match (old_rest.last(), new_rest.last()) {
+        (Some(old), Some(new)) => offset(new.range) - offset(old.range),
+        _ => 0,
+    };
+    let moved = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(old, new)| {
+            old.tag == new.tag
+                && old.range.len() == new.range.len()
+                && offset(new.range) - offset(old.range) == shift
+        })
+        .count();
+
+    HighlightDelta {
+        unchanged,
+        removed: old_rest[..old_rest.len() - moved].iter().map(|it| it.range).collect(),
+        added: new_rest[..new_rest.len() - moved].to_vec(),
+        moved,
+        shift: if moved == 0 { 0 } else { shift },
+    }
+}
+
+fn offset(range: TextRange) -> i64 {
+    i64::from(u32::from(range.start()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file;
+
+    use super::*;
+
+    #[test]
+    fn diff_highlights_for_local_edit() {
+        let (analysis, file_id) = single_file("fn foo() { 1 }\nfn bar() {}\n");
+        let old = analysis.highlight(file_id).unwrap();
+        let (analysis, file_id) = single_file("fn foo() { \"hello\" }\nfn bar() {}\n");
+        let new = analysis.highlight(file_id).unwrap();
+
+        let delta = diff_highlights(&old, &new);
+        assert_eq!(delta.removed.len(), 1);
+        assert_eq!(delta.added.len(), 1);
+        assert_eq!(delta.added[0].tag, "string");
+        assert_eq!(delta.shift, 6);
+        assert_eq!(delta.unchanged + delta.added.len() + delta.moved, new.len());
+    }
+
+    #[test]
+    fn highlights_macro_generated_ranges() {
+        let (analysis, file_id) = single_file(
+            "
+            macro_rules! make_fn {
+                ($name:ident) => { fn $name() {} }
+            }
+            make_fn!(foo);
+            ",
+        );
+        let plain = analysis.highlight(file_id).unwrap();
+        assert!(plain.iter().all(|it| !it.macro_generated));
+
+        let options = HighlightOptions { macro_generated: true };
+        let highlights = analysis.highlight_with_options(file_id, options).unwrap();
+        let generated = highlights.iter().filter(|it| it.macro_generated).collect::<Vec<_>>();
+        assert_eq!(generated.len(), 1);
+        assert_eq!(generated[0].tag, "function");
+        let text = analysis.file_text(file_id);
+        assert_eq!(&text.as_str()[generated[0].range], "foo");
+    }
+
+    #[test]
+    fn diff_identical_highlights() {
+        let (analysis, file_id) = single_file("fn foo() { 1 }");
+        let highlights = analysis.highlight(file_id).unwrap();
+
+        let delta = diff_highlights(&highlights, &highlights);
+        assert_eq!(delta.unchanged, highlights.len());
+        assert!(delta.removed.is_empty());
+        assert!(delta.added.is_empty());
+        assert_eq!(delta.moved, 0);
+    }
 }
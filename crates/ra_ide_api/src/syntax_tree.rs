@@ -1,12 +1,46 @@
 use ra_db::SourceDatabase;
 use crate::db::RootDatabase;
 use ra_syntax::{
-    SourceFile, SyntaxNode, TextRange, AstNode,
+    SourceFile, SyntaxNode, TextRange, AstNode, SyntaxTreeDump, RangeStyle,
     algo::{self, visit::{visitor, Visitor}}, ast::{self, AstToken}
 };
 
 pub use ra_db::FileId;
 
+/// What to show in a syntax tree dump. The default options give the same
+/// output as `SyntaxNode::debug_dump`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxTreeOptions {
+    /// Whether to show whitespace and comments.
+    pub trivia: bool,
+    pub ranges: RangeStyle,
+    /// Omits the nodes nested deeper than this, relative to the dumped node.
+    pub max_depth: Option<usize>,
+    pub errors: bool,
+}
+
+impl Default for SyntaxTreeOptions {
+    fn default() -> SyntaxTreeOptions {
+        SyntaxTreeOptions {
+            trivia: true,
+            ranges: RangeStyle::Offsets,
+            max_depth: None,
+            errors: true,
+        }
+    }
+}
+
+impl SyntaxTreeOptions {
+    fn dump(&self, node: &SyntaxNode) -> String {
+        let mut dump =
+            SyntaxTreeDump::new(node).trivia(self.trivia).ranges(self.ranges).errors(self.errors);
+        if let Some(max_depth) = self.max_depth {
+            dump = dump.max_depth(max_depth);
+        }
+        dump.dump()
+    }
+}
+
 pub(crate) fn syntax_tree(
     db: &RootDatabase,
     file_id: FileId,
@@ -16,7 +50,8 @@ pub(crate) fn syntax_tree(
         let file = db.parse(file_id);
         let node = algo::find_covering_node(file.syntax(), text_range);
 
-        if let Some(tree) = syntax_tree_for_string(node, text_range) {
+        let options = SyntaxTreeOptions::default();
+        if let Some(tree) = syntax_tree_for_string(node, text_range, options) {
             return tree;
         }
 
@@ -26,19 +61,49 @@ pub(crate) fn syntax_tree(
     }
 }
 
+/// Like `syntax_tree`, but only the errors inside of the selected node are
+/// shown.
+pub(crate) fn syntax_tree_with_options(
+    db: &RootDatabase,
+    file_id: FileId,
+    text_range: Option<TextRange>,
+    options: SyntaxTreeOptions,
+) -> String {
+    let file = db.parse(file_id);
+    let node = match text_range {
+        Some(text_range) => {
+            let node = algo::find_covering_node(file.syntax(), text_range);
+            if let Some(tree) = syntax_tree_for_string(node, text_range, options) {
+                return tree;
+            }
+            node
+        }
+        None => file.syntax(),
+    };
+    options.dump(node)
+}
+
 /// Attempts parsing the selected contents of a string literal
 /// as rust syntax and returns its syntax tree
-fn syntax_tree_for_string(node: &SyntaxNode, text_range: TextRange) -> Option<String> {
+fn syntax_tree_for_string(
+    node: &SyntaxNode,
+    text_range: TextRange,
+    options: SyntaxTreeOptions,
+) -> Option<String> {
     // When the range is inside a string
     // we'll attempt parsing it as rust syntax
     // to provide the syntax tree of the contents of the string
     visitor()
-        .visit(|node: &ast::String| syntax_tree_for_token(node, text_range))
-        .visit(|node: &ast::RawString| syntax_tree_for_token(node, text_range))
+        .visit(|node: &ast::String| syntax_tree_for_token(node, text_range, options))
+        .visit(|node: &ast::RawString| syntax_tree_for_token(node, text_range, options))
         .accept(node)?
 }
 
-fn syntax_tree_for_token<T: AstToken>(node: &T, text_range: TextRange) -> Option<String> {
+fn syntax_tree_for_token<T: AstToken>(
+    node: &T,
+    text_range: TextRange,
+    options: SyntaxTreeOptions,
+) -> Option<String> {
     // Range of the full node
     let node_range = node.syntax().range();
     let text = node.text().to_string();
@@ -80,7 +145,7 @@ fn syntax_tree_for_token<T: AstToken>(node: &T, text_range: TextRange) -> Option
     // If the "file" parsed without errors,
     // return its syntax
     if parsed.errors().is_empty() {
-        return Some(parsed.syntax().debug_dump());
+        return Some(options.dump(parsed.syntax()));
     }
 
     None
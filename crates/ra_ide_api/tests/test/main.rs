@@ -1,10 +1,14 @@
 use insta::assert_debug_snapshot_matches;
 use ra_ide_api::{
-    mock_analysis::{single_file, single_file_with_position, single_file_with_range, MockAnalysis},
-    AnalysisChange, CrateGraph, Edition::Edition2018, Query, NavigationTarget,
-    ReferenceSearchResult,
+    mock_analysis::{
+        analysis_and_position, single_file, single_file_with_position, single_file_with_range,
+        MockAnalysis,
+    },
+    AnalysisChange, CrateGraph,
+    Edition::Edition2018,
+    Query, NavigationTarget, ReferenceSearchResult, SyntaxTreeOptions,
 };
-use ra_syntax::SmolStr;
+use ra_syntax::{SmolStr, RangeStyle};
 
 #[test]
 fn test_unresolved_module_diagnostic() {
@@ -21,6 +25,44 @@ fn test_unresolved_module_diagnostic_no_diag_for_inline_mode() {
     assert!(diagnostics.is_empty());
 }
 
+#[test]
+fn test_duplicate_definition_diagnostic() {
+    let (analysis, file_id) = single_file(
+        "
+        struct Foo;
+        fn Foo() {}
+        enum Bar {}
+        use self::Bar as Foo;
+        struct Baz;
+        struct Baz;
+        ",
+    );
+    let diagnostics = analysis.diagnostics(file_id).unwrap();
+    let mut messages = diagnostics.iter().map(|it| it.message.as_str()).collect::<Vec<_>>();
+    messages.sort();
+    assert_eq!(
+        messages,
+        vec![
+            "the name `Baz` is defined multiple times",
+            "the name `Foo` is defined multiple times",
+            "the name `Foo` is defined multiple times",
+        ]
+    );
+}
+
+#[test]
+fn test_no_duplicate_definition_diagnostic_for_globs() {
+    let (analysis, file_id) = single_file(
+        "
+        mod foo { pub struct Foo; }
+        use self::foo::*;
+        struct Foo;
+        ",
+    );
+    let diagnostics = analysis.diagnostics(file_id).unwrap();
+    assert!(diagnostics.is_empty());
+}
+
 #[test]
 fn test_resolve_crate_root() {
     let mock = MockAnalysis::with_files(
@@ -52,7 +94,7 @@ fn get_all_refs(text: &str) -> ReferenceSearchResult {
 
 fn get_symbols_matching(text: &str, query: &str) -> Vec<NavigationTarget> {
     let (analysis, _) = single_file(text);
-    analysis.symbol_search(Query::new(query.into())).unwrap()
+    analysis.symbol_search(Query::parse(query)).unwrap()
 }
 
 #[test]
@@ -96,6 +138,51 @@ fn test_find_all_refs_for_fn_param() {
     assert_eq!(refs.len(), 2);
 }
 
+#[test]
+fn test_find_all_refs_for_fn_across_files() {
+    let (analysis, position) = analysis_and_position(
+        "
+        //- /lib.rs
+        mod foo;
+        use crate::foo::bar;
+        fn main() {
+            bar();
+            foo::bar();
+            let bar = 92;
+            bar;
+        }
+        //- /foo.rs
+        pub fn bar<|>() {}
+        fn baz() { bar() }
+        ",
+    );
+    let refs = analysis.find_all_refs(position).unwrap().unwrap();
+    assert_eq!(refs.declaration().name().as_str(), "bar");
+    let mut files = refs.references().iter().map(|it| it.file_id).collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    assert_eq!(files.len(), 2);
+    // The import, two calls in `main` and one in `baz`, but not the local.
+    assert_eq!(refs.len(), 5);
+}
+
+#[test]
+fn test_find_all_refs_for_method() {
+    let code = r#"
+    struct S;
+    impl S {
+        fn frobnicate(&self) {}
+    }
+    fn main() {
+        S.frobnicate();
+        S.frobnicate<|>();
+        S::frobnicate(&S);
+    }"#;
+
+    let refs = get_all_refs(code);
+    assert_eq!(refs.len(), 4);
+}
+
 #[test]
 fn test_world_symbols_with_no_container() {
     let code = r#"
@@ -110,6 +197,21 @@ fn test_world_symbols_with_no_container() {
     assert!(s.container_name().is_none());
 }
 
+#[test]
+fn test_world_symbols_only_types() {
+    let code = r#"
+fn foo_bar() {}
+struct FooBaz;
+    "#;
+
+    let symbols = get_symbols_matching(code, "foo");
+    assert_eq!(symbols.len(), 2);
+
+    let symbols = get_symbols_matching(code, "#foo");
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].name(), "FooBaz");
+}
+
 #[test]
 fn test_world_symbols_include_container_name() {
     let code = r#"
@@ -390,3 +492,35 @@ SOURCE_FILE@[0; 25)
         .trim()
     );
 }
+
+#[test]
+fn test_syntax_tree_with_options() {
+    let (analysis, range) = single_file_with_range(
+        r#"fn foo() {
+    <|>let x = 1 + /* two */ 2;<|>
+}"#,
+    );
+    let options = SyntaxTreeOptions {
+        trivia: false,
+        ranges: RangeStyle::LineCol,
+        max_depth: Some(2),
+        ..SyntaxTreeOptions::default()
+    };
+    let syn = analysis.syntax_tree_with_options(range.file_id, Some(range.range), options);
+    assert_eq!(
+        syn.trim(),
+        r#"
+LET_STMT@2:5..2:29
+  LET_KW@2:5..2:8
+  BIND_PAT@2:9..2:10
+    NAME@2:9..2:10
+  EQ@2:11..2:12
+  BIN_EXPR@2:13..2:28
+    LITERAL@2:13..2:14
+    PLUS@2:15..2:16
+    LITERAL@2:27..2:28
+  SEMI@2:28..2:29
+"#
+        .trim()
+    );
+}
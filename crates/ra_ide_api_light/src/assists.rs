@@ -0,0 +1,401 @@
+//! Light-weight assists which need only the syntax tree of a single file.
+//!
+//! Each assist is a function from an `AssistCtx` to an optional `LocalEdit`.
+//! Assists which need semantic information live in the `ra_assists` crate.
+
+use ra_text_edit::TextEditBuilder;
+use ra_syntax::{
+    AstNode, SourceFile, SyntaxNode, TextRange, TextUnit, Direction,
+    algo::{
+        find_covering_node, find_leaf_at_offset, find_node_at_offset, non_trivia_sibling,
+        LeafAtOffset,
+    },
+    ast::{self, AttrsOwner},
+    SyntaxKind::*,
+};
+
+use crate::{IndentStyle, LocalEdit};
+
+mod inline_variable;
+mod remove_dbg;
+mod extract_function;
+
+/// The file and the selected range an assist is invoked with.
+struct AssistCtx<'a> {
+    file: &'a SourceFile,
+    range: TextRange,
+    /// The indentation of the file, for the code the assists generate.
+    style: &'a IndentStyle,
+}
+
+impl<'a> AssistCtx<'a> {
+    fn leaf_at_offset(&self) -> LeafAtOffset<&'a SyntaxNode> {
+        find_leaf_at_offset(self.file.syntax(), self.range.start())
+    }
+
+    fn node_at_offset<N: AstNode>(&self) -> Option<&'a N> {
+        find_node_at_offset(self.file.syntax(), self.range.start())
+    }
+
+    fn covering_node(&self) -> &'a SyntaxNode {
+        find_covering_node(self.file.syntax(), self.range)
+    }
+}
+
+const ASSISTS: &[fn(&AssistCtx) -> Option<LocalEdit>] = &[
+    flip_comma,
+    flip_binexpr,
+    change_visibility,
+    add_derive,
+    introduce_variable,
+    inline_variable::inline_variable,
+    remove_dbg::remove_dbg,
+    extract_function::extract_function,
+];
+
+/// Returns all the single-file assists applicable to `range`.
+pub fn assists(file: &SourceFile, range: TextRange, style: &IndentStyle) -> Vec<LocalEdit> {
+    let ctx = AssistCtx { file, range, style };
+    ASSISTS.iter().filter_map(|assist| assist(&ctx)).collect()
+}
+
+fn flip_comma(ctx: &AssistCtx) -> Option<LocalEdit> {
+    let comma = ctx.leaf_at_offset().find(|leaf| leaf.kind() == COMMA)?;
+    let prev = non_trivia_sibling(comma, Direction::Prev)?;
+    let next = non_trivia_sibling(comma, Direction::Next)?;
+    // A trailing comma has nothing to be flipped with.
+    match next.kind() {
+        R_PAREN | R_CURLY | R_BRACK | R_ANGLE => return None,
+        _ => (),
+    }
+    let mut edit = TextEditBuilder::default();
+    edit.replace(prev.range(), next.text().to_string());
+    edit.replace(next.range(), prev.text().to_string());
+    Some(LocalEdit {
+        label: "flip comma".to_string(),
+        edit: edit.finish(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+fn flip_binexpr(ctx: &AssistCtx) -> Option<LocalEdit> {
+    let (op, expr) =
+        ctx.leaf_at_offset().filter(|leaf| !leaf.kind().is_trivia()).find_map(|leaf| {
+            let expr = leaf.parent().and_then(ast::BinExpr::cast)?;
+            Some((leaf, expr))
+        })?;
+    // Operators which are not symmetric are flipped into their mirror image,
+    // `a < b` becomes `b > a`.
+    let flipped_op = match op.kind() {
+        EQEQ | NEQ | PLUS | STAR | AMPAMP | PIPEPIPE | AMP | PIPE | CARET => None,
+        L_ANGLE => Some(">"),
+        R_ANGLE => Some("<"),
+        LTEQ => Some(">="),
+        GTEQ => Some("<="),
+        _ => return None,
+    };
+    let (lhs, rhs) = match expr.sub_exprs() {
+        (Some(lhs), Some(rhs)) => (lhs.syntax(), rhs.syntax()),
+        _ => return None,
+    };
+    let mut edit = TextEditBuilder::default();
+    edit.replace(lhs.range(), rhs.text().to_string());
+    edit.replace(rhs.range(), lhs.text().to_string());
+    if let Some(flipped_op) = flipped_op {
+        edit.replace(op.range(), flipped_op.to_string());
+    }
+    Some(LocalEdit {
+        label: "flip binary expression".to_string(),
+        edit: edit.finish(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+fn change_visibility(ctx: &AssistCtx) -> Option<LocalEdit> {
+    let mut edit = TextEditBuilder::default();
+    if let Some(vis) = ctx.node_at_offset::<ast::Visibility>() {
+        let range = vis.syntax().range();
+        let (label, new_vis) = match vis.syntax().text().to_string().as_str() {
+            "pub" => ("change to pub(crate)", "pub(crate)"),
+            "pub(crate)" => ("change to pub", "pub"),
+            _ => return None,
+        };
+        edit.replace(range, new_vis.to_string());
+        return Some(LocalEdit {
+            label: label.to_string(),
+            edit: edit.finish(),
+            cursor_position: Some(range.start()),
+            typing: None,
+        });
+    }
+
+    let keyword = ctx.leaf_at_offset().find(|leaf| match leaf.kind() {
+        FN_KW | MOD_KW | STRUCT_KW | ENUM_KW | TRAIT_KW => true,
+        _ => false,
+    })?;
+    let item = keyword.parent()?;
+    let has_vis = match item.kind() {
+        FN_DEF | MODULE | STRUCT_DEF | ENUM_DEF | TRAIT_DEF => {
+            item.children().any(|it| it.kind() == VISIBILITY)
+        }
+        _ => return None,
+    };
+    if has_vis {
+        return None;
+    }
+    let offset = first_non_trivia_child(item)?.range().start();
+    edit.insert(offset, "pub(crate) ".to_string());
+    Some(LocalEdit {
+        label: "make pub(crate)".to_string(),
+        edit: edit.finish(),
+        cursor_position: Some(offset),
+        typing: None,
+    })
+}
+
+fn add_derive(ctx: &AssistCtx) -> Option<LocalEdit> {
+    let nominal = ctx.node_at_offset::<ast::NominalDef>()?;
+    let derive = nominal
+        .attrs()
+        .filter_map(|it| it.as_call())
+        .find(|(name, _arg)| name == "derive")
+        .map(|(_name, arg)| arg);
+    let mut edit = TextEditBuilder::default();
+    let cursor_position = match derive {
+        Some(tt) => tt.syntax().range().end() - TextUnit::of_char(')'),
+        None => {
+            let offset = first_non_trivia_child(nominal.syntax())?.range().start();
+            edit.insert(offset, "#[derive()]\n".to_string());
+            offset + TextUnit::of_str("#[derive(")
+        }
+    };
+    Some(LocalEdit {
+        label: "add `#[derive]`".to_string(),
+        edit: edit.finish(),
+        cursor_position: Some(cursor_position),
+        typing: None,
+    })
+}
+
+/// Only handles the simple case of an expression in a statement or in the
+/// tail of a block; `ra_assists` has the complete version.
+fn introduce_variable(ctx: &AssistCtx) -> Option<LocalEdit> {
+    if ctx.range.is_empty() {
+        return None;
+    }
+    let expr = ctx.covering_node().ancestors().find_map(ast::Expr::cast)?;
+    if expr.syntax().kind() == PATH_EXPR {
+        return None;
+    }
+    let anchor = expr.syntax().ancestors().find(|node| {
+        ast::Stmt::cast(node).is_some()
+            || node
+                .parent()
+                .and_then(ast::Block::cast)
+                .and_then(|it| it.expr())
+                .map(|it| it.syntax())
+                == Some(node)
+    })?;
+    let indent = anchor.prev_sibling().filter(|it| it.kind() == WHITESPACE)?;
+    let indent = indent.text().to_string();
+    let indent = indent.rsplit('\n').next().unwrap_or("");
+
+    let mut edit = TextEditBuilder::default();
+    edit.insert(
+        anchor.range().start(),
+        format!("let var_name = {};\n{}", expr.syntax().text(), indent),
+    );
+    edit.replace(expr.syntax().range(), "var_name".to_string());
+    Some(LocalEdit {
+        label: "introduce variable".to_string(),
+        edit: edit.finish(),
+        cursor_position: Some(anchor.range().start() + TextUnit::of_str("let ")),
+        typing: None,
+    })
+}
+
+/// Whether `expr` has to be parenthesized when it replaces the `replaced`
+/// expression.
+fn needs_parens(expr: &ast::Expr, replaced: &SyntaxNode) -> bool {
+    let parent = match replaced.parent().and_then(ast::Expr::cast) {
+        Some(it) => it,
+        None => return false,
+    };
+    let prec = precedence(expr);
+    let parent_prec = precedence(parent);
+    // `replaced` is not necessarily an `ast::Expr`, it might be a macro call.
+    let is_first_child =
+        parent.syntax().children().find(|it| !it.kind().is_trivia()) == Some(replaced);
+    match parent.kind() {
+        ast::ExprKind::BinExpr(_) => {
+            // Binary operators are left associative, except for comparisons,
+            // which don't associate at all.
+            prec < parent_prec
+                || (prec == parent_prec && (!is_first_child || parent_prec == COMPARISON_PREC))
+        }
+        ast::ExprKind::RangeExpr(_) | ast::ExprKind::PrefixExpr(_) | ast::ExprKind::RefExpr(_) => {
+            prec <= parent_prec
+        }
+        ast::ExprKind::CastExpr(_)
+        | ast::ExprKind::CallExpr(_)
+        | ast::ExprKind::IndexExpr(_)
+        | ast::ExprKind::MethodCallExpr(_)
+        | ast::ExprKind::FieldExpr(_)
+        | ast::ExprKind::TryExpr(_) => is_first_child && prec < parent_prec,
+        _ => false,
+    }
+}
+
+const COMPARISON_PREC: u8 = 6;
+
+/// Binding power of the outermost operator of `expr`, higher binds tighter.
+fn precedence(expr: &ast::Expr) -> u8 {
+    match expr.kind() {
+        ast::ExprKind::LambdaExpr(_)
+        | ast::ExprKind::ReturnExpr(_)
+        | ast::ExprKind::BreakExpr(_) => 1,
+        ast::ExprKind::BinExpr(it) => match it.op() {
+            Some(ast::BinOp::BooleanOr) => 4,
+            Some(ast::BinOp::BooleanAnd) => 5,
+            Some(ast::BinOp::EqualityTest)
+            | Some(ast::BinOp::NegatedEqualityTest)
+            | Some(ast::BinOp::LesserEqualTest)
+            | Some(ast::BinOp::GreaterEqualTest)
+            | Some(ast::BinOp::LesserTest)
+            | Some(ast::BinOp::GreaterTest) => COMPARISON_PREC,
+            Some(ast::BinOp::BitwiseOr) => 7,
+            Some(ast::BinOp::BitwiseXor) => 8,
+            Some(ast::BinOp::BitwiseAnd) => 9,
+            Some(ast::BinOp::LeftShift) | Some(ast::BinOp::RightShift) => 10,
+            Some(ast::BinOp::Addition) | Some(ast::BinOp::Subtraction) => 11,
+            Some(ast::BinOp::Multiplication)
+            | Some(ast::BinOp::Division)
+            | Some(ast::BinOp::Remainder) => 12,
+            Some(ast::BinOp::RangeRightOpen) | Some(ast::BinOp::RangeRightClosed) => 3,
+            // Assignments
+            _ => 2,
+        },
+        ast::ExprKind::RangeExpr(_) => 3,
+        ast::ExprKind::CastExpr(_) => 13,
+        ast::ExprKind::PrefixExpr(_) | ast::ExprKind::RefExpr(_) => 14,
+        _ => 15,
+    }
+}
+
+fn first_non_trivia_child(node: &SyntaxNode) -> Option<&SyntaxNode> {
+    node.children().find(|it| !it.kind().is_trivia())
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot_matches;
+    use ra_syntax::{SourceFile, TextRange};
+    use test_utils::{add_cursor, extract_offset, extract_range};
+
+    use crate::IndentStyle;
+    use super::assists;
+
+    /// Renders the result of every applicable assist, with the cursor.
+    pub(super) fn check(before: &str) -> String {
+        let (range, before) = if before.matches("<|>").count() == 2 {
+            extract_range(before)
+        } else {
+            let (offset, before) = extract_offset(before);
+            (TextRange::offset_len(offset, 0.into()), before)
+        };
+        let file = SourceFile::parse(&before);
+        let style = IndentStyle::detect(&before);
+        let mut buf = String::new();
+        for assist in assists(&file, range, &style) {
+            let mut after = assist.edit.apply(&before);
+            if let Some(offset) = assist.cursor_position {
+                after = add_cursor(&after, offset);
+            }
+            buf.push_str(&format!("\n{}:\n{}\n", assist.label, after));
+        }
+        buf
+    }
+
+    #[test]
+    fn flip_comma_and_change_visibility() {
+        assert_snapshot_matches!(
+            check("fn foo(x: i32,<|> y: u32) {}"),
+            @r###"
+flip comma:
+fn foo(y: u32, x: i32) {}
+"###
+        );
+        assert_snapshot_matches!(
+            check("/// doc\nf<|>n foo() {}"),
+            @r###"
+make pub(crate):
+/// doc
+<|>pub(crate) fn foo() {}
+"###
+        );
+        assert_snapshot_matches!(
+            check("pub(cr<|>ate) fn foo() {}"),
+            @r###"
+change to pub:
+<|>pub fn foo() {}
+"###
+        );
+    }
+
+    #[test]
+    fn flip_binexpr() {
+        assert_snapshot_matches!(
+            check("fn f() { let x = a ==<|> b + c; }"),
+            @r###"
+flip binary expression:
+fn f() { let x = b + c == a; }
+"###
+        );
+        assert_snapshot_matches!(
+            check("fn f() { let x = a.len() <|><= 92; }"),
+            @r###"
+flip binary expression:
+fn f() { let x = 92 >= a.len(); }
+"###
+        );
+        assert!(check("fn f() { let x = a -<|> b; }").is_empty());
+        assert!(check("fn f() { let x = <|>a < b; }").is_empty());
+    }
+
+    #[test]
+    fn add_derive() {
+        assert_snapshot_matches!(
+            check("struct Foo { a: i32,<|> }"),
+            @r###"
+add `#[derive]`:
+#[derive(<|>)]
+struct Foo { a: i32, }
+"###
+        );
+        assert_snapshot_matches!(
+            check("#[derive(Clone)]\nstruct <|>Foo;"),
+            @r###"
+add `#[derive]`:
+#[derive(Clone<|>)]
+struct Foo;
+"###
+        );
+    }
+
+    #[test]
+    fn introduce_variable() {
+        assert_snapshot_matches!(
+            check("fn foo() {\n    bar(<|>1 + 2<|>);\n}"),
+            @r###"
+introduce variable:
+fn foo() {
+    let <|>var_name = 1 + 2;
+    bar(var_name);
+}
+"###
+        );
+        assert!(check("fn foo() {\n    <|>bar<|>;\n}").is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+use std::fmt::Write;
+
+use rustc_hash::FxHashSet;
+use ra_text_edit::TextEditBuilder;
+use ra_syntax::{
+    AstNode, SyntaxNode, SmolStr, TextRange, TextUnit,
+    ast::{self, NameOwner},
+    SyntaxKind::{self, *},
+};
+
+use crate::{LocalEdit, assists::AssistCtx};
+
+const FN_NAME: &str = "fun_name";
+
+/// Moves the selected statements of a block into a new function, placed after
+/// the enclosing one. Locals defined before the selection become parameters,
+/// passed by value. Without type information, the types of the parameters
+/// and of the returned value are left as `_` placeholders. Inside impls and
+/// traits, the new function is an associated one, called through `Self::`.
+///
+/// Selections which would need to change control flow (`return`, `?`,
+/// `break`), which use `self` or which define locals used after the selection
+/// are not supported. Neither are the ones where a macro call mentions one of
+/// the locals, as we can't tell whether the macro uses it.
+pub(super) fn extract_function(ctx: &AssistCtx) -> Option<LocalEdit> {
+    if ctx.range.is_empty() {
+        return None;
+    }
+    let block = ctx.covering_node().ancestors().find_map(ast::Block::cast)?;
+    let fn_def = block.syntax().ancestors().find_map(ast::FnDef::cast)?;
+
+    let mut selected = Vec::new();
+    let mut following = Vec::new();
+    for child in block.syntax().children() {
+        if ast::Stmt::cast(child).is_none() && ast::Expr::cast(child).is_none() {
+            continue;
+        }
+        let range = child.range();
+        if range.is_subrange(&ctx.range) {
+            selected.push(child);
+        } else if range.intersection(&ctx.range).map_or(false, |it| !it.is_empty()) {
+            return None;
+        } else if range.start() >= ctx.range.end() {
+            following.push(child);
+        }
+    }
+    let first = *selected.first()?;
+    let last = *selected.last()?;
+    let selection = TextRange::from_to(first.range().start(), last.range().end());
+    let has_tail = block.expr().map(|it| it.syntax()) == Some(last);
+
+    if selected.iter().any(|stmt| stmt.descendants().any(|it| changes_control_flow(stmt, it))) {
+        return None;
+    }
+
+    let bound_before = bound_before(fn_def, block, selection.start());
+    let bound_inside: Vec<_> = selected
+        .iter()
+        .flat_map(|it| it.descendants())
+        .filter_map(ast::BindPat::cast)
+        .filter_map(|it| Some((it.name()?.text().clone(), it.syntax().range().end())))
+        .collect();
+    let is_bound_inside = |name: &SmolStr| bound_inside.iter().any(|(bound, _)| bound == name);
+    if following
+        .iter()
+        .flat_map(|it| it.descendants())
+        .filter_map(local_name)
+        .any(|name| is_bound_inside(&name))
+        || following.iter().flat_map(|it| macro_idents(it)).any(is_bound_inside)
+        || selected.iter().flat_map(|it| macro_idents(it)).any(|it| bound_before.contains(it))
+    {
+        return None;
+    }
+
+    let mut params: Vec<SmolStr> = Vec::new();
+    for node in selected.iter().flat_map(|it| it.descendants()) {
+        let name = match local_name(node) {
+            Some(it) => it,
+            None => continue,
+        };
+        let shadowed =
+            bound_inside.iter().any(|(bound, end)| *bound == name && *end <= node.range().start());
+        if bound_before.contains(&name) && !shadowed && !params.contains(&name) {
+            params.push(name);
+        }
+    }
+
+    let call = {
+        let args: Vec<&str> = params.iter().map(|it| it.as_str()).collect();
+        let qualifier = if is_associated(fn_def) { "Self::" } else { "" };
+        let mut buf = format!("{}{}({})", qualifier, FN_NAME, args.join(", "));
+        if !has_tail {
+            buf.push(';');
+        }
+        buf
+    };
+    let fn_indent = indent_of(fn_def.syntax());
+    let new_fn = FnDefBuilder {
+        name: FN_NAME,
+        params: params.iter().map(|it| it.as_str()).collect(),
+        returns_value: has_tail,
+        body: &ctx.file.syntax().text().to_string()
+            [selection.start().to_usize()..selection.end().to_usize()],
+        body_indent: &indent_of(first),
+        indent_unit: &ctx.style.unit(),
+    }
+    .build(&fn_indent);
+    let prefix = format!("\n\n{}", fn_indent);
+
+    let mut edit = TextEditBuilder::default();
+    edit.replace(selection, call.clone());
+    edit.insert(fn_def.syntax().range().end(), format!("{}{}", prefix, new_fn));
+
+    // The new function is inserted after the selection, so the cursor has to
+    // be shifted by the size of the replaced text.
+    let cursor_position = fn_def.syntax().range().end().to_usize() + call.len()
+        - selection.len().to_usize()
+        + prefix.len()
+        + "fn ".len();
+    Some(LocalEdit {
+        label: "extract into function".to_string(),
+        edit: edit.finish(),
+        cursor_position: Some(TextUnit::from_usize(cursor_position)),
+        typing: None,
+    })
+}
+
+/// A textual template of a function definition.
+struct FnDefBuilder<'a> {
+    name: &'a str,
+    params: Vec<&'a str>,
+    returns_value: bool,
+    /// The text of the statements, with all lines but the first one indented
+    /// by `body_indent`.
+    body: &'a str,
+    body_indent: &'a str,
+    /// A single level of indentation, as used in the file.
+    indent_unit: &'a str,
+}
+
+impl FnDefBuilder<'_> {
+    /// Renders the function. The first line is not indented, as it is
+    /// expected to follow existing indentation.
+    fn build(&self, indent: &str) -> String {
+        let mut buf = String::new();
+        let params: Vec<String> = self.params.iter().map(|it| format!("{}: _", it)).collect();
+        write!(buf, "fn {}({})", self.name, params.join(", ")).unwrap();
+        if self.returns_value {
+            buf.push_str(" -> _");
+        }
+        buf.push_str(" {\n");
+        for line in self.body.lines() {
+            let line = if line.starts_with(self.body_indent) {
+                &line[self.body_indent.len()..]
+            } else {
+                line.trim_start()
+            };
+            if !line.is_empty() {
+                write!(buf, "{}{}{}", indent, self.indent_unit, line).unwrap();
+            }
+            buf.push('\n');
+        }
+        write!(buf, "{}}}", indent).unwrap();
+        buf
+    }
+}
+
+/// Names of locals bound in `fn_def` before `offset`, in a scope which
+/// includes `block`.
+fn bound_before(fn_def: &ast::FnDef, block: &ast::Block, offset: TextUnit) -> FxHashSet<SmolStr> {
+    let visible_blocks: Vec<&SyntaxNode> =
+        block.syntax().ancestors().take_while(|it| *it != fn_def.syntax()).collect();
+    fn_def
+        .syntax()
+        .descendants()
+        .filter_map(ast::BindPat::cast)
+        .filter(|it| it.syntax().range().end() <= offset)
+        .filter(|it| match it.syntax().ancestors().find(|node| node.kind() == BLOCK) {
+            Some(scope) => visible_blocks.contains(&scope),
+            // A parameter
+            None => true,
+        })
+        .filter_map(|it| Some(it.name()?.text().clone()))
+        .collect()
+}
+
+/// The name of a reference to a local, like `x` or a field shorthand `S { x }`.
+fn local_name(node: &SyntaxNode) -> Option<SmolStr> {
+    if let Some(field) = ast::NamedField::cast(node) {
+        if field.expr().is_some() {
+            return None;
+        }
+        return Some(field.name_ref()?.text().clone());
+    }
+    let path = ast::PathExpr::cast(node)?.path()?;
+    if path.qualifier().is_some() {
+        return None;
+    }
+    Some(path.segment()?.name_ref()?.text().clone())
+}
+
+/// The identifiers in the arguments of the macro calls in `node`.
+fn macro_idents(node: &SyntaxNode) -> impl Iterator<Item = &SmolStr> {
+    node.descendants()
+        .filter(|it| it.kind() == IDENT && it.parent().map(|it| it.kind()) == Some(TOKEN_TREE))
+        .filter_map(|it| it.leaf_text())
+}
+
+/// Whether `node` transfers control out of `stmt`, or refers to `self`.
+fn changes_control_flow(stmt: &SyntaxNode, node: &SyntaxNode) -> bool {
+    let is_inside = |kinds: &[SyntaxKind]| {
+        node.ancestors().take_while(|it| *it != stmt).any(|it| kinds.contains(&it.kind()))
+    };
+    match node.kind() {
+        RETURN_EXPR | TRY_EXPR => !is_inside(&[LAMBDA_EXPR]),
+        BREAK_EXPR | CONTINUE_EXPR => !is_inside(&[LAMBDA_EXPR, LOOP_EXPR, WHILE_EXPR, FOR_EXPR]),
+        SELF_KW => true,
+        _ => false,
+    }
+}
+
+fn is_associated(fn_def: &ast::FnDef) -> bool {
+    fn_def
+        .syntax()
+        .parent()
+        .filter(|it| it.kind() == ITEM_LIST)
+        .and_then(|it| it.parent())
+        .map_or(false, |it| it.kind() == IMPL_BLOCK || it.kind() == TRAIT_DEF)
+}
+
+fn indent_of(node: &SyntaxNode) -> String {
+    match node.prev_sibling().filter(|it| it.kind() == WHITESPACE) {
+        Some(ws) => ws.text().to_string().rsplit('\n').next().unwrap_or("").to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot_matches;
+
+    use crate::assists::tests::check;
+
+    #[test]
+    fn extract_statements() {
+        assert_snapshot_matches!(
+            check(
+                "
+fn foo(a: u32) {
+    let b = 1;
+    <|>let c = a + b;
+    if c > 0 {
+        bar(c);
+    }<|>
+    baz();
+}"
+            ),
+            @r###"
+extract into function:
+
+fn foo(a: u32) {
+    let b = 1;
+    fun_name(a, b);
+    baz();
+}
+
+fn <|>fun_name(a: _, b: _) {
+    let c = a + b;
+    if c > 0 {
+        bar(c);
+    }
+}
+"###
+        );
+    }
+
+    #[test]
+    fn extract_tail_expression_in_impl() {
+        assert_snapshot_matches!(
+            check(
+                "
+impl S {
+    fn foo() -> u32 {
+        let x = 92;
+        <|>let y = x * 2;
+        y + 1<|>
+    }
+}"
+            ),
+            @r###"
+extract into function:
+
+impl S {
+    fn foo() -> u32 {
+        let x = 92;
+        Self::fun_name(x)
+    }
+
+    fn <|>fun_name(x: _) -> _ {
+        let y = x * 2;
+        y + 1
+    }
+}
+"###
+        );
+    }
+
+    #[test]
+    fn extract_follows_indent_style() {
+        let after = check("fn foo() {\n\tlet x = 1;\n\t<|>bar(x);<|>\n}");
+        assert!(after.contains("fun_name(x: _) {\n\tbar(x);\n}"), "{}", after);
+    }
+
+    #[test]
+    fn extract_passes_locals_of_field_shorthands() {
+        let after = check("fn foo() {\n    let a = 1;\n    <|>bar(S { a });<|>\n}");
+        assert!(after.contains("fun_name(a);"), "{}", after);
+        assert!(after.contains("fun_name(a: _) {\n    bar(S { a });\n}"), "{}", after);
+    }
+
+    #[test]
+    fn extract_function_not_applicable() {
+        // Would need to return early from `foo`.
+        assert!(!check("fn foo() { <|>if x { return; }<|> }").contains("extract into function"));
+        // `y` is used after the selection.
+        assert!(!check("fn foo() { <|>let y = 1;<|> bar(y); }").contains("extract into function"));
+        assert!(!check("fn foo() { <|>let y = 1;<|> dbg!(y); }").contains("extract into function"));
+        // `a` might be used by the macro.
+        assert!(!check("fn foo() { let a = 1; <|>println!(\"{}\", a);<|> }")
+            .contains("extract into function"));
+        assert!(!check("fn foo() { bar(<|>1 + 1<|>); }").contains("extract into function"));
+    }
+}
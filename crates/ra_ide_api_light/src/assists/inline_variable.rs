@@ -0,0 +1,171 @@
+use ra_text_edit::TextEditBuilder;
+use ra_syntax::{
+    AstNode, SyntaxNode, TextRange,
+    ast::{self, NameOwner},
+    SyntaxKind::*,
+};
+
+use crate::{
+    LocalEdit,
+    assists::{AssistCtx, needs_parens},
+};
+
+/// Replaces all uses of an immutable `let`-bound local in its block with the
+/// initializer. Bails out if the local might be shadowed in a nested scope or
+/// used from a macro call, as we can't tell the uses apart without name
+/// resolution.
+pub(super) fn inline_variable(ctx: &AssistCtx) -> Option<LocalEdit> {
+    let let_stmt = ctx.node_at_offset::<ast::LetStmt>()?;
+    if !ctx.range.is_subrange(&let_stmt.syntax().range()) {
+        return None;
+    }
+    let bind_pat = match let_stmt.pat()?.kind() {
+        ast::PatKind::BindPat(it) => it,
+        _ => return None,
+    };
+    if bind_pat.is_mutable() || bind_pat.is_ref() || bind_pat.pat().is_some() {
+        return None;
+    }
+    let name = bind_pat.name()?.text().clone();
+    let initializer = let_stmt.initializer()?;
+    let block = let_stmt.syntax().parent().and_then(ast::Block::cast)?;
+
+    // The statements and the tail expression following the `let`, up to and
+    // including the initializer of a `let` which shadows the local.
+    let mut scope: Vec<&SyntaxNode> = Vec::new();
+    let following = block
+        .syntax()
+        .children()
+        .skip_while(|it| *it != let_stmt.syntax())
+        .skip(1)
+        .filter(|it| ast::Stmt::cast(it).is_some() || ast::Expr::cast(it).is_some());
+    for node in following {
+        if let Some(shadowing) = ast::LetStmt::cast(node).filter(|it| binds(it, &name)) {
+            scope.extend(shadowing.initializer().map(|it| it.syntax()));
+            break;
+        }
+        scope.push(node);
+    }
+
+    let mut usages = Vec::new();
+    for node in scope.iter().flat_map(|it| it.descendants()) {
+        match node.kind() {
+            BIND_PAT if ast::BindPat::cast(node)?.name().map(|it| it.text()) == Some(&name) => {
+                return None;
+            }
+            TOKEN_TREE if node.children().any(|it| it.leaf_text() == Some(&name)) => return None,
+            PATH_EXPR => {
+                let path = ast::PathExpr::cast(node)?.path()?;
+                let name_ref = path.segment()?.name_ref();
+                if path.qualifier().is_none() && name_ref.map(|it| it.text()) == Some(&name) {
+                    usages.push(node);
+                }
+            }
+            // A shorthand field, `S { x }`, has no `PATH_EXPR`.
+            NAMED_FIELD => {
+                let field = ast::NamedField::cast(node)?;
+                if field.expr().is_none() && field.name_ref().map(|it| it.text()) == Some(&name) {
+                    usages.push(node);
+                }
+            }
+            _ => (),
+        }
+    }
+    if usages.is_empty() {
+        return None;
+    }
+
+    let mut edit = TextEditBuilder::default();
+    edit.delete(stmt_range_with_trailing_ws(let_stmt.syntax()));
+    let init_text = initializer.syntax().text().to_string();
+    for usage in usages {
+        let replacement = if usage.kind() == NAMED_FIELD {
+            format!("{}: {}", name, init_text)
+        } else if needs_parens(initializer, usage) {
+            format!("({})", init_text)
+        } else {
+            init_text.clone()
+        };
+        edit.replace(usage.range(), replacement);
+    }
+    Some(LocalEdit {
+        label: "inline variable".to_string(),
+        edit: edit.finish(),
+        cursor_position: None,
+        typing: None,
+    })
+}
+
+fn binds(let_stmt: &ast::LetStmt, name: &str) -> bool {
+    let_stmt
+        .pat()
+        .and_then(|pat| pat.syntax().descendants().find_map(ast::BindPat::cast))
+        .and_then(|it| it.name())
+        .map_or(false, |it| it.text() == name)
+}
+
+fn stmt_range_with_trailing_ws(stmt: &SyntaxNode) -> TextRange {
+    match stmt.next_sibling().filter(|it| it.kind() == WHITESPACE) {
+        Some(ws) => TextRange::from_to(stmt.range().start(), ws.range().end()),
+        None => stmt.range(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot_matches;
+
+    use crate::assists::tests::check;
+
+    #[test]
+    fn inline_variable() {
+        assert_snapshot_matches!(
+            check("fn f() {\n    let <|>x = a + b;\n    foo(x);\n    x * 2\n}"),
+            @r###"
+inline variable:
+fn f() {
+    foo(a + b);
+    (a + b) * 2
+}
+"###
+        );
+    }
+
+    #[test]
+    fn inline_variable_stops_at_shadowing() {
+        assert_snapshot_matches!(
+            check("fn f() {\n    let <|>x = 1;\n    let x = x + 1;\n    x\n}"),
+            @r###"
+inline variable:
+fn f() {
+    let x = 1 + 1;
+    x
+}
+"###
+        );
+    }
+
+    #[test]
+    fn inline_variable_into_field_shorthand() {
+        assert_snapshot_matches!(
+            check("fn f() {\n    let <|>x = 1;\n    foo(x);\n    S { x }\n}"),
+            @r###"
+inline variable:
+fn f() {
+    foo(1);
+    S { x: 1 }
+}
+"###
+        );
+    }
+
+    #[test]
+    fn inline_variable_not_applicable() {
+        // Shadowed in a nested scope.
+        assert!(check("fn f() { let <|>x = 1; let g = |x| x; x }").is_empty());
+        // Used from a macro call.
+        assert!(check("fn f() { let <|>x = 1; println!(\"{}\", x); }").is_empty());
+        assert!(check("fn f() { let <|>mut x = 1; x += 1; }").is_empty());
+        assert!(check("fn f() { let <|>x = 1; }").is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use ra_text_edit::TextEditBuilder;
+use ra_syntax::{AstNode, SourceFile, ast, SyntaxKind::*};
+
+use crate::{
+    LocalEdit,
+    assists::{AssistCtx, needs_parens},
+};
+
+/// Replaces `dbg!(expr)` with `expr`, keeping the parentheses only if they
+/// are needed where the macro call stands.
+pub(super) fn remove_dbg(ctx: &AssistCtx) -> Option<LocalEdit> {
+    let macro_call = ctx.node_at_offset::<ast::MacroCall>()?;
+    let name_ref = macro_call.path()?.segment()?.name_ref()?;
+    if name_ref.text() != "dbg" {
+        return None;
+    }
+    let tt = macro_call.token_tree()?;
+    let first = tt.syntax().first_child()?;
+    let last = tt.syntax().last_child()?;
+    if first.kind() != L_PAREN || last.kind() != R_PAREN || first == last {
+        return None;
+    }
+    let text = tt.syntax().text().to_string();
+    let inner = text[1..text.len() - 1].trim();
+    let inner = inner.trim_end_matches(',').trim_end();
+
+    let replacement = if inner.is_empty() {
+        "()".to_string()
+    } else {
+        // The token tree is not parsed, so we parse the argument separately to
+        // find out its precedence.
+        let fragment = SourceFile::parse(&format!("fn f() {{ {} }}", inner));
+        if !fragment.errors().is_empty() {
+            return None;
+        }
+        let block = fragment.syntax().descendants().find_map(ast::Block::cast)?;
+        if block.statements().next().is_some() {
+            return None;
+        }
+        let expr = block.expr()?;
+        if needs_parens(expr, macro_call.syntax()) {
+            format!("({})", inner)
+        } else {
+            inner.to_string()
+        }
+    };
+
+    let mut edit = TextEditBuilder::default();
+    edit.replace(macro_call.syntax().range(), replacement);
+    Some(LocalEdit {
+        label: "remove dbg!()".to_string(),
+        edit: edit.finish(),
+        cursor_position: Some(macro_call.syntax().range().start()),
+        typing: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot_matches;
+
+    use crate::assists::tests::check;
+
+    #[test]
+    fn remove_dbg() {
+        assert_snapshot_matches!(
+            check("fn f() { <|>dbg!(1 + 1); }"),
+            @r###"
+remove dbg!():
+fn f() { <|>1 + 1; }
+"###
+        );
+        assert_snapshot_matches!(
+            check("fn f() { let x = d<|>bg!(a + b) * c; }"),
+            @r###"
+remove dbg!():
+fn f() { let x = <|>(a + b) * c; }
+"###
+        );
+        assert_snapshot_matches!(
+            check("fn f() { let x = dbg!(a + b<|>,).len(); }"),
+            @r###"
+remove dbg!():
+fn f() { let x = <|>(a + b).len(); }
+"###
+        );
+        assert_snapshot_matches!(
+            check("fn f() { let x = dbg!(a.len()<|>) + b; }"),
+            @r###"
+remove dbg!():
+fn f() { let x = <|>a.len() + b; }
+"###
+        );
+    }
+
+    #[test]
+    fn remove_dbg_not_applicable() {
+        assert!(check("fn f() { <|>vec!(1 + 1); }").is_empty());
+        assert!(check("fn f() { <|>dbg!(a, b); }").is_empty());
+    }
+}
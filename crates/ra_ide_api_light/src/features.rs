@@ -0,0 +1,145 @@
+//! A runtime description of the operations this crate provides.
+//!
+//! Embedders which are built against different versions of the crate can
+//! check for an operation here, instead of finding out about a moved or
+//! changed function from a compile error.
+
+/// An operation provided by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    MatchingBrace,
+    Highlight,
+    FileStructure,
+    FileStructureInRange,
+    FoldingRanges,
+    FoldingRangesInRange,
+    Assists,
+    OnEnter,
+    OnEqTyped,
+    OnDotTyped,
+    IndentAt,
+    IsInStringOrComment,
+    IsRangeInStringOrComment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureInfo {
+    pub feature: Feature,
+    /// The name of the function implementing the feature.
+    pub name: &'static str,
+    /// Bumped whenever the signature or the behavior of the feature changes
+    /// in a way embedders should know about.
+    pub version: u32,
+}
+
+/// The set of features available in this build of the crate.
+#[derive(Debug, Clone, Copy)]
+pub struct Features {
+    features: &'static [FeatureInfo],
+}
+
+const FEATURES: &[FeatureInfo] = &[
+    FeatureInfo { feature: Feature::MatchingBrace, name: "matching_brace", version: 1 },
+    FeatureInfo { feature: Feature::Highlight, name: "highlight", version: 1 },
+    FeatureInfo { feature: Feature::FileStructure, name: "file_structure", version: 1 },
+    FeatureInfo {
+        feature: Feature::FileStructureInRange,
+        name: "file_structure_in_range",
+        version: 1,
+    },
+    FeatureInfo { feature: Feature::FoldingRanges, name: "folding_ranges", version: 1 },
+    FeatureInfo {
+        feature: Feature::FoldingRangesInRange,
+        name: "folding_ranges_in_range",
+        version: 1,
+    },
+    FeatureInfo { feature: Feature::Assists, name: "assists", version: 1 },
+    FeatureInfo { feature: Feature::OnEnter, name: "on_enter", version: 1 },
+    FeatureInfo { feature: Feature::OnEqTyped, name: "on_eq_typed", version: 1 },
+    FeatureInfo { feature: Feature::OnDotTyped, name: "on_dot_typed", version: 1 },
+    FeatureInfo { feature: Feature::IndentAt, name: "indent_at", version: 1 },
+    FeatureInfo {
+        feature: Feature::IsInStringOrComment,
+        name: "is_in_string_or_comment",
+        version: 1,
+    },
+    FeatureInfo {
+        feature: Feature::IsRangeInStringOrComment,
+        name: "is_range_in_string_or_comment",
+        version: 1,
+    },
+];
+
+impl Features {
+    pub fn current() -> Features {
+        Features { features: FEATURES }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'static FeatureInfo> {
+        self.features.iter()
+    }
+
+    /// The version of `feature`, or `None` if it is not available.
+    pub fn version(&self, feature: Feature) -> Option<u32> {
+        self.iter().find(|it| it.feature == feature).map(|it| it.version)
+    }
+
+    /// Whether `feature` is available in `min_version` or a later one.
+    pub fn supports(&self, feature: Feature, min_version: u32) -> bool {
+        self.version(feature).map_or(false, |version| version >= min_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails to compile when a variant is added to `Feature`, as a reminder
+    /// to list it in `FEATURES` and in the test below.
+    fn is_listed(feature: Feature) -> bool {
+        let listed_once = |feature| FEATURES.iter().filter(|it| it.feature == feature).count() == 1;
+        match feature {
+            Feature::MatchingBrace
+            | Feature::Highlight
+            | Feature::FileStructure
+            | Feature::FileStructureInRange
+            | Feature::FoldingRanges
+            | Feature::FoldingRangesInRange
+            | Feature::Assists
+            | Feature::OnEnter
+            | Feature::OnEqTyped
+            | Feature::OnDotTyped
+            | Feature::IndentAt
+            | Feature::IsInStringOrComment
+            | Feature::IsRangeInStringOrComment => listed_once(feature),
+        }
+    }
+
+    #[test]
+    fn every_feature_is_listed_once() {
+        let all = [
+            Feature::MatchingBrace,
+            Feature::Highlight,
+            Feature::FileStructure,
+            Feature::FileStructureInRange,
+            Feature::FoldingRanges,
+            Feature::FoldingRangesInRange,
+            Feature::Assists,
+            Feature::OnEnter,
+            Feature::OnEqTyped,
+            Feature::OnDotTyped,
+            Feature::IndentAt,
+            Feature::IsInStringOrComment,
+            Feature::IsRangeInStringOrComment,
+        ];
+        for &feature in all.iter() {
+            assert!(is_listed(feature), "{:?} is not listed exactly once", feature);
+        }
+        assert_eq!(all.len(), FEATURES.len());
+
+        let features = Features::current();
+        assert!(features.iter().all(|it| it.version == 1));
+        assert!(features.supports(Feature::FoldingRanges, 1));
+        assert!(!features.supports(Feature::Assists, 2));
+    }
+}
@@ -20,45 +20,90 @@ pub struct Fold {
     pub kind: FoldKind,
 }
 
-pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
-    let mut res = vec![];
-    let mut visited_comments = FxHashSet::default();
-    let mut visited_imports = FxHashSet::default();
-    let mut visited_mods = FxHashSet::default();
+pub fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
+    folding_ranges_in_range(file, None)
+}
+
+/// Like `folding_ranges`, but if `range` is given, only the folds intersecting
+/// it are computed. Nodes outside of the range are not traversed, except for
+/// the siblings of the nodes inside of it, so that groups of comments,
+/// imports and mods are folded as a whole.
+pub fn folding_ranges_in_range(file: &SourceFile, range: Option<TextRange>) -> Vec<Fold> {
+    let nodes: Vec<&SyntaxNode> = match range {
+        None => file.syntax().descendants().collect(),
+        Some(range) => {
+            let mut nodes = vec![file.syntax()];
+            nodes_in_range(file.syntax(), range, &mut nodes);
+            nodes
+        }
+    };
+    let mut collector = FoldCollector::default();
+    for node in nodes {
+        collector.visit(node);
+    }
+    collector.finish(range)
+}
+
+/// Computes folds one node at a time, so that it can share a tree traversal
+/// with other analyses. Nodes must be visited in preorder.
+#[derive(Default)]
+pub struct FoldCollector<'a> {
+    visited_comments: FxHashSet<&'a SyntaxNode>,
+    visited_imports: FxHashSet<&'a SyntaxNode>,
+    visited_mods: FxHashSet<&'a SyntaxNode>,
+    res: Vec<Fold>,
+}
 
-    for node in file.syntax().descendants() {
+impl<'a> FoldCollector<'a> {
+    pub fn visit(&mut self, node: &'a SyntaxNode) {
         // Fold items that span multiple lines
         if let Some(kind) = fold_kind(node.kind()) {
             if node.text().contains('\n') {
-                res.push(Fold { range: node.range(), kind });
+                self.res.push(Fold { range: node.range(), kind });
             }
         }
 
         // Fold groups of comments
-        if node.kind() == COMMENT && !visited_comments.contains(&node) {
-            if let Some(range) = contiguous_range_for_comment(node, &mut visited_comments) {
-                res.push(Fold { range, kind: FoldKind::Comment })
+        if node.kind() == COMMENT && !self.visited_comments.contains(&node) {
+            if let Some(range) = contiguous_range_for_comment(node, &mut self.visited_comments) {
+                self.res.push(Fold { range, kind: FoldKind::Comment })
             }
         }
 
         // Fold groups of imports
-        if node.kind() == USE_ITEM && !visited_imports.contains(&node) {
-            if let Some(range) = contiguous_range_for_group(node, &mut visited_imports) {
-                res.push(Fold { range, kind: FoldKind::Imports })
+        if node.kind() == USE_ITEM && !self.visited_imports.contains(&node) {
+            if let Some(range) = contiguous_range_for_group(node, &mut self.visited_imports) {
+                self.res.push(Fold { range, kind: FoldKind::Imports })
             }
         }
 
         // Fold groups of mods
-        if node.kind() == MODULE && !has_visibility(&node) && !visited_mods.contains(&node) {
+        if node.kind() == MODULE && !has_visibility(&node) && !self.visited_mods.contains(&node) {
             if let Some(range) =
-                contiguous_range_for_group_unless(node, has_visibility, &mut visited_mods)
+                contiguous_range_for_group_unless(node, has_visibility, &mut self.visited_mods)
             {
-                res.push(Fold { range, kind: FoldKind::Mods })
+                self.res.push(Fold { range, kind: FoldKind::Mods })
             }
         }
     }
 
-    res
+    /// Returns the folds intersecting `range`, or all of them.
+    pub fn finish(self, range: Option<TextRange>) -> Vec<Fold> {
+        let mut res = self.res;
+        res.retain(|fold| range.map_or(true, |range| range.intersection(&fold.range).is_some()));
+        res
+    }
+}
+
+/// Collects the children of `node` in preorder, descending only into the
+/// ones intersecting `range`.
+fn nodes_in_range<'a>(node: &'a SyntaxNode, range: TextRange, acc: &mut Vec<&'a SyntaxNode>) {
+    for child in node.children() {
+        acc.push(child);
+        if range.intersection(&child.range()).is_some() {
+            nodes_in_range(child, range, acc);
+        }
+    }
 }
 
 fn fold_kind(kind: SyntaxKind) -> Option<FoldKind> {
@@ -162,6 +207,7 @@ fn contiguous_range_for_comment<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ra_syntax::TextUnit;
     use test_utils::extract_ranges;
 
     fn do_check(text: &str, fold_kinds: &[FoldKind]) {
@@ -323,4 +369,24 @@ macro_rules! foo <fold>{
         let folds = &[FoldKind::Block];
         do_check(text, folds);
     }
+
+    #[test]
+    fn test_folding_ranges_in_range() {
+        let text = "use a;\nuse b;\n\nfn foo() {\n    bar();\n}\n\nfn baz() {\n    quux();\n}\n";
+        let file = SourceFile::parse(text);
+        let at = |needle: &str| {
+            let offset = TextUnit::from_usize(text.find(needle).unwrap());
+            Some(TextRange::offset_len(offset, 1.into()))
+        };
+
+        let folds = folding_ranges_in_range(&file, at("b;"));
+        assert_eq!(folds.len(), 1);
+        assert_eq!(folds[0].kind, FoldKind::Imports);
+        assert_eq!(folds[0].range, TextRange::from_to(0.into(), 13.into()));
+
+        let folds = folding_ranges_in_range(&file, at("quux"));
+        assert_eq!(folds.len(), 1);
+        assert_eq!(folds[0].kind, FoldKind::Block);
+        assert_eq!(&text[folds[0].range.start().to_usize()..], "{\n    quux();\n}\n");
+    }
 }
@@ -0,0 +1,152 @@
+use ra_syntax::{
+    SourceFile, SyntaxNode, TextRange, TextUnit,
+    SyntaxKind::{self, *},
+    algo::{find_covering_node, find_leaf_at_offset, generate},
+};
+use ra_fmt::IndentStyle;
+
+/// Computes the indentation of a line starting at `offset`, such that the
+/// text after `offset` is nested properly in the surrounding code.
+///
+/// The indentation is derived from the line where the enclosing construct
+/// starts, so it follows the existing indentation of the file rather than
+/// the nesting depth.
+pub fn indent_at(file: &SourceFile, offset: TextUnit, style: &IndentStyle) -> String {
+    let text = file.syntax().text().to_string();
+    let next = next_token(file.syntax(), &text, offset);
+
+    // A continuation of a method call chain.
+    if let Some(dot) = next.filter(|it| it.kind() == DOT) {
+        let chain =
+            dot.parent().filter(|it| it.kind() == METHOD_CALL_EXPR || it.kind() == FIELD_EXPR);
+        if let Some(chain) = chain {
+            return line_indent(&text, chain.range().start()) + &style.unit();
+        }
+    }
+
+    let node = find_covering_node(file.syntax(), TextRange::offset_len(offset, 0.into()));
+    for node in node.ancestors() {
+        match node.kind() {
+            WHERE_CLAUSE | MATCH_ARM => {
+                let separator = if node.kind() == WHERE_CLAUSE { WHERE_KW } else { FAT_ARROW };
+                let separator = match node.children().find(|it| it.kind() == separator) {
+                    Some(it) => it,
+                    None => continue,
+                };
+                if offset >= separator.range().end() {
+                    return line_indent(&text, node.range().start()) + &style.unit();
+                }
+            }
+            kind if is_delimited(kind) => {
+                let (open, close) = match delimiters(node) {
+                    Some(it) => it,
+                    None => continue,
+                };
+                if offset < open.range().end()
+                    || close.map_or(false, |it| offset > it.range().start())
+                {
+                    continue;
+                }
+                let indent = line_indent(&text, open.range().start());
+                if next.is_some() && next == close {
+                    return indent;
+                }
+                return indent + &style.unit();
+            }
+            _ => (),
+        }
+    }
+    String::new()
+}
+
+fn is_delimited(kind: SyntaxKind) -> bool {
+    match kind {
+        BLOCK | ITEM_LIST | EXTERN_ITEM_LIST | MATCH_ARM_LIST | NAMED_FIELD_DEF_LIST
+        | POS_FIELD_DEF_LIST | ENUM_VARIANT_LIST | NAMED_FIELD_LIST | FIELD_PAT_LIST
+        | USE_TREE_LIST | PARAM_LIST | ARG_LIST | TYPE_PARAM_LIST | TYPE_ARG_LIST | ARRAY_EXPR
+        | TUPLE_EXPR | PAREN_EXPR | TOKEN_TREE => true,
+        _ => false,
+    }
+}
+
+/// The opening and the closing delimiters of a list. The closing one is
+/// missing in incomplete code.
+fn delimiters(node: &SyntaxNode) -> Option<(&SyntaxNode, Option<&SyntaxNode>)> {
+    let open = node.first_child().filter(|it| match it.kind() {
+        L_CURLY | L_PAREN | L_BRACK | L_ANGLE => true,
+        _ => false,
+    })?;
+    let close = node.last_child().filter(|it| match it.kind() {
+        R_CURLY | R_PAREN | R_BRACK | R_ANGLE => true,
+        _ => false,
+    });
+    Some((open, close))
+}
+
+/// The first token after `offset`, if it is on the same line.
+fn next_token<'a>(root: &'a SyntaxNode, text: &str, offset: TextUnit) -> Option<&'a SyntaxNode> {
+    let leaf = find_leaf_at_offset(root, offset).right_biased()?;
+    let token = if leaf.kind() == WHITESPACE { next_leaf(leaf)? } else { leaf };
+    if token.range().start() > offset
+        && text[offset.to_usize()..token.range().start().to_usize()].contains('\n')
+    {
+        return None;
+    }
+    Some(token)
+}
+
+fn next_leaf(node: &SyntaxNode) -> Option<&SyntaxNode> {
+    generate(node.ancestors().find_map(SyntaxNode::next_sibling), |it| it.first_child()).last()
+}
+
+/// The indentation of the line containing `offset`.
+fn line_indent(text: &str, offset: TextUnit) -> String {
+    let line_start = text[..offset.to_usize()].rfind('\n').map_or(0, |it| it + 1);
+    text[line_start..].chars().take_while(|&c| c == ' ' || c == '\t').collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::extract_offset;
+
+    use super::*;
+
+    fn check_indent(style: IndentStyle, before: &str, indent: &str) {
+        let (offset, before) = extract_offset(before);
+        let file = SourceFile::parse(&before);
+        assert_eq!(indent_at(&file, offset, &style), indent);
+    }
+
+    #[test]
+    fn test_indent_at() {
+        let style = IndentStyle::default();
+        check_indent(style, "fn foo() {\n<|>\n}", "    ");
+        check_indent(style, "fn foo() {\n    1\n<|>}", "");
+        check_indent(style, "fn foo() {\n    if x {\n<|>\n    }\n}", "        ");
+        check_indent(style, "fn foo(\n<|>a: u32,\n) {}", "    ");
+        check_indent(
+            style,
+            "fn foo() {\n    match x {\n        A =>\n<|>1,\n    }\n}",
+            "            ",
+        );
+        check_indent(style, "fn foo() {\n    foo\n<|>.bar()\n}", "        ");
+        check_indent(style, "fn foo<T>()\nwhere\n<|>T: Clone,\n{\n}", "    ");
+        check_indent(style, "<|>fn foo() {}", "");
+        check_indent(IndentStyle { use_tabs: true, width: 4 }, "struct S {\n<|>x: u32,\n}", "\t");
+    }
+
+    #[test]
+    fn test_detect_indent_style() {
+        let detect = IndentStyle::detect;
+        assert_eq!(detect("fn foo() {}"), IndentStyle::default());
+        assert_eq!(
+            detect("fn foo() {\n  if x {\n    1\n  }\n}"),
+            IndentStyle { use_tabs: false, width: 2 }
+        );
+        assert_eq!(
+            detect("fn foo() {\n\tif x {\n\t\t1\n\t}\n}"),
+            IndentStyle { use_tabs: true, width: 4 }
+        );
+        assert_eq!(detect("/*\n * docs\n */\nfn foo() {\n    1\n}"), IndentStyle::default());
+    }
+}
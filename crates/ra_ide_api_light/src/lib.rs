@@ -3,34 +3,68 @@
 //! This usually means functions which take syntax tree as an input and produce
 //! an edit or some auxiliary info.
 
+mod assists;
 mod structure;
+mod folding_ranges;
 mod typing;
+mod indent;
+mod macro_body;
+mod features;
 
 use rustc_hash::FxHashSet;
 use ra_text_edit::TextEditBuilder;
 use ra_syntax::{
     SourceFile, SyntaxNode, TextRange, TextUnit, Direction,
-    algo::find_leaf_at_offset,
+    algo::{find_leaf_at_offset, find_covering_node},
     SyntaxKind::{self, *},
     ast::{self, AstNode},
 };
 
 pub use crate::{
-    structure::{file_structure, StructureNode},
-    typing::{on_enter, on_dot_typed, on_eq_typed},
+    assists::assists,
+    structure::{
+        file_structure, file_structure_in_range, StructureCollector, StructureNode,
+        StructureNodeKind,
+    },
+    typing::{on_enter, on_dot_typed, on_eq_typed, TypingEdit, TypingHandler},
+    folding_ranges::{folding_ranges, folding_ranges_in_range, Fold, FoldCollector, FoldKind},
+    indent::indent_at,
+    features::{Feature, FeatureInfo, Features},
 };
+pub use ra_fmt::IndentStyle;
+
+/// The entry points of the crate which are tracked in `Features`, for
+/// embedders to glob-import.
+pub mod prelude {
+    pub use crate::{
+        assists, file_structure, file_structure_in_range, folding_ranges, folding_ranges_in_range,
+        highlight, indent_at, is_in_string_or_comment, is_range_in_string_or_comment,
+        matching_brace, on_dot_typed, on_enter, on_eq_typed, Feature, Features, IndentStyle,
+        LocalEdit,
+    };
+}
+
+use crate::macro_body::MacroBody;
 
 #[derive(Debug)]
 pub struct LocalEdit {
     pub label: String,
     pub edit: ra_text_edit::TextEdit,
     pub cursor_position: Option<TextUnit>,
+    /// Set if the edit was produced by a typing handler in response to a
+    /// keystroke, rather than requested explicitly.
+    pub typing: Option<TypingEdit>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HighlightedRange {
     pub range: TextRange,
     pub tag: &'static str,
+    /// Whether the range comes from a best-effort parse of a macro call body.
+    pub heuristic: bool,
+    /// Whether the meaning of the range comes from a macro expansion, and the
+    /// range is where the expanded tokens were written in the macro call.
+    pub macro_generated: bool,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -56,19 +90,74 @@ pub fn matching_brace(file: &SourceFile, offset: TextUnit) -> Option<TextUnit> {
             Some((node, idx))
         })
         .next()?;
-    let parent = brace_node.parent()?;
     let matching_kind = BRACES[brace_idx ^ 1];
-    let matching_node = parent.children().find(|node| node.kind() == matching_kind)?;
-    Some(matching_node.range().start())
+    let direction = if brace_idx % 2 == 0 { Direction::Next } else { Direction::Prev };
+    // Inside of token trees, only the delimiters get their own nodes: several
+    // pairs of `<>` may share the parent, so the nesting is counted.
+    let mut depth = 0;
+    for sibling in brace_node.siblings(direction) {
+        if sibling.kind() == brace_node.kind() {
+            depth += 1;
+        } else if sibling.kind() == matching_kind {
+            depth -= 1;
+            if depth == 0 {
+                return Some(sibling.range().start());
+            }
+        }
+    }
+    None
+}
+
+/// Whether `offset` is inside of a string or char literal, or of a comment.
+/// Hosts use this to turn off auto-closing of brackets, typing handlers and
+/// completion there.
+pub fn is_in_string_or_comment(file: &SourceFile, offset: TextUnit) -> bool {
+    find_leaf_at_offset(file.syntax(), offset).any(|leaf| contains(leaf, offset, offset))
+}
+
+/// Like `is_in_string_or_comment`, but for the whole `range`, which must be
+/// inside of a single literal or comment.
+pub fn is_range_in_string_or_comment(file: &SourceFile, range: TextRange) -> bool {
+    let node = find_covering_node(file.syntax(), range);
+    contains(node, range.start(), range.end())
+}
+
+fn contains(token: &SyntaxNode, start: TextUnit, end: TextUnit) -> bool {
+    let range = token.range();
+    match token.kind() {
+        // A line comment runs until the end of the line, so the end of the
+        // comment is still inside of it.
+        COMMENT if token.text().starts_with("//") => range.start() < start,
+        COMMENT => range.start() < start && end < range.end(),
+        STRING | RAW_STRING | BYTE_STRING | RAW_BYTE_STRING | CHAR | BYTE => {
+            range.start() < start && end < range.end()
+        }
+        _ => false,
+    }
 }
 
 pub fn highlight(root: &SyntaxNode) -> Vec<HighlightedRange> {
-    // Visited nodes to handle highlighting priorities
-    let mut highlighted = FxHashSet::default();
-    let mut res = Vec::new();
+    let mut highlighter = Highlighter::default();
     for node in root.descendants() {
-        if highlighted.contains(&node) {
-            continue;
+        highlighter.visit(node);
+    }
+    highlighter.finish()
+}
+
+/// Computes highlighting one node at a time, so that it can share a tree
+/// traversal with other analyses. Nodes must be visited in preorder.
+#[derive(Default)]
+pub struct Highlighter<'a> {
+    /// Visited nodes to handle highlighting priorities
+    highlighted: FxHashSet<&'a SyntaxNode>,
+    macro_calls: Vec<&'a ast::MacroCall>,
+    res: Vec<HighlightedRange>,
+}
+
+impl<'a> Highlighter<'a> {
+    pub fn visit(&mut self, node: &'a SyntaxNode) {
+        if self.highlighted.contains(&node) {
+            return;
         }
         let tag = match node.kind() {
             COMMENT => "comment",
@@ -76,15 +165,16 @@ pub fn highlight(root: &SyntaxNode) -> Vec<HighlightedRange> {
             ATTR => "attribute",
             NAME_REF => "text",
             NAME => "function",
-            INT_NUMBER | FLOAT_NUMBER | CHAR | BYTE => "literal",
+            k if k.is_literal() => "literal",
             LIFETIME => "parameter",
             k if k.is_keyword() => "keyword",
             _ => {
                 if let Some(macro_call) = ast::MacroCall::cast(node) {
+                    self.macro_calls.push(macro_call);
                     if let Some(path) = macro_call.path() {
                         if let Some(segment) = path.segment() {
                             if let Some(name_ref) = segment.name_ref() {
-                                highlighted.insert(name_ref.syntax());
+                                self.highlighted.insert(name_ref.syntax());
                                 let range_start = name_ref.syntax().range().start();
                                 let mut range_end = name_ref.syntax().range().end();
                                 for sibling in path.syntax().siblings(Direction::Next) {
@@ -93,20 +183,55 @@ pub fn highlight(root: &SyntaxNode) -> Vec<HighlightedRange> {
                                         _ => (),
                                     }
                                 }
-                                res.push(HighlightedRange {
+                                self.res.push(HighlightedRange {
                                     range: TextRange::from_to(range_start, range_end),
                                     tag: "macro",
+                                    heuristic: false,
+                                    macro_generated: false,
                                 })
                             }
                         }
                     }
                 }
-                continue;
+                return;
             }
         };
-        res.push(HighlightedRange { range: node.range(), tag })
+        self.res.push(HighlightedRange {
+            range: node.range(),
+            tag,
+            heuristic: false,
+            macro_generated: false,
+        })
+    }
+
+    pub fn finish(self) -> Vec<HighlightedRange> {
+        let mut res = self.res;
+        let mut highlighted_ranges: FxHashSet<TextRange> = res.iter().map(|it| it.range).collect();
+        for macro_call in self.macro_calls {
+            let body = match MacroBody::parse(macro_call) {
+                Some(it) => it,
+                None => continue,
+            };
+            for hl in highlight(body.file().syntax()) {
+                let range = match body.map_range(hl.range) {
+                    Some(it) => it,
+                    None => continue,
+                };
+                // Keywords, literals and the like are highlighted even inside of
+                // token trees.
+                if highlighted_ranges.insert(range) {
+                    res.push(HighlightedRange {
+                        range,
+                        tag: hl.tag,
+                        heuristic: true,
+                        macro_generated: false,
+                    });
+                }
+            }
+        }
+        res.sort_by_key(|it| it.range.start());
+        res
     }
-    res
 }
 
 #[cfg(test)]
@@ -131,6 +256,21 @@ fn main() {}
         assert_debug_snapshot_matches!("highlighting", hls);
     }
 
+    #[test]
+    fn test_highlighting_in_macro_bodies() {
+        let file = SourceFile::parse("thread_local! { static FOO: u32 = 92; }");
+        let hls = highlight(file.syntax());
+
+        let name = hls.iter().find(|it| it.tag == "function").unwrap();
+        assert_eq!(name.range, TextRange::from_to(23.into(), 26.into()));
+        assert!(name.heuristic);
+        // Keywords are highlighted without parsing the body.
+        let static_kw: Vec<_> =
+            hls.iter().filter(|it| it.range == TextRange::from_to(16.into(), 22.into())).collect();
+        assert_eq!(static_kw.len(), 1);
+        assert!(!static_kw[0].heuristic);
+    }
+
     #[test]
     fn test_matching_brace() {
         fn do_check(before: &str, after: &str) {
@@ -145,6 +285,41 @@ fn main() {}
         }
 
         do_check("struct Foo { a: i32, }<|>", "struct Foo <|>{ a: i32, }");
+        do_check("m! { (a) [b] }<|>", "m! <|>{ (a) [b] }");
+        do_check("m! { (a) [b<|>] }", "m! { (a) <|>[b] }");
+        do_check("m! { Vec<|><Vec<u8>> }", "m! { Vec<Vec<u8><|>> }");
+        do_check("m! { Vec<Vec<|><u8>> }", "m! { Vec<Vec<u8<|>>> }");
+    }
+
+    #[test]
+    fn test_is_in_string_or_comment() {
+        fn check(code: &str) -> bool {
+            let (offset, code) = extract_offset(code);
+            is_in_string_or_comment(&SourceFile::parse(&code), offset)
+        }
+        assert!(check(r#"fn f() { "fo<|>o" }"#));
+        assert!(check("fn f() { 'a<|>' }"));
+        assert!(check("fn f() { // foo<|>\n }"));
+        assert!(check("fn f() { /* f<|>oo */ }"));
+        assert!(!check(r#"fn f() { "foo"<|> }"#));
+        assert!(!check(r#"fn f() { <|>"foo" }"#));
+        assert!(!check("fn f() { /* foo */<|> }"));
+
+        let file = SourceFile::parse(r#"fn f() { "foo" }"#);
+        let range = |start: u32, end: u32| TextRange::from_to(start.into(), end.into());
+        assert!(is_range_in_string_or_comment(&file, range(10, 13)));
+        assert!(!is_range_in_string_or_comment(&file, range(9, 13)));
+    }
+
+    #[test]
+    fn test_highlighting_in_token_trees() {
+        // `#name` can't be parsed, but the tokens are highlighted anyway.
+        let file = SourceFile::parse(r#"quote! { fn #name() -> u32 { /* c */ let s = "s"; 92 } }"#);
+        let hls = highlight(file.syntax());
+        let tags = hls.iter().map(|it| (it.range, it.tag)).collect::<Vec<_>>();
+        assert!(tags.contains(&(TextRange::from_to(29.into(), 36.into()), "comment")));
+        assert!(tags.contains(&(TextRange::from_to(45.into(), 48.into()), "string")));
+        assert!(tags.contains(&(TextRange::from_to(50.into(), 52.into()), "literal")));
     }
 
 }
@@ -0,0 +1,70 @@
+//! Best-effort parsing of unexpanded macro calls.
+//!
+//! Many macros, like `lazy_static!` or `thread_local!`, take a `{}`-delimited
+//! body which looks like ordinary items or statements. We can't expand such
+//! macros without name resolution, but we can parse the body as if it were a
+//! block, which is good enough for highlighting and file structure. Results
+//! obtained this way are marked as heuristic.
+
+use ra_syntax::{
+    SourceFile, TextRange, TextUnit, TreeArc,
+    SyntaxKind::*,
+    ast::{self, AstNode, NameOwner},
+};
+
+/// The body is wrapped into a function, so that both items and statements can
+/// be parsed.
+const PREFIX: &str = "fn f() {";
+const SUFFIX: &str = "}";
+
+pub(crate) struct MacroBody {
+    file: TreeArc<SourceFile>,
+    /// Offset of the body (without the `{`) in the original file.
+    offset: TextUnit,
+    len: TextUnit,
+}
+
+impl MacroBody {
+    pub(crate) fn parse(macro_call: &ast::MacroCall) -> Option<MacroBody> {
+        // `macro_rules! foo {}` is a macro definition, not a call.
+        if macro_call.name().is_some() {
+            return None;
+        }
+        let token_tree = macro_call.token_tree()?.syntax();
+        if token_tree.first_child()?.kind() != L_CURLY || token_tree.last_child()?.kind() != R_CURLY
+        {
+            return None;
+        }
+        let text = token_tree.text().to_string();
+        let body = &text[1..text.len() - 1];
+        let file = SourceFile::parse(&format!("{}{}{}", PREFIX, body, SUFFIX));
+
+        let block = file.syntax().children().find_map(ast::FnDef::cast)?.body()?;
+        let is_empty = block
+            .syntax()
+            .children()
+            .all(|it| it.kind().is_trivia() || it.kind() == L_CURLY || it.kind() == R_CURLY);
+        if is_empty {
+            return None;
+        }
+        Some(MacroBody {
+            file,
+            offset: token_tree.range().start() + TextUnit::of_char('{'),
+            len: TextUnit::of_str(body),
+        })
+    }
+
+    pub(crate) fn file(&self) -> &SourceFile {
+        &self.file
+    }
+
+    /// Maps a range in the parsed body to the original file. Returns `None`
+    /// for ranges which are not fully inside the body.
+    pub(crate) fn map_range(&self, range: TextRange) -> Option<TextRange> {
+        let prefix_len = TextUnit::of_str(PREFIX);
+        if range.start() < prefix_len || range.end() > prefix_len + self.len {
+            return None;
+        }
+        Some(TextRange::offset_len(range.start() - prefix_len + self.offset, range.len()))
+    }
+}
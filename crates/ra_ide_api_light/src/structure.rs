@@ -1,9 +1,11 @@
-use crate::TextRange;
+use crate::{TextRange, macro_body::MacroBody};
 
 use ra_syntax::{
     algo::visit::{visitor, Visitor},
     ast::{self, AttrsOwner, NameOwner, TypeParamsOwner, TypeAscriptionOwner},
-    AstNode, SourceFile, SyntaxKind, SyntaxNode, WalkEvent,
+    AstNode, SourceFile,
+    SyntaxKind::{IMPL_BLOCK, ITEM_LIST, TRAIT_DEF},
+    SyntaxNode,
 };
 
 #[derive(Debug, Clone)]
@@ -12,47 +14,146 @@ pub struct StructureNode {
     pub label: String,
     pub navigation_range: TextRange,
     pub node_range: TextRange,
-    pub kind: SyntaxKind,
+    pub kind: StructureNodeKind,
     pub detail: Option<String>,
     pub deprecated: bool,
+    /// Whether the node comes from a best-effort parse of a macro call body.
+    pub heuristic: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureNodeKind {
+    Function,
+    Struct,
+    Union,
+    Enum,
+    EnumVariant,
+    Trait,
+    Module,
+    TypeAlias,
+    /// An associated type in a trait or an impl.
+    AssocType,
+    Field,
+    Static,
+    Const,
+    /// An associated const in a trait or an impl.
+    AssocConst,
+    Impl,
+    /// A `macro_rules!` definition.
+    Macro,
 }
 
 pub fn file_structure(file: &SourceFile) -> Vec<StructureNode> {
-    let mut res = Vec::new();
-    let mut stack = Vec::new();
-
-    for event in file.syntax().preorder() {
-        match event {
-            WalkEvent::Enter(node) => {
-                if let Some(mut symbol) = structure_node(node) {
-                    symbol.parent = stack.last().map(|&n| n);
-                    stack.push(res.len());
-                    res.push(symbol);
-                }
-            }
-            WalkEvent::Leave(node) => {
-                if structure_node(node).is_some() {
-                    stack.pop().unwrap();
-                }
+    file_structure_in_range(file, None)
+}
+
+/// Like `file_structure`, but if `range` is given, only the nodes
+/// intersecting it are included, and only their subtrees are traversed.
+pub fn file_structure_in_range(file: &SourceFile, range: Option<TextRange>) -> Vec<StructureNode> {
+    let mut collector = StructureCollector::default();
+    collect_structure(file.syntax(), range, &mut collector);
+    collector.finish()
+}
+
+fn collect_structure<'a>(
+    node: &'a SyntaxNode,
+    range: Option<TextRange>,
+    collector: &mut StructureCollector<'a>,
+) {
+    for child in node.children() {
+        if let Some(range) = range {
+            if range.intersection(&child.range()).is_none() {
+                continue;
             }
         }
+        collector.enter(child);
+        collect_structure(child, range, collector);
+        collector.leave(child);
+    }
+}
+
+/// Computes the file structure one node at a time, so that it can share a
+/// tree traversal with other analyses. Nodes must be entered in preorder,
+/// and left once their subtree is done.
+#[derive(Default)]
+pub struct StructureCollector<'a> {
+    /// The nodes which produced a structure node, along with its index.
+    parents: Vec<(&'a SyntaxNode, usize)>,
+    res: Vec<StructureNode>,
+}
+
+impl<'a> StructureCollector<'a> {
+    pub fn enter(&mut self, node: &'a SyntaxNode) {
+        let mut parent = self.parents.last().map(|&(_, idx)| idx);
+        if let Some(mut symbol) = structure_node(node) {
+            symbol.parent = parent;
+            parent = Some(self.res.len());
+            self.parents.push((node, self.res.len()));
+            self.res.push(symbol);
+        }
+        if let Some(macro_call) = ast::MacroCall::cast(node) {
+            macro_body_structure(macro_call, parent, &mut self.res);
+        }
+    }
+
+    pub fn leave(&mut self, node: &'a SyntaxNode) {
+        if self.parents.last().map(|&(it, _)| it) == Some(node) {
+            self.parents.pop();
+        }
+    }
+
+    pub fn finish(self) -> Vec<StructureNode> {
+        self.res
+    }
+}
+
+fn macro_body_structure(
+    macro_call: &ast::MacroCall,
+    parent: Option<usize>,
+    res: &mut Vec<StructureNode>,
+) {
+    let body = match MacroBody::parse(macro_call) {
+        Some(it) => it,
+        None => return,
+    };
+    // Index in `res` for each node of the body. Nodes outside of the body
+    // (that is, the wrapping function) are skipped.
+    let mut indices = Vec::new();
+    for node in file_structure(body.file()) {
+        let ranges = (body.map_range(node.node_range), body.map_range(node.navigation_range));
+        let idx = match ranges {
+            (Some(node_range), Some(navigation_range)) => {
+                let parent = node.parent.and_then(|it| indices[it]).or(parent);
+                res.push(StructureNode {
+                    parent,
+                    node_range,
+                    navigation_range,
+                    heuristic: true,
+                    ..node
+                });
+                Some(res.len() - 1)
+            }
+            _ => None,
+        };
+        indices.push(idx);
     }
-    res
 }
 
 fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
-    fn decl<N: NameOwner + AttrsOwner>(node: &N) -> Option<StructureNode> {
-        decl_with_detail(node, None)
+    fn decl<N: NameOwner + AttrsOwner>(node: &N, kind: StructureNodeKind) -> Option<StructureNode> {
+        decl_with_detail(node, kind, None)
     }
 
     fn decl_with_ascription<N: NameOwner + AttrsOwner + TypeAscriptionOwner>(
         node: &N,
+        kind: StructureNodeKind,
     ) -> Option<StructureNode> {
-        decl_with_type_ref(node, node.ascribed_type())
+        decl_with_type_ref(node, kind, node.ascribed_type())
     }
 
     fn decl_with_type_ref<N: NameOwner + AttrsOwner>(
         node: &N,
+        kind: StructureNodeKind,
         type_ref: Option<&ast::TypeRef>,
     ) -> Option<StructureNode> {
         let detail = type_ref.map(|type_ref| {
@@ -60,11 +161,12 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
             collapse_ws(type_ref.syntax(), &mut detail);
             detail
         });
-        decl_with_detail(node, detail)
+        decl_with_detail(node, kind, detail)
     }
 
     fn decl_with_detail<N: NameOwner + AttrsOwner>(
         node: &N,
+        kind: StructureNodeKind,
         detail: Option<String>,
     ) -> Option<StructureNode> {
         let name = node.name()?;
@@ -74,9 +176,10 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
             label: name.text().to_string(),
             navigation_range: name.syntax().range(),
             node_range: node.syntax().range(),
-            kind: node.syntax().kind(),
+            kind,
             detail,
             deprecated: node.attrs().filter_map(|x| x.as_named()).any(|x| x == "deprecated"),
+            heuristic: false,
         })
     }
 
@@ -96,6 +199,18 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
         }
     }
 
+    /// Whether the item is in the item list of a trait or an impl.
+    fn is_assoc_item(node: &SyntaxNode) -> bool {
+        let item_list = match node.parent() {
+            Some(it) if it.kind() == ITEM_LIST => it,
+            _ => return false,
+        };
+        match item_list.parent().map(|it| it.kind()) {
+            Some(IMPL_BLOCK) | Some(TRAIT_DEF) => true,
+            _ => false,
+        }
+    }
+
     visitor()
         .visit(|fn_def: &ast::FnDef| {
             let mut detail = String::from("fn");
@@ -110,17 +225,35 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
                 collapse_ws(ret_type.syntax(), &mut detail);
             }
 
-            decl_with_detail(fn_def, Some(detail))
+            decl_with_detail(fn_def, StructureNodeKind::Function, Some(detail))
         })
-        .visit(decl::<ast::StructDef>)
-        .visit(decl::<ast::EnumDef>)
-        .visit(decl::<ast::EnumVariant>)
-        .visit(decl::<ast::TraitDef>)
-        .visit(decl::<ast::Module>)
-        .visit(|td: &ast::TypeAliasDef| decl_with_type_ref(td, td.type_ref()))
-        .visit(decl_with_ascription::<ast::NamedFieldDef>)
-        .visit(decl_with_ascription::<ast::ConstDef>)
-        .visit(decl_with_ascription::<ast::StaticDef>)
+        .visit(|st: &ast::StructDef| {
+            let kind =
+                if st.is_union() { StructureNodeKind::Union } else { StructureNodeKind::Struct };
+            decl(st, kind)
+        })
+        .visit(|it: &ast::EnumDef| decl(it, StructureNodeKind::Enum))
+        .visit(|it: &ast::EnumVariant| decl(it, StructureNodeKind::EnumVariant))
+        .visit(|it: &ast::TraitDef| decl(it, StructureNodeKind::Trait))
+        .visit(|it: &ast::Module| decl(it, StructureNodeKind::Module))
+        .visit(|td: &ast::TypeAliasDef| {
+            let kind = if is_assoc_item(td.syntax()) {
+                StructureNodeKind::AssocType
+            } else {
+                StructureNodeKind::TypeAlias
+            };
+            decl_with_type_ref(td, kind, td.type_ref())
+        })
+        .visit(|it: &ast::NamedFieldDef| decl_with_ascription(it, StructureNodeKind::Field))
+        .visit(|it: &ast::ConstDef| {
+            let kind = if is_assoc_item(it.syntax()) {
+                StructureNodeKind::AssocConst
+            } else {
+                StructureNodeKind::Const
+            };
+            decl_with_ascription(it, kind)
+        })
+        .visit(|it: &ast::StaticDef| decl_with_ascription(it, StructureNodeKind::Static))
         .visit(|im: &ast::ImplBlock| {
             let target_type = im.target_type()?;
             let target_trait = im.target_trait();
@@ -136,12 +269,17 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
                 label,
                 navigation_range: target_type.syntax().range(),
                 node_range: im.syntax().range(),
-                kind: im.syntax().kind(),
+                kind: StructureNodeKind::Impl,
                 detail: None,
                 deprecated: false,
+                heuristic: false,
             };
             Some(node)
         })
+        .visit(|mc: &ast::MacroCall| {
+            // Only `macro_rules! foo {}` has a name.
+            decl(mc, StructureNodeKind::Macro)
+        })
         .accept(node)?
 }
 
@@ -149,6 +287,7 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
 mod tests {
     use super::*;
     use insta::assert_debug_snapshot_matches;
+    use ra_syntax::TextUnit;
 
     #[test]
     fn test_file_structure() {
@@ -182,9 +321,53 @@ fn obsolete() {}
 
 #[deprecated(note = "for awhile")]
 fn very_obsolete() {}
+
+union U { f: u32 }
+
+trait Tr {
+    type Item;
+    const SIZE: usize;
+}
+
+impl Tr for U {
+    type Item = u32;
+    const SIZE: usize = 4;
+}
+
+macro_rules! mac { () => {} }
 "#,
         );
         let structure = file_structure(&file);
         assert_debug_snapshot_matches!("file_structure", structure);
     }
+
+    #[test]
+    fn test_file_structure_in_macro_bodies() {
+        let file = SourceFile::parse(
+            r#"
+mod m {
+    thread_local! { static FOO: u32 = 92; }
+}
+"#,
+        );
+        let structure = file_structure(&file);
+        assert_eq!(structure.len(), 2);
+        let foo = &structure[1];
+        assert_eq!(foo.label, "FOO");
+        assert_eq!(foo.kind, StructureNodeKind::Static);
+        assert_eq!(foo.parent, Some(0));
+        assert_eq!(foo.navigation_range, TextRange::from_to(36.into(), 39.into()));
+        assert!(foo.heuristic);
+    }
+
+    #[test]
+    fn test_file_structure_in_range() {
+        let text = "mod m {\n    fn bar1() {}\n    fn bar2() {}\n}\nfn baz() {}\n";
+        let file = SourceFile::parse(text);
+        let offset = TextUnit::from_usize(text.find("bar2").unwrap());
+        let range = TextRange::offset_len(offset, 0.into());
+        let structure = file_structure_in_range(&file, Some(range));
+        let labels = structure.iter().map(|it| (it.label.as_str(), it.parent)).collect::<Vec<_>>();
+        assert_eq!(labels, vec![("m", None), ("bar2", Some(0))]);
+    }
 }
@@ -4,12 +4,46 @@ use ra_syntax::{
     algo::{find_node_at_offset, find_leaf_at_offset, LeafAtOffset},
     ast::{self, AstToken},
 };
-use ra_fmt::leading_indent;
+use ra_fmt::{leading_indent, IndentStyle, LineEnding};
 use crate::{LocalEdit, TextEditBuilder};
 
-pub fn on_enter(file: &SourceFile, offset: TextUnit) -> Option<LocalEdit> {
-    let comment =
-        find_leaf_at_offset(file.syntax(), offset).left_biased().and_then(ast::Comment::cast)?;
+/// The typing handler which produced an edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypingHandler {
+    OnEnter,
+    OnEqTyped,
+    OnDotTyped,
+}
+
+/// Describes an edit made in response to a keystroke, so that the editor can
+/// record it properly in the undo stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypingEdit {
+    pub handler: TypingHandler,
+    /// Whether the edit should be undone together with the keystroke which
+    /// triggered it. This is the case for edits applied on top of the typed
+    /// character, like an auto-inserted `;`, but not for edits which replace
+    /// the keystroke altogether, like `on_enter`.
+    pub coalesce_with_keystroke: bool,
+}
+
+impl TypingEdit {
+    fn new(handler: TypingHandler) -> TypingEdit {
+        let coalesce_with_keystroke = match handler {
+            TypingHandler::OnEnter => false,
+            TypingHandler::OnEqTyped | TypingHandler::OnDotTyped => true,
+        };
+        TypingEdit { handler, coalesce_with_keystroke }
+    }
+}
+
+pub fn on_enter(file: &SourceFile, offset: TextUnit, style: &IndentStyle) -> Option<LocalEdit> {
+    let leaf = find_leaf_at_offset(file.syntax(), offset).left_biased()?;
+    let line_ending = LineEnding::detect(&file.syntax().text().to_string());
+    if let Some(string) = ast::String::cast(leaf) {
+        return on_enter_in_string(string, offset, line_ending, style);
+    }
+    let comment = ast::Comment::cast(leaf)?;
 
     if let ast::CommentFlavor::Multiline = comment.flavor() {
         return None;
@@ -21,7 +55,7 @@ pub fn on_enter(file: &SourceFile, offset: TextUnit) -> Option<LocalEdit> {
     }
 
     let indent = node_indent(file, comment.syntax())?;
-    let inserted = format!("\n{}{} ", indent, prefix);
+    let inserted = format!("{}{}{} ", line_ending.as_str(), indent, prefix);
     let cursor_position = offset + TextUnit::of_str(&inserted);
     let mut edit = TextEditBuilder::default();
     edit.insert(offset, inserted);
@@ -29,6 +63,39 @@ pub fn on_enter(file: &SourceFile, offset: TextUnit) -> Option<LocalEdit> {
         label: "on enter".to_string(),
         edit: edit.finish(),
         cursor_position: Some(cursor_position),
+        typing: Some(TypingEdit::new(TypingHandler::OnEnter)),
+    })
+}
+
+/// Splits the string literal with a line continuation, so that the string
+/// stays terminated and its value doesn't change.
+fn on_enter_in_string(
+    string: &ast::String,
+    offset: TextUnit,
+    line_ending: LineEnding,
+    style: &IndentStyle,
+) -> Option<LocalEdit> {
+    let range = string.syntax().range();
+    if offset <= range.start() || offset >= range.end() {
+        return None;
+    }
+    let before_cursor = &string.text()[..(offset - range.start()).to_usize()];
+    // Don't break an escape sequence in two.
+    let trailing_backslashes = before_cursor.chars().rev().take_while(|&c| c == '\\').count();
+    if trailing_backslashes % 2 == 1 {
+        return None;
+    }
+
+    let indent = leading_indent(string.syntax()).unwrap_or("");
+    let inserted = format!("\\{}{}{}", line_ending.as_str(), indent, style.unit());
+    let cursor_position = offset + TextUnit::of_str(&inserted);
+    let mut edit = TextEditBuilder::default();
+    edit.insert(offset, inserted);
+    Some(LocalEdit {
+        label: "split string".to_string(),
+        edit: edit.finish(),
+        cursor_position: Some(cursor_position),
+        typing: Some(TypingEdit::new(TypingHandler::OnEnter)),
     })
 }
 
@@ -76,10 +143,15 @@ pub fn on_eq_typed(file: &SourceFile, eq_offset: TextUnit) -> Option<LocalEdit>
         label: "add semicolon".to_string(),
         edit: edit.finish(),
         cursor_position: None,
+        typing: Some(TypingEdit::new(TypingHandler::OnEqTyped)),
     })
 }
 
-pub fn on_dot_typed(file: &SourceFile, dot_offset: TextUnit) -> Option<LocalEdit> {
+pub fn on_dot_typed(
+    file: &SourceFile,
+    dot_offset: TextUnit,
+    style: &IndentStyle,
+) -> Option<LocalEdit> {
     assert_eq!(file.syntax().text().char_at(dot_offset), Some('.'));
 
     let whitespace = find_leaf_at_offset(file.syntax(), dot_offset)
@@ -96,7 +168,7 @@ pub fn on_dot_typed(file: &SourceFile, dot_offset: TextUnit) -> Option<LocalEdit
     // Make sure dot is a part of call chain
     let field_expr = whitespace.syntax().parent().and_then(ast::FieldExpr::cast)?;
     let prev_indent = leading_indent(field_expr.syntax())?;
-    let target_indent = format!("    {}", prev_indent);
+    let target_indent = format!("{}{}", style.unit(), prev_indent);
     let target_indent_len = TextUnit::of_str(&target_indent);
     if current_indent_len == target_indent_len {
         return None;
@@ -112,6 +184,7 @@ pub fn on_dot_typed(file: &SourceFile, dot_offset: TextUnit) -> Option<LocalEdit
         cursor_position: Some(
             dot_offset + target_indent_len - current_indent_len + TextUnit::of_char('.'),
         ),
+        typing: Some(TypingEdit::new(TypingHandler::OnDotTyped)),
     };
     Some(res)
 }
@@ -178,7 +251,7 @@ fn foo() {
         edit.insert(offset, ".".to_string());
         let before = edit.finish().apply(&before);
         let file = SourceFile::parse(&before);
-        if let Some(result) = on_dot_typed(&file, offset) {
+        if let Some(result) = on_dot_typed(&file, offset, &IndentStyle::default()) {
             let actual = result.edit.apply(&before);
             assert_eq_text!(after, &actual);
         } else {
@@ -359,7 +432,7 @@ fn foo() {
         fn apply_on_enter(before: &str) -> Option<String> {
             let (offset, before) = extract_offset(before);
             let file = SourceFile::parse(&before);
-            let result = on_enter(&file, offset)?;
+            let result = on_enter(&file, offset, &IndentStyle::default())?;
             let actual = result.edit.apply(&before);
             let actual = add_cursor(&actual, result.cursor_position.unwrap());
             Some(actual)
@@ -403,5 +476,58 @@ impl S {
 ",
         );
         do_check_noop(r"<|>//! docz");
+        do_check(
+            r#"
+fn foo() {
+    let s = "hello, <|>world";
+}
+"#,
+            r#"
+fn foo() {
+    let s = "hello, \
+        <|>world";
+}
+"#,
+        );
+        do_check_noop(r#"fn foo() { let s = <|>"hello"; }"#);
+        do_check_noop(r#"fn foo() { let s = "hello\<|>n"; }"#);
+        do_check_noop(r##"fn foo() { let s = r#"hello<|>"#; }"##);
+        do_check(
+            "impl S {\r\n    /// Some<|> docs.\r\n    fn foo() {}\r\n}\r\n",
+            "impl S {\r\n    /// Some\r\n    /// <|> docs.\r\n    fn foo() {}\r\n}\r\n",
+        );
+        do_check(
+            "fn foo() {\r\n    let s = \"hello, <|>world\";\r\n}\r\n",
+            "fn foo() {\r\n    let s = \"hello, \\\r\n        <|>world\";\r\n}\r\n",
+        );
+    }
+
+    #[test]
+    fn on_enter_in_string_follows_indent_style() {
+        let (offset, before) = extract_offset("fn foo() {\n\tlet s = \"hello, <|>world\";\n}\n");
+        let file = SourceFile::parse(&before);
+        let style = IndentStyle { use_tabs: true, width: 4 };
+        let edit = on_enter(&file, offset, &style).unwrap();
+        assert_eq_text!(
+            "fn foo() {\n\tlet s = \"hello, \\\n\t\tworld\";\n}\n",
+            &edit.edit.apply(&before)
+        );
+    }
+
+    #[test]
+    fn typing_edits_are_tagged_with_handler() {
+        let file = SourceFile::parse("fn foo() {\n    let x = 1 + 1\n}");
+        let edit = on_eq_typed(&file, 21.into()).unwrap();
+        assert_eq!(
+            edit.typing,
+            Some(TypingEdit { handler: TypingHandler::OnEqTyped, coalesce_with_keystroke: true })
+        );
+
+        let file = SourceFile::parse("// foo\nfn bar() {}");
+        let edit = on_enter(&file, 6.into(), &IndentStyle::default()).unwrap();
+        assert_eq!(
+            edit.typing,
+            Some(TypingEdit { handler: TypingHandler::OnEnter, coalesce_with_keystroke: false })
+        );
     }
 }
@@ -14,13 +14,14 @@ pub(crate) fn runnable_args(
     let spec = CargoTargetSpec::for_file(world, file_id)?;
     let mut res = Vec::new();
     match kind {
-        RunnableKind::Test { name } => {
+        RunnableKind::Test { path } => {
             res.push("test".to_string());
             if let Some(spec) = spec {
                 spec.push_to(&mut res);
             }
             res.push("--".to_string());
-            res.push(name.to_string());
+            res.push(path.to_string());
+            res.push("--exact".to_string());
             res.push("--nocapture".to_string());
         }
         RunnableKind::TestMod { path } => {
@@ -32,13 +33,14 @@ pub(crate) fn runnable_args(
             res.push(path.to_string());
             res.push("--nocapture".to_string());
         }
-        RunnableKind::Bench { name } => {
+        RunnableKind::Bench { path } => {
             res.push("bench".to_string());
             if let Some(spec) = spec {
                 spec.push_to(&mut res);
             }
             res.push("--".to_string());
-            res.push(name.to_string());
+            res.push(path.to_string());
+            res.push("--exact".to_string());
             res.push("--nocapture".to_string());
         }
         RunnableKind::Bin => {
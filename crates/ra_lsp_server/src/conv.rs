@@ -6,8 +6,8 @@ use lsp_types::{
 };
 use ra_ide_api::{
     CompletionItem, CompletionItemKind, FileId, FilePosition, FileRange, FileSystemEdit,
-    NavigationTarget, SourceChange, SourceFileEdit, RangeInfo,
-    LineCol, LineIndex, translate_offset_with_edit, InsertTextFormat
+    NavigationTarget, SourceChange, SourceFileEdit, RangeInfo, StructureNodeKind,
+    LineCol, LineIndex, translate_offset_with_edit, InsertTextFormat, TypingHandler
 };
 use ra_syntax::{SyntaxKind, TextRange, TextUnit};
 use ra_text_edit::{AtomTextEdit, TextEdit};
@@ -52,6 +52,30 @@ impl Conv for SyntaxKind {
     }
 }
 
+impl Conv for StructureNodeKind {
+    type Output = SymbolKind;
+
+    fn conv(self) -> <Self as Conv>::Output {
+        match self {
+            StructureNodeKind::Function => SymbolKind::Function,
+            StructureNodeKind::Struct | StructureNodeKind::Union => SymbolKind::Struct,
+            StructureNodeKind::Enum => SymbolKind::Enum,
+            StructureNodeKind::EnumVariant => SymbolKind::EnumMember,
+            StructureNodeKind::Trait => SymbolKind::Interface,
+            StructureNodeKind::Module => SymbolKind::Module,
+            StructureNodeKind::TypeAlias | StructureNodeKind::AssocType => {
+                SymbolKind::TypeParameter
+            }
+            StructureNodeKind::Field => SymbolKind::Field,
+            StructureNodeKind::Static
+            | StructureNodeKind::Const
+            | StructureNodeKind::AssocConst => SymbolKind::Constant,
+            StructureNodeKind::Impl => SymbolKind::Object,
+            StructureNodeKind::Macro => SymbolKind::Function,
+        }
+    }
+}
+
 impl Conv for CompletionItemKind {
     type Output = ::lsp_types::CompletionItemKind;
 
@@ -164,6 +188,18 @@ impl ConvWith for Range {
     }
 }
 
+impl Conv for ra_ide_api::TypingEdit {
+    type Output = req::TypingEdit;
+    fn conv(self) -> req::TypingEdit {
+        let handler = match self.handler {
+            TypingHandler::OnEnter => "onEnter",
+            TypingHandler::OnEqTyped => "onEqTyped",
+            TypingHandler::OnDotTyped => "onDotTyped",
+        };
+        req::TypingEdit { handler, coalesce_with_keystroke: self.coalesce_with_keystroke }
+    }
+}
+
 impl Conv for ra_ide_api::Documentation {
     type Output = lsp_types::Documentation;
     fn conv(self) -> Documentation {
@@ -313,7 +349,12 @@ impl TryConvWith for SourceChange {
             changes: None,
             document_changes: Some(DocumentChanges::Operations(document_changes)),
         };
-        Ok(req::SourceChange { label: self.label, workspace_edit, cursor_position })
+        Ok(req::SourceChange {
+            label: self.label,
+            workspace_edit,
+            cursor_position,
+            typing: self.typing.map(|it| it.conv()),
+        })
     }
 }
 
@@ -17,11 +17,23 @@ pub struct InitializationOptions {
     /// Defaults to `true`
     #[serde(deserialize_with = "nullable_bool_true")]
     pub show_workspace_loaded: bool,
+
+    /// Whether the symbols of the workspace should be saved on exit, to speed
+    /// up the symbol search after the next start. The cache is stored in the
+    /// `target` directory of the workspace.
+    ///
+    /// Defaults to `false`
+    #[serde(deserialize_with = "nullable_bool_false")]
+    pub symbol_cache: bool,
 }
 
 impl Default for InitializationOptions {
     fn default() -> InitializationOptions {
-        InitializationOptions { publish_decorations: false, show_workspace_loaded: true }
+        InitializationOptions {
+            publish_decorations: false,
+            show_workspace_loaded: true,
+            symbol_cache: false,
+        }
     }
 }
 
@@ -49,13 +61,15 @@ mod test {
 
     #[test]
     fn deserialize_init_options_defaults() {
-        // check that null == default for both fields
+        // check that null == default for all fields
         let default = InitializationOptions::default();
         assert_eq!(default, serde_json::from_str(r#"{}"#).unwrap());
         assert_eq!(
             default,
-            serde_json::from_str(r#"{"publishDecorations":null, "showWorkspaceLoaded":null}"#)
-                .unwrap()
+            serde_json::from_str(
+                r#"{"publishDecorations":null, "showWorkspaceLoaded":null, "symbolCache":null}"#
+            )
+            .unwrap()
         );
     }
 }
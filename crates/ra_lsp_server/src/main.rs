@@ -11,6 +11,7 @@ fn main() -> Result<()> {
         Ok(ref v) if v == "1" => logger.log_to_file().directory("log").start()?,
         _ => logger.start()?,
     };
+    init_profiling();
     log::info!("lifecycle: server started");
     match ::std::panic::catch_unwind(main_inner) {
         Ok(res) => {
@@ -24,6 +25,17 @@ fn main() -> Result<()> {
     }
 }
 
+/// `RA_PROFILE=<ms>` prints the operations which took at least `<ms>`
+/// milliseconds to stderr.
+fn init_profiling() {
+    let threshold = match ::std::env::var("RA_PROFILE").ok().and_then(|it| it.parse().ok()) {
+        Some(ms) => ::std::time::Duration::from_millis(ms),
+        None => return,
+    };
+    let sink = |span: &ra_prof::Span| eprintln!("{}", span);
+    ra_prof::set_sink(::std::sync::Arc::new(sink), threshold);
+}
+
 fn main_inner() -> Result<()> {
     let (receiver, sender, threads) = stdio_transport();
     let cwd = ::std::env::current_dir()?;
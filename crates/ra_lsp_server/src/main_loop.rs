@@ -1,7 +1,11 @@
 mod handlers;
 mod subscriptions;
 
-use std::{fmt, path::PathBuf, sync::Arc};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crossbeam_channel::{select, unbounded, Receiver, RecvError, Sender};
 use failure::{bail, format_err};
@@ -10,7 +14,7 @@ use gen_lsp_server::{
     handle_shutdown, ErrorCode, RawMessage, RawNotification, RawRequest, RawResponse,
 };
 use lsp_types::NumberOrString;
-use ra_ide_api::{Canceled, FileId, LibraryData};
+use ra_ide_api::{Analysis, Canceled, FileId, LibraryData, SymbolCache};
 use ra_vfs::VfsTask;
 use rustc_hash::FxHashSet;
 use serde::{de::DeserializeOwned, Serialize};
@@ -76,6 +80,18 @@ pub fn main_loop(
 
     let mut state = ServerWorldState::new(ws_root.clone(), workspaces);
 
+    let symbol_cache_path = if options.symbol_cache {
+        Some(ws_root.join("target").join("rust-analyzer").join("symbols"))
+    } else {
+        None
+    };
+    let symbol_cache = match &symbol_cache_path {
+        Some(path) => load_symbol_cache(path),
+        None => SymbolCache::default(),
+    };
+    state.analysis_host.set_symbol_cache(symbol_cache.clone());
+    let symbol_cache = Arc::new(symbol_cache);
+
     log::info!("server initialized, serving requests");
 
     let mut pending_requests = FxHashSet::default();
@@ -87,6 +103,7 @@ pub fn main_loop(
         msg_receiver,
         task_sender,
         task_receiver.clone(),
+        &symbol_cache,
         &mut state,
         &mut pending_requests,
         &mut subs,
@@ -99,12 +116,42 @@ pub fn main_loop(
     drop(pool);
     log::info!("...threadpool has finished");
 
+    if let Some(path) = symbol_cache_path {
+        save_symbol_cache(state.analysis(), &path);
+    }
+
     let vfs = Arc::try_unwrap(state.vfs).expect("all snapshots should be dead");
     drop(vfs);
 
     main_res
 }
 
+/// Loads the symbols saved by `save_symbol_cache` in the previous session.
+fn load_symbol_cache(path: &Path) -> SymbolCache {
+    let bytes = match fs::read(path) {
+        Ok(it) => it,
+        Err(_) => return SymbolCache::default(),
+    };
+    SymbolCache::from_bytes(&bytes).unwrap_or_else(|| {
+        log::warn!("ignoring the outdated symbol cache {}", path.display());
+        SymbolCache::default()
+    })
+}
+
+fn save_symbol_cache(analysis: &Analysis, path: &Path) {
+    let cache = match analysis.symbol_cache() {
+        Ok(it) => it,
+        Err(_) => return,
+    };
+    let res = match path.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    };
+    if let Err(e) = res.and_then(|()| fs::write(path, cache.to_bytes())) {
+        log::error!("failed to save the symbol cache to {}: {}", path.display(), e);
+    }
+}
+
 enum Event {
     Msg(RawMessage),
     Task(Task),
@@ -154,6 +201,7 @@ fn main_loop_inner(
     msg_receiver: &Receiver<RawMessage>,
     task_sender: Sender<Task>,
     task_receiver: Receiver<Task>,
+    symbol_cache: &Arc<SymbolCache>,
     state: &mut ServerWorldState,
     pending_requests: &mut FxHashSet<u64>,
     subs: &mut Subscriptions,
@@ -234,10 +282,11 @@ fn main_loop_inner(
             let (root, files) = pending_libraries.pop().unwrap();
             in_flight_libraries += 1;
             let sender = libdata_sender.clone();
+            let symbol_cache = Arc::clone(symbol_cache);
             pool.execute(move || {
                 let start = ::std::time::Instant::now();
                 log::info!("indexing {:?} ... ", root);
-                let data = LibraryData::prepare(root, files);
+                let data = LibraryData::prepare_with_cache(root, files, &symbol_cache);
                 log::info!("indexed {:?} {:?}", start.elapsed(), root);
                 sender.send(data).unwrap();
             });
@@ -9,9 +9,9 @@ use lsp_types::{
 };
 use ra_ide_api::{
     FileId, FilePosition, FileRange, FoldKind, Query, RangeInfo, RunnableKind, Severity, Cancelable,
-    AssistId,
+    AssistId, StructureNodeKind, SyntaxTreeOptions,
 };
-use ra_syntax::{AstNode, SyntaxKind, TextUnit};
+use ra_syntax::{AstNode, TextUnit, RangeStyle};
 use rustc_hash::FxHashMap;
 use serde::{Serialize, Deserialize};
 use serde_json::to_value;
@@ -34,7 +34,13 @@ pub fn handle_syntax_tree(world: ServerWorld, params: req::SyntaxTreeParams) ->
     let id = params.text_document.try_conv_with(&world)?;
     let line_index = world.analysis().file_line_index(id);
     let text_range = params.range.map(|p| p.conv_with(&line_index));
-    let res = world.analysis().syntax_tree(id, text_range);
+    let options = SyntaxTreeOptions {
+        trivia: !params.hide_trivia,
+        ranges: if params.line_col { RangeStyle::LineCol } else { RangeStyle::Offsets },
+        max_depth: params.max_depth,
+        ..SyntaxTreeOptions::default()
+    };
+    let res = world.analysis().syntax_tree_with_options(id, text_range, options);
     Ok(res)
 }
 
@@ -163,26 +169,9 @@ pub fn handle_workspace_symbol(
     world: ServerWorld,
     params: req::WorkspaceSymbolParams,
 ) -> Result<Option<Vec<SymbolInformation>>> {
-    let all_symbols = params.query.contains('#');
-    let libs = params.query.contains('*');
-    let query = {
-        let query: String = params.query.chars().filter(|&c| c != '#' && c != '*').collect();
-        let mut q = Query::new(query);
-        if !all_symbols {
-            q.only_types();
-        }
-        if libs {
-            q.libs();
-        }
-        q.limit(128);
-        q
-    };
-    let mut res = exec_query(&world, query)?;
-    if res.is_empty() && !all_symbols {
-        let mut query = Query::new(params.query);
-        query.limit(128);
-        res = exec_query(&world, query)?;
-    }
+    let mut query = Query::parse(&params.query);
+    query.limit(128);
+    let res = exec_query(&world, query)?;
 
     return Ok(Some(res));
 
@@ -275,9 +264,9 @@ pub fn handle_runnables(
         let r = req::Runnable {
             range: runnable.range.conv_with(&line_index),
             label: match &runnable.kind {
-                RunnableKind::Test { name } => format!("test {}", name),
+                RunnableKind::Test { path } => format!("test {}", path),
                 RunnableKind::TestMod { path } => format!("test-mod {}", path),
-                RunnableKind::Bench { name } => format!("bench {}", name),
+                RunnableKind::Bench { path } => format!("bench {}", path),
                 RunnableKind::Bin => "run binary".to_string(),
             },
             bin: "cargo".to_string(),
@@ -486,8 +475,10 @@ pub fn handle_rename(world: ServerWorld, params: RenameParams) -> Result<Option<
         .into());
     }
 
-    let optional_change =
-        world.analysis().rename(FilePosition { file_id, offset }, &*params.new_name)?;
+    let optional_change = world
+        .analysis()
+        .rename(FilePosition { file_id, offset }, &*params.new_name)?
+        .map_err(|conflict| LspError::new(ErrorCode::InvalidParams as i32, conflict.to_string()))?;
     let change = match optional_change {
         None => return Ok(None),
         Some(it) => it,
@@ -683,7 +674,10 @@ pub fn handle_code_lens(
             .file_structure(file_id)
             .into_iter()
             .filter(|it| match it.kind {
-                SyntaxKind::TRAIT_DEF | SyntaxKind::STRUCT_DEF | SyntaxKind::ENUM_DEF => true,
+                StructureNodeKind::Trait
+                | StructureNodeKind::Struct
+                | StructureNodeKind::Union
+                | StructureNodeKind::Enum => true,
                 _ => false,
             })
             .map(|it| {
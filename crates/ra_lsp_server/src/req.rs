@@ -41,6 +41,13 @@ impl Request for SyntaxTree {
 pub struct SyntaxTreeParams {
     pub text_document: TextDocumentIdentifier,
     pub range: Option<Range>,
+    /// Hides whitespace and comments.
+    #[serde(default)]
+    pub hide_trivia: bool,
+    /// Shows `line:column` positions instead of offsets.
+    #[serde(default)]
+    pub line_col: bool,
+    pub max_depth: Option<usize>,
 }
 
 pub enum ExtendSelection {}
@@ -171,4 +178,12 @@ pub struct SourceChange {
     pub label: String,
     pub workspace_edit: WorkspaceEdit,
     pub cursor_position: Option<TextDocumentPositionParams>,
+    pub typing: Option<TypingEdit>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingEdit {
+    pub handler: &'static str,
+    pub coalesce_with_keystroke: bool,
 }
@@ -20,6 +20,8 @@ mod mbe_parser;
 mod mbe_expander;
 mod syntax_bridge;
 
+use std::fmt;
+
 use ra_syntax::SmolStr;
 
 pub use tt::{Delimiter, Punct};
@@ -34,9 +36,60 @@ pub enum ExpandError {
     NoMatchingRule,
     UnexpectedToken,
     BindingError(String),
+    /// The expansion is too large, see `ExpandLimits`.
+    LimitExceeded(ExpandLimit),
+}
+
+/// A limit on the size of a macro expansion, which protects against runaway
+/// allocations on pathological macros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpandLimit {
+    /// The maximal number of token trees in the expansion.
+    TokenCount(usize),
+    /// The maximal nesting of subtrees in the expansion.
+    Depth(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpandLimits {
+    pub max_tokens: usize,
+    pub max_depth: usize,
+}
+
+impl Default for ExpandLimits {
+    fn default() -> ExpandLimits {
+        ExpandLimits { max_tokens: 100_000, max_depth: 128 }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Expected(it) => write!(f, "expected {}", it),
+        }
+    }
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpandError::NoMatchingRule => f.write_str("no rule matches the input"),
+            ExpandError::UnexpectedToken => f.write_str("unexpected token"),
+            ExpandError::BindingError(it) => f.write_str(it),
+            ExpandError::LimitExceeded(ExpandLimit::TokenCount(n)) => {
+                write!(f, "expansion produces more than {} tokens", n)
+            }
+            ExpandError::LimitExceeded(ExpandLimit::Depth(n)) => {
+                write!(f, "expansion is nested deeper than {} levels", n)
+            }
+        }
+    }
 }
 
-pub use crate::syntax_bridge::{ast_to_token_tree, token_tree_to_ast_item_list};
+pub use crate::syntax_bridge::{
+    ast_to_token_tree, token_tree_to_ast_item_list, token_tree_to_ast_item_list_with_map, TokenMap,
+    RevTokenMap,
+};
 
 /// This struct contains AST for a single `macro_rules` definition. What might
 /// be very confusing is that AST has almost exactly the same shape as
@@ -52,7 +105,14 @@ impl MacroRules {
         mbe_parser::parse(tt)
     }
     pub fn expand(&self, tt: &tt::Subtree) -> Result<tt::Subtree, ExpandError> {
-        mbe_expander::expand(self, tt)
+        self.expand_with_limits(tt, ExpandLimits::default())
+    }
+    pub fn expand_with_limits(
+        &self,
+        tt: &tt::Subtree,
+        limits: ExpandLimits,
+    ) -> Result<tt::Subtree, ExpandError> {
+        mbe_expander::expand(self, tt, limits)
     }
 }
 
@@ -117,7 +177,7 @@ pub(crate) struct Var {
 
 #[cfg(test)]
 mod tests {
-    use ra_syntax::{ast, AstNode};
+    use ra_syntax::{ast, AstNode, SyntaxKind, TextRange};
 
     use super::*;
 
@@ -337,4 +397,33 @@ SOURCE_FILE@[0; 40)
         );
     }
 
+    #[test]
+    fn expansion_maps_tokens_to_invocation() {
+        let rules = create_rules(
+            "
+            macro_rules! structs {
+                ($($i:ident),*) => {
+                    $(struct $i { field: u32 } )*
+                }
+            }
+            ",
+        );
+        let source_file = ast::SourceFile::parse("structs!(Foo, Bar)");
+        let macro_invocation =
+            source_file.syntax().descendants().find_map(ast::MacroCall::cast).unwrap();
+        let (invocation_tt, token_map) =
+            ast_to_token_tree(macro_invocation.token_tree().unwrap()).unwrap();
+        let expansion = rules.expand(&invocation_tt).unwrap();
+        let (tree, rev_map) = token_tree_to_ast_item_list_with_map(&expansion);
+
+        let name = tree.syntax().descendants().find(|it| it.kind() == SyntaxKind::NAME).unwrap();
+        let ids = rev_map.tokens_in(name.range()).collect::<Vec<_>>();
+        assert_eq!(ids.len(), 1);
+        // `Foo` in `(Foo, Bar)`, relative to the token tree.
+        assert_eq!(
+            token_map.relative_range_of(ids[0]),
+            Some(TextRange::from_to(1.into(), 4.into()))
+        );
+        assert_eq!(rev_map.range_of(ids[0]), Some(name.range()));
+    }
 }
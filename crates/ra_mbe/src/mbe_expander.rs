@@ -5,23 +5,78 @@ use rustc_hash::FxHashMap;
 use ra_syntax::SmolStr;
 use tt::TokenId;
 
-use crate::ExpandError;
+use crate::{ExpandError, ExpandLimit, ExpandLimits};
 use crate::tt_cursor::TtCursor;
 
 pub(crate) fn expand(
     rules: &crate::MacroRules,
     input: &tt::Subtree,
+    limits: ExpandLimits,
 ) -> Result<tt::Subtree, ExpandError> {
-    rules.rules.iter().find_map(|it| expand_rule(it, input).ok()).ok_or(ExpandError::NoMatchingRule)
+    for rule in rules.rules.iter() {
+        match expand_rule(rule, input, limits) {
+            Ok(it) => return Ok(it),
+            // The rule matches, but the expansion is too large: trying the
+            // other rules would hide the real problem.
+            Err(err @ ExpandError::LimitExceeded(_)) => return Err(err),
+            Err(_) => (),
+        }
+    }
+    Err(ExpandError::NoMatchingRule)
 }
 
-fn expand_rule(rule: &crate::Rule, input: &tt::Subtree) -> Result<tt::Subtree, ExpandError> {
+fn expand_rule(
+    rule: &crate::Rule,
+    input: &tt::Subtree,
+    limits: ExpandLimits,
+) -> Result<tt::Subtree, ExpandError> {
     let mut input = TtCursor::new(input);
     let bindings = match_lhs(&rule.lhs, &mut input)?;
     if !input.is_eof() {
         return Err(ExpandError::UnexpectedToken);
     }
-    expand_subtree(&rule.rhs, &bindings, &mut Vec::new())
+    let mut budget = Budget { limits, tokens: 0, depth: 0 };
+    expand_subtree(&rule.rhs, &bindings, &mut Vec::new(), &mut budget)
+}
+
+/// Tracks the size of the expansion produced so far against `ExpandLimits`.
+struct Budget {
+    limits: ExpandLimits,
+    tokens: usize,
+    depth: usize,
+}
+
+impl Budget {
+    fn add_tokens(&mut self, n: usize) -> Result<(), ExpandError> {
+        self.tokens += n;
+        if self.tokens > self.limits.max_tokens {
+            return Err(ExpandError::LimitExceeded(ExpandLimit::TokenCount(
+                self.limits.max_tokens,
+            )));
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Result<(), ExpandError> {
+        if self.depth == self.limits.max_depth {
+            return Err(ExpandError::LimitExceeded(ExpandLimit::Depth(self.limits.max_depth)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn exit(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+fn count_tokens(tt: &tt::TokenTree) -> usize {
+    match tt {
+        tt::TokenTree::Leaf(_) => 1,
+        tt::TokenTree::Subtree(subtree) => {
+            1 + subtree.token_trees.iter().map(count_tokens).sum::<usize>()
+        }
+    }
 }
 
 /// The actual algorithm for expansion is not too hard, but is pretty tricky.
@@ -183,12 +238,16 @@ fn expand_subtree(
     template: &crate::Subtree,
     bindings: &Bindings,
     nesting: &mut Vec<usize>,
+    budget: &mut Budget,
 ) -> Result<tt::Subtree, ExpandError> {
+    budget.enter()?;
     let token_trees = template
         .token_trees
         .iter()
-        .map(|it| expand_tt(it, bindings, nesting))
-        .collect::<Result<Vec<_>, ExpandError>>()?;
+        .map(|it| expand_tt(it, bindings, nesting, budget))
+        .collect::<Result<Vec<_>, ExpandError>>();
+    budget.exit();
+    let token_trees = token_trees?;
 
     Ok(tt::Subtree { token_trees, delimiter: template.delimiter })
 }
@@ -197,16 +256,24 @@ fn expand_tt(
     template: &crate::TokenTree,
     bindings: &Bindings,
     nesting: &mut Vec<usize>,
+    budget: &mut Budget,
 ) -> Result<tt::TokenTree, ExpandError> {
     let res: tt::TokenTree = match template {
-        crate::TokenTree::Subtree(subtree) => expand_subtree(subtree, bindings, nesting)?.into(),
+        crate::TokenTree::Subtree(subtree) => {
+            expand_subtree(subtree, bindings, nesting, budget)?.into()
+        }
         crate::TokenTree::Repeat(repeat) => {
             let mut token_trees = Vec::new();
             nesting.push(0);
             // Dirty hack to make macro-expansion terminate.
             // This should be replaced by a propper macro-by-example implementation
             let mut limit = 128;
-            while let Ok(t) = expand_subtree(&repeat.subtree, bindings, nesting) {
+            loop {
+                let t = match expand_subtree(&repeat.subtree, bindings, nesting, budget) {
+                    Ok(it) => it,
+                    Err(err @ ExpandError::LimitExceeded(_)) => return Err(err),
+                    Err(_) => break,
+                };
                 limit -= 1;
                 if limit == 0 {
                     break;
@@ -228,6 +295,12 @@ fn expand_tt(
             crate::Leaf::Literal(l) => tt::Leaf::from(tt::Literal { text: l.text.clone() }).into(),
         },
     };
+    // The contents of subtrees built from the template are already counted.
+    let n_tokens = match template {
+        crate::TokenTree::Leaf(crate::Leaf::Var(_)) => count_tokens(&res),
+        _ => 1,
+    };
+    budget.add_tokens(n_tokens)?;
     Ok(res)
 }
 
@@ -258,6 +331,23 @@ mod tests {
         assert_err("($i:) => ($i)", "foo!{a}", ExpandError::UnexpectedToken);
     }
 
+    #[test]
+    fn test_expand_limits() {
+        let rules = create_rules(&format_macro("($($i:ident)*) => ($( { $i $i } )*)"));
+        let (input, _) = {
+            let source_file = ast::SourceFile::parse("foo!{a b c}");
+            let call = source_file.syntax().descendants().find_map(ast::MacroCall::cast).unwrap();
+            ast_to_token_tree(call.token_tree().unwrap()).unwrap()
+        };
+        let expand = |max_tokens, max_depth| {
+            rules.expand_with_limits(&input, ExpandLimits { max_tokens, max_depth })
+        };
+
+        assert!(expand(100, 100).is_ok());
+        assert_eq!(expand(5, 100), Err(ExpandError::LimitExceeded(ExpandLimit::TokenCount(5))));
+        assert_eq!(expand(100, 2), Err(ExpandError::LimitExceeded(ExpandLimit::Depth(2))));
+    }
+
     fn assert_err(macro_body: &str, invocation: &str, err: ExpandError) {
         assert_eq!(expand_first(&create_rules(&format_macro(macro_body)), invocation), Err(err));
     }
@@ -292,6 +382,6 @@ mod tests {
 
         let (invocation_tt, _) = ast_to_token_tree(macro_invocation.token_tree().unwrap()).unwrap();
 
-        expand_rule(&rules.rules[0], &invocation_tt)
+        expand_rule(&rules.rules[0], &invocation_tt, ExpandLimits::default())
     }
 }
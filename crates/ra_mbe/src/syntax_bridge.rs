@@ -5,7 +5,7 @@ use ra_syntax::{
 };
 
 /// Maps `tt::TokenId` to the relative range of the original token.
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct TokenMap {
     /// Maps `tt::TokenId` to the *relative* source range.
     tokens: Vec<TextRange>,
@@ -20,13 +20,29 @@ pub fn ast_to_token_tree(ast: &ast::TokenTree) -> Option<(tt::Subtree, TokenMap)
     Some((tt, token_map))
 }
 
+/// Maps ranges of the tokens in the macro expansion back to `tt::TokenId`s of
+/// the macro call tokens they originate from.
+#[derive(Debug, Default)]
+pub struct RevTokenMap {
+    ranges: Vec<(TextRange, tt::TokenId)>,
+}
+
 /// Parses the token tree (result of macro expansion) as a sequence of items
 pub fn token_tree_to_ast_item_list(tt: &tt::Subtree) -> TreeArc<ast::SourceFile> {
+    token_tree_to_ast_item_list_with_map(tt).0
+}
+
+/// Like `token_tree_to_ast_item_list`, but also records where the tokens of
+/// the macro call ended up in the expansion.
+pub fn token_tree_to_ast_item_list_with_map(
+    tt: &tt::Subtree,
+) -> (TreeArc<ast::SourceFile>, RevTokenMap) {
     let token_source = TtTokenSource::new(tt);
     let mut tree_sink = TtTreeSink::new(&token_source.tokens);
     ra_parser::parse(&token_source, &mut tree_sink);
     let syntax = tree_sink.inner.finish();
-    ast::SourceFile::cast(&syntax).unwrap().to_owned()
+    let file = ast::SourceFile::cast(&syntax).unwrap().to_owned();
+    (file, tree_sink.rev_map)
 }
 
 impl TokenMap {
@@ -42,6 +58,26 @@ impl TokenMap {
     }
 }
 
+impl RevTokenMap {
+    /// Range of the token with the given id in the expansion. If the token was
+    /// used several times, returns the first occurrence.
+    pub fn range_of(&self, tt: tt::TokenId) -> Option<TextRange> {
+        self.ranges.iter().find(|(_, id)| *id == tt).map(|&(range, _)| range)
+    }
+
+    /// Ids of the tokens which lie completely within `range`.
+    pub fn tokens_in<'a>(&'a self, range: TextRange) -> impl Iterator<Item = tt::TokenId> + 'a {
+        self.ranges
+            .iter()
+            .filter(move |(token_range, _)| token_range.is_subrange(&range))
+            .map(|&(_, id)| id)
+    }
+
+    fn add(&mut self, range: TextRange, tt: tt::TokenId) {
+        self.ranges.push((range, tt))
+    }
+}
+
 fn convert_tt(
     token_map: &mut TokenMap,
     global_offset: TextUnit,
@@ -103,6 +139,7 @@ struct TtToken {
     kind: SyntaxKind,
     is_joint_to_next: bool,
     text: SmolStr,
+    id: Option<tt::TokenId>,
 }
 
 impl TtTokenSource {
@@ -128,6 +165,7 @@ impl TtTokenSource {
                 kind: SyntaxKind::INT_NUMBER, // FIXME
                 is_joint_to_next: false,
                 text: l.text.clone(),
+                id: None,
             },
             tt::Leaf::Punct(p) => {
                 let kind = match p.char {
@@ -144,11 +182,13 @@ impl TtTokenSource {
                     let s: &str = p.char.encode_utf8(&mut buf);
                     SmolStr::new(s)
                 };
-                TtToken { kind, is_joint_to_next: p.spacing == tt::Spacing::Joint, text }
+                let is_joint_to_next = p.spacing == tt::Spacing::Joint;
+                TtToken { kind, is_joint_to_next, text, id: None }
             }
             tt::Leaf::Ident(ident) => {
                 let kind = SyntaxKind::from_keyword(ident.text.as_str()).unwrap_or(IDENT);
-                TtToken { kind, is_joint_to_next: false, text: ident.text.clone() }
+                let id = if ident.id == tt::TokenId::unspecified() { None } else { Some(ident.id) };
+                TtToken { kind, is_joint_to_next: false, text: ident.text.clone(), id }
             }
         };
         self.tokens.push(tok)
@@ -163,7 +203,7 @@ impl TtTokenSource {
         let idx = closing as usize;
         let kind = kinds[idx];
         let text = &texts[idx..texts.len() - (1 - idx)];
-        let tok = TtToken { kind, is_joint_to_next: false, text: SmolStr::new(text) };
+        let tok = TtToken { kind, is_joint_to_next: false, text: SmolStr::new(text), id: None };
         self.tokens.push(tok)
     }
 }
@@ -182,6 +222,14 @@ impl TokenSource for TtTokenSource {
     fn is_keyword(&self, pos: usize, kw: &str) -> bool {
         self.tokens[pos].text == *kw
     }
+    fn line_start_indent(&self, _pos: usize) -> Option<usize> {
+        // Token trees don't preserve the layout of the source.
+        None
+    }
+    fn unclosed_curly_indent(&self, _pos: usize) -> Option<usize> {
+        // Token trees are always balanced.
+        None
+    }
 }
 
 #[derive(Default)]
@@ -191,6 +239,7 @@ struct TtTreeSink<'a> {
     text_pos: TextUnit,
     token_pos: usize,
     inner: SyntaxTreeBuilder,
+    rev_map: RevTokenMap,
 }
 
 impl<'a> TtTreeSink<'a> {
@@ -201,6 +250,7 @@ impl<'a> TtTreeSink<'a> {
             text_pos: 0.into(),
             token_pos: 0,
             inner: SyntaxTreeBuilder::default(),
+            rev_map: RevTokenMap::default(),
         }
     }
 }
@@ -208,7 +258,12 @@ impl<'a> TtTreeSink<'a> {
 impl<'a> TreeSink for TtTreeSink<'a> {
     fn leaf(&mut self, kind: SyntaxKind, n_tokens: u8) {
         for _ in 0..n_tokens {
-            self.buf += self.tokens[self.token_pos].text.as_str();
+            let token = &self.tokens[self.token_pos];
+            if let Some(id) = token.id {
+                let start = self.text_pos + TextUnit::of_str(&self.buf);
+                self.rev_map.add(TextRange::offset_len(start, TextUnit::of_str(&token.text)), id);
+            }
+            self.buf += token.text.as_str();
             self.token_pos += 1;
         }
         self.text_pos += TextUnit::of_str(&self.buf);
@@ -45,7 +45,7 @@ use crate::{
 pub(crate) fn root(p: &mut Parser) {
     let m = p.start();
     p.eat(SHEBANG);
-    items::mod_contents(p, false);
+    items::mod_contents(p, false, None);
     m.complete(p, SOURCE_FILE);
 }
 
@@ -193,8 +193,21 @@ fn error_block(p: &mut Parser, message: &str) {
     assert!(p.at(L_CURLY));
     let m = p.start();
     p.error(message);
+    let unclosed_indent = p.unclosed_curly_indent();
     p.bump();
-    expressions::expr_block_contents(p);
+    expressions::expr_block_contents(p, unclosed_indent);
     p.eat(R_CURLY);
     m.complete(p, ERROR);
 }
+
+/// Checks if the current token starts an item on a new line, indented no
+/// deeper than the line of the unclosed `{` of the enclosing block. Such a
+/// block most likely misses its `}`, so it ends here instead of swallowing
+/// the rest of the file.
+fn at_unclosed_block_end(p: &Parser, unclosed_indent: Option<usize>) -> bool {
+    let indent = match unclosed_indent {
+        Some(it) => it,
+        None => return false,
+    };
+    p.at_ts(items::ITEM_START_SET) && p.line_start_indent().map_or(false, |it| it <= indent)
+}
@@ -32,8 +32,9 @@ pub(crate) fn block(p: &mut Parser) {
         return;
     }
     let m = p.start();
+    let unclosed_indent = p.unclosed_curly_indent();
     p.bump();
-    expr_block_contents(p);
+    expr_block_contents(p, unclosed_indent);
     p.expect(R_CURLY);
     m.complete(p, BLOCK);
 }
@@ -45,11 +46,11 @@ fn is_expr_stmt_attr_allowed(kind: SyntaxKind) -> bool {
     }
 }
 
-pub(crate) fn expr_block_contents(p: &mut Parser) {
+pub(crate) fn expr_block_contents(p: &mut Parser, unclosed_indent: Option<usize>) {
     // This is checked by a validator
     attributes::inner_attributes(p);
 
-    while !p.at(EOF) && !p.at(R_CURLY) {
+    while !p.at(EOF) && !p.at(R_CURLY) && !at_unclosed_block_end(p, unclosed_indent) {
         // test nocontentexpr
         // fn foo(){
         //     ;;;some_expr();;;;{;;;};;;;Ok(())
@@ -323,6 +323,7 @@ fn match_expr(p: &mut Parser) -> CompletedMarker {
 pub(crate) fn match_arm_list(p: &mut Parser) {
     assert!(p.at(L_CURLY));
     let m = p.start();
+    let unclosed_indent = p.unclosed_curly_indent();
     p.eat(L_CURLY);
 
     // test match_arms_inner_attribute
@@ -336,7 +337,7 @@ pub(crate) fn match_arm_list(p: &mut Parser) {
     // }
     attributes::inner_attributes(p);
 
-    while !p.at(EOF) && !p.at(R_CURLY) {
+    while !p.at(EOF) && !p.at(R_CURLY) && !at_unclosed_block_end(p, unclosed_indent) {
         if p.at(L_CURLY) {
             error_block(p, "expected match arm");
             continue;
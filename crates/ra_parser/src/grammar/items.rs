@@ -17,9 +17,12 @@ use super::*;
 // foo::bar!();
 // super::baz! {}
 // struct S;
-pub(super) fn mod_contents(p: &mut Parser, stop_on_r_curly: bool) {
+pub(super) fn mod_contents(p: &mut Parser, stop_on_r_curly: bool, unclosed_indent: Option<usize>) {
     attributes::inner_attributes(p);
-    while !p.at(EOF) && !(stop_on_r_curly && p.at(R_CURLY)) {
+    while !p.at(EOF)
+        && !(stop_on_r_curly && p.at(R_CURLY))
+        && !at_unclosed_block_end(p, unclosed_indent)
+    {
         item_or_macro(p, stop_on_r_curly, ItemFlavor::Mod)
     }
 }
@@ -34,6 +37,13 @@ pub(super) const ITEM_RECOVERY_SET: TokenSet = token_set![
     CRATE_KW
 ];
 
+/// Tokens which start an item. An unclosed block ends at one of these, if it
+/// is at the start of a dedented line, see `at_unclosed_block_end`.
+pub(super) const ITEM_START_SET: TokenSet = token_set![
+    POUND, FN_KW, STRUCT_KW, ENUM_KW, IMPL_KW, TRAIT_KW, TYPE_KW, CONST_KW, STATIC_KW, MOD_KW,
+    USE_KW, EXTERN_KW, PUB_KW
+];
+
 pub(super) fn item_or_macro(p: &mut Parser, stop_on_r_curly: bool, flavor: ItemFlavor) {
     let m = p.start();
     attributes::outer_attributes(p);
@@ -237,8 +247,9 @@ fn extern_crate_item(p: &mut Parser, m: Marker) {
 pub(crate) fn extern_item_list(p: &mut Parser) {
     assert!(p.at(L_CURLY));
     let m = p.start();
+    let unclosed_indent = p.unclosed_curly_indent();
     p.bump();
-    mod_contents(p, true);
+    mod_contents(p, true, unclosed_indent);
     p.expect(R_CURLY);
     m.complete(p, EXTERN_ITEM_LIST);
 }
@@ -321,8 +332,9 @@ pub(crate) fn mod_item(p: &mut Parser, m: Marker) {
 pub(crate) fn mod_item_list(p: &mut Parser) {
     assert!(p.at(L_CURLY));
     let m = p.start();
+    let unclosed_indent = p.unclosed_curly_indent();
     p.bump();
-    mod_contents(p, true);
+    mod_contents(p, true, unclosed_indent);
     p.expect(R_CURLY);
     m.complete(p, ITEM_LIST);
 }
@@ -28,8 +28,9 @@ pub(super) fn trait_def(p: &mut Parser) {
 pub(crate) fn trait_item_list(p: &mut Parser) {
     assert!(p.at(L_CURLY));
     let m = p.start();
+    let unclosed_indent = p.unclosed_curly_indent();
     p.bump();
-    while !p.at(EOF) && !p.at(R_CURLY) {
+    while !p.at(EOF) && !p.at(R_CURLY) && !at_unclosed_block_end(p, unclosed_indent) {
         if p.at(L_CURLY) {
             error_block(p, "expected an item");
             continue;
@@ -77,6 +78,7 @@ pub(super) fn impl_block(p: &mut Parser) {
 pub(crate) fn impl_item_list(p: &mut Parser) {
     assert!(p.at(L_CURLY));
     let m = p.start();
+    let unclosed_indent = p.unclosed_curly_indent();
     p.bump();
     // test impl_inner_attributes
     // enum F{}
@@ -86,7 +88,7 @@ pub(crate) fn impl_item_list(p: &mut Parser) {
     // }
     attributes::inner_attributes(p);
 
-    while !p.at(EOF) && !p.at(R_CURLY) {
+    while !p.at(EOF) && !p.at(R_CURLY) && !at_unclosed_block_end(p, unclosed_indent) {
         if p.at(L_CURLY) {
             error_block(p, "expected an item");
             continue;
@@ -36,6 +36,12 @@ pub trait TokenSource {
     fn is_token_joint_to_next(&self, pos: usize) -> bool;
     /// Is the current token a specified keyword?
     fn is_keyword(&self, pos: usize, kw: &str) -> bool;
+    /// If the token is the first one on its line, what is the indentation of
+    /// the line?
+    fn line_start_indent(&self, pos: usize) -> Option<usize>;
+    /// If the token is a `{` which is never closed, what is the indentation
+    /// of the line it is on?
+    fn unclosed_curly_indent(&self, pos: usize) -> Option<usize>;
 }
 
 /// `TreeSink` abstracts details of a particular syntax tree implementation.
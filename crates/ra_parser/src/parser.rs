@@ -95,6 +95,19 @@ impl<'t> Parser<'t> {
         self.token_source.is_keyword(self.token_pos, kw)
     }
 
+    /// If the current token is the first one on its line, returns the
+    /// indentation of the line.
+    pub(crate) fn line_start_indent(&self) -> Option<usize> {
+        self.token_source.line_start_indent(self.token_pos)
+    }
+
+    /// If the current token is a `{` which is never closed, returns the
+    /// indentation of the line it is on. The indentation is used to guess
+    /// where the block should have ended.
+    pub(crate) fn unclosed_curly_indent(&self) -> Option<usize> {
+        self.token_source.unclosed_curly_indent(self.token_pos)
+    }
+
     /// Starts a new node in the syntax tree. All nodes and tokens
     /// consumed between the `start` and the corresponding `Marker::complete`
     /// belong to the same node.
@@ -18,7 +18,7 @@ pub(crate) struct SyntaxInfo {
 impl SyntaxKind {
     pub fn is_trivia(self) -> bool {
         match self {
-            SyntaxKind::WHITESPACE | SyntaxKind::COMMENT => true,
+            SyntaxKind::WHITESPACE | SyntaxKind::COMMENT | SyntaxKind::BYTE_ORDER_MARK => true,
             _ => false,
         }
     }
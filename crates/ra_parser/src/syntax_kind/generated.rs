@@ -7,6 +7,7 @@ use super::SyntaxInfo;
 
 /// The kind of syntax node, e.g. `IDENT`, `USE_KW`, or `STRUCT_DEF`.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
 pub enum SyntaxKind {
     // Technical SyntaxKinds: they appear temporally during parsing,
     // but never end up in the final tree
@@ -119,6 +120,7 @@ pub enum SyntaxKind {
     LIFETIME,
     COMMENT,
     SHEBANG,
+    BYTE_ORDER_MARK,
     SOURCE_FILE,
     STRUCT_DEF,
     ENUM_DEF,
@@ -351,6 +353,25 @@ impl SyntaxKind {
         }
     }
 
+    /// Items which can appear in a module, see `ast::ModuleItem`.
+    pub fn is_item(self) -> bool {
+        match self {
+            | STRUCT_DEF
+            | ENUM_DEF
+            | FN_DEF
+            | TRAIT_DEF
+            | TYPE_ALIAS_DEF
+            | IMPL_BLOCK
+            | USE_ITEM
+            | EXTERN_CRATE_ITEM
+            | CONST_DEF
+            | STATIC_DEF
+            | MODULE
+                => true,
+            _ => false
+        }
+    }
+
     pub(crate) fn info(self) -> &'static SyntaxInfo {
         match self {
             SEMI => &SyntaxInfo { name: "SEMI" },
@@ -458,6 +479,7 @@ impl SyntaxKind {
             LIFETIME => &SyntaxInfo { name: "LIFETIME" },
             COMMENT => &SyntaxInfo { name: "COMMENT" },
             SHEBANG => &SyntaxInfo { name: "SHEBANG" },
+            BYTE_ORDER_MARK => &SyntaxInfo { name: "BYTE_ORDER_MARK" },
             SOURCE_FILE => &SyntaxInfo { name: "SOURCE_FILE" },
             STRUCT_DEF => &SyntaxInfo { name: "STRUCT_DEF" },
             ENUM_DEF => &SyntaxInfo { name: "ENUM_DEF" },
@@ -571,6 +593,231 @@ impl SyntaxKind {
             EOF => &SyntaxInfo { name: "EOF" },
         }
     }
+    /// Converts a value obtained with `kind as u16` back to a `SyntaxKind`.
+    pub fn from_u16(raw: u16) -> Option<SyntaxKind> {
+        let kind = match raw {
+            0 => TOMBSTONE,
+            1 => EOF,
+            2 => SEMI,
+            3 => COMMA,
+            4 => L_PAREN,
+            5 => R_PAREN,
+            6 => L_CURLY,
+            7 => R_CURLY,
+            8 => L_BRACK,
+            9 => R_BRACK,
+            10 => L_ANGLE,
+            11 => R_ANGLE,
+            12 => AT,
+            13 => POUND,
+            14 => TILDE,
+            15 => QUESTION,
+            16 => DOLLAR,
+            17 => AMP,
+            18 => PIPE,
+            19 => PLUS,
+            20 => STAR,
+            21 => SLASH,
+            22 => CARET,
+            23 => PERCENT,
+            24 => UNDERSCORE,
+            25 => DOT,
+            26 => DOTDOT,
+            27 => DOTDOTDOT,
+            28 => DOTDOTEQ,
+            29 => COLON,
+            30 => COLONCOLON,
+            31 => EQ,
+            32 => EQEQ,
+            33 => FAT_ARROW,
+            34 => EXCL,
+            35 => NEQ,
+            36 => MINUS,
+            37 => THIN_ARROW,
+            38 => LTEQ,
+            39 => GTEQ,
+            40 => PLUSEQ,
+            41 => MINUSEQ,
+            42 => PIPEEQ,
+            43 => AMPEQ,
+            44 => CARETEQ,
+            45 => SLASHEQ,
+            46 => STAREQ,
+            47 => PERCENTEQ,
+            48 => AMPAMP,
+            49 => PIPEPIPE,
+            50 => SHL,
+            51 => SHR,
+            52 => SHLEQ,
+            53 => SHREQ,
+            54 => ASYNC_KW,
+            55 => USE_KW,
+            56 => FN_KW,
+            57 => STRUCT_KW,
+            58 => ENUM_KW,
+            59 => TRAIT_KW,
+            60 => IMPL_KW,
+            61 => DYN_KW,
+            62 => TRUE_KW,
+            63 => FALSE_KW,
+            64 => AS_KW,
+            65 => EXTERN_KW,
+            66 => CRATE_KW,
+            67 => MOD_KW,
+            68 => PUB_KW,
+            69 => SELF_KW,
+            70 => SUPER_KW,
+            71 => IN_KW,
+            72 => WHERE_KW,
+            73 => FOR_KW,
+            74 => LOOP_KW,
+            75 => WHILE_KW,
+            76 => CONTINUE_KW,
+            77 => BREAK_KW,
+            78 => IF_KW,
+            79 => ELSE_KW,
+            80 => MATCH_KW,
+            81 => CONST_KW,
+            82 => STATIC_KW,
+            83 => MUT_KW,
+            84 => UNSAFE_KW,
+            85 => TYPE_KW,
+            86 => REF_KW,
+            87 => LET_KW,
+            88 => MOVE_KW,
+            89 => RETURN_KW,
+            90 => AUTO_KW,
+            91 => DEFAULT_KW,
+            92 => UNION_KW,
+            93 => INT_NUMBER,
+            94 => FLOAT_NUMBER,
+            95 => CHAR,
+            96 => BYTE,
+            97 => STRING,
+            98 => RAW_STRING,
+            99 => BYTE_STRING,
+            100 => RAW_BYTE_STRING,
+            101 => ERROR,
+            102 => IDENT,
+            103 => WHITESPACE,
+            104 => LIFETIME,
+            105 => COMMENT,
+            106 => SHEBANG,
+            107 => BYTE_ORDER_MARK,
+            108 => SOURCE_FILE,
+            109 => STRUCT_DEF,
+            110 => ENUM_DEF,
+            111 => FN_DEF,
+            112 => RET_TYPE,
+            113 => EXTERN_CRATE_ITEM,
+            114 => MODULE,
+            115 => USE_ITEM,
+            116 => STATIC_DEF,
+            117 => CONST_DEF,
+            118 => TRAIT_DEF,
+            119 => IMPL_BLOCK,
+            120 => TYPE_ALIAS_DEF,
+            121 => MACRO_CALL,
+            122 => TOKEN_TREE,
+            123 => PAREN_TYPE,
+            124 => TUPLE_TYPE,
+            125 => NEVER_TYPE,
+            126 => PATH_TYPE,
+            127 => POINTER_TYPE,
+            128 => ARRAY_TYPE,
+            129 => SLICE_TYPE,
+            130 => REFERENCE_TYPE,
+            131 => PLACEHOLDER_TYPE,
+            132 => FN_POINTER_TYPE,
+            133 => FOR_TYPE,
+            134 => IMPL_TRAIT_TYPE,
+            135 => DYN_TRAIT_TYPE,
+            136 => REF_PAT,
+            137 => BIND_PAT,
+            138 => PLACEHOLDER_PAT,
+            139 => PATH_PAT,
+            140 => STRUCT_PAT,
+            141 => FIELD_PAT_LIST,
+            142 => FIELD_PAT,
+            143 => TUPLE_STRUCT_PAT,
+            144 => TUPLE_PAT,
+            145 => SLICE_PAT,
+            146 => RANGE_PAT,
+            147 => LITERAL_PAT,
+            148 => TUPLE_EXPR,
+            149 => ARRAY_EXPR,
+            150 => PAREN_EXPR,
+            151 => PATH_EXPR,
+            152 => LAMBDA_EXPR,
+            153 => IF_EXPR,
+            154 => WHILE_EXPR,
+            155 => CONDITION,
+            156 => LOOP_EXPR,
+            157 => FOR_EXPR,
+            158 => CONTINUE_EXPR,
+            159 => BREAK_EXPR,
+            160 => LABEL,
+            161 => BLOCK_EXPR,
+            162 => RETURN_EXPR,
+            163 => MATCH_EXPR,
+            164 => MATCH_ARM_LIST,
+            165 => MATCH_ARM,
+            166 => MATCH_GUARD,
+            167 => STRUCT_LIT,
+            168 => NAMED_FIELD_LIST,
+            169 => NAMED_FIELD,
+            170 => CALL_EXPR,
+            171 => INDEX_EXPR,
+            172 => METHOD_CALL_EXPR,
+            173 => FIELD_EXPR,
+            174 => TRY_EXPR,
+            175 => CAST_EXPR,
+            176 => REF_EXPR,
+            177 => PREFIX_EXPR,
+            178 => RANGE_EXPR,
+            179 => BIN_EXPR,
+            180 => BLOCK,
+            181 => EXTERN_BLOCK,
+            182 => EXTERN_ITEM_LIST,
+            183 => ENUM_VARIANT,
+            184 => NAMED_FIELD_DEF_LIST,
+            185 => NAMED_FIELD_DEF,
+            186 => POS_FIELD_DEF_LIST,
+            187 => POS_FIELD_DEF,
+            188 => ENUM_VARIANT_LIST,
+            189 => ITEM_LIST,
+            190 => ATTR,
+            191 => META_ITEM,
+            192 => USE_TREE,
+            193 => USE_TREE_LIST,
+            194 => PATH,
+            195 => PATH_SEGMENT,
+            196 => LITERAL,
+            197 => ALIAS,
+            198 => VISIBILITY,
+            199 => WHERE_CLAUSE,
+            200 => WHERE_PRED,
+            201 => ABI,
+            202 => NAME,
+            203 => NAME_REF,
+            204 => LET_STMT,
+            205 => EXPR_STMT,
+            206 => TYPE_PARAM_LIST,
+            207 => LIFETIME_PARAM,
+            208 => TYPE_PARAM,
+            209 => TYPE_ARG_LIST,
+            210 => LIFETIME_ARG,
+            211 => TYPE_ARG,
+            212 => ASSOC_TYPE_ARG,
+            213 => PARAM_LIST,
+            214 => PARAM,
+            215 => SELF_PARAM,
+            216 => ARG_LIST,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
     pub fn from_keyword(ident: &str) -> Option<SyntaxKind> {
         let kw = match ident {
             "async" => ASYNC_KW,
@@ -0,0 +1,232 @@
+//! A lightweight hierarchical profiler.
+//!
+//! Interesting operations are wrapped in spans with `profile`:
+//!
+//! ```
+//! fn collect_defs() {
+//!     let _p = ra_prof::profile("collect_defs");
+//!     // ...
+//! }
+//! ```
+//!
+//! Spans opened while another span is active on the same thread become its
+//! children. When an outermost span finishes and took at least the threshold,
+//! the whole tree is passed to the installed `ProfileSink`. Without a sink,
+//! `profile` is just a load of an atomic flag, so spans can be left in hot
+//! code.
+//!
+//! Alternatively, `capture` records the spans of a single call on the current
+//! thread, even if no sink is installed.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+/// Receives the spans which took longer than the threshold.
+pub trait ProfileSink: Send + Sync {
+    fn report(&self, span: &Span);
+}
+
+impl<F: Fn(&Span) + Send + Sync> ProfileSink for F {
+    fn report(&self, span: &Span) {
+        self(span)
+    }
+}
+
+/// A finished span, together with the spans nested into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub label: &'static str,
+    pub duration: Duration,
+    pub children: Vec<Span>,
+}
+
+struct Installed {
+    sink: Arc<dyn ProfileSink>,
+    threshold: Duration,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref SINK: RwLock<Option<Installed>> = RwLock::new(None);
+}
+
+thread_local! {
+    static STACK: RefCell<Vec<Frame>> = RefCell::new(Vec::new());
+    /// The number of active `capture`s on this thread.
+    static CAPTURES: Cell<usize> = Cell::new(0);
+}
+
+struct Frame {
+    label: &'static str,
+    start: Instant,
+    children: Vec<Span>,
+}
+
+/// Installs `sink`, which will receive the outermost spans which took at
+/// least `threshold`. Replaces the previously installed sink.
+pub fn set_sink(sink: Arc<dyn ProfileSink>, threshold: Duration) {
+    *SINK.write().unwrap() = Some(Installed { sink, threshold });
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Removes the installed sink, disabling profiling.
+pub fn clear_sink() {
+    ENABLED.store(false, Ordering::SeqCst);
+    *SINK.write().unwrap() = None;
+}
+
+/// Opens a span, which is closed when the returned guard is dropped.
+pub fn profile(label: &'static str) -> Profiler {
+    if !ENABLED.load(Ordering::Relaxed) && CAPTURES.with(|it| it.get()) == 0 {
+        return Profiler { active: false };
+    }
+    push_frame(label);
+    Profiler { active: true }
+}
+
+/// Runs `f` in a span labeled `label`, and returns the span instead of
+/// reporting it to the sink. Spans opened by `f` on the current thread are
+/// recorded even if profiling is disabled.
+pub fn capture<T>(label: &'static str, f: impl FnOnce() -> T) -> (T, Span) {
+    CAPTURES.with(|it| it.set(it.get() + 1));
+    push_frame(label);
+    let mut guard = Capture { span: None };
+    let value = f();
+    guard.finish();
+    (value, guard.span.take().unwrap())
+}
+
+/// Closes the span of `capture`, even if `f` panics.
+struct Capture {
+    span: Option<Span>,
+}
+
+impl Capture {
+    fn finish(&mut self) {
+        CAPTURES.with(|it| it.set(it.get() - 1));
+        self.span = pop_frame();
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        if self.span.is_none() && std::thread::panicking() {
+            self.finish();
+        }
+    }
+}
+
+fn push_frame(label: &'static str) {
+    STACK.with(|stack| {
+        stack.borrow_mut().push(Frame { label, start: Instant::now(), children: Vec::new() })
+    });
+}
+
+fn pop_frame() -> Option<Span> {
+    STACK.with(|stack| {
+        let frame = stack.borrow_mut().pop()?;
+        Some(Span { label: frame.label, duration: frame.start.elapsed(), children: frame.children })
+    })
+}
+
+/// The guard of an open span.
+pub struct Profiler {
+    active: bool,
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let span = match pop_frame() {
+            Some(it) => it,
+            None => return,
+        };
+        let finished = STACK.with(|stack| match stack.borrow_mut().last_mut() {
+            Some(parent) => {
+                parent.children.push(span);
+                None
+            }
+            None => Some(span),
+        });
+        if let Some(span) = finished {
+            report(&span);
+        }
+    }
+}
+
+fn report(span: &Span) {
+    let installed = SINK.read().unwrap();
+    if let Some(installed) = &*installed {
+        if span.duration >= installed.threshold {
+            installed.sink.report(span);
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fn go(span: &Span, depth: usize, fmt: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(fmt, "{}{:?} - {}", "    ".repeat(depth), span.duration, span.label)?;
+            for child in span.children.iter() {
+                go(child, depth + 1, fmt)?;
+            }
+            Ok(())
+        }
+        go(self, 0, fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn reports_nested_spans_over_threshold() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let reported = Arc::clone(&reported);
+            move |span: &Span| reported.lock().unwrap().push(span.clone())
+        };
+        set_sink(Arc::new(sink), Duration::from_millis(0));
+        {
+            let _p = profile("outer");
+            let _p = profile("inner");
+        }
+        clear_sink();
+        {
+            let _p = profile("disabled");
+        }
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].label, "outer");
+        let children = reported[0].children.iter().map(|it| it.label).collect::<Vec<_>>();
+        assert_eq!(children, ["inner"]);
+    }
+
+    #[test]
+    fn captures_spans_without_sink() {
+        let (value, span) = capture("request", || {
+            let _p = profile("query");
+            92
+        });
+        assert_eq!(value, 92);
+        assert_eq!(span.label, "request");
+        let children = span.children.iter().map(|it| it.label).collect::<Vec<_>>();
+        assert_eq!(children, ["query"]);
+    }
+}
@@ -156,12 +156,9 @@ impl ProjectWorkspace {
                         if let (Some(&from), Some(&to)) =
                             (crates.get(&from_crate_id), crates.get(&to_crate_id))
                         {
-                            if let Err(_) = crate_graph.add_dep(from, dep.name.clone().into(), to) {
-                                log::error!(
-                                    "cyclic dependency {:?} -> {:?}",
-                                    from_crate_id,
-                                    to_crate_id
-                                );
+                            if let Err(err) = crate_graph.add_dep(from, dep.name.clone().into(), to)
+                            {
+                                log::error!("{:?} -> {:?}: {}", from_crate_id, to_crate_id, err);
                             }
                         }
                     }
@@ -183,8 +180,8 @@ impl ProjectWorkspace {
                         if let (Some(&from), Some(&to)) =
                             (sysroot_crates.get(&from), sysroot_crates.get(&to))
                         {
-                            if let Err(_) = crate_graph.add_dep(from, name.into(), to) {
-                                log::error!("cyclic dependency between sysroot crates")
+                            if let Err(err) = crate_graph.add_dep(from, name.into(), to) {
+                                log::error!("sysroot crate {}: {}", name, err)
                             }
                         }
                     }
@@ -214,19 +211,16 @@ impl ProjectWorkspace {
                     for &from in pkg_crates.get(&pkg).into_iter().flatten() {
                         if let Some(to) = lib_tgt {
                             if to != from {
-                                if let Err(_) =
+                                if let Err(err) =
                                     crate_graph.add_dep(from, pkg.name(&cargo).into(), to)
                                 {
-                                    log::error!(
-                                        "cyclic dependency between targets of {}",
-                                        pkg.name(&cargo)
-                                    )
+                                    log::error!("targets of {}: {}", pkg.name(&cargo), err)
                                 }
                             }
                         }
                         if let Some(std) = libstd {
-                            if let Err(_) = crate_graph.add_dep(from, "std".into(), std) {
-                                log::error!("cyclic dependency on std for {}", pkg.name(&cargo))
+                            if let Err(err) = crate_graph.add_dep(from, "std".into(), std) {
+                                log::error!("std for {}: {}", pkg.name(&cargo), err)
                             }
                         }
                     }
@@ -238,13 +232,14 @@ impl ProjectWorkspace {
                     for dep in pkg.dependencies(&cargo) {
                         if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
                             for &from in pkg_crates.get(&pkg).into_iter().flatten() {
-                                if let Err(_) =
+                                if let Err(err) =
                                     crate_graph.add_dep(from, dep.name.clone().into(), to)
                                 {
                                     log::error!(
-                                        "cyclic dependency {} -> {}",
+                                        "{} -> {}: {}",
                                         pkg.name(&cargo),
-                                        dep.pkg.name(&cargo)
+                                        dep.pkg.name(&cargo),
+                                        err
                                     )
                                 }
                             }
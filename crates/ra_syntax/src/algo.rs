@@ -1,4 +1,5 @@
 pub mod visit;
+pub mod context;
 
 use rowan::TransparentNewType;
 
@@ -0,0 +1,153 @@
+//! Classification of the syntactic context at a cursor position.
+
+use crate::{
+    AstNode, SourceFile, SyntaxNode, TextUnit,
+    SyntaxKind::{self, *},
+    algo::{find_leaf_at_offset, LeafAtOffset},
+    ast,
+};
+
+/// The syntactic context of an offset, as seen by someone typing there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxContext {
+    /// Between items, like at the top level of a file or in an `impl`.
+    ItemList,
+    /// Where an expression or a statement is expected.
+    ExprPosition,
+    TypePosition,
+    PatternPosition,
+    /// Inside of a `use` item.
+    UseTree,
+    Attr,
+    /// Inside of a string, char or byte literal, between the quotes.
+    StringLiteral,
+    Comment,
+    /// None of the above, like inside of a macro call.
+    Other,
+}
+
+/// Classifies the context at `offset`.
+///
+/// An offset at the boundary of two tokens belongs to the token which the
+/// user would be extending by typing there: an identifier or a keyword to the
+/// left wins, otherwise the token to the right does. Literals and comments
+/// only count if the offset is strictly inside of them, except that the end
+/// of a line comment or of an unterminated literal is still inside.
+pub fn classify_context(file: &SourceFile, offset: TextUnit) -> SyntaxContext {
+    let leaf = match find_leaf_at_offset(file.syntax(), offset) {
+        LeafAtOffset::None => return SyntaxContext::ItemList,
+        LeafAtOffset::Single(leaf) => leaf,
+        LeafAtOffset::Between(left, right) => {
+            if let Some(context) = leaf_context(left, offset) {
+                return context;
+            }
+            if is_word(left.kind()) || right.kind().is_trivia() {
+                left
+            } else {
+                right
+            }
+        }
+    };
+    if let Some(context) = leaf_context(leaf, offset) {
+        return context;
+    }
+
+    for node in leaf.ancestors() {
+        let context = match node.kind() {
+            ATTR => SyntaxContext::Attr,
+            USE_ITEM => SyntaxContext::UseTree,
+            TOKEN_TREE if node.ancestors().any(|it| it.kind() == ATTR) => SyntaxContext::Attr,
+            TOKEN_TREE => SyntaxContext::Other,
+            SOURCE_FILE | ITEM_LIST => SyntaxContext::ItemList,
+            BLOCK => SyntaxContext::ExprPosition,
+            _ if ast::TypeRef::cast(node).is_some() => SyntaxContext::TypePosition,
+            _ if ast::Pat::cast(node).is_some() => SyntaxContext::PatternPosition,
+            _ if ast::Expr::cast(node).is_some() => SyntaxContext::ExprPosition,
+            _ => continue,
+        };
+        return context;
+    }
+    SyntaxContext::Other
+}
+
+/// Context determined by the leaf itself, for comments and literals.
+fn leaf_context(leaf: &SyntaxNode, offset: TextUnit) -> Option<SyntaxContext> {
+    let range = leaf.range();
+    let inside = range.start() < offset && offset < range.end();
+    let text = leaf.leaf_text()?;
+    let at_open_end = offset == range.end();
+    match leaf.kind() {
+        COMMENT => {
+            let is_line_comment = !text.starts_with("/*");
+            if inside || (at_open_end && is_line_comment) {
+                return Some(SyntaxContext::Comment);
+            }
+        }
+        STRING | RAW_STRING | BYTE_STRING | RAW_BYTE_STRING | CHAR | BYTE => {
+            // `"foo` and `r#"foo` are unterminated, `"foo"` and `"` are not.
+            let quote = if leaf.kind() == CHAR || leaf.kind() == BYTE { '\'' } else { '"' };
+            let is_closed = text.len() > 1
+                && text.trim_end_matches('#').ends_with(quote)
+                && text.find(quote) != text.rfind(quote);
+            if inside || (at_open_end && !is_closed) {
+                return Some(SyntaxContext::StringLiteral);
+            }
+        }
+        _ => (),
+    }
+    None
+}
+
+fn is_word(kind: SyntaxKind) -> bool {
+    kind == IDENT || kind == LIFETIME || kind.is_keyword()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::extract_offset;
+
+    use crate::SourceFile;
+
+    use super::{classify_context, SyntaxContext};
+
+    fn check(code: &str, expected: SyntaxContext) {
+        let (offset, code) = extract_offset(code);
+        let file = SourceFile::parse(&code);
+        assert_eq!(classify_context(&file, offset), expected, "{}", code);
+    }
+
+    #[test]
+    fn classifies_items_and_expressions() {
+        check("<|>", SyntaxContext::ItemList);
+        check("fn foo() {}\n<|>\nfn bar() {}", SyntaxContext::ItemList);
+        check("impl S { <|> }", SyntaxContext::ItemList);
+        check("fn foo() { <|> }", SyntaxContext::ExprPosition);
+        check("fn foo() { let x = ba<|> }", SyntaxContext::ExprPosition);
+        check("fn foo() { bar(<|>) }", SyntaxContext::ExprPosition);
+    }
+
+    #[test]
+    fn classifies_types_and_patterns() {
+        check("fn foo() { let x: Ve<|> }", SyntaxContext::TypePosition);
+        check("fn foo(x: u3<|>) {}", SyntaxContext::TypePosition);
+        check("fn foo() { let x<|> = 92; }", SyntaxContext::PatternPosition);
+        check("fn foo() { match x { Some(y<|>) => () } }", SyntaxContext::PatternPosition);
+    }
+
+    #[test]
+    fn classifies_use_trees_and_attrs() {
+        check("use std::{co<|>};", SyntaxContext::UseTree);
+        check("#[derive(Cl<|>)] struct S;", SyntaxContext::Attr);
+    }
+
+    #[test]
+    fn classifies_literals_and_comments() {
+        check(r#"fn foo() { "ab<|>c"; }"#, SyntaxContext::StringLiteral);
+        check(r#"fn foo() { "abc"<|>; }"#, SyntaxContext::ExprPosition);
+        check(r#"fn foo() { <|>"abc"; }"#, SyntaxContext::ExprPosition);
+        check(r#"fn foo() { "abc<|>"#, SyntaxContext::StringLiteral);
+        check("// foo<|>\nfn foo() {}", SyntaxContext::Comment);
+        check("/* foo */<|>\nfn foo() {}", SyntaxContext::ItemList);
+        check("<|>// foo\nfn foo() {}", SyntaxContext::ItemList);
+    }
+}
@@ -1,5 +1,7 @@
 //! Abstract Syntax Tree, layered on top of untyped `SyntaxNode`s
 mod generated;
+pub mod edit;
+pub mod make;
 
 use std::marker::PhantomData;
 
@@ -8,6 +10,7 @@ use itertools::Itertools;
 pub use self::generated::*;
 use crate::{
     syntax_node::{SyntaxNode, SyntaxNodeChildren, TreeArc, RaTypes},
+    string_lexing::{self, StringComponentKind},
     SmolStr,
     SyntaxKind::*,
 };
@@ -126,25 +129,18 @@ pub trait DocCommentsOwner: AstNode {
 
     /// Returns the textual content of a doc comment block as a single string.
     /// That is, strips leading `///` (+ optional 1 character of whitespace)
-    /// and joins lines.
+    /// and joins lines. `#[doc = "..."]` attributes are treated like doc
+    /// comments, and are merged with them in source order. Docs included from
+    /// files can't be resolved, so a placeholder is shown instead.
     fn doc_comment_text(&self) -> Option<std::string::String> {
         let docs = self
-            .doc_comments()
-            .filter(|comment| comment.is_doc_comment())
-            .map(|comment| {
-                let prefix_len = comment.prefix().len();
-
-                let line = comment.text().as_str();
-
-                // Determine if the prefix or prefix + 1 char is stripped
-                let pos =
-                    if line.chars().nth(prefix_len).map(|c| c.is_whitespace()).unwrap_or(false) {
-                        prefix_len + 1
-                    } else {
-                        prefix_len
-                    };
-
-                line[pos..].to_owned()
+            .syntax()
+            .children()
+            .filter_map(|node| {
+                if let Some(comment) = Comment::cast(node) {
+                    return comment_doc_text(comment);
+                }
+                Attr::cast(node).and_then(|attr| attr.doc_text())
             })
             .join("\n");
 
@@ -156,6 +152,56 @@ pub trait DocCommentsOwner: AstNode {
     }
 }
 
+/// Nodes which can contain inner docs, `//!` comments and `#![doc = "..."]`
+/// attributes, which document the enclosing item.
+pub trait InnerDocCommentsOwner: AstNode {
+    /// Like `DocCommentsOwner::doc_comment_text`, but for inner docs.
+    fn inner_doc_comment_text(&self) -> Option<std::string::String> {
+        let docs = self
+            .syntax()
+            .children()
+            // The parser attaches comments right before an item to the item.
+            .flat_map(|child| {
+                let leading = child.children().take_while(|it| it.kind().is_trivia());
+                std::iter::once(child).chain(leading)
+            })
+            .filter_map(|node| {
+                if let Some(comment) = Comment::cast(node) {
+                    if comment.flavor() != CommentFlavor::ModuleDoc {
+                        return None;
+                    }
+                    return comment_doc_text(comment);
+                }
+                Attr::cast(node).and_then(|attr| attr.inner_doc_text())
+            })
+            .join("\n");
+
+        if docs.is_empty() {
+            None
+        } else {
+            Some(docs)
+        }
+    }
+}
+
+fn comment_doc_text(comment: &Comment) -> Option<std::string::String> {
+    if !comment.is_doc_comment() {
+        return None;
+    }
+    let prefix_len = comment.prefix().len();
+
+    let line = comment.text().as_str();
+
+    // Determine if the prefix or prefix + 1 char is stripped
+    let pos = if line.chars().nth(prefix_len).map(|c| c.is_whitespace()).unwrap_or(false) {
+        prefix_len + 1
+    } else {
+        prefix_len
+    };
+
+    Some(line[pos..].to_owned())
+}
+
 impl Attr {
     pub fn is_inner(&self) -> bool {
         let tt = match self.value() {
@@ -192,6 +238,54 @@ impl Attr {
         }
     }
 
+    /// The documentation of an outer `#[doc = "..."]` attribute. For
+    /// `#[doc = include_str!("...")]` and `#[doc(include = "...")]`, returns a
+    /// placeholder mentioning the included file.
+    pub fn doc_text(&self) -> Option<std::string::String> {
+        if self.is_inner() {
+            return None;
+        }
+        self.doc_text_impl()
+    }
+
+    /// Like `doc_text`, but for an inner `#![doc = "..."]` attribute.
+    pub fn inner_doc_text(&self) -> Option<std::string::String> {
+        if !self.is_inner() {
+            return None;
+        }
+        self.doc_text_impl()
+    }
+
+    fn doc_text_impl(&self) -> Option<std::string::String> {
+        let tt = self.value()?;
+        let mut tokens = tt.syntax().children().filter(|it| !it.kind().is_trivia()).skip(1);
+        if tokens.next()?.leaf_text()?.as_str() != "doc" {
+            return None;
+        }
+        let next = tokens.next()?;
+        if next.kind() == EQ {
+            let value = tokens.next()?;
+            if let Some(text) = string_literal_value(value) {
+                // `/// foo` desugars to `#[doc = " foo"]`
+                let text = text.lines().map(strip_leading_whitespace_char).join("\n");
+                return Some(text);
+            }
+            if value.leaf_text()?.as_str() != "include_str" {
+                return None;
+            }
+        } else if next.kind() == TOKEN_TREE {
+            let first_arg =
+                next.children().find(|it| !it.kind().is_trivia() && it.kind() != L_PAREN)?;
+            if first_arg.leaf_text()?.as_str() != "include" {
+                return None;
+            }
+        } else {
+            return None;
+        }
+        let path = tt.syntax().descendants().find_map(string_literal_value)?;
+        Some(format!("*documentation included from `{}`*", path))
+    }
+
     pub fn as_named(&self) -> Option<SmolStr> {
         let tt = self.value()?;
         let attr = tt.syntax().children().nth(1)?;
@@ -271,6 +365,78 @@ impl Whitespace {
     }
 }
 
+impl String {
+    /// The value of the string literal, with escape sequences resolved. Returns
+    /// `None` if the literal is malformed.
+    pub fn value(&self) -> Option<std::string::String> {
+        let text = self.text().as_str();
+        let mut components = string_lexing::parse_string_literal(text);
+        let mut buf = std::string::String::new();
+        for component in &mut components {
+            let component_text = &text[component.range];
+            match component.kind {
+                StringComponentKind::IgnoreNewline => (),
+                StringComponentKind::CodePoint => buf.push_str(component_text),
+                StringComponentKind::AsciiEscape => buf.push(match &component_text[1..] {
+                    "n" => '\n',
+                    "r" => '\r',
+                    "t" => '\t',
+                    "0" => '\0',
+                    "\\" => '\\',
+                    "'" => '\'',
+                    "\"" => '"',
+                    _ => return None,
+                }),
+                StringComponentKind::AsciiCodeEscape => {
+                    let code = u8::from_str_radix(component_text.get(2..)?, 16).ok()?;
+                    if code > 0x7F {
+                        return None;
+                    }
+                    buf.push(code as char);
+                }
+                StringComponentKind::UnicodeEscape => {
+                    let hex =
+                        component_text.get(2..)?.trim_start_matches('{').trim_end_matches('}');
+                    let hex: std::string::String = hex.chars().filter(|&c| c != '_').collect();
+                    let code = u32::from_str_radix(&hex, 16).ok()?;
+                    buf.push(std::char::from_u32(code)?);
+                }
+            }
+        }
+        if !components.has_closing_quote {
+            return None;
+        }
+        Some(buf)
+    }
+}
+
+impl RawString {
+    /// The value of the raw string literal, that is, the text between the
+    /// quotes.
+    pub fn value(&self) -> Option<std::string::String> {
+        let text = self.text().as_str();
+        let text = text.get(1..)?.trim_matches('#');
+        if text.len() < 2 || !text.starts_with('"') || !text.ends_with('"') {
+            return None;
+        }
+        Some(text[1..text.len() - 1].to_string())
+    }
+}
+
+fn string_literal_value(node: &SyntaxNode) -> Option<std::string::String> {
+    if let Some(it) = String::cast(node) {
+        return it.value();
+    }
+    RawString::cast(node)?.value()
+}
+
+fn strip_leading_whitespace_char(line: &str) -> &str {
+    match line.chars().next() {
+        Some(c) if c.is_whitespace() => &line[c.len_utf8()..],
+        _ => line,
+    }
+}
+
 impl Name {
     pub fn text(&self) -> &SmolStr {
         let ident = self.syntax().first_child().unwrap();
@@ -308,6 +474,30 @@ impl ImplBlock {
     }
 }
 
+impl TypeParam {
+    /// Type bounds, like `Clone` in `T: Clone + 'a = Foo`.
+    pub fn bounds(&self) -> impl Iterator<Item = &TypeRef> {
+        self.syntax().children().take_while(|it| it.kind() != EQ).filter_map(TypeRef::cast)
+    }
+
+    /// Lifetime bounds, like `'a` in `T: Clone + 'a = Foo`.
+    pub fn lifetime_bounds(&self) -> impl Iterator<Item = &Lifetime> {
+        self.syntax().children().take_while(|it| it.kind() != EQ).filter_map(Lifetime::cast)
+    }
+
+    /// The default type, like `Foo` in `T: Clone + 'a = Foo`.
+    pub fn default_type(&self) -> Option<&TypeRef> {
+        self.syntax().children().skip_while(|it| it.kind() != EQ).find_map(TypeRef::cast)
+    }
+}
+
+impl LifetimeParam {
+    /// Lifetime bounds, like `'b` in `'a: 'b`.
+    pub fn bounds(&self) -> impl Iterator<Item = &Lifetime> {
+        self.syntax().children().filter_map(Lifetime::cast).skip(1)
+    }
+}
+
 impl Module {
     pub fn has_semi(&self) -> bool {
         match self.syntax().last_child() {
@@ -477,6 +667,12 @@ impl StructDef {
     pub fn flavor(&self) -> StructFlavor {
         StructFlavor::from_node(self)
     }
+
+    /// Unions are parsed as structs, which start with `union` instead of
+    /// `struct`.
+    pub fn is_union(&self) -> bool {
+        self.syntax().children().any(|it| it.kind() == UNION_KW)
+    }
 }
 
 impl EnumVariant {
@@ -492,6 +688,31 @@ impl EnumVariant {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityKind<'a> {
+    In(&'a Path),
+    PubCrate,
+    PubSuper,
+    PubSelf,
+    Pub,
+}
+
+impl Visibility {
+    pub fn kind(&self) -> VisibilityKind {
+        if let Some(path) = child_opt(self) {
+            VisibilityKind::In(path)
+        } else if self.syntax().children().any(|it| it.kind() == CRATE_KW) {
+            VisibilityKind::PubCrate
+        } else if self.syntax().children().any(|it| it.kind() == SUPER_KW) {
+            VisibilityKind::PubSuper
+        } else if self.syntax().children().any(|it| it.kind() == SELF_KW) {
+            VisibilityKind::PubSelf
+        } else {
+            VisibilityKind::Pub
+        }
+    }
+}
+
 impl PointerType {
     pub fn is_mut(&self) -> bool {
         self.syntax().children().any(|n| n.kind() == MUT_KW)
@@ -743,6 +964,28 @@ impl BindPat {
     }
 }
 
+#[test]
+fn test_type_param_bounds_and_default() {
+    let file = SourceFile::parse("struct S<'a: 'b + 'c, T: Clone + 'a = Foo, U>;");
+    let params = file.syntax().descendants().find_map(TypeParamList::cast).unwrap();
+
+    let lifetime = params.lifetime_params().next().unwrap();
+    let bounds: Vec<_> = lifetime.bounds().map(|it| it.text().as_str()).collect();
+    assert_eq!(bounds, ["'b", "'c"]);
+
+    let mut type_params = params.type_params();
+    let t = type_params.next().unwrap();
+    let bounds: Vec<_> = t.bounds().map(|it| it.syntax().text().to_string()).collect();
+    assert_eq!(bounds, ["Clone"]);
+    let bounds: Vec<_> = t.lifetime_bounds().map(|it| it.text().as_str()).collect();
+    assert_eq!(bounds, ["'a"]);
+    assert_eq!(t.default_type().unwrap().syntax().text().to_string(), "Foo");
+
+    let u = type_params.next().unwrap();
+    assert_eq!(u.bounds().count(), 0);
+    assert!(u.default_type().is_none());
+}
+
 #[test]
 fn test_doc_comment_none() {
     let file = SourceFile::parse(
@@ -784,3 +1027,57 @@ fn test_doc_comment_preserves_indents() {
     let module = file.syntax().descendants().find_map(Module::cast).unwrap();
     assert_eq!("doc1\n```\nfn foo() {\n    // ...\n}\n```", module.doc_comment_text().unwrap());
 }
+
+#[test]
+fn test_doc_attrs_merged_with_doc_comments() {
+    let file = SourceFile::parse(
+        r#"
+        /// first
+        #[doc = " second\n third"]
+        #[derive(Debug)]
+        #[doc(hidden)]
+        #[doc = r"fourth"]
+        /// fifth
+        struct Foo;
+        "#,
+    );
+    let def = file.syntax().descendants().find_map(StructDef::cast).unwrap();
+    assert_eq!("first\nsecond\nthird\nfourth\nfifth", def.doc_comment_text().unwrap());
+}
+
+#[test]
+fn test_inner_doc_comments() {
+    let file = SourceFile::parse(
+        r#"
+        //! crate
+        #![doc = " docs"]
+        fn foo() {}
+        mod bar {
+            //! bar
+            fn baz() {}
+        }
+        "#,
+    );
+    assert_eq!("crate\ndocs", file.inner_doc_comment_text().unwrap());
+    let item_list = file.syntax().descendants().find_map(ItemList::cast).unwrap();
+    assert_eq!("bar", item_list.inner_doc_comment_text().unwrap());
+}
+
+#[test]
+fn test_doc_attr_include() {
+    let file = SourceFile::parse(
+        r#"
+        #[doc = include_str!("foo.md")]
+        fn foo() {}
+        "#,
+    );
+    let def = file.syntax().descendants().find_map(FnDef::cast).unwrap();
+    assert_eq!("*documentation included from `foo.md`*", def.doc_comment_text().unwrap());
+}
+
+#[test]
+fn test_string_value() {
+    let file = SourceFile::parse(r#"const S: &str = "a\tb\x41\u{1F_600}\"";"#);
+    let string = file.syntax().descendants().find_map(String::cast).unwrap();
+    assert_eq!(string.value().unwrap(), "a\tbA\u{1F600}\"");
+}
@@ -0,0 +1,254 @@
+//! Structural editing of syntax trees.
+//!
+//! Trees are immutable, so the functions here build new nodes, detached from
+//! the original tree, with the whitespace and the commas around the edited
+//! children fixed up. To apply such an edit to a file, replace the text of the
+//! original node with the text of the new one.
+
+use crate::{
+    SmolStr, SyntaxKind, SyntaxNode, SyntaxTreeBuilder, TreeArc,
+    SyntaxKind::*,
+    ast::{self, AstNode, StructFlavor},
+};
+
+/// Returns a copy of `root` with `old`, one of its descendants, replaced with
+/// `new`.
+pub fn replace_descendant<N: AstNode>(root: &N, old: &SyntaxNode, new: &SyntaxNode) -> TreeArc<N> {
+    assert!(root.syntax() != old, "can't replace the root itself");
+    let mut builder = SyntaxTreeBuilder::default();
+    copy(&mut builder, root.syntax(), Some((old, new)));
+    let root = builder.finish();
+    N::cast(&root).unwrap().to_owned()
+}
+
+/// Adds `field` after the last field of a struct with named fields, using the
+/// same separator as between the existing fields. Returns `None` for tuple and
+/// unit structs.
+pub fn add_field_to_struct(
+    strukt: &ast::StructDef,
+    field: &ast::NamedFieldDef,
+) -> Option<TreeArc<ast::StructDef>> {
+    let fields = match strukt.flavor() {
+        StructFlavor::Named(it) => it,
+        StructFlavor::Tuple(_) | StructFlavor::Unit => return None,
+    };
+    let new_fields = append_to_list(fields, field.syntax());
+    Some(replace_descendant(strukt, fields.syntax(), new_fields.syntax()))
+}
+
+/// Removes `tree` from `list`, together with its comma and the whitespace
+/// separating it from its neighbours.
+pub fn remove_use_tree_item(
+    list: &ast::UseTreeList,
+    tree: &ast::UseTree,
+) -> TreeArc<ast::UseTreeList> {
+    remove_from_list(list, tree.syntax())
+}
+
+/// Adds `field` after the last field of a struct literal.
+pub fn add_named_field(
+    fields: &ast::NamedFieldList,
+    field: &ast::NamedField,
+) -> TreeArc<ast::NamedFieldList> {
+    append_to_list(fields, field.syntax())
+}
+
+/// Adds `arm` after the last arm of a `match`.
+pub fn add_match_arm(arms: &ast::MatchArmList, arm: &ast::MatchArm) -> TreeArc<ast::MatchArmList> {
+    append_to_list(arms, arm.syntax())
+}
+
+/// Wraps `expr` in a block: `{ expr }`.
+pub fn wrap_in_block(expr: &ast::Expr) -> TreeArc<ast::BlockExpr> {
+    let block: TreeArc<ast::Block> = build(
+        BLOCK,
+        vec![
+            Child::Leaf(L_CURLY, "{".into()),
+            whitespace(" "),
+            Child::Node(expr.syntax()),
+            whitespace(" "),
+            Child::Leaf(R_CURLY, "}".into()),
+        ],
+    );
+    build(BLOCK_EXPR, vec![Child::Node(block.syntax())])
+}
+
+/// A child of a node being built: either a copy of an existing node or a new
+/// token.
+enum Child<'a> {
+    Node(&'a SyntaxNode),
+    Leaf(SyntaxKind, SmolStr),
+}
+
+fn whitespace(text: &str) -> Child<'static> {
+    Child::Leaf(WHITESPACE, text.into())
+}
+
+fn comma() -> Child<'static> {
+    Child::Leaf(COMMA, ",".into())
+}
+
+/// Appends `item` to a comma separated list in curly braces.
+fn append_to_list<N: AstNode>(list: &N, item: &SyntaxNode) -> TreeArc<N> {
+    let children = list.syntax().children().collect::<Vec<_>>();
+    let mut res = Vec::new();
+    let last_item = children.iter().rposition(|it| it.kind() == item.kind());
+    match last_item {
+        Some(last_item) => {
+            let trailing_comma =
+                next_non_trivia(&children, last_item).filter(|&idx| children[idx].kind() == COMMA);
+            let separator = match last_item.checked_sub(1).map(|it| children[it]) {
+                Some(it) if it.kind() == WHITESPACE => Child::Node(it),
+                _ => whitespace(" "),
+            };
+            let insert_at = trailing_comma.unwrap_or(last_item) + 1;
+            res.extend(children[..insert_at].iter().map(|&it| Child::Node(it)));
+            if trailing_comma.is_none() {
+                res.push(comma());
+            }
+            res.push(separator);
+            res.push(Child::Node(item));
+            if trailing_comma.is_some() {
+                res.push(comma());
+            }
+            res.extend(children[insert_at..].iter().map(|&it| Child::Node(it)));
+        }
+        // `{}` becomes `{ item }`.
+        None => {
+            let mut children = children.iter().filter(|it| it.kind() != WHITESPACE);
+            res.extend(children.next().map(|&it| Child::Node(it)));
+            res.push(whitespace(" "));
+            res.push(Child::Node(item));
+            res.push(whitespace(" "));
+            res.extend(children.map(|&it| Child::Node(it)));
+        }
+    }
+    build(list.syntax().kind(), res)
+}
+
+fn remove_from_list<N: AstNode>(list: &N, item: &SyntaxNode) -> TreeArc<N> {
+    let children = list.syntax().children().collect::<Vec<_>>();
+    let idx = children.iter().position(|&it| it == item).expect("not a child of the list");
+    let is_last = !children[idx + 1..].iter().any(|it| it.kind() == item.kind());
+    let prev_item = children[..idx].iter().rposition(|it| it.kind() == item.kind());
+    let comma = next_non_trivia(&children, idx).filter(|&it| children[it].kind() == COMMA);
+
+    let (start, end) = match (comma, prev_item) {
+        // `a, <item>, b` => `a, b`
+        (Some(comma), _) if !is_last => {
+            let after_whitespace = children.get(comma + 1).map(|it| it.kind()) == Some(WHITESPACE);
+            (idx, comma + if after_whitespace { 1 } else { 0 })
+        }
+        // `a, <item>,` => `a,`
+        (Some(comma), _) => {
+            let before_whitespace = idx > 0 && children[idx - 1].kind() == WHITESPACE;
+            (idx - if before_whitespace { 1 } else { 0 }, comma)
+        }
+        // `a, <item>` => `a`
+        (None, Some(prev_item)) => (prev_item + 1, idx),
+        (None, None) => (idx, idx),
+    };
+    let res = children
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i < start || end < i)
+        .map(|(_, &it)| Child::Node(it))
+        .collect();
+    build(list.syntax().kind(), res)
+}
+
+fn next_non_trivia(children: &[&SyntaxNode], idx: usize) -> Option<usize> {
+    (idx + 1..children.len()).find(|&it| !children[it].kind().is_trivia())
+}
+
+fn build<N: AstNode>(kind: SyntaxKind, children: Vec<Child>) -> TreeArc<N> {
+    let mut builder = SyntaxTreeBuilder::default();
+    builder.start_branch(kind);
+    for child in children {
+        match child {
+            Child::Node(node) => copy(&mut builder, node, None),
+            Child::Leaf(kind, text) => builder.leaf(kind, text),
+        }
+    }
+    builder.finish_branch();
+    let root = builder.finish();
+    N::cast(&root).unwrap().to_owned()
+}
+
+/// Copies `node` into `builder`, replacing the `(old, new)` descendant.
+fn copy(
+    builder: &mut SyntaxTreeBuilder,
+    node: &SyntaxNode,
+    replace: Option<(&SyntaxNode, &SyntaxNode)>,
+) {
+    let node = match replace {
+        Some((old, new)) if node == old => new,
+        _ => node,
+    };
+    match node.leaf_text() {
+        Some(text) => builder.leaf(node.kind(), text.clone()),
+        None => {
+            builder.start_branch(node.kind());
+            for child in node.children() {
+                copy(builder, child, replace);
+            }
+            builder.finish_branch();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SourceFile, algo::find_node_at_offset};
+
+    use super::*;
+
+    fn first<N: AstNode>(file: &SourceFile) -> &N {
+        file.syntax().descendants().find_map(N::cast).unwrap()
+    }
+
+    #[test]
+    fn adds_fields_to_structs() {
+        let field_file = SourceFile::parse("struct F { baz: bool }");
+        let field = first::<ast::NamedFieldDef>(&field_file);
+        let check = |before: &str, after: &str| {
+            let file = SourceFile::parse(before);
+            let strukt = add_field_to_struct(first(&file), field).unwrap();
+            assert_eq!(strukt.syntax().text().to_string(), after);
+        };
+
+        check("struct S { foo: u32 }", "struct S { foo: u32, baz: bool }");
+        check("struct S {}", "struct S { baz: bool }");
+        check(
+            "struct S {\n    foo: u32,\n    bar: i32,\n}",
+            "struct S {\n    foo: u32,\n    bar: i32,\n    baz: bool,\n}",
+        );
+    }
+
+    #[test]
+    fn removes_use_tree_items() {
+        let check = |before: &str, name: &str, after: &str| {
+            let file = SourceFile::parse(before);
+            let offset = before.find(name).unwrap();
+            let tree =
+                find_node_at_offset::<ast::UseTree>(file.syntax(), (offset as u32).into()).unwrap();
+            let list = first::<ast::UseTreeList>(&file);
+            let list = remove_use_tree_item(list, tree);
+            assert_eq!(list.syntax().text().to_string(), after);
+        };
+
+        check("use foo::{bar, baz, qux};", "baz", "{bar, qux}");
+        check("use foo::{bar, baz};", "baz", "{bar}");
+        check("use foo::{bar};", "bar", "{}");
+        check("use foo::{\n    bar,\n    baz,\n};", "baz", "{\n    bar,\n}");
+    }
+
+    #[test]
+    fn wraps_expressions_in_blocks() {
+        let file = SourceFile::parse("fn f() { 1 + 1; }");
+        let expr = first::<ast::BinExpr>(&file);
+        let block = wrap_in_block(expr.into());
+        assert_eq!(block.syntax().text().to_string(), "{ 1 + 1 }");
+        assert!(block.block().unwrap().expr().is_some());
+    }
+}
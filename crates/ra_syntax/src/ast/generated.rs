@@ -302,6 +302,7 @@ impl ToOwned for Block {
 
 
 impl ast::AttrsOwner for Block {}
+impl ast::ModuleItemOwner for Block {}
 impl Block {
     pub fn statements(&self) -> impl Iterator<Item = &Stmt> {
         super::children(self)
@@ -374,6 +375,10 @@ impl BreakExpr {
     pub fn expr(&self) -> Option<&Expr> {
         super::child_opt(self)
     }
+
+    pub fn lifetime(&self) -> Option<&Lifetime> {
+        super::child_opt(self)
+    }
 }
 
 // Byte
@@ -629,7 +634,11 @@ impl ast::TypeParamsOwner for ConstDef {}
 impl ast::AttrsOwner for ConstDef {}
 impl ast::DocCommentsOwner for ConstDef {}
 impl ast::TypeAscriptionOwner for ConstDef {}
-impl ConstDef {}
+impl ConstDef {
+    pub fn body(&self) -> Option<&Expr> {
+        super::child_opt(self)
+    }
+}
 
 // ContinueExpr
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -657,7 +666,11 @@ impl ToOwned for ContinueExpr {
 }
 
 
-impl ContinueExpr {}
+impl ContinueExpr {
+    pub fn lifetime(&self) -> Option<&Lifetime> {
+        super::child_opt(self)
+    }
+}
 
 // DynTraitType
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -830,6 +843,7 @@ pub enum ExprKind<'a> {
     RangeExpr(&'a RangeExpr),
     BinExpr(&'a BinExpr),
     Literal(&'a Literal),
+    MacroCall(&'a MacroCall),
 }
 impl<'a> From<&'a TupleExpr> for &'a Expr {
     fn from(n: &'a TupleExpr) -> &'a Expr {
@@ -966,6 +980,11 @@ impl<'a> From<&'a Literal> for &'a Expr {
         Expr::cast(&n.syntax).unwrap()
     }
 }
+impl<'a> From<&'a MacroCall> for &'a Expr {
+    fn from(n: &'a MacroCall) -> &'a Expr {
+        Expr::cast(&n.syntax).unwrap()
+    }
+}
 
 
 impl AstNode for Expr {
@@ -997,7 +1016,8 @@ impl AstNode for Expr {
             | PREFIX_EXPR
             | RANGE_EXPR
             | BIN_EXPR
-            | LITERAL => Some(Expr::from_repr(syntax.into_repr())),
+            | LITERAL
+            | MACRO_CALL => Some(Expr::from_repr(syntax.into_repr())),
             _ => None,
         }
     }
@@ -1039,6 +1059,7 @@ impl Expr {
             RANGE_EXPR => ExprKind::RangeExpr(RangeExpr::cast(&self.syntax).unwrap()),
             BIN_EXPR => ExprKind::BinExpr(BinExpr::cast(&self.syntax).unwrap()),
             LITERAL => ExprKind::Literal(Literal::cast(&self.syntax).unwrap()),
+            MACRO_CALL => ExprKind::MacroCall(MacroCall::cast(&self.syntax).unwrap()),
             _ => unreachable!(),
         }
     }
@@ -1104,6 +1125,7 @@ impl ToOwned for ExternCrateItem {
 }
 
 
+impl ast::VisibilityOwner for ExternCrateItem {}
 impl ExternCrateItem {
     pub fn name_ref(&self) -> Option<&NameRef> {
         super::child_opt(self)
@@ -1393,6 +1415,10 @@ impl ForExpr {
     pub fn iterable(&self) -> Option<&Expr> {
         super::child_opt(self)
     }
+
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
 }
 
 // ForType
@@ -1668,6 +1694,7 @@ impl ToOwned for ItemList {
 
 impl ast::FnDefOwner for ItemList {}
 impl ast::ModuleItemOwner for ItemList {}
+impl ast::InnerDocCommentsOwner for ItemList {}
 impl ItemList {
     pub fn impl_items(&self) -> impl Iterator<Item = &ImplItem> {
         super::children(self)
@@ -1700,7 +1727,11 @@ impl ToOwned for Label {
 }
 
 
-impl Label {}
+impl Label {
+    pub fn lifetime(&self) -> Option<&Lifetime> {
+        super::child_opt(self)
+    }
+}
 
 // LambdaExpr
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -2079,7 +2110,11 @@ impl ToOwned for LoopExpr {
 
 
 impl ast::LoopBodyOwner for LoopExpr {}
-impl LoopExpr {}
+impl LoopExpr {
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
+}
 
 // MacroCall
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -3769,6 +3804,7 @@ impl ToOwned for SourceFile {
 
 impl ast::ModuleItemOwner for SourceFile {}
 impl ast::FnDefOwner for SourceFile {}
+impl ast::InnerDocCommentsOwner for SourceFile {}
 impl SourceFile {
     pub fn modules(&self) -> impl Iterator<Item = &Module> {
         super::children(self)
@@ -3807,7 +3843,11 @@ impl ast::TypeParamsOwner for StaticDef {}
 impl ast::AttrsOwner for StaticDef {}
 impl ast::DocCommentsOwner for StaticDef {}
 impl ast::TypeAscriptionOwner for StaticDef {}
-impl StaticDef {}
+impl StaticDef {
+    pub fn body(&self) -> Option<&Expr> {
+        super::child_opt(self)
+    }
+}
 
 // Stmt
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -4061,7 +4101,11 @@ impl ast::NameOwner for TraitDef {}
 impl ast::AttrsOwner for TraitDef {}
 impl ast::DocCommentsOwner for TraitDef {}
 impl ast::TypeParamsOwner for TraitDef {}
-impl TraitDef {}
+impl TraitDef {
+    pub fn item_list(&self) -> Option<&ItemList> {
+        super::child_opt(self)
+    }
+}
 
 // TrueKw
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -4601,6 +4645,7 @@ impl ToOwned for UseItem {
 
 
 impl ast::AttrsOwner for UseItem {}
+impl ast::VisibilityOwner for UseItem {}
 impl UseItem {
     pub fn use_tree(&self) -> Option<&UseTree> {
         super::child_opt(self)
@@ -4766,6 +4811,10 @@ impl WhileExpr {
     pub fn condition(&self) -> Option<&Condition> {
         super::child_opt(self)
     }
+
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
 }
 
 // Whitespace
@@ -0,0 +1,118 @@
+//! Constructors of AST nodes.
+//!
+//! Each node is parsed from a small snippet of code containing it. This is
+//! simpler than assembling the trees by hand, and the result is guaranteed to
+//! be well-formed: in debug builds, snippets with syntax errors panic.
+
+use itertools::Itertools;
+
+use crate::{
+    SourceFile, TreeArc,
+    ast::{self, AstNode},
+};
+
+pub fn name_ref(text: &str) -> TreeArc<ast::NameRef> {
+    ast_from_text(&format!("fn f() {{ {}; }}", text))
+}
+
+pub fn path_from_text(text: &str) -> TreeArc<ast::Path> {
+    ast_from_text(&format!("type __ = {};", text))
+}
+
+/// `qualifier::segment`
+pub fn path_qualified(qualifier: &ast::Path, segment: &str) -> TreeArc<ast::Path> {
+    path_from_text(&format!("{}::{}", qualifier.syntax().text(), segment))
+}
+
+/// `path` or `path as alias`
+pub fn use_tree(path: &ast::Path, alias: Option<&str>) -> TreeArc<ast::UseTree> {
+    let text = match alias {
+        Some(alias) => format!("use {} as {};", path.syntax().text(), alias),
+        None => format!("use {};", path.syntax().text()),
+    };
+    ast_from_text(&text)
+}
+
+pub fn use_item(use_tree: &ast::UseTree) -> TreeArc<ast::UseItem> {
+    ast_from_text(&format!("use {};", use_tree.syntax().text()))
+}
+
+pub fn pat_from_text(text: &str) -> TreeArc<ast::Pat> {
+    ast_from_text(&format!("fn f() {{ let {}; }}", text))
+}
+
+pub fn placeholder_pat() -> TreeArc<ast::Pat> {
+    ast_from_text("fn f() { let _; }")
+}
+
+/// `()`
+pub fn expr_unit() -> TreeArc<ast::Expr> {
+    ast_from_text("fn f() { (); }")
+}
+
+/// `name: expr`, a field of a struct literal.
+pub fn named_field(name: &str, expr: &ast::Expr) -> TreeArc<ast::NamedField> {
+    ast_from_text(&format!("fn f() {{ S {{ {}: {} }}; }}", name, expr.syntax().text()))
+}
+
+/// `pat1 | pat2 => expr`
+pub fn match_arm<'a>(
+    pats: impl IntoIterator<Item = &'a ast::Pat>,
+    expr: &ast::Expr,
+) -> TreeArc<ast::MatchArm> {
+    let pats = pats.into_iter().map(|it| it.syntax().text().to_string()).join(" | ");
+    ast_from_text(&format!("fn f() {{ match () {{ {} => {} }} }}", pats, expr.syntax().text()))
+}
+
+/// Puts each of the arms on a separate line, indented by four spaces.
+pub fn match_arm_list<'a>(
+    arms: impl IntoIterator<Item = &'a ast::MatchArm>,
+) -> TreeArc<ast::MatchArmList> {
+    let arms = arms.into_iter().map(|it| format!("    {},\n", it.syntax().text())).join("");
+    ast_from_text(&format!("fn f() {{ match () {{\n{}}} }}", arms))
+}
+
+/// A function with an `unimplemented!()` body. `params` is the text between
+/// the parentheses.
+pub fn fn_stub(name: &str, params: &str, ret_type: Option<&str>) -> TreeArc<ast::FnDef> {
+    let ret_type = ret_type.map(|it| format!(" -> {}", it)).unwrap_or_default();
+    ast_from_text(&format!("fn {}({}){} {{\n    unimplemented!()\n}}", name, params, ret_type))
+}
+
+fn ast_from_text<N: AstNode>(text: &str) -> TreeArc<N> {
+    let file = SourceFile::parse(text);
+    debug_assert!(file.errors().is_empty(), "syntax errors in {:?}: {:?}", text, file.errors());
+    let node = file
+        .syntax()
+        .descendants()
+        .find_map(N::cast)
+        .unwrap_or_else(|| panic!("no node of the requested kind in {:?}", text));
+    node.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_nodes_from_snippets() {
+        let path = path_qualified(&path_from_text("std::collections"), "HashMap");
+        assert_eq!(path.syntax().text().to_string(), "std::collections::HashMap");
+        assert_eq!(
+            use_item(&use_tree(&path, Some("Map"))).syntax().text().to_string(),
+            "use std::collections::HashMap as Map;"
+        );
+
+        let pat = placeholder_pat();
+        let expr = ast_from_text::<ast::Expr>("fn f() { 92; }");
+        let arm = match_arm(vec![&*pat], &expr);
+        assert_eq!(arm.syntax().text().to_string(), "_ => 92");
+        assert_eq!(match_arm_list(vec![&*arm]).syntax().text().to_string(), "{\n    _ => 92,\n}");
+
+        let stub = fn_stub("foo", "x: u32", Some("bool"));
+        assert_eq!(
+            stub.syntax().text().to_string(),
+            "fn foo(x: u32) -> bool {\n    unimplemented!()\n}"
+        );
+    }
+}
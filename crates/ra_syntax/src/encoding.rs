@@ -0,0 +1,189 @@
+//! Building blocks of the binary encodings used to persist data across IDE
+//! sessions: a stable hash, and a writer and a reader of LEB128 numbers.
+
+use std::hash::Hasher;
+
+use crate::{SyntaxKind, TextRange, TextUnit};
+
+/// FNV-1a. Unlike `DefaultHasher` or `FxHasher`, it is guaranteed to be
+/// stable across compiler versions and platforms.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StableHasher {
+    /// Hashes the length of `s` before the text, so that `"ab", "c"` and
+    /// `"a", "bc"` hash differently.
+    pub fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The stable hash of `bytes`.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = StableHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// A hash of the names of all the `SyntaxKind`s, in the order of their
+/// values. Encodings which store kinds as numbers record it, so that data
+/// written with a different set of kinds is not misread.
+pub fn syntax_kinds_fingerprint() -> u64 {
+    let mut hasher = StableHasher::default();
+    let kinds = (0..).map(SyntaxKind::from_u16).take_while(Option::is_some).flatten();
+    for kind in kinds {
+        // The `Debug` representation is the name of the kind.
+        hasher.write(format!("{:?};", kind).as_bytes());
+    }
+    hasher.finish()
+}
+
+/// Writes `value` in LEB128, as most numbers in the encodings are small.
+pub fn write_u32(buf: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+pub fn write_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_str(buf: &mut Vec<u8>, s: &str) {
+    write_u32(buf, s.len() as u32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+pub fn write_range(buf: &mut Vec<u8>, range: TextRange) {
+    write_u32(buf, range.start().to_usize() as u32);
+    write_u32(buf, range.end().to_usize() as u32);
+}
+
+/// Reads the values written by the `write_*` functions. Every method returns
+/// `None` if the data is malformed.
+#[derive(Debug)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes }
+    }
+
+    /// Whether all the data has been read.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < n {
+            return None;
+        }
+        let (res, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(res)
+    }
+
+    pub fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|it| it[0])
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        let mut res: u32 = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.byte()?;
+            res |= u32::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(res);
+            }
+        }
+        None
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    pub fn str(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).ok()
+    }
+
+    pub fn kind(&mut self) -> Option<SyntaxKind> {
+        let raw = self.u32()?;
+        if raw > u32::from(u16::max_value()) {
+            return None;
+        }
+        SyntaxKind::from_u16(raw as u16)
+    }
+
+    pub fn text_unit(&mut self) -> Option<TextUnit> {
+        self.u32().map(TextUnit::from)
+    }
+
+    pub fn range(&mut self) -> Option<TextRange> {
+        let start = self.text_unit()?;
+        let end = self.text_unit()?;
+        if start > end {
+            return None;
+        }
+        Some(TextRange::from_to(start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_roundtrip_through_leb128() {
+        let values = [0, 1, 0x7f, 0x80, 0x3fff, 0x4000, u32::max_value()];
+        let mut buf = Vec::new();
+        for &value in values.iter() {
+            write_u32(&mut buf, value);
+        }
+        let mut reader = Reader::new(&buf);
+        for &value in values.iter() {
+            assert_eq!(reader.u32(), Some(value));
+        }
+        assert!(reader.is_empty());
+        assert_eq!(Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]).u32(), None);
+    }
+}
@@ -21,14 +21,17 @@
 
 mod syntax_node;
 mod syntax_text;
+mod syntax_dump;
 mod syntax_error;
 mod parsing;
 mod string_lexing;
 mod validation;
 mod ptr;
+mod persistent;
 
 pub mod algo;
 pub mod ast;
+pub mod encoding;
 #[doc(hidden)]
 pub mod fuzz;
 
@@ -38,12 +41,13 @@ pub use crate::{
     ast::AstNode,
     syntax_error::{SyntaxError, SyntaxErrorKind, Location},
     syntax_text::SyntaxText,
+    syntax_dump::{SyntaxTreeDump, RangeStyle},
     syntax_node::{Direction,  SyntaxNode, WalkEvent, TreeArc, SyntaxTreeBuilder},
     ptr::{SyntaxNodePtr, AstPtr},
-    parsing::{tokenize, Token},
+    parsing::{tokenize, relex, Token, OffsetMap},
 };
 
-use ra_text_edit::AtomTextEdit;
+use ra_text_edit::{AtomTextEdit, TextEdit};
 use crate::syntax_node::GreenNode;
 
 /// `SourceFile` represents a parse tree for a single Rust file.
@@ -73,16 +77,66 @@ impl SourceFile {
             .map(|(green_node, errors)| SourceFile::new(green_node, errors))
     }
 
+    /// Like `incremental_reparse`, but also returns an `OffsetMap` for the
+    /// applied edit.
+    pub fn incremental_reparse_with_offset_map(
+        &self,
+        edit: &AtomTextEdit,
+    ) -> Option<(TreeArc<SourceFile>, OffsetMap)> {
+        let file = self.incremental_reparse(edit)?;
+        Some((file, OffsetMap::new(edit)))
+    }
+
+    /// Applies a batch of edits, reparsing only the touched parts of the file
+    /// if possible.
+    pub fn reparse_edits(&self, edit: &TextEdit) -> TreeArc<SourceFile> {
+        self.incremental_reparse_edits(edit).unwrap_or_else(|| {
+            let text = edit.apply(&self.syntax().text().to_string());
+            SourceFile::parse(&text)
+        })
+    }
+
+    pub fn incremental_reparse_edits(&self, edit: &TextEdit) -> Option<TreeArc<SourceFile>> {
+        parsing::incremental_reparse_edits(self.syntax(), edit, self.errors())
+            .map(|(green_node, errors)| SourceFile::new(green_node, errors))
+    }
+
     fn full_reparse(&self, edit: &AtomTextEdit) -> TreeArc<SourceFile> {
         let text = edit.apply(self.syntax().text().to_string());
         SourceFile::parse(&text)
     }
 
+    /// Encodes the tree and the parser errors in a compact binary format, to
+    /// be loaded back with `from_bytes` in a later session.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        persistent::encode(self)
+    }
+
+    /// Loads a tree saved with `to_bytes`, if it was saved for a file with the
+    /// contents `text`.
+    pub fn from_bytes(bytes: &[u8], text: &str) -> Option<TreeArc<SourceFile>> {
+        persistent::decode(bytes, text)
+    }
+
     pub fn errors(&self) -> Vec<SyntaxError> {
         let mut errors = self.syntax.root_data().clone();
         errors.extend(validation::validate(self));
         errors
     }
+
+    /// Runs only the validation pass (parser errors are not included) and
+    /// groups the errors by kind, in the order of their first occurrence.
+    pub fn validation_errors(&self) -> Vec<(SyntaxErrorKind, Vec<SyntaxError>)> {
+        let mut res: Vec<(SyntaxErrorKind, Vec<SyntaxError>)> = Vec::new();
+        for error in validation::validate(self) {
+            let kind = error.kind();
+            match res.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, errors)) => errors.push(error),
+                None => res.push((kind, vec![error])),
+            }
+        }
+        res
+    }
 }
 
 /// This test does not assert anything and instead just shows off the crate's
@@ -11,9 +11,10 @@ use crate::{
     syntax_node::GreenNode,
 };
 
-pub use self::lexer::{tokenize, Token};
+pub use self::lexer::{tokenize, relex, Token};
 
-pub(crate) use self::reparsing::incremental_reparse;
+pub(crate) use self::reparsing::{incremental_reparse, incremental_reparse_edits};
+pub use self::reparsing::OffsetMap;
 
 pub(crate) fn parse_text(text: &str) -> (GreenNode, Vec<SyntaxError>) {
     let tokens = tokenize(&text);
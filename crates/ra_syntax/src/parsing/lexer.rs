@@ -4,6 +4,8 @@ mod numbers;
 mod ptr;
 mod strings;
 
+use ra_text_edit::AtomTextEdit;
+
 use crate::{
     SyntaxKind::{self, *},
     TextUnit,
@@ -19,6 +21,8 @@ use self::{
     },
 };
 
+const BOM: char = '\u{feff}';
+
 /// A token of Rust source.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Token {
@@ -32,6 +36,12 @@ pub struct Token {
 pub fn tokenize(text: &str) -> Vec<Token> {
     let mut text = text;
     let mut acc = Vec::new();
+    // A byte order mark is only meaningful at the very start of the file.
+    if text.starts_with(BOM) {
+        let len = TextUnit::of_char(BOM);
+        acc.push(Token { kind: BYTE_ORDER_MARK, len });
+        text = &text[len.to_usize()..];
+    }
     while !text.is_empty() {
         let token = next_token(text);
         acc.push(token);
@@ -41,6 +51,65 @@ pub fn tokenize(text: &str) -> Vec<Token> {
     acc
 }
 
+/// The lexer looks this many bytes past the end of a token at most, when
+/// deciding where the token ends.
+const LOOKAHEAD_WINDOW: u32 = 16;
+
+/// Re-lexes `text`, the text after `edit`, reusing the `old_tokens` of the
+/// text before the edit where possible.
+///
+/// The lexer has no state between tokens, so once re-lexing arrives at the
+/// start of an old token after the edit, all the following old tokens are
+/// still valid. Tokens just before the edit are re-lexed too, because their
+/// end may depend on the edited text. Only the old tokens around the edit are
+/// inspected; the rest are moved over as they are.
+pub fn relex(
+    old_tokens: impl IntoIterator<Item = Token>,
+    text: &str,
+    edit: &AtomTextEdit,
+) -> Vec<Token> {
+    let window = TextUnit::from(LOOKAHEAD_WINDOW);
+    let mut old_tokens = old_tokens.into_iter().peekable();
+    let mut res = Vec::new();
+    let mut relex_start = TextUnit::from(0);
+    while let Some(&token) = old_tokens.peek() {
+        if relex_start + token.len + window > edit.delete.start() {
+            break;
+        }
+        res.push(token);
+        relex_start += token.len;
+        old_tokens.next();
+    }
+    // `tokenize` handles the byte order mark, which may only come first.
+    if res.is_empty() {
+        return tokenize(text);
+    }
+
+    let insert_end = edit.delete.start() + TextUnit::of_str(&edit.insert);
+    let mut old_offset = relex_start;
+    let mut offset = relex_start;
+    while offset.to_usize() < text.len() {
+        let token = next_token(&text[offset.to_usize()..]);
+        res.push(token);
+        offset += token.len;
+        if offset < insert_end {
+            continue;
+        }
+        let offset_before_edit = offset - insert_end + edit.delete.end();
+        while old_offset < offset_before_edit {
+            match old_tokens.next() {
+                Some(old_token) => old_offset += old_token.len,
+                None => break,
+            }
+        }
+        if old_offset == offset_before_edit {
+            res.extend(old_tokens);
+            break;
+        }
+    }
+    res
+}
+
 /// Get the next token from a string
 pub fn next_token(text: &str) -> Token {
     assert!(!text.is_empty());
@@ -5,18 +5,24 @@
 //!     letter), we replace only this token.
 //!   - otherwise, we search for the nearest `{}` block which contains the edit
 //!     and try to parse only this block.
+//!
+//! A batch of edits is applied token by token if every edit touches only a
+//! single token, and otherwise as a single edit spanning all of them.
+
+use std::iter;
 
-use ra_text_edit::AtomTextEdit;
-use ra_parser::Reparser;
+use itertools::Either;
+use ra_text_edit::{AtomTextEdit, TextEdit};
+use ra_parser::{Reparser, ParseError};
 
 use crate::{
-    SyntaxKind::*, TextRange, TextUnit, SyntaxError,
+    SyntaxKind::*, TextRange, TextUnit, SyntaxError, SyntaxErrorKind,
     algo,
     syntax_node::{GreenNode, SyntaxNode},
     parsing::{
         text_token_source::TextTokenSource,
         text_tree_sink::TextTreeSink,
-        lexer::{tokenize, Token},
+        lexer::{tokenize, relex, Token},
     }
 };
 
@@ -25,6 +31,7 @@ pub(crate) fn incremental_reparse(
     edit: &AtomTextEdit,
     errors: Vec<SyntaxError>,
 ) -> Option<(GreenNode, Vec<SyntaxError>)> {
+    let _p = ra_prof::profile("incremental_reparse");
     let (node, green, new_errors) =
         reparse_leaf(node, &edit).or_else(|| reparse_block(node, &edit))?;
     let green_root = node.replace_with(green);
@@ -32,6 +39,99 @@ pub(crate) fn incremental_reparse(
     Some((green_root, errors))
 }
 
+/// Like `incremental_reparse`, but for several edits at once, as produced by
+/// typing with multiple cursors.
+pub(crate) fn incremental_reparse_edits(
+    node: &SyntaxNode,
+    edit: &TextEdit,
+    errors: Vec<SyntaxError>,
+) -> Option<(GreenNode, Vec<SyntaxError>)> {
+    let atoms = edit.as_atoms();
+    match atoms {
+        [] => return None,
+        [atom] => return incremental_reparse(node, atom, errors),
+        _ => (),
+    }
+    let _p = ra_prof::profile("incremental_reparse_edits");
+    reparse_leaves(node, atoms, errors.clone()).or_else(|| {
+        let edit = combine_edits(node, atoms);
+        incremental_reparse(node, &edit, errors)
+    })
+}
+
+/// Applies the edits to single tokens, starting from the last one, so that the
+/// offsets of the remaining edits stay valid.
+fn reparse_leaves(
+    root: &SyntaxNode,
+    atoms: &[AtomTextEdit],
+    mut errors: Vec<SyntaxError>,
+) -> Option<(GreenNode, Vec<SyntaxError>)> {
+    let mut root = root.to_owned();
+    let mut green_root = None;
+    for atom in atoms.iter().rev() {
+        let (node, green, new_errors) = reparse_leaf(&root, atom)?;
+        let green = node.replace_with(green);
+        errors = merge_errors(errors, new_errors, node, atom);
+        root = SyntaxNode::new(green.clone(), errors.clone());
+        green_root = Some(green);
+    }
+    Some((green_root?, errors))
+}
+
+/// Turns sorted, non-overlapping edits into a single edit, which replaces the
+/// whole range from the start of the first edit to the end of the last one.
+fn combine_edits(root: &SyntaxNode, atoms: &[AtomTextEdit]) -> AtomTextEdit {
+    let delete = TextRange::from_to(atoms[0].delete.start(), atoms[atoms.len() - 1].delete.end());
+    let mut insert = String::new();
+    let mut prev = delete.start();
+    for atom in atoms {
+        root.text().slice(TextRange::from_to(prev, atom.delete.start())).push_to(&mut insert);
+        insert.push_str(&atom.insert);
+        prev = atom.delete.end();
+    }
+    AtomTextEdit::replace(delete, insert)
+}
+
+/// Maps offsets in the text before an edit to the corresponding offsets in the
+/// text after it. Stateful consumers (like highlighting caches) can use this
+/// to shift ranges which were not touched by the edit instead of recomputing
+/// them.
+#[derive(Debug, Clone)]
+pub struct OffsetMap {
+    delete: TextRange,
+    insert_len: TextUnit,
+}
+
+impl OffsetMap {
+    pub fn new(edit: &AtomTextEdit) -> OffsetMap {
+        OffsetMap { delete: edit.delete, insert_len: TextUnit::of_str(&edit.insert) }
+    }
+
+    /// Returns `None` if the offset was strictly inside the deleted range.
+    pub fn map_offset(&self, offset: TextUnit) -> Option<TextUnit> {
+        if offset <= self.delete.start() {
+            Some(offset)
+        } else if offset >= self.delete.end() {
+            Some(offset - self.delete.len() + self.insert_len)
+        } else {
+            None
+        }
+    }
+
+    /// Returns `None` if the range overlaps with the edit. Ranges which just
+    /// touch the edit are preserved.
+    pub fn map_range(&self, range: TextRange) -> Option<TextRange> {
+        if range.end() <= self.delete.start() {
+            Some(range)
+        } else if range.start() >= self.delete.end() {
+            let start = range.start() - self.delete.len() + self.insert_len;
+            Some(TextRange::offset_len(start, range.len()))
+        } else {
+            None
+        }
+    }
+}
+
 fn reparse_leaf<'node>(
     root: &'node SyntaxNode,
     edit: &AtomTextEdit,
@@ -39,7 +139,7 @@ fn reparse_leaf<'node>(
     let node = algo::find_covering_node(root, edit.delete);
     match node.kind() {
         WHITESPACE | COMMENT | IDENT | STRING | RAW_STRING => {
-            if node.kind() == WHITESPACE || node.kind() == COMMENT {
+            if node.kind().is_trivia() {
                 // removing a new line may extends previous token
                 if node.text().to_string()[edit.delete - node.range().start()].contains('\n') {
                     return None;
@@ -47,6 +147,9 @@ fn reparse_leaf<'node>(
             }
 
             let text = get_text_after_edit(node, &edit);
+            if node.kind() == WHITESPACE && changes_indentation(root, node, &text) {
+                return None;
+            }
             let tokens = tokenize(&text);
             let token = match tokens[..] {
                 [token] if token.kind == node.kind() => token,
@@ -58,7 +161,13 @@ fn reparse_leaf<'node>(
             }
 
             if let Some(next_char) = root.text().char_at(node.range().end()) {
-                let tokens_with_next_char = tokenize(&format!("{}{}", text, next_char));
+                let mut text_with_next_char = format!("{}{}", text, next_char);
+                // A `\r` terminates a comment only as a part of `\r\n`.
+                if next_char == '\r' {
+                    let after_cr = node.range().end() + TextUnit::of_char('\r');
+                    text_with_next_char.extend(root.text().char_at(after_cr));
+                }
+                let tokens_with_next_char = tokenize(&text_with_next_char);
                 if tokens_with_next_char.len() == 1 {
                     return None;
                 }
@@ -72,13 +181,31 @@ fn reparse_leaf<'node>(
     }
 }
 
+/// The parser uses the indentation of lines to recover from unclosed `{`, so
+/// if there are any, the whitespace at the start of lines is significant.
+fn changes_indentation(root: &SyntaxNode, node: &SyntaxNode, new_text: &str) -> bool {
+    let at_line_start =
+        node.range().start() == 0.into() || node.text().contains('\n') || new_text.contains('\n');
+    at_line_start && has_unclosed_curly(root)
+}
+
+/// The parser reports each `{` without a matching `}` as a missing `R_CURLY`,
+/// so looking at the errors of the file is enough.
+fn has_unclosed_curly(root: &SyntaxNode) -> bool {
+    root.root_data().iter().any(|error| match error.kind() {
+        SyntaxErrorKind::ParseError(ParseError(message)) => message == "expected R_CURLY",
+        _ => false,
+    })
+}
+
 fn reparse_block<'node>(
     node: &'node SyntaxNode,
     edit: &AtomTextEdit,
 ) -> Option<(&'node SyntaxNode, GreenNode, Vec<SyntaxError>)> {
     let (node, reparser) = find_reparsable_node(node, edit.delete)?;
-    let text = get_text_after_edit(node, &edit);
-    let tokens = tokenize(&text);
+    let edit = AtomTextEdit::replace(edit.delete - node.range().start(), edit.insert.clone());
+    let text = edit.apply(node.text().to_string());
+    let tokens = relex(tokens_of(node), &text, &edit);
     if !is_balanced(&tokens) {
         return None;
     }
@@ -94,6 +221,19 @@ fn get_text_after_edit(node: &SyntaxNode, edit: &AtomTextEdit) -> String {
     edit.apply(node.text().to_string())
 }
 
+/// Recovers the tokens of `node` from its leaves, lazily, so that `relex`
+/// only looks at the leaves around the edit. The parser glues some tokens
+/// together and remaps contextual keywords, so such leaves are lexed again.
+fn tokens_of(node: &SyntaxNode) -> impl Iterator<Item = Token> + '_ {
+    node.descendants().filter_map(|leaf| Some((leaf.kind(), leaf.leaf_text()?))).flat_map(
+        |(kind, text)| match kind {
+            kind if kind.is_punct() => Either::Left(tokenize(text).into_iter()),
+            AUTO_KW | DEFAULT_KW | UNION_KW => Either::Left(tokenize(text).into_iter()),
+            kind => Either::Right(iter::once(Token { kind, len: TextUnit::of_str(text) })),
+        },
+    )
+}
+
 fn is_contextual_kw(text: &str) -> bool {
     match text {
         "auto" | "default" | "union" => true,
@@ -288,6 +428,47 @@ extern {
         );
     }
 
+    fn do_check_edits(before: &str, replace_with: &[&str]) {
+        let (ranges, before) = test_utils::extract_ranges(before, "edit");
+        let mut builder = ra_text_edit::TextEditBuilder::default();
+        for (range, text) in ranges.into_iter().zip(replace_with.iter()) {
+            builder.replace(range, text.to_string());
+        }
+        let edit = builder.finish();
+        let after = edit.apply(&before);
+
+        let fully_reparsed = SourceFile::parse(&after);
+        let f = SourceFile::parse(&before);
+        let incrementally_reparsed =
+            f.incremental_reparse_edits(&edit).expect("cannot incrementally reparse");
+        assert_eq_text!(
+            &fully_reparsed.syntax().debug_dump(),
+            &incrementally_reparsed.syntax().debug_dump(),
+        )
+    }
+
+    #[test]
+    fn reparse_edits_tests() {
+        // Every edit touches a single token.
+        do_check_edits(
+            r"
+fn <edit>foo</edit>() {
+    let <edit>x</edit> = 92;
+}
+",
+            &["bar", "y"],
+        );
+        // The edits are reparsed together, as a block.
+        do_check_edits(
+            r"
+fn foo() {
+    let x = <edit>1</edit> + <edit>2</edit>;
+}
+",
+            &["(1", "2) * 3"],
+        );
+    }
+
     #[test]
     fn reparse_leaf_tests() {
         let do_check = |before, replace_to| do_check(before, replace_to, reparse_leaf);
@@ -368,5 +549,69 @@ enum Foo {
 ",
             "Clone",
         );
+        do_check("/// foo <|><|>omment\r\nmod { }\r\n", "c");
+        do_check("fn foo() {\r\n <|><|>\r\n}\r\n", "    ");
+    }
+
+    #[test]
+    fn leaf_reparse_keeps_indentation_of_unclosed_blocks() {
+        let (range, text) = extract_range("fn foo() {\n<|>    <|>fn bar() {}\n");
+        let file = SourceFile::parse(&text);
+        let edit = AtomTextEdit::delete(range);
+        assert!(reparse_leaf(file.syntax(), &edit).is_none());
+    }
+
+    #[test]
+    fn offset_map_shifts_untouched_ranges() {
+        let edit = AtomTextEdit::replace(TextRange::from_to(4.into(), 6.into()), "abcd".into());
+        let map = OffsetMap::new(&edit);
+        let range = |start: u32, end: u32| TextRange::from_to(start.into(), end.into());
+
+        assert_eq!(map.map_offset(2.into()), Some(2.into()));
+        assert_eq!(map.map_offset(4.into()), Some(4.into()));
+        assert_eq!(map.map_offset(5.into()), None);
+        assert_eq!(map.map_offset(6.into()), Some(8.into()));
+        assert_eq!(map.map_offset(10.into()), Some(12.into()));
+
+        assert_eq!(map.map_range(range(0, 4)), Some(range(0, 4)));
+        assert_eq!(map.map_range(range(6, 9)), Some(range(8, 11)));
+        assert_eq!(map.map_range(range(3, 5)), None);
+        assert_eq!(map.map_range(range(2, 10)), None);
+    }
+
+    #[test]
+    fn offset_map_matches_incremental_reparse() {
+        let before = "fn foo() { 1 + 1 }
+fn bar() {}
+";
+        let edit = AtomTextEdit::replace(TextRange::from_to(11.into(), 16.into()), "92".into());
+        let file = SourceFile::parse(before);
+        let (new_file, map) = file.incremental_reparse_with_offset_map(&edit).unwrap();
+
+        let old_bar = file.syntax().children().nth(2).unwrap().range();
+        let new_bar = new_file.syntax().children().nth(2).unwrap().range();
+        assert_eq!(map.map_range(old_bar), Some(new_bar));
+    }
+
+    #[test]
+    fn relex_matches_tokenize() {
+        fn check(before: &str, replace_with: &str) {
+            let (range, before) = extract_range(before);
+            let edit = AtomTextEdit::replace(range, replace_with.to_owned());
+            let after = edit.apply(before.clone());
+            assert_eq!(relex(tokenize(&before), &after, &edit), tokenize(&after));
+        }
+
+        let text = "fn foo() {\n    let x = 92;\n    let y = \"hello\";\n}\nfn bar() {}\n";
+        let text_with_edit = |start: usize, end: usize| {
+            format!("{}<|>{}<|>{}", &text[..start], &text[start..end], &text[end..])
+        };
+        check(&text_with_edit(30, 30), "r#\"");
+        check(&text_with_edit(30, 30), "/*");
+        check(&text_with_edit(24, 26), "");
+        check(&text_with_edit(24, 26), "1.");
+        check(&text_with_edit(40, 47), "\"");
+        check(&text_with_edit(0, 0), "#!/bin/sh\n");
+        check(&text_with_edit(60, 60), "// comment");
     }
 }
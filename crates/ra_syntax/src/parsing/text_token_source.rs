@@ -1,7 +1,9 @@
 use ra_parser::TokenSource;
 
 use crate::{
-    SyntaxKind, SyntaxKind::EOF, TextRange, TextUnit,
+    SyntaxKind,
+    SyntaxKind::{EOF, WHITESPACE, L_CURLY, R_CURLY},
+    TextRange, TextUnit,
     parsing::lexer::Token,
 };
 
@@ -23,6 +25,12 @@ pub(crate) struct TextTokenSource<'t> {
     /// ```
     /// tokens: `[struct, Foo, {, }]`
     tokens: Vec<Token>,
+    /// For each token which is the first one on its line, the indentation of
+    /// the line.
+    line_start_indents: Vec<Option<usize>>,
+    /// Sorted positions of the `{` tokens without a matching `}`, together
+    /// with the indentation of their lines.
+    unclosed_curlies: Vec<(usize, Option<usize>)>,
 }
 
 impl<'t> TokenSource for TextTokenSource<'t> {
@@ -46,6 +54,13 @@ impl<'t> TokenSource for TextTokenSource<'t> {
 
         self.text[range] == *kw
     }
+    fn line_start_indent(&self, pos: usize) -> Option<usize> {
+        self.line_start_indents.get(pos).cloned().unwrap_or(None)
+    }
+    fn unclosed_curly_indent(&self, pos: usize) -> Option<usize> {
+        let idx = self.unclosed_curlies.binary_search_by_key(&pos, |&(pos, _)| pos).ok()?;
+        self.unclosed_curlies[idx].1
+    }
 }
 
 impl<'t> TextTokenSource<'t> {
@@ -53,15 +68,50 @@ impl<'t> TextTokenSource<'t> {
     pub fn new(text: &'t str, raw_tokens: &'t [Token]) -> TextTokenSource<'t> {
         let mut tokens = Vec::new();
         let mut start_offsets = Vec::new();
+        let mut line_start_indents = Vec::new();
+        let mut unclosed_curlies = Vec::new();
+        // The indentation so far, if the current line has only whitespace.
+        let mut indent = Some(0);
+        // The indentation of the last line which starts with a token.
+        let mut line_indent = None;
         let mut len = 0.into();
         for &token in raw_tokens.iter() {
-            if !token.kind.is_trivia() {
-                tokens.push(token);
-                start_offsets.push(len);
+            let range = TextRange::offset_len(len, token.len);
+            match token.kind {
+                WHITESPACE => {
+                    let ws = &text[range];
+                    let (ws, is_new_line) = match ws.rfind('\n') {
+                        Some(idx) => (&ws[idx + 1..], true),
+                        None => (ws, false),
+                    };
+                    let is_blank = ws.chars().all(|c| c == ' ' || c == '\t');
+                    indent = match (is_new_line, indent) {
+                        _ if !is_blank => None,
+                        (true, _) => Some(ws.len()),
+                        (false, indent) => indent.map(|it| it + ws.len()),
+                    };
+                }
+                kind if kind.is_trivia() => indent = None,
+                kind => {
+                    if indent.is_some() {
+                        line_indent = indent;
+                    }
+                    match kind {
+                        L_CURLY => unclosed_curlies.push((tokens.len(), line_indent)),
+                        R_CURLY => {
+                            unclosed_curlies.pop();
+                        }
+                        _ => (),
+                    }
+                    tokens.push(token);
+                    start_offsets.push(len);
+                    line_start_indents.push(indent);
+                    indent = None;
+                }
             }
             len += token.len;
         }
 
-        TextTokenSource { text, start_offsets, tokens }
+        TextTokenSource { text, start_offsets, tokens, line_start_indents, unclosed_curlies }
     }
 }
@@ -0,0 +1,191 @@
+//! A compact binary encoding of parse trees, used to persist them across IDE
+//! sessions.
+//!
+//! The encoding stores the shape of the tree, node kinds and token lengths,
+//! but not the text itself: the tokens are sliced from the file contents when
+//! the tree is loaded back. A hash of the contents is stored alongside the
+//! tree, so that a stale tree is never loaded for a modified file.
+//!
+//! Only the parser errors are stored, the validation ones are recomputed on
+//! demand anyway.
+
+use ra_parser::ParseError;
+
+use crate::{
+    SourceFile, SyntaxKind, SyntaxError, SyntaxErrorKind, Location, SyntaxTreeBuilder, TreeArc,
+    WalkEvent, AstNode,
+    encoding::{
+        Reader, stable_hash, syntax_kinds_fingerprint, write_range, write_str, write_u32, write_u64,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"RAST";
+/// Bump this when changing the encoding. Changes to the `SyntaxKind`s are
+/// detected by `syntax_kinds_fingerprint`.
+const FORMAT_VERSION: u32 = 2;
+
+const START_NODE: u8 = 0;
+const LEAF: u8 = 1;
+const FINISH_NODE: u8 = 2;
+const END: u8 = 3;
+
+const OFFSET: u8 = 0;
+const RANGE: u8 = 1;
+
+pub(crate) fn encode(file: &SourceFile) -> Vec<u8> {
+    let text = file.syntax().text().to_string();
+    let mut buf = Vec::new();
+    buf.extend_from_slice(MAGIC);
+    write_u32(&mut buf, FORMAT_VERSION);
+    write_u64(&mut buf, syntax_kinds_fingerprint());
+    write_u64(&mut buf, stable_hash(text.as_bytes()));
+
+    for event in file.syntax().preorder() {
+        match event {
+            WalkEvent::Enter(node) if node.is_leaf() => {
+                buf.push(LEAF);
+                write_u32(&mut buf, node.kind() as u32);
+                write_u32(&mut buf, node.range().len().to_usize() as u32);
+            }
+            WalkEvent::Enter(node) => {
+                buf.push(START_NODE);
+                write_u32(&mut buf, node.kind() as u32);
+            }
+            WalkEvent::Leave(node) if node.is_leaf() => (),
+            WalkEvent::Leave(_) => buf.push(FINISH_NODE),
+        }
+    }
+    buf.push(END);
+
+    let errors: Vec<(ParseError, Location)> = file
+        .syntax()
+        .root_data()
+        .iter()
+        .filter_map(|error| match error.kind() {
+            SyntaxErrorKind::ParseError(it) => Some((it, error.location())),
+            _ => None,
+        })
+        .collect();
+    write_u32(&mut buf, errors.len() as u32);
+    for (error, location) in errors {
+        match location {
+            Location::Offset(offset) => {
+                buf.push(OFFSET);
+                write_u32(&mut buf, offset.to_usize() as u32);
+            }
+            Location::Range(range) => {
+                buf.push(RANGE);
+                write_range(&mut buf, range);
+            }
+        }
+        write_str(&mut buf, &error.0);
+    }
+    buf
+}
+
+/// Loads a tree encoded with `encode`. Returns `None` if the data is
+/// malformed, was written with a different encoding or set of `SyntaxKind`s,
+/// or describes a file with contents other than `text`.
+pub(crate) fn decode(bytes: &[u8], text: &str) -> Option<TreeArc<SourceFile>> {
+    let mut reader = Reader::new(bytes);
+    if reader.take(MAGIC.len())? != MAGIC || reader.u32()? != FORMAT_VERSION {
+        return None;
+    }
+    if reader.u64()? != syntax_kinds_fingerprint() || reader.u64()? != stable_hash(text.as_bytes())
+    {
+        return None;
+    }
+
+    let mut builder = SyntaxTreeBuilder::default();
+    let mut offset = 0;
+    let mut depth = 0;
+    let mut has_root = false;
+    loop {
+        match reader.byte()? {
+            START_NODE => {
+                let kind = reader.kind()?;
+                if depth == 0 {
+                    // There must be a single `SOURCE_FILE` root.
+                    if has_root || kind != SyntaxKind::SOURCE_FILE {
+                        return None;
+                    }
+                    has_root = true;
+                }
+                builder.start_branch(kind);
+                depth += 1;
+            }
+            LEAF => {
+                let kind = reader.kind()?;
+                let len = reader.u32()? as usize;
+                if depth == 0 {
+                    return None;
+                }
+                let leaf_text = text.get(offset..offset + len)?;
+                builder.leaf(kind, leaf_text.into());
+                offset += len;
+            }
+            FINISH_NODE => {
+                if depth == 0 {
+                    return None;
+                }
+                builder.finish_branch();
+                depth -= 1;
+            }
+            END => break,
+            _ => return None,
+        }
+    }
+    if !has_root || depth != 0 || offset != text.len() {
+        return None;
+    }
+
+    let n_errors = reader.u32()?;
+    let mut errors = Vec::new();
+    for _ in 0..n_errors {
+        let location = match reader.byte()? {
+            OFFSET => Location::Offset(reader.text_unit()?),
+            RANGE => Location::Range(reader.range()?),
+            _ => return None,
+        };
+        let msg = reader.str()?;
+        let kind = SyntaxErrorKind::ParseError(ParseError(msg.to_string()));
+        errors.push(SyntaxError::new(kind, location));
+    }
+    if !reader.is_empty() {
+        return None;
+    }
+
+    let (green, _) = builder.finish_raw();
+    Some(SourceFile::new(green, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_through_persistent_encoding() {
+        let text = "fn foo() {\n    let x = 92;\n    bar(x, \"ы\"\n}\nstruct S { f: u32 }";
+        let file = SourceFile::parse(text);
+        assert!(!file.errors().is_empty());
+
+        let bytes = file.to_bytes();
+        let loaded = SourceFile::from_bytes(&bytes, text).unwrap();
+        assert_eq!(loaded.syntax().debug_dump(), file.syntax().debug_dump());
+        assert_eq!(loaded.errors(), file.errors());
+
+        assert!(SourceFile::from_bytes(&bytes, "fn foo() {}").is_none());
+        assert!(SourceFile::from_bytes(&bytes[..bytes.len() - 1], text).is_none());
+        assert!(SourceFile::from_bytes(b"garbage", text).is_none());
+    }
+
+    #[test]
+    fn syntax_kinds_roundtrip_through_u16() {
+        let mut raw = 0;
+        while let Some(kind) = SyntaxKind::from_u16(raw) {
+            assert_eq!(kind as u16, raw);
+            raw += 1;
+        }
+        assert!(raw > SyntaxKind::SOURCE_FILE as u16);
+    }
+}
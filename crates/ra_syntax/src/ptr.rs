@@ -18,12 +18,24 @@ impl SyntaxNodePtr {
         SyntaxNodePtr { range: node.range(), kind: node.kind() }
     }
 
+    /// Creates a pointer from its parts, for example, when loading it from
+    /// disk. Such pointers may not match the file, so they should be resolved
+    /// with `try_to_node`.
+    pub fn from_raw(range: TextRange, kind: SyntaxKind) -> SyntaxNodePtr {
+        SyntaxNodePtr { range, kind }
+    }
+
     pub fn to_node(self, source_file: &SourceFile) -> &SyntaxNode {
+        self.try_to_node(source_file)
+            .unwrap_or_else(|| panic!("can't resolve local ptr to SyntaxNode: {:?}", self))
+    }
+
+    /// Like `to_node`, but returns `None` if there's no such node.
+    pub fn try_to_node(self, source_file: &SourceFile) -> Option<&SyntaxNode> {
         generate(Some(source_file.syntax()), |&node| {
             node.children().find(|it| self.range.is_subrange(&it.range()))
         })
         .find(|it| it.range() == self.range && it.kind() == self.kind)
-        .unwrap_or_else(|| panic!("can't resolve local ptr to SyntaxNode: {:?}", self))
     }
 
     pub fn range(self) -> TextRange {
@@ -0,0 +1,201 @@
+//! A configurable version of `SyntaxNode::debug_dump`, for looking at trees
+//! while debugging.
+
+use std::fmt::Write;
+
+use crate::{
+    SyntaxNode, SourceFile, TextRange, TextUnit, WalkEvent, algo::find_covering_node,
+    syntax_node::has_short_text,
+};
+
+/// How the ranges of the nodes are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeStyle {
+    None,
+    /// `[start; end)` offsets, as in `debug_dump`.
+    Offsets,
+    /// One-based `line:column` positions, columns are counted in chars.
+    LineCol,
+}
+
+/// Renders a syntax tree as an indented list of nodes. By default, the
+/// output is the same as of `debug_dump`:
+///
+/// ```
+/// # use ra_syntax::{SourceFile, AstNode, RangeStyle, SyntaxTreeDump};
+/// let file = SourceFile::parse("fn foo() {}");
+/// let dump = SyntaxTreeDump::new(file.syntax())
+///     .trivia(false)
+///     .ranges(RangeStyle::LineCol)
+///     .max_depth(1)
+///     .dump();
+/// assert_eq!(dump, "SOURCE_FILE@1:1..1:12\n  FN_DEF@1:1..1:12\n");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SyntaxTreeDump<'a> {
+    node: &'a SyntaxNode,
+    trivia: bool,
+    ranges: RangeStyle,
+    max_depth: Option<usize>,
+    errors: bool,
+}
+
+impl<'a> SyntaxTreeDump<'a> {
+    pub fn new(node: &'a SyntaxNode) -> SyntaxTreeDump<'a> {
+        SyntaxTreeDump {
+            node,
+            trivia: true,
+            ranges: RangeStyle::Offsets,
+            max_depth: None,
+            errors: true,
+        }
+    }
+
+    /// Whether to show whitespace and comments.
+    pub fn trivia(mut self, trivia: bool) -> SyntaxTreeDump<'a> {
+        self.trivia = trivia;
+        self
+    }
+
+    pub fn ranges(mut self, ranges: RangeStyle) -> SyntaxTreeDump<'a> {
+        self.ranges = ranges;
+        self
+    }
+
+    /// Omits the nodes nested deeper than `max_depth`. The node itself has
+    /// depth `0`.
+    pub fn max_depth(mut self, max_depth: usize) -> SyntaxTreeDump<'a> {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Whether to show the syntax errors inside of the node.
+    pub fn errors(mut self, errors: bool) -> SyntaxTreeDump<'a> {
+        self.errors = errors;
+        self
+    }
+
+    /// Dumps the smallest node covering `range` instead of the whole node.
+    pub fn covering(mut self, range: TextRange) -> SyntaxTreeDump<'a> {
+        self.node = find_covering_node(self.node, range);
+        self
+    }
+
+    pub fn dump(&self) -> String {
+        let root_text = match self.ranges {
+            RangeStyle::LineCol => self.node.ancestors().last().unwrap().text().to_string(),
+            _ => String::new(),
+        };
+        let line_starts = line_starts(&root_text);
+        let mut errors = match (self.errors, self.node.ancestors().find_map(SourceFile::cast)) {
+            (true, Some(file)) => file.errors(),
+            (true, None) => self.node.root_data().to_vec(),
+            (false, _) => Vec::new(),
+        };
+        let node_range = self.node.range();
+        errors.retain(|e| node_range.start() <= e.offset() && e.offset() <= node_range.end());
+        errors.sort_by_key(|e| e.offset());
+
+        let mut buf = String::new();
+        let mut err_pos = 0;
+        let mut depth = 0;
+        for event in self.node.preorder() {
+            let node = match event {
+                WalkEvent::Enter(node) => node,
+                WalkEvent::Leave(_) => {
+                    depth -= 1;
+                    continue;
+                }
+            };
+            depth += 1;
+            let level = depth - 1;
+            if self.max_depth.map_or(false, |max_depth| level > max_depth)
+                || (!self.trivia && node.kind().is_trivia())
+            {
+                continue;
+            }
+
+            let indent = "  ".repeat(level);
+            write!(buf, "{}{:?}", indent, node.kind()).unwrap();
+            match self.ranges {
+                RangeStyle::None => (),
+                RangeStyle::Offsets => write!(buf, "@{:?}", node.range()).unwrap(),
+                RangeStyle::LineCol => {
+                    let start = line_col(&root_text, &line_starts, node.range().start());
+                    let end = line_col(&root_text, &line_starts, node.range().end());
+                    write!(buf, "@{}:{}..{}:{}", start.0, start.1, end.0, end.1).unwrap();
+                }
+            }
+            if has_short_text(node.kind()) {
+                write!(buf, " \"{}\"", node.text()).unwrap();
+            }
+            buf.push('\n');
+
+            // Show the errors after the last node shown before them.
+            let shows_children = node.first_child().is_some()
+                && self.max_depth.map_or(true, |max_depth| level < max_depth);
+            if !shows_children {
+                while err_pos < errors.len() && errors[err_pos].offset() <= node.range().end() {
+                    writeln!(buf, "{}err: `{}`", indent, errors[err_pos]).unwrap();
+                    err_pos += 1;
+                }
+            }
+        }
+        for err in errors[err_pos..].iter() {
+            writeln!(buf, "err: `{}`", err).unwrap();
+        }
+        buf
+    }
+}
+
+fn line_starts(text: &str) -> Vec<TextUnit> {
+    let mut res = vec![TextUnit::from(0)];
+    res.extend(text.match_indices('\n').map(|(i, _)| TextUnit::from_usize(i + 1)));
+    res
+}
+
+fn line_col(text: &str, line_starts: &[TextUnit], offset: TextUnit) -> (usize, usize) {
+    let line = match line_starts.binary_search(&offset) {
+        Ok(line) => line,
+        Err(line) => line - 1,
+    };
+    let line_start = line_starts[line].to_usize();
+    let col = text[line_start..offset.to_usize()].chars().count();
+    (line + 1, col + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AstNode;
+
+    use super::*;
+
+    #[test]
+    fn dumps_subtree_without_trivia() {
+        let file = SourceFile::parse("fn foo() {\n    let x = ;\n}");
+        let dump = SyntaxTreeDump::new(file.syntax())
+            .covering(TextRange::from_to(15.into(), 24.into()))
+            .trivia(false)
+            .ranges(RangeStyle::LineCol)
+            .dump();
+        assert_eq!(
+            dump,
+            r#"LET_STMT@2:5..2:15
+  LET_KW@2:5..2:8
+  BIND_PAT@2:9..2:10
+    NAME@2:9..2:10
+      IDENT@2:9..2:10 "x"
+  EQ@2:11..2:12
+  err: `expected expression`
+  SEMI@2:14..2:15
+"#
+        );
+
+        let dump = SyntaxTreeDump::new(file.syntax())
+            .ranges(RangeStyle::None)
+            .max_depth(1)
+            .errors(false)
+            .dump();
+        assert_eq!(dump, "SOURCE_FILE\n  FN_DEF\n");
+    }
+}
@@ -93,6 +93,10 @@ pub enum SyntaxErrorKind {
     UnicodeEscapeOutOfRange,
     UnclosedString,
     InvalidSuffix,
+    InvalidNumericSuffix,
+    InvalidIntDigit,
+    EmptyIntDigits,
+    UnbalancedAttrTokenTree,
     InvalidBlockAttr,
     InvalidMatchInnerAttr,
 }
@@ -133,6 +137,10 @@ impl fmt::Display for SyntaxErrorKind {
             UnicodeEscapeOutOfRange => write!(f, "Unicode escape code should be at most 0x10FFFF"),
             UnclosedString => write!(f, "Unclosed string literal"),
             InvalidSuffix => write!(f, "Invalid literal suffix"),
+            InvalidNumericSuffix => write!(f, "Invalid suffix for a number literal"),
+            InvalidIntDigit => write!(f, "Invalid digit for the base of this literal"),
+            EmptyIntDigits => write!(f, "Missing digits after the integer base prefix"),
+            UnbalancedAttrTokenTree => write!(f, "Unbalanced delimiters in attribute"),
             InvalidBlockAttr => {
                 write!(f, "A block in this position cannot accept inner attributes")
             }
@@ -9,6 +9,8 @@
 use std::{
     fmt::{self, Write},
     borrow::Borrow,
+    hash::{Hash, Hasher},
+    collections::hash_map::DefaultHasher,
 };
 
 use ra_parser::ParseError;
@@ -202,6 +204,32 @@ impl SyntaxNode {
         })
     }
 
+    /// Checks whether two trees are equal, ignoring trivia (whitespace and
+    /// comments). That is, whether they have the same shape, node kinds and
+    /// token texts.
+    pub fn structural_eq(&self, other: &SyntaxNode) -> bool {
+        self.structural_events().eq(other.structural_events())
+    }
+
+    /// A hash consistent with `structural_eq`: structurally equal trees have
+    /// equal hashes.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for event in self.structural_events() {
+            event.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn structural_events(&self) -> impl Iterator<Item = StructuralEvent> {
+        self.preorder().filter_map(|event| match event {
+            WalkEvent::Enter(node) if node.kind().is_trivia() => None,
+            WalkEvent::Enter(node) => Some(StructuralEvent::Enter(node.kind(), node.leaf_text())),
+            WalkEvent::Leave(node) if node.kind().is_trivia() => None,
+            WalkEvent::Leave(_) => Some(StructuralEvent::Leave),
+        })
+    }
+
     pub fn memory_size_of_subtree(&self) -> usize {
         self.0.memory_size_of_subtree()
     }
@@ -259,6 +287,12 @@ impl SyntaxNode {
     }
 }
 
+#[derive(PartialEq, Eq, Hash)]
+enum StructuralEvent<'a> {
+    Enter(SyntaxKind, Option<&'a SmolStr>),
+    Leave,
+}
+
 #[derive(Debug)]
 pub struct SyntaxNodeChildren<'a>(rowan::SyntaxNodeChildren<'a, RaTypes>);
 
@@ -270,7 +304,7 @@ impl<'a> Iterator for SyntaxNodeChildren<'a> {
     }
 }
 
-fn has_short_text(kind: SyntaxKind) -> bool {
+pub(crate) fn has_short_text(kind: SyntaxKind) -> bool {
     use crate::SyntaxKind::*;
     match kind {
         IDENT | LIFETIME | INT_NUMBER | FLOAT_NUMBER => true,
@@ -321,3 +355,20 @@ impl SyntaxTreeBuilder {
         self.errors.push(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{SourceFile, AstNode};
+
+    #[test]
+    fn structural_eq_ignores_trivia() {
+        let lhs = SourceFile::parse("fn foo() { 1 + 1 }");
+        let rhs = SourceFile::parse("fn  foo( ) {\n    // comment\n    1 +\n1\n}");
+        assert!(lhs.syntax().structural_eq(rhs.syntax()));
+        assert_eq!(lhs.syntax().structural_hash(), rhs.syntax().structural_hash());
+
+        let other = SourceFile::parse("fn foo() { 1 + 2 }");
+        assert!(!lhs.syntax().structural_eq(other.syntax()));
+        assert_ne!(lhs.syntax().structural_hash(), other.syntax().structural_hash());
+    }
+}
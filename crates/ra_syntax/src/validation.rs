@@ -3,6 +3,8 @@ mod byte_string;
 mod char;
 mod string;
 mod block;
+mod numeric;
+mod attr;
 
 use crate::{
     SourceFile, SyntaxError, AstNode, SyntaxNode,
@@ -20,6 +22,9 @@ pub(crate) fn validate(file: &SourceFile) -> Vec<SyntaxError> {
             .visit::<ast::Char, _>(char::validate_char_node)
             .visit::<ast::String, _>(string::validate_string_node)
             .visit::<ast::Block, _>(block::validate_block_node)
+            .visit::<ast::IntNumber, _>(numeric::validate_int_node)
+            .visit::<ast::FloatNumber, _>(numeric::validate_float_node)
+            .visit::<ast::Attr, _>(attr::validate_attr_node)
             .accept(node);
     }
     errors
@@ -0,0 +1,57 @@
+//! Validation of attribute token trees
+
+use crate::{
+    SyntaxKind::{self, *},
+    ast::{self, AstNode},
+    SyntaxError,
+    SyntaxErrorKind::*,
+};
+
+pub(super) fn validate_attr_node(node: &ast::Attr, errors: &mut Vec<SyntaxError>) {
+    let tt = match node.value() {
+        Some(tt) => tt,
+        None => return,
+    };
+    for tt in tt.syntax().descendants().filter(|it| it.kind() == TOKEN_TREE) {
+        let first = tt.first_child().map(|it| it.kind());
+        let last = tt.last_child().map(|it| it.kind());
+        let balanced = match (first.and_then(closing_delimiter), last) {
+            (Some(expected), Some(last)) => {
+                // A lone opening delimiter is both the first and the last child.
+                expected == last && tt.first_child() != tt.last_child()
+            }
+            _ => false,
+        };
+        if !balanced {
+            errors.push(SyntaxError::new(UnbalancedAttrTokenTree, node.syntax().range()));
+            return;
+        }
+    }
+}
+
+fn closing_delimiter(kind: SyntaxKind) -> Option<SyntaxKind> {
+    let res = match kind {
+        L_PAREN => R_PAREN,
+        L_BRACK => R_BRACK,
+        L_CURLY => R_CURLY,
+        _ => return None,
+    };
+    Some(res)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{SourceFile, SyntaxErrorKind};
+
+    #[test]
+    fn test_unbalanced_attr_token_tree() {
+        let file = SourceFile::parse("#[cfg(test]\nfn foo() {}");
+        assert!(file
+            .errors()
+            .iter()
+            .any(|it| it.kind() == SyntaxErrorKind::UnbalancedAttrTokenTree));
+
+        let file = SourceFile::parse("#[cfg(all(test, unix))]\nfn foo() {}");
+        assert!(file.errors().is_empty());
+    }
+}
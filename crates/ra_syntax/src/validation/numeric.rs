@@ -0,0 +1,159 @@
+//! Validation of integer and float literals
+
+use crate::{
+    ast::{self, AstNode, AstToken},
+    TextRange, TextUnit, SyntaxError,
+    SyntaxErrorKind::*,
+};
+
+const INT_SUFFIXES: &[&str] =
+    &["isize", "i128", "i64", "i32", "i16", "i8", "usize", "u128", "u64", "u32", "u16", "u8"];
+const FLOAT_SUFFIXES: &[&str] = &["f64", "f32"];
+
+pub(super) fn validate_int_node(node: &ast::IntNumber, errors: &mut Vec<SyntaxError>) {
+    let literal_text = node.text();
+    let literal_range = node.syntax().range();
+
+    let (radix, prefix_len) = match literal_text.get(..2) {
+        Some("0x") => (16, 2),
+        Some("0o") => (8, 2),
+        Some("0b") => (2, 2),
+        _ => (10, 0),
+    };
+    // The lexer accepts any decimal digit after `0b` and `0o`, so we scan
+    // decimal digits here as well and report the ones which are out of range.
+    let scan_radix = if radix == 16 { 16 } else { 10 };
+    let digits_len = literal_text[prefix_len..]
+        .find(|c: char| c != '_' && !c.is_digit(scan_radix))
+        .unwrap_or(literal_text.len() - prefix_len);
+    let digits = &literal_text[prefix_len..prefix_len + digits_len];
+
+    if radix != 10 && !digits.chars().any(|c| c != '_') {
+        errors.push(SyntaxError::new(EmptyIntDigits, literal_range));
+    }
+
+    for (idx, c) in digits.char_indices() {
+        if c != '_' && !c.is_digit(radix) {
+            let start = literal_range.start() + TextUnit::from_usize(prefix_len + idx);
+            let range = TextRange::offset_len(start, TextUnit::of_char(c));
+            errors.push(SyntaxError::new(InvalidIntDigit, range));
+        }
+    }
+
+    let suffix_start = prefix_len + digits_len;
+    let suffix = &literal_text[suffix_start..];
+    let float_suffix_allowed = radix == 10 && FLOAT_SUFFIXES.contains(&suffix);
+    if !suffix.is_empty() && !INT_SUFFIXES.contains(&suffix) && !float_suffix_allowed {
+        let range = TextRange::from_to(
+            literal_range.start() + TextUnit::from_usize(suffix_start),
+            literal_range.end(),
+        );
+        errors.push(SyntaxError::new(InvalidNumericSuffix, range));
+    }
+}
+
+pub(super) fn validate_float_node(node: &ast::FloatNumber, errors: &mut Vec<SyntaxError>) {
+    let literal_text = node.text();
+    let literal_range = node.syntax().range();
+
+    let mut chars = literal_text.char_indices().peekable();
+    let mut suffix_start = literal_text.len();
+    let mut seen_exponent = false;
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '0'...'9' | '_' | '.' => (),
+            'e' | 'E' if !seen_exponent => {
+                seen_exponent = true;
+                if let Some(&(_, '+')) | Some(&(_, '-')) = chars.peek() {
+                    chars.next();
+                }
+            }
+            _ => {
+                suffix_start = idx;
+                break;
+            }
+        }
+    }
+
+    let suffix = &literal_text[suffix_start..];
+    if !suffix.is_empty() && !FLOAT_SUFFIXES.contains(&suffix) {
+        let range = TextRange::from_to(
+            literal_range.start() + TextUnit::from_usize(suffix_start),
+            literal_range.end(),
+        );
+        errors.push(SyntaxError::new(InvalidNumericSuffix, range));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{SourceFile, SyntaxErrorKind, TreeArc};
+
+    fn build_file(literal: &str) -> TreeArc<SourceFile> {
+        let src = format!("const C: u32 = {};", literal);
+        SourceFile::parse(&src)
+    }
+
+    fn error_kinds(literal: &str) -> Vec<SyntaxErrorKind> {
+        build_file(literal).errors().iter().map(|it| it.kind()).collect()
+    }
+
+    #[test]
+    fn test_valid_numbers() {
+        let valid = [
+            "92",
+            "92u8",
+            "92_i128",
+            "0x_FF",
+            "0xFFu8",
+            "0o777",
+            "0b1010_1010usize",
+            "1f32",
+            "1.0",
+            "1.0e10",
+            "1e-3f64",
+            "1_000.5_f32",
+        ];
+        for literal in &valid {
+            assert_eq!(error_kinds(literal), vec![], "literal: {}", literal);
+        }
+    }
+
+    #[test]
+    fn test_invalid_suffixes() {
+        for literal in &["92u7", "92foo", "0x1fz", "1.0u8", "1e3i32", "0b1f32"] {
+            assert_eq!(
+                error_kinds(literal),
+                vec![SyntaxErrorKind::InvalidNumericSuffix],
+                "literal: {}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_digits() {
+        assert_eq!(error_kinds("0b102"), vec![SyntaxErrorKind::InvalidIntDigit]);
+        assert_eq!(error_kinds("0o78"), vec![SyntaxErrorKind::InvalidIntDigit]);
+        assert_eq!(error_kinds("0x__"), vec![SyntaxErrorKind::EmptyIntDigits]);
+    }
+
+    #[test]
+    fn test_errors_are_grouped_by_kind() {
+        let file = SourceFile::parse("const A: u8 = 1u7; const B: u8 = 0b2; const C: u8 = 2u9;");
+        let groups = file.validation_errors();
+        let kinds: Vec<_> =
+            groups.iter().map(|(kind, errors)| (kind.clone(), errors.len())).collect();
+        assert_eq!(
+            kinds,
+            vec![(SyntaxErrorKind::InvalidNumericSuffix, 2), (SyntaxErrorKind::InvalidIntDigit, 1)]
+        );
+    }
+
+    #[test]
+    fn test_suffix_range() {
+        let file = build_file("92u7");
+        let error = &file.errors()[0];
+        assert_eq!(format!("{:?}", error.location()), "Range([17; 19))");
+    }
+}
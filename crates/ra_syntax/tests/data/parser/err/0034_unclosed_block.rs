@@ -0,0 +1,6 @@
+fn foo() {
+    if x {
+        bar();
+}
+
+struct S;
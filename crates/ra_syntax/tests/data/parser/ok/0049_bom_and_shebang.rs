@@ -0,0 +1,2 @@
+﻿#!/usr/bin/env rust-script
+fn main() {}
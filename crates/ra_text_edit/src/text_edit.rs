@@ -78,4 +78,175 @@ impl TextEdit {
         }
         Some(res)
     }
+
+    /// Translates `range` to the edited text. Returns `None` if any of the
+    /// ends of the range is deleted by the edit.
+    pub fn apply_to_range(&self, range: TextRange) -> Option<TextRange> {
+        let start = self.apply_to_offset(range.start())?;
+        let end = self.apply_to_offset(range.end())?;
+        Some(TextRange::from_to(start, end))
+    }
+
+    /// Returns an edit which turns the result of applying `self` to `text`
+    /// back into `text`.
+    pub fn invert(&self, text: &str) -> TextEdit {
+        let mut atoms = Vec::with_capacity(self.atoms.len());
+        let mut inserted = TextUnit::from(0);
+        let mut deleted = TextUnit::from(0);
+        for atom in self.atoms.iter() {
+            let start = atom.delete.start() + inserted - deleted;
+            let range = TextRange::offset_len(start, TextUnit::of_str(&atom.insert));
+            let start = u32::from(atom.delete.start()) as usize;
+            let end = u32::from(atom.delete.end()) as usize;
+            atoms.push(AtomTextEdit::replace(range, text[start..end].to_string()));
+            inserted += TextUnit::of_str(&atom.insert);
+            deleted += atom.delete.len();
+        }
+        TextEdit { atoms }
+    }
+
+    /// Composes two edits into one: applying the result is the same as
+    /// applying `self` and then `next`, whose offsets refer to the text
+    /// edited by `self`.
+    pub fn compose(&self, next: &TextEdit) -> TextEdit {
+        // The text edited by `self`, as a sequence of the retained parts of
+        // the original text and of the inserted strings. The last part
+        // extends up to the end of the text, whatever its length is.
+        let mut pieces = Vec::with_capacity(2 * self.atoms.len() + 1);
+        let mut prev = TextUnit::from(0);
+        for atom in self.atoms.iter() {
+            pieces.push(Piece::Keep(TextRange::from_to(prev, atom.delete.start())));
+            pieces.push(Piece::Insert(&atom.insert));
+            prev = atom.delete.end();
+        }
+        pieces.push(Piece::Keep(TextRange::from_to(prev, TextUnit::from(u32::max_value()))));
+
+        // Cut out the ranges deleted by `next` and add its insertions.
+        let mut composed = Vec::new();
+        let mut next_atoms = next.atoms.iter().peekable();
+        let mut piece_start = 0;
+        for piece in pieces {
+            let piece_end = piece_start + piece.len();
+            let mut pos = piece_start;
+            while let Some(atom) = next_atoms.peek() {
+                let start = u32::from(atom.delete.start()) as usize;
+                let end = u32::from(atom.delete.end()) as usize;
+                if start >= piece_end {
+                    break;
+                }
+                // An atom spanning several pieces is inserted only once,
+                // with the first of them.
+                if start >= piece_start {
+                    if start > pos {
+                        composed.push(piece.slice(pos - piece_start, start - piece_start));
+                    }
+                    composed.push(Piece::Insert(&atom.insert));
+                }
+                pos = pos.max(end.min(piece_end));
+                if end > piece_end {
+                    break;
+                }
+                next_atoms.next();
+            }
+            if pos < piece_end {
+                composed.push(piece.slice(pos - piece_start, piece_end - piece_start));
+            }
+            piece_start = piece_end;
+        }
+
+        let mut atoms = Vec::new();
+        let mut prev = TextUnit::from(0);
+        let mut insert = String::new();
+        for piece in composed {
+            match piece {
+                Piece::Insert(text) => insert.push_str(text),
+                Piece::Keep(range) => {
+                    if range.start() > prev || !insert.is_empty() {
+                        let delete = TextRange::from_to(prev, range.start());
+                        let replace_with = std::mem::replace(&mut insert, String::new());
+                        atoms.push(AtomTextEdit::replace(delete, replace_with));
+                    }
+                    prev = range.end();
+                }
+            }
+        }
+        TextEdit { atoms }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Piece<'a> {
+    Keep(TextRange),
+    Insert(&'a str),
+}
+
+impl<'a> Piece<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Piece::Keep(range) => u32::from(range.len()) as usize,
+            Piece::Insert(text) => text.len(),
+        }
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Piece<'a> {
+        match self {
+            Piece::Keep(range) => Piece::Keep(TextRange::from_to(
+                range.start() + TextUnit::from(start as u32),
+                range.start() + TextUnit::from(end as u32),
+            )),
+            Piece::Insert(text) => Piece::Insert(&text[start..end]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::{prelude::*, proptest};
+
+    use super::*;
+    use crate::test_utils::{arb_text_edit, arb_text_with_edit, ArbTextWithEdit};
+
+    fn arb_two_edits() -> BoxedStrategy<(ArbTextWithEdit, TextEdit)> {
+        arb_text_with_edit()
+            .prop_flat_map(|x| {
+                let edited = x.edit.apply(&x.text);
+                (Just(x), arb_text_edit(&edited))
+            })
+            .boxed()
+    }
+
+    #[test]
+    fn composes_edits() {
+        let mut first = TextEditBuilder::default();
+        first.replace(TextRange::from_to(3.into(), 6.into()), "foo_bar".to_string());
+        let first = first.finish();
+        let mut second = TextEditBuilder::default();
+        second.delete(TextRange::from_to(6.into(), 10.into()));
+        second.insert(11.into(), "x: i32".to_string());
+        let second = second.finish();
+
+        let text = "fn foo() {}";
+        assert_eq!(second.apply(&first.apply(text)), "fn foo(x: i32) {}");
+        let composed = first.compose(&second);
+        assert_eq!(composed.apply(text), "fn foo(x: i32) {}");
+        assert_eq!(composed.invert(text).apply("fn foo(x: i32) {}"), text);
+        assert_eq!(
+            composed.apply_to_range(TextRange::from_to(9.into(), 11.into())),
+            Some(TextRange::from_to(15.into(), 17.into()))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn compose_is_sequential_application((x, next) in arb_two_edits()) {
+            let expected = next.apply(&x.edit.apply(&x.text));
+            assert_eq!(x.edit.compose(&next).apply(&x.text), expected);
+        }
+
+        #[test]
+        fn invert_restores_the_text(x in arb_text_with_edit()) {
+            let edited = x.edit.apply(&x.text);
+            assert_eq!(x.edit.invert(&x.text).apply(&edited), x.text);
+        }
+    }
 }
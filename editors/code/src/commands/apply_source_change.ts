@@ -7,6 +7,12 @@ export interface SourceChange {
     label: string;
     workspaceEdit: lc.WorkspaceEdit;
     cursorPosition?: lc.TextDocumentPositionParams;
+    typing?: TypingEdit;
+}
+
+export interface TypingEdit {
+    handler: 'onEnter' | 'onEqTyped' | 'onDotTyped';
+    coalesceWithKeystroke: boolean;
 }
 
 export async function handle(change: SourceChange) {